@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS invoice_pages (
+    id TEXT PRIMARY KEY,
+    invoice_id TEXT NOT NULL,
+    page_number INTEGER NOT NULL,
+    text TEXT NOT NULL,
+    extraction_method TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY(invoice_id) REFERENCES invoices(id) ON DELETE CASCADE,
+    UNIQUE(invoice_id, page_number)
+);
@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS invoice_field_evidence (
+    id TEXT PRIMARY KEY,
+    invoice_id TEXT NOT NULL,
+    field_name TEXT NOT NULL,
+    value TEXT,
+    confidence REAL NOT NULL,
+    page_number INTEGER,
+    text_offset INTEGER,
+    text_length INTEGER,
+    snippet TEXT,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY(invoice_id) REFERENCES invoices(id) ON DELETE CASCADE,
+    UNIQUE(invoice_id, field_name)
+);
+
+CREATE INDEX IF NOT EXISTS idx_field_evidence_confidence ON invoice_field_evidence(confidence);
@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS invoice_line_items (
+    id TEXT PRIMARY KEY,
+    invoice_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    description TEXT,
+    quantity REAL,
+    unit TEXT,
+    unit_price_minor INTEGER,
+    net_amount_minor INTEGER,
+    vat_rate REAL,
+    line_total_minor INTEGER,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY(invoice_id) REFERENCES invoices(id) ON DELETE CASCADE,
+    UNIQUE(invoice_id, position)
+);
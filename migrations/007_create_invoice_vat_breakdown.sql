@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS invoice_vat_breakdown (
+    id TEXT PRIMARY KEY,
+    invoice_id TEXT NOT NULL,
+    rate REAL NOT NULL,
+    net_amount_minor INTEGER NOT NULL,
+    tax_amount_minor INTEGER NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY(invoice_id) REFERENCES invoices(id) ON DELETE CASCADE,
+    UNIQUE(invoice_id, rate)
+);
+
+CREATE INDEX IF NOT EXISTS idx_vat_breakdown_rate ON invoice_vat_breakdown(rate);
@@ -0,0 +1,16 @@
+-- Amounts move from decimal TEXT to INTEGER minor units (cents) so that SUM()
+-- is exact. Existing values are always written with two decimals.
+ALTER TABLE invoices ADD COLUMN total_amount_minor INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE invoices ADD COLUMN tax_amount_minor INTEGER;
+ALTER TABLE invoices ADD COLUMN net_amount_minor INTEGER;
+
+UPDATE invoices SET
+    total_amount_minor = CAST(ROUND(CAST(REPLACE(total_amount, ',', '.') AS REAL) * 100) AS INTEGER),
+    tax_amount_minor = CASE WHEN NULLIF(TRIM(tax_amount), '') IS NULL THEN NULL
+        ELSE CAST(ROUND(CAST(REPLACE(tax_amount, ',', '.') AS REAL) * 100) AS INTEGER) END,
+    net_amount_minor = CASE WHEN NULLIF(TRIM(net_amount), '') IS NULL THEN NULL
+        ELSE CAST(ROUND(CAST(REPLACE(net_amount, ',', '.') AS REAL) * 100) AS INTEGER) END;
+
+ALTER TABLE invoices DROP COLUMN total_amount;
+ALTER TABLE invoices DROP COLUMN tax_amount;
+ALTER TABLE invoices DROP COLUMN net_amount;
@@ -0,0 +1,20 @@
+CREATE TABLE IF NOT EXISTS exchange_rates (
+    id TEXT PRIMARY KEY,
+    rate_date TEXT NOT NULL,
+    base_currency TEXT NOT NULL DEFAULT 'EUR',
+    quote_currency TEXT NOT NULL,
+    rate REAL NOT NULL,
+    source TEXT NOT NULL DEFAULT 'ecb',
+    created_at TEXT NOT NULL,
+    UNIQUE(base_currency, quote_currency, rate_date)
+);
+
+CREATE INDEX IF NOT EXISTS idx_exchange_rates_lookup ON exchange_rates(quote_currency, rate_date);
+
+ALTER TABLE invoices ADD COLUMN base_currency TEXT;
+ALTER TABLE invoices ADD COLUMN exchange_rate REAL;
+ALTER TABLE invoices ADD COLUMN base_total_minor INTEGER;
+
+UPDATE invoices
+SET base_currency = 'EUR', exchange_rate = 1.0, base_total_minor = total_amount_minor
+WHERE currency = 'EUR';
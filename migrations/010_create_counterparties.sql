@@ -0,0 +1,27 @@
+CREATE TABLE IF NOT EXISTS counterparties (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    normalized_name TEXT NOT NULL,
+    vat_id TEXT,
+    iban TEXT,
+    address TEXT,
+    default_category TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_counterparties_normalized_name ON counterparties(normalized_name);
+CREATE INDEX IF NOT EXISTS idx_counterparties_vat_id ON counterparties(vat_id);
+
+CREATE TABLE IF NOT EXISTS counterparty_aliases (
+    id TEXT PRIMARY KEY,
+    counterparty_id TEXT NOT NULL,
+    alias TEXT NOT NULL,
+    normalized_alias TEXT NOT NULL UNIQUE,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY(counterparty_id) REFERENCES counterparties(id) ON DELETE CASCADE
+);
+
+ALTER TABLE invoices ADD COLUMN counterparty_id TEXT REFERENCES counterparties(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_invoices_counterparty_id ON invoices(counterparty_id);
@@ -0,0 +1,33 @@
+CREATE TABLE IF NOT EXISTS accounts (
+    id TEXT PRIMARY KEY,
+    chart TEXT NOT NULL,
+    number TEXT NOT NULL,
+    name TEXT NOT NULL,
+    category TEXT NOT NULL CHECK (category IN ('revenue', 'payable')),
+    expense_category TEXT,
+    vat_rate REAL,
+    active INTEGER NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    UNIQUE(chart, number)
+);
+
+CREATE TABLE IF NOT EXISTS account_rules (
+    id TEXT PRIMARY KEY,
+    chart TEXT NOT NULL,
+    account_number TEXT NOT NULL,
+    counterparty_id TEXT,
+    keyword TEXT,
+    priority INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY(chart, account_number) REFERENCES accounts(chart, number) ON DELETE CASCADE ON UPDATE CASCADE,
+    FOREIGN KEY(counterparty_id) REFERENCES counterparties(id) ON DELETE CASCADE,
+    CHECK (counterparty_id IS NOT NULL OR keyword IS NOT NULL)
+);
+
+CREATE INDEX IF NOT EXISTS idx_account_rules_chart ON account_rules(chart);
+
+ALTER TABLE invoices ADD COLUMN account_number TEXT;
+ALTER TABLE invoices ADD COLUMN account_source TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_invoices_account_number ON invoices(account_number);
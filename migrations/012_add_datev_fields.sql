@@ -0,0 +1,8 @@
+ALTER TABLE accounts ADD COLUMN automatic_vat INTEGER NOT NULL DEFAULT 0;
+
+UPDATE accounts
+SET automatic_vat = 1
+WHERE chart IN ('SKR03', 'SKR04')
+  AND expense_category IN ('sales_19', 'sales_7', 'sales_eu', 'sales_export', 'goods_19', 'goods_7');
+
+ALTER TABLE counterparties ADD COLUMN personal_account TEXT;
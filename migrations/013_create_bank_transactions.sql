@@ -0,0 +1,35 @@
+CREATE TABLE IF NOT EXISTS bank_transactions (
+    id TEXT PRIMARY KEY,
+    import_hash TEXT NOT NULL UNIQUE,
+    source TEXT NOT NULL,
+    account_iban TEXT,
+    booking_date TEXT NOT NULL,
+    value_date TEXT,
+    amount_minor INTEGER NOT NULL,
+    currency TEXT NOT NULL,
+    counterparty_name TEXT,
+    counterparty_iban TEXT,
+    remittance TEXT,
+    reference TEXT,
+    status TEXT NOT NULL DEFAULT 'unmatched',
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_bank_transactions_status ON bank_transactions(status);
+CREATE INDEX IF NOT EXISTS idx_bank_transactions_booking_date ON bank_transactions(booking_date);
+
+CREATE TABLE IF NOT EXISTS bank_transaction_matches (
+    id TEXT PRIMARY KEY,
+    transaction_id TEXT NOT NULL,
+    invoice_id TEXT NOT NULL,
+    amount_minor INTEGER NOT NULL,
+    confidence REAL NOT NULL,
+    reasons TEXT NOT NULL DEFAULT '',
+    status TEXT NOT NULL DEFAULT 'suggested',
+    created_at TEXT NOT NULL,
+    UNIQUE(transaction_id, invoice_id),
+    FOREIGN KEY(transaction_id) REFERENCES bank_transactions(id) ON DELETE CASCADE,
+    FOREIGN KEY(invoice_id) REFERENCES invoices(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_bank_transaction_matches_invoice_id ON bank_transaction_matches(invoice_id);
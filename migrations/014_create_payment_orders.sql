@@ -0,0 +1,24 @@
+ALTER TABLE counterparties ADD COLUMN bic TEXT;
+
+-- Transfers handed to the bank, kept even if their invoice row goes away so
+-- a payable cannot be paid twice.
+CREATE TABLE IF NOT EXISTS payment_orders (
+    id TEXT PRIMARY KEY,
+    message_id TEXT NOT NULL,
+    invoice_id TEXT NOT NULL,
+    end_to_end_id TEXT NOT NULL,
+    creditor_name TEXT NOT NULL,
+    creditor_iban TEXT NOT NULL,
+    creditor_bic TEXT,
+    amount_minor INTEGER NOT NULL,
+    discount_minor INTEGER NOT NULL DEFAULT 0,
+    currency TEXT NOT NULL,
+    remittance TEXT NOT NULL,
+    execution_date TEXT NOT NULL,
+    file_path TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY(invoice_id) REFERENCES invoices(id) ON DELETE RESTRICT
+);
+
+CREATE INDEX IF NOT EXISTS idx_payment_orders_invoice_id ON payment_orders(invoice_id);
+CREATE INDEX IF NOT EXISTS idx_payment_orders_message_id ON payment_orders(message_id);
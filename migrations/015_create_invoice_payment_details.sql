@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS invoice_payment_details (
+    invoice_id TEXT PRIMARY KEY,
+    iban TEXT,
+    bic TEXT,
+    payment_reference TEXT,
+    payment_terms TEXT,
+    discount_percent REAL,
+    discount_due_date TEXT,
+    FOREIGN KEY(invoice_id) REFERENCES invoices(id) ON DELETE CASCADE
+);
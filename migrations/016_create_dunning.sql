@@ -0,0 +1,47 @@
+ALTER TABLE counterparties ADD COLUMN consumer INTEGER NOT NULL DEFAULT 0;
+
+-- The log shows when a customer was put in default, so it may not be
+-- deleted together with its invoice.
+CREATE TABLE IF NOT EXISTS dunning_steps (
+    id TEXT PRIMARY KEY,
+    invoice_id TEXT NOT NULL,
+    level INTEGER NOT NULL,
+    outstanding_minor INTEGER NOT NULL,
+    interest_minor INTEGER NOT NULL DEFAULT 0,
+    fees_minor INTEGER NOT NULL DEFAULT 0,
+    interest_rate REAL,
+    default_since TEXT,
+    deadline TEXT NOT NULL,
+    file_path TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY(invoice_id) REFERENCES invoices(id) ON DELETE RESTRICT
+);
+
+CREATE INDEX IF NOT EXISTS idx_dunning_steps_invoice_id ON dunning_steps(invoice_id);
+
+CREATE TABLE IF NOT EXISTS dunning_templates (
+    level INTEGER PRIMARY KEY,
+    subject TEXT NOT NULL,
+    body TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+-- Basiszinssatz (§ 247 BGB) as published by the Deutsche Bundesbank on
+-- 1 January and 1 July. Rates published after a release go into a new
+-- migration with INSERT OR IGNORE, so rates entered by users are kept; until
+-- then users add them under Dunning > Base Interest Rates.
+CREATE TABLE IF NOT EXISTS base_interest_rates (
+    valid_from TEXT PRIMARY KEY,
+    rate REAL NOT NULL
+);
+
+INSERT OR IGNORE INTO base_interest_rates (valid_from, rate) VALUES
+    ('2016-07-01', -0.88),
+    ('2023-01-01', 1.62),
+    ('2023-07-01', 3.12),
+    ('2024-01-01', 3.62),
+    ('2024-07-01', 3.37),
+    ('2025-01-01', 2.27),
+    ('2025-07-01', 1.27),
+    ('2026-01-01', 1.27),
+    ('2026-07-01', 1.27);
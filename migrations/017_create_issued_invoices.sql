@@ -0,0 +1,15 @@
+ALTER TABLE counterparties ADD COLUMN email TEXT;
+
+-- Revenue invoices written by Billly. A number is taken by inserting here,
+-- together with the invoice, so each scope's sequence has no gaps.
+CREATE TABLE IF NOT EXISTS issued_invoices (
+    invoice_id TEXT PRIMARY KEY,
+    invoice_number TEXT NOT NULL UNIQUE,
+    number_scope TEXT NOT NULL,
+    sequence INTEGER NOT NULL,
+    format TEXT NOT NULL,
+    draft_json TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    UNIQUE(number_scope, sequence),
+    FOREIGN KEY(invoice_id) REFERENCES invoices(id)
+);
@@ -0,0 +1,150 @@
+use crate::models::{Account, AccountRule, AccountTotal};
+use crate::services::accounts;
+use crate::services::state::AppState;
+use serde::Deserialize;
+use tauri::State;
+
+#[derive(Deserialize)]
+pub struct AccountRulePayload {
+    pub account_number: String,
+    pub counterparty_id: Option<String>,
+    pub keyword: Option<String>,
+    pub priority: Option<i64>,
+}
+
+#[tauri::command]
+pub async fn get_accounts(state: State<'_, AppState>) -> Result<Vec<Account>, String> {
+    let settings = state
+        .settings
+        .lock()
+        .map_err(|_| "Settings lock".to_string())?
+        .clone();
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    db.get_accounts(&settings.chart_of_accounts)
+        .map_err(|e| e.to_string())
+}
+
+/// Creates or updates an account of the active chart, then re-runs the
+/// account assignment.
+#[tauri::command]
+pub async fn save_account(account: Account, state: State<'_, AppState>) -> Result<(), String> {
+    let settings = state
+        .settings
+        .lock()
+        .map_err(|_| "Settings lock".to_string())?
+        .clone();
+    let number = account.number.trim().to_string();
+    if number.is_empty() || account.name.trim().is_empty() {
+        return Err("Account number and name are required".to_string());
+    }
+    if account.category != "revenue" && account.category != "payable" {
+        return Err(format!("Unknown category: {}", account.category));
+    }
+
+    let account = Account {
+        chart: settings.chart_of_accounts.clone(),
+        number,
+        name: account.name.trim().to_string(),
+        ..account
+    };
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    db.save_account(&account, true).map_err(|e| e.to_string())?;
+    accounts::reassign_all(&db, &settings.chart_of_accounts).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Loads the standard SKR03 or SKR04 accounts into the active chart.
+#[tauri::command]
+pub async fn seed_accounts(state: State<'_, AppState>) -> Result<usize, String> {
+    let settings = state
+        .settings
+        .lock()
+        .map_err(|_| "Settings lock".to_string())?
+        .clone();
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    let seeded = accounts::seed_chart(&db, &settings.chart_of_accounts).map_err(|e| e.to_string())?;
+    accounts::reassign_all(&db, &settings.chart_of_accounts).map_err(|e| e.to_string())?;
+    Ok(seeded)
+}
+
+#[tauri::command]
+pub async fn get_account_rules(state: State<'_, AppState>) -> Result<Vec<AccountRule>, String> {
+    let settings = state
+        .settings
+        .lock()
+        .map_err(|_| "Settings lock".to_string())?
+        .clone();
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    db.get_account_rules(&settings.chart_of_accounts)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn add_account_rule(payload: AccountRulePayload, state: State<'_, AppState>) -> Result<(), String> {
+    let settings = state
+        .settings
+        .lock()
+        .map_err(|_| "Settings lock".to_string())?
+        .clone();
+    let keyword = payload
+        .keyword
+        .map(|keyword| keyword.trim().to_string())
+        .filter(|keyword| !keyword.is_empty());
+    let counterparty_id = payload.counterparty_id.filter(|id| !id.is_empty());
+    if keyword.is_none() && counterparty_id.is_none() {
+        return Err("A rule needs a counterparty or a keyword".to_string());
+    }
+
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    accounts::validate_account(&db, &settings.chart_of_accounts, &payload.account_number)
+        .map_err(|e| e.to_string())?;
+    let rule = AccountRule {
+        id: String::new(),
+        chart: settings.chart_of_accounts.clone(),
+        account_number: payload.account_number,
+        counterparty_id,
+        counterparty_name: None,
+        keyword,
+        priority: payload.priority.unwrap_or(0),
+    };
+    db.insert_account_rule(&rule).map_err(|e| e.to_string())?;
+    accounts::reassign_all(&db, &settings.chart_of_accounts).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_account_rule(rule_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    let settings = state
+        .settings
+        .lock()
+        .map_err(|_| "Settings lock".to_string())?
+        .clone();
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    db.delete_account_rule(&rule_id).map_err(|e| e.to_string())?;
+    accounts::reassign_all(&db, &settings.chart_of_accounts).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Per-account sums of one year for the tax advisor, in the base currency
+/// and on the configured reporting basis.
+#[tauri::command]
+pub async fn get_account_totals(
+    category: String,
+    year: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<AccountTotal>, String> {
+    let settings = state
+        .settings
+        .lock()
+        .map_err(|_| "Settings lock".to_string())?
+        .clone();
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    db.get_account_totals(
+        &category,
+        &year,
+        &settings.chart_of_accounts,
+        &settings.base_currency,
+        &settings.reporting_basis,
+    )
+    .map_err(|e| e.to_string())
+}
@@ -0,0 +1,105 @@
+use crate::models::{BankImportSummary, BankMatch, BankTransactionDetail};
+use crate::services::bank_import::{self, CsvMapping};
+use crate::services::reconciliation;
+use crate::services::state::AppState;
+use serde::Deserialize;
+use std::collections::HashMap;
+use tauri::State;
+
+#[derive(Deserialize)]
+pub struct BankImportPayload {
+    pub path: Option<String>,
+    pub csv_profile: Option<String>,
+    pub csv_mapping: Option<CsvMapping>,
+}
+
+#[tauri::command]
+pub async fn get_bank_csv_profiles() -> Result<Vec<String>, String> {
+    Ok(bank_import::csv_profiles())
+}
+
+/// Imports a CAMT.053, MT940 or CSV statement and reconciles it with the
+/// open invoices. Without a path a file dialog is shown; a cancelled dialog
+/// returns `None`.
+#[tauri::command]
+pub async fn import_bank_statement(
+    payload: BankImportPayload,
+    state: State<'_, AppState>,
+) -> Result<Option<BankImportSummary>, String> {
+    let path = match payload.path {
+        Some(path) => std::path::PathBuf::from(path),
+        None => match rfd::FileDialog::new()
+            .add_filter("Bank statements", &["xml", "sta", "mt940", "txt", "csv"])
+            .pick_file()
+        {
+            Some(path) => path,
+            None => return Ok(None),
+        },
+    };
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    reconciliation::import_statement(
+        &db,
+        &path,
+        payload.csv_profile.as_deref(),
+        payload.csv_mapping.as_ref(),
+    )
+    .map(Some)
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_bank_transactions(
+    status: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<BankTransactionDetail>, String> {
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    let transactions = db
+        .get_bank_transactions(status.as_deref())
+        .map_err(|e| e.to_string())?;
+    let mut matches: HashMap<String, Vec<BankMatch>> = HashMap::new();
+    for found in db.get_bank_matches(None).map_err(|e| e.to_string())? {
+        matches.entry(found.transaction_id.clone()).or_default().push(found);
+    }
+    Ok(transactions
+        .into_iter()
+        .map(|transaction| BankTransactionDetail {
+            matches: matches.remove(&transaction.id).unwrap_or_default(),
+            transaction,
+        })
+        .collect())
+}
+
+/// Re-runs matching for unmatched transactions, e.g. after new invoices.
+#[tauri::command]
+pub async fn reconcile_bank_transactions(state: State<'_, AppState>) -> Result<(usize, usize), String> {
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    reconciliation::reconcile(&db).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn confirm_bank_match(match_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    reconciliation::confirm_match(&db, &match_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn reject_bank_match(match_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    reconciliation::reject_match(&db, &match_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn assign_bank_transaction(
+    transaction_id: String,
+    invoice_ids: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    reconciliation::assign(&db, &transaction_id, &invoice_ids).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn ignore_bank_transaction(transaction_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    reconciliation::ignore(&db, &transaction_id).map_err(|e| e.to_string())
+}
@@ -0,0 +1,39 @@
+use crate::models::Counterparty;
+use crate::services::counterparties;
+use crate::services::state::AppState;
+use tauri::State;
+
+#[tauri::command]
+pub async fn get_counterparties(state: State<'_, AppState>) -> Result<Vec<Counterparty>, String> {
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    db.get_counterparties(None).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_counterparty(counterparty: Counterparty, state: State<'_, AppState>) -> Result<(), String> {
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    counterparties::update(&db, &counterparty).map_err(|e| e.to_string())
+}
+
+/// Creates a counterparty by hand; `category` is its default category.
+#[tauri::command]
+pub async fn create_counterparty(
+    name: String,
+    category: String,
+    state: State<'_, AppState>,
+) -> Result<Counterparty, String> {
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    counterparties::create(&db, &name, &category).map_err(|e| e.to_string())
+}
+
+/// Merges `source_id` into `target_id`: its invoices and spellings move over
+/// and the source record is deleted.
+#[tauri::command]
+pub async fn merge_counterparties(
+    target_id: String,
+    source_id: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    counterparties::merge(&db, &target_id, &source_id).map_err(|e| e.to_string())
+}
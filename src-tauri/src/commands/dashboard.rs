@@ -1,4 +1,5 @@
 use crate::models::DashboardStats;
+use crate::utils::Money;
 use crate::services::state::AppState;
 use chrono::{Datelike, Local, NaiveDate};
 use tauri::State;
@@ -12,32 +13,54 @@ pub async fn get_dashboard_stats(
     let current_year_month = year_month.unwrap_or_else(|| format!("{}-{:02}", now.year(), now.month()));
     let current_year = &current_year_month[0..4];
 
+    let settings = state
+        .settings
+        .lock()
+        .map_err(|_| "Settings lock".to_string())?
+        .clone();
+    let base_currency = settings.base_currency;
+    let basis = settings.reporting_basis;
     let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
 
     let revenue_month = db
-        .get_monthly_sum("revenue", &current_year_month)
+        .get_monthly_sum("revenue", &current_year_month, &base_currency, &basis)
         .map_err(|e| e.to_string())?;
     let payable_month = db
-        .get_monthly_sum("payable", &current_year_month)
+        .get_monthly_sum("payable", &current_year_month, &base_currency, &basis)
         .map_err(|e| e.to_string())?;
     let revenue_year = db
-        .get_yearly_sum("revenue", current_year)
+        .get_yearly_sum("revenue", current_year, &base_currency, &basis)
         .map_err(|e| e.to_string())?;
     let payable_year = db
-        .get_yearly_sum("payable", current_year)
+        .get_yearly_sum("payable", current_year, &base_currency, &basis)
         .map_err(|e| e.to_string())?;
-    let open_payables = db.get_open_payables_total().map_err(|e| e.to_string())?;
+    let open_payables = db
+        .get_open_payables_total(&base_currency)
+        .map_err(|e| e.to_string())?;
+    let unconverted_invoices = db
+        .count_unconverted_invoices(&base_currency)
+        .map_err(|e| e.to_string())?;
+    let undated_payments = if basis == "cash" {
+        db.count_paid_without_payment_date().map_err(|e| e.to_string())?
+    } else {
+        0
+    };
 
     let recent_revenue = db
-        .get_recent_invoices("revenue", 5)
+        .get_recent_invoices("revenue", 5, &basis)
         .map_err(|e| e.to_string())?;
     let recent_payables = db
-        .get_recent_invoices("payable", 5)
+        .get_recent_invoices("payable", 5, &basis)
         .map_err(|e| e.to_string())?;
 
-    let (chart_months, chart_revenue, chart_payables, chart_profit) = build_chart_series(&*db, &current_year_month)?;
+    let (chart_months, chart_revenue, chart_payables, chart_profit) = build_chart_series(&*db, &current_year_month, &base_currency, &basis)?;
 
     Ok(DashboardStats {
+        base_currency,
+        reporting_basis: basis,
+        unconverted_invoices,
+        undated_payments,
+        pdf_ocr_available: state.pdf_ocr_available,
         revenue_month,
         revenue_year,
         payable_month,
@@ -57,7 +80,9 @@ pub async fn get_dashboard_stats(
 fn build_chart_series(
     db: &crate::db::Database,
     current_year_month: &str,
-) -> Result<(Vec<String>, Vec<f64>, Vec<f64>, Vec<f64>), String> {
+    base_currency: &str,
+    basis: &str,
+) -> Result<(Vec<String>, Vec<Money>, Vec<Money>, Vec<Money>), String> {
     let base_date = NaiveDate::parse_from_str(&format!("{}-01", current_year_month), "%Y-%m-%d")
         .map_err(|e| e.to_string())?;
 
@@ -72,8 +97,12 @@ fn build_chart_series(
             .and_then(|d| d.checked_sub_months(chrono::Months::new(offset as u32)))
             .ok_or_else(|| "Invalid date".to_string())?;
         let ym = format!("{}-{:02}", date.year(), date.month());
-        let rev = db.get_monthly_sum("revenue", &ym).map_err(|e| e.to_string())?;
-        let pay = db.get_monthly_sum("payable", &ym).map_err(|e| e.to_string())?;
+        let rev = db
+            .get_monthly_sum("revenue", &ym, base_currency, basis)
+            .map_err(|e| e.to_string())?;
+        let pay = db
+            .get_monthly_sum("payable", &ym, base_currency, basis)
+            .map_err(|e| e.to_string())?;
         months.push(ym);
         revenue.push(rev);
         payables.push(pay);
@@ -0,0 +1,101 @@
+use crate::models::{DunningOverview, DunningStep, DunningTemplate};
+use crate::services::dunning;
+use crate::services::state::AppState;
+use chrono::{Local, NaiveDate};
+use tauri::State;
+
+#[tauri::command]
+pub async fn get_dunning_overview(state: State<'_, AppState>) -> Result<DunningOverview, String> {
+    let settings = state
+        .settings
+        .lock()
+        .map_err(|_| "Settings lock".to_string())?
+        .clone();
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    dunning::overview(&db, &settings, Local::now().date_naive()).map_err(|e| e.to_string())
+}
+
+/// Writes the next reminder letter for an overdue revenue invoice as PDF and
+/// logs the step. Without a path a save dialog is shown; a cancelled dialog
+/// returns `None`.
+#[tauri::command]
+pub async fn create_dunning_letter(
+    invoice_id: String,
+    path: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Option<DunningStep>, String> {
+    let settings = state
+        .settings
+        .lock()
+        .map_err(|_| "Settings lock".to_string())?
+        .clone();
+    if settings.company_name.as_deref().unwrap_or_default().trim().is_empty() {
+        return Err("Set the company name and address in the settings first".to_string());
+    }
+    let today = Local::now().date_naive();
+
+    let path = match path {
+        Some(path) => std::path::PathBuf::from(path),
+        None => {
+            let file_name = {
+                let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+                dunning::letter_file_name(&db, &settings, &invoice_id, today).map_err(|e| e.to_string())?
+            };
+            match rfd::FileDialog::new()
+                .add_filter("PDF", &["pdf"])
+                .set_file_name(file_name)
+                .save_file()
+            {
+                Some(path) => path,
+                None => return Ok(None),
+            }
+        }
+    };
+
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    dunning::create_letter(&db, &settings, &invoice_id, today, &path)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+/// Removes a step from the log, e.g. a letter that was never sent. The PDF
+/// is left in place.
+#[tauri::command]
+pub async fn delete_dunning_step(step_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    db.delete_dunning_step(&step_id).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_dunning_templates(state: State<'_, AppState>) -> Result<Vec<DunningTemplate>, String> {
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    dunning::templates(&db).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn save_dunning_template(template: DunningTemplate, state: State<'_, AppState>) -> Result<(), String> {
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    dunning::save_template(&db, &template).map_err(|e| e.to_string())
+}
+
+/// Goes back to the built-in text for a level.
+#[tauri::command]
+pub async fn reset_dunning_template(level: u8, state: State<'_, AppState>) -> Result<(), String> {
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    db.delete_dunning_template(level).map_err(|e| e.to_string())
+}
+
+/// Adds or corrects a base interest rate (Basiszinssatz) in percent, valid
+/// from `valid_from` (YYYY-MM-DD).
+#[tauri::command]
+pub async fn set_base_interest_rate(valid_from: String, rate: f64, state: State<'_, AppState>) -> Result<(), String> {
+    let date = NaiveDate::parse_from_str(valid_from.trim(), "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date: {}", valid_from))?;
+    if !rate.is_finite() || rate.abs() > 20.0 {
+        return Err(format!("Implausible base rate: {}", rate));
+    }
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    db.set_base_interest_rate(&date.format("%Y-%m-%d").to_string(), rate)
+        .map_err(|e| e.to_string())
+}
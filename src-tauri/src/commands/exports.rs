@@ -0,0 +1,151 @@
+use crate::models::{ExportColumn, ExportSummary};
+use crate::services::bookkeeping::{load_invoices, InvoiceFilter};
+use crate::services::datev::{self, BatchHeader, ExportInvoice};
+use crate::services::export::{self, ExportFormat, Locale};
+use crate::services::state::AppState;
+use chrono::NaiveDate;
+use serde::Deserialize;
+use tauri::State;
+
+#[derive(Deserialize)]
+pub struct InvoiceExportPayload {
+    pub format: String,
+    pub category: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub status: Option<String>,
+    #[serde(default)]
+    pub columns: Vec<String>,
+    pub locale: Option<String>,
+    pub path: Option<String>,
+}
+
+#[tauri::command]
+pub async fn get_export_columns() -> Result<Vec<ExportColumn>, String> {
+    Ok(export::columns())
+}
+
+/// Writes processed invoices (overrides applied) as CSV, XLSX or JSON.
+/// Without a path a save dialog is shown; a cancelled dialog returns `None`.
+#[tauri::command]
+pub async fn export_invoices(
+    payload: InvoiceExportPayload,
+    state: State<'_, AppState>,
+) -> Result<Option<ExportSummary>, String> {
+    let format = ExportFormat::parse(&payload.format).map_err(|e| e.to_string())?;
+    let columns = export::resolve_columns(&payload.columns).map_err(|e| e.to_string())?;
+    let locale = Locale::parse(payload.locale.as_deref());
+    let non_empty = |value: Option<String>| value.filter(|value| !value.trim().is_empty());
+    let filter = InvoiceFilter {
+        category: non_empty(payload.category),
+        from: non_empty(payload.from).as_deref().map(parse_date).transpose()?,
+        to: non_empty(payload.to).as_deref().map(parse_date).transpose()?,
+        status: non_empty(payload.status),
+        by_payment_date: false,
+    };
+
+    let path = match payload.path {
+        Some(path) => std::path::PathBuf::from(path),
+        None => match rfd::FileDialog::new()
+            .add_filter(format.extension().to_uppercase(), &[format.extension()])
+            .set_file_name(format!("invoices.{}", format.extension()))
+            .save_file()
+        {
+            Some(path) => path,
+            None => return Ok(None),
+        },
+    };
+
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    let (invoices, skipped_undated) = load_invoices(&db, &filter).map_err(|e| e.to_string())?;
+    export::write(&path, format, locale, &columns, &invoices).map_err(|e| e.to_string())?;
+    Ok(Some(ExportSummary {
+        path: path.to_string_lossy().to_string(),
+        invoices: invoices.len(),
+        records: invoices.len(),
+        skipped_undated,
+    }))
+}
+
+/// Writes the processed invoices of `from`..=`to` (overrides applied) as a
+/// DATEV EXTF Buchungsstapel. Without a path a save dialog is shown; a
+/// cancelled dialog returns `None`.
+#[tauri::command]
+pub async fn export_datev(
+    from: String,
+    to: String,
+    path: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Option<ExportSummary>, String> {
+    let settings = state
+        .settings
+        .lock()
+        .map_err(|_| "Settings lock".to_string())?
+        .clone();
+    let from = parse_date(&from)?;
+    let to = parse_date(&to)?;
+    let number = |value: Option<&str>, name: &str| -> Result<u32, String> {
+        value
+            .and_then(|value| value.trim().parse().ok())
+            .ok_or_else(|| format!("Set the DATEV {} number in the settings first", name))
+    };
+    let header = BatchHeader {
+        consultant_number: number(settings.datev_consultant_number.as_deref(), "consultant")?,
+        client_number: number(settings.datev_client_number.as_deref(), "client")?,
+        chart: settings.chart_of_accounts.clone(),
+        from,
+        to,
+    };
+    datev::validate_header(&header).map_err(|e| e.to_string())?;
+
+    let path = match path {
+        Some(path) => std::path::PathBuf::from(path),
+        None => match rfd::FileDialog::new()
+            .add_filter("DATEV Buchungsstapel", &["csv"])
+            .set_file_name(format!("EXTF_Buchungsstapel_{}_{}.csv", from, to))
+            .save_file()
+        {
+            Some(path) => path,
+            None => return Ok(None),
+        },
+    };
+
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    let filter = InvoiceFilter {
+        from: Some(from),
+        to: Some(to),
+        ..InvoiceFilter::default()
+    };
+    let (invoices, skipped_undated) = load_invoices(&db, &filter).map_err(|e| e.to_string())?;
+    let counterparties = db.get_counterparties(None).map_err(|e| e.to_string())?;
+    let mut entries = Vec::with_capacity(invoices.len());
+    for invoice in invoices {
+        let vat_breakdown = db.get_vat_breakdown(&invoice.id).map_err(|e| e.to_string())?;
+        let personal_account = invoice.counterparty_id.as_deref().and_then(|id| {
+            counterparties
+                .iter()
+                .find(|counterparty| counterparty.id == id)
+                .and_then(|counterparty| counterparty.personal_account.clone())
+        });
+        entries.push(ExportInvoice {
+            invoice,
+            vat_breakdown,
+            personal_account,
+        });
+    }
+    let accounts = db
+        .get_accounts(&settings.chart_of_accounts)
+        .map_err(|e| e.to_string())?;
+
+    let records = datev::write_batch(&path, &header, &entries, &accounts).map_err(|e| e.to_string())?;
+    Ok(Some(ExportSummary {
+        path: path.to_string_lossy().to_string(),
+        invoices: entries.len(),
+        records,
+        skipped_undated,
+    }))
+}
+
+fn parse_date(raw: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(raw.trim(), "%Y-%m-%d").map_err(|_| format!("Invalid date: {}", raw))
+}
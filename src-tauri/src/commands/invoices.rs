@@ -1,6 +1,18 @@
-use crate::models::{InvoiceDetail, InvoiceOverride, InvoiceSummary};
+use crate::models::{InvoiceDetail, InvoiceOverride, InvoiceSummary, ReviewQueueItem};
+use crate::db::Database;
+use crate::services::accounts;
+use crate::services::bookkeeping::apply_overrides;
+use crate::services::counterparties;
+use crate::services::einvoice::EInvoiceParser;
+use crate::services::evidence::REVIEW_THRESHOLD;
+use crate::services::exchange_rates;
+use crate::services::payments;
 use crate::services::processor::process_invoice;
+use crate::services::sepa;
 use crate::services::state::AppState;
+use crate::services::watcher::is_xml;
+use crate::utils::{normalize_date, Money};
+use chrono::NaiveDate;
 use serde::Deserialize;
 use tauri::State;
 
@@ -11,6 +23,9 @@ pub struct UpdateInvoicePayload {
     pub value: String,
 }
 
+/// Overrides that change the amount in the base currency.
+const CONVERSION_FIELDS: [&str; 3] = ["total_amount", "currency", "invoice_date"];
+
 #[tauri::command]
 pub async fn get_invoices(category: String, state: State<'_, AppState>) -> Result<Vec<InvoiceSummary>, String> {
     let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
@@ -35,30 +50,123 @@ pub async fn get_invoice_detail(invoice_id: String, state: State<'_, AppState>)
         .ok_or_else(|| "Invoice not found".to_string())?;
     let overrides = db.get_overrides(&invoice_id).map_err(|e| e.to_string())?;
     apply_overrides(&mut invoice, &overrides);
-    Ok(InvoiceDetail { invoice, overrides })
+    let pages = db.get_invoice_pages(&invoice_id).map_err(|e| e.to_string())?;
+    let evidence = db.get_field_evidence(&invoice_id).map_err(|e| e.to_string())?;
+    let line_items = db.get_line_items(&invoice_id).map_err(|e| e.to_string())?;
+    let vat_breakdown = db.get_vat_breakdown(&invoice_id).map_err(|e| e.to_string())?;
+    let counterparty = match invoice.counterparty_id.as_deref() {
+        Some(id) => db
+            .get_counterparties(Some(id))
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .next(),
+        None => None,
+    };
+    let extracted_payment = db.get_payment_details(&invoice_id).map_err(|e| e.to_string())?;
+    let payment = payments::payment_details(extracted_payment.as_ref(), &overrides, counterparty.as_ref());
+    let payment_orders = db.get_payment_orders(&invoice_id).map_err(|e| e.to_string())?;
+    let dunning_steps = db.get_dunning_steps(Some(&invoice_id)).map_err(|e| e.to_string())?;
+    Ok(InvoiceDetail {
+        invoice,
+        overrides,
+        pages,
+        evidence,
+        line_items,
+        vat_breakdown,
+        counterparty,
+        payment,
+        payment_orders,
+        dunning_steps,
+    })
+}
+
+#[tauri::command]
+pub async fn get_review_queue(
+    category: Option<String>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<ReviewQueueItem>, String> {
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    db.get_review_queue(category.as_deref(), REVIEW_THRESHOLD, limit.unwrap_or(100))
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn update_invoice_field(payload: UpdateInvoicePayload, state: State<'_, AppState>) -> Result<(), String> {
+    let value = match payload.field_name.as_str() {
+        "total_amount" | "tax_amount" | "net_amount" => Money::parse(&payload.value)
+            .map_err(|e| e.to_string())?
+            .to_string(),
+        "account_number" => payload.value.trim().to_string(),
+        "currency" => Some(payload.value.trim().to_uppercase())
+            .filter(|code| code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic()))
+            .ok_or_else(|| format!("Invalid currency code: {}", payload.value))?,
+        "iban" => sepa::normalize_iban(&payload.value).ok_or_else(|| format!("Invalid IBAN: {}", payload.value))?,
+        "bic" => sepa::normalize_bic(&payload.value).ok_or_else(|| format!("Invalid BIC: {}", payload.value))?,
+        "discount_percent" => match payload.value.trim().replace(',', ".").parse::<f64>() {
+            Ok(percent) if percent > 0.0 && percent < 100.0 => percent.to_string(),
+            _ => return Err(format!("Invalid Skonto percent: {}", payload.value)),
+        },
+        "discount_due_date" => normalize_date(Some(payload.value.clone()))
+            .filter(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok())
+            .ok_or_else(|| format!("Invalid date: {}", payload.value))?,
+        _ => payload.value.clone(),
+    };
+    let settings = state
+        .settings
+        .lock()
+        .map_err(|_| "Settings lock".to_string())?
+        .clone();
     let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
-    db.set_override(&payload.invoice_id, &payload.field_name, &payload.value)
+    if payload.field_name == "account_number" {
+        accounts::validate_account(&db, &settings.chart_of_accounts, &value).map_err(|e| e.to_string())?;
+    }
+    db.set_override(&payload.invoice_id, &payload.field_name, &value)
         .map_err(|e| e.to_string())?;
+    if payload.field_name == "counterparty_name" {
+        counterparties::relink_invoice(&db, &payload.invoice_id).map_err(|e| e.to_string())?;
+    }
+    if CONVERSION_FIELDS.contains(&payload.field_name.as_str()) {
+        exchange_rates::refresh_base_amount(&db, &payload.invoice_id, &settings.base_currency)
+            .map_err(|e| e.to_string())?;
+    }
+    reassign_account(&db, &payload.invoice_id, &settings.chart_of_accounts)?;
     Ok(())
 }
 
 #[tauri::command]
 pub async fn clear_overrides(invoice_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    let settings = state
+        .settings
+        .lock()
+        .map_err(|_| "Settings lock".to_string())?
+        .clone();
     let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
     db.clear_all_overrides(&invoice_id)
         .map_err(|e| e.to_string())?;
+    counterparties::relink_invoice(&db, &invoice_id).map_err(|e| e.to_string())?;
+    exchange_rates::refresh_base_amount(&db, &invoice_id, &settings.base_currency).map_err(|e| e.to_string())?;
+    reassign_account(&db, &invoice_id, &settings.chart_of_accounts)?;
     Ok(())
 }
 
 #[tauri::command]
 pub async fn clear_override(invoice_id: String, field_name: String, state: State<'_, AppState>) -> Result<(), String> {
+    let settings = state
+        .settings
+        .lock()
+        .map_err(|_| "Settings lock".to_string())?
+        .clone();
     let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
     db.clear_override(&invoice_id, &field_name)
         .map_err(|e| e.to_string())?;
+    if field_name == "counterparty_name" {
+        counterparties::relink_invoice(&db, &invoice_id).map_err(|e| e.to_string())?;
+    }
+    if CONVERSION_FIELDS.contains(&field_name.as_str()) {
+        exchange_rates::refresh_base_amount(&db, &invoice_id, &settings.base_currency).map_err(|e| e.to_string())?;
+    }
+    reassign_account(&db, &invoice_id, &settings.chart_of_accounts)?;
     Ok(())
 }
 
@@ -89,26 +197,30 @@ pub async fn reprocess_invoice(invoice_id: String, state: State<'_, AppState>) -
 
 #[tauri::command]
 pub async fn open_invoice_file(path: String) -> Result<(), String> {
+    let file = std::path::Path::new(&path);
+    if is_xml(file) {
+        let rendered = EInvoiceParser::render_html_file(file).map_err(|e| e.to_string())?;
+        open::that(rendered).map_err(|e| e.to_string())?;
+        return Ok(());
+    }
     open::that(path).map_err(|e| e.to_string())?;
     Ok(())
 }
 
-fn apply_overrides(invoice: &mut crate::models::Invoice, overrides: &[InvoiceOverride]) {
-    for override_entry in overrides {
-        match override_entry.field_name.as_str() {
-            "invoice_number" => invoice.invoice_number = Some(override_entry.override_value.clone()),
-            "invoice_date" => invoice.invoice_date = Some(override_entry.override_value.clone()),
-            "due_date" => invoice.due_date = Some(override_entry.override_value.clone()),
-            "counterparty_name" => invoice.counterparty_name = Some(override_entry.override_value.clone()),
-            "total_amount" => invoice.total_amount = override_entry.override_value.clone(),
-            "currency" => invoice.currency = override_entry.override_value.clone(),
-            "tax_amount" => invoice.tax_amount = Some(override_entry.override_value.clone()),
-            "net_amount" => invoice.net_amount = Some(override_entry.override_value.clone()),
-            "status" => invoice.status = override_entry.override_value.clone(),
-            "paid_at" => invoice.paid_at = Some(override_entry.override_value.clone()),
-            _ => {}
-        }
-    }
+/// Account rules can depend on the counterparty, so the account is
+/// re-assigned after any override change.
+fn reassign_account(db: &Database, invoice_id: &str, chart: &str) -> Result<(), String> {
+    let mut invoice = db
+        .get_invoice_by_id(invoice_id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Invoice not found".to_string())?;
+    accounts::assign(db, &mut invoice, chart).map_err(|e| e.to_string())?;
+    db.set_invoice_account(
+        invoice_id,
+        invoice.account_number.as_deref(),
+        invoice.account_source.as_deref(),
+    )
+    .map_err(|e| e.to_string())
 }
 
 fn apply_overrides_to_summary(summary: &mut InvoiceSummary, overrides: &[InvoiceOverride]) {
@@ -116,7 +228,11 @@ fn apply_overrides_to_summary(summary: &mut InvoiceSummary, overrides: &[Invoice
         match override_entry.field_name.as_str() {
             "invoice_date" => summary.invoice_date = Some(override_entry.override_value.clone()),
             "counterparty_name" => summary.counterparty_name = Some(override_entry.override_value.clone()),
-            "total_amount" => summary.total_amount = override_entry.override_value.clone(),
+            "total_amount" => {
+                if let Ok(amount) = Money::parse(&override_entry.override_value) {
+                    summary.total_amount = amount;
+                }
+            }
             "status" => summary.status = override_entry.override_value.clone(),
             _ => {}
         }
@@ -0,0 +1,35 @@
+use crate::models::{Invoice, InvoiceDraft};
+use crate::services::bookkeeping::parse_date;
+use crate::services::invoicing;
+use crate::services::state::AppState;
+use chrono::Local;
+use tauri::State;
+
+/// The number an invoice dated `invoice_date` (default today) would get.
+#[tauri::command]
+pub async fn get_next_invoice_number(
+    invoice_date: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    let settings = state
+        .settings
+        .lock()
+        .map_err(|_| "Settings lock".to_string())?
+        .clone();
+    let date = parse_date(invoice_date.as_deref()).unwrap_or_else(|| Local::now().date_naive());
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    invoicing::preview_number(&db, &settings, date).map_err(|e| e.to_string())
+}
+
+/// Numbers, writes and registers an outgoing invoice. The DB stays locked
+/// until it is stored, so the folder watcher finds it already processed.
+#[tauri::command]
+pub async fn create_invoice(draft: InvoiceDraft, state: State<'_, AppState>) -> Result<Invoice, String> {
+    let settings = state
+        .settings
+        .lock()
+        .map_err(|_| "Settings lock".to_string())?
+        .clone();
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    invoicing::create(&db, &settings, &draft).map_err(|e| e.to_string())
+}
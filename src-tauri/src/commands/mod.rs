@@ -1,3 +1,11 @@
+pub mod accounts;
+pub mod bank;
+pub mod counterparties;
 pub mod dashboard;
+pub mod dunning;
+pub mod exports;
 pub mod invoices;
+pub mod invoicing;
+pub mod payments;
+pub mod reports;
 pub mod settings;
@@ -0,0 +1,67 @@
+use crate::models::SepaTransferSummary;
+use crate::services::payments;
+use crate::services::sepa::Debtor;
+use crate::services::state::AppState;
+use chrono::{Local, NaiveDate};
+use serde::Deserialize;
+use tauri::State;
+
+#[derive(Deserialize)]
+pub struct SepaTransferPayload {
+    pub invoice_ids: Vec<String>,
+    pub execution_date: Option<String>,
+    pub path: Option<String>,
+}
+
+/// Writes a SEPA credit transfer file (pain.001.001.09) for the selected
+/// open payables and marks them as scheduled. The execution date defaults
+/// to today. Without a path a save dialog is shown; a cancelled dialog
+/// returns `None`.
+#[tauri::command]
+pub async fn create_sepa_transfer(
+    payload: SepaTransferPayload,
+    state: State<'_, AppState>,
+) -> Result<Option<SepaTransferSummary>, String> {
+    let settings = state
+        .settings
+        .lock()
+        .map_err(|_| "Settings lock".to_string())?
+        .clone();
+    let non_empty = |value: Option<String>| value.filter(|value| !value.trim().is_empty());
+    let (Some(name), Some(iban)) = (non_empty(settings.company_name), non_empty(settings.company_iban)) else {
+        return Err("Set the company name and IBAN in the settings first".to_string());
+    };
+    let debtor = Debtor {
+        name,
+        iban,
+        bic: non_empty(settings.company_bic),
+    };
+    let execution_date = match non_empty(payload.execution_date) {
+        Some(raw) => NaiveDate::parse_from_str(raw.trim(), "%Y-%m-%d").map_err(|_| format!("Invalid date: {}", raw))?,
+        None => Local::now().date_naive(),
+    };
+
+    let path = match payload.path {
+        Some(path) => std::path::PathBuf::from(path),
+        None => match rfd::FileDialog::new()
+            .add_filter("SEPA credit transfer", &["xml"])
+            .set_file_name(format!("pain.001_{}.xml", execution_date))
+            .save_file()
+        {
+            Some(path) => path,
+            None => return Ok(None),
+        },
+    };
+
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    payments::schedule_transfers(&db, &payload.invoice_ids, execution_date, &debtor, &path)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+/// GiroCode for paying an open payable today, as PNG bytes.
+#[tauri::command]
+pub async fn get_girocode(invoice_id: String, state: State<'_, AppState>) -> Result<Vec<u8>, String> {
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    payments::girocode(&db, &invoice_id, Local::now().date_naive()).map_err(|e| e.to_string())
+}
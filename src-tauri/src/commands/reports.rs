@@ -0,0 +1,64 @@
+use crate::models::{AgingReport, EuerReport, VatReturn};
+use crate::services::aging;
+use crate::services::euer;
+use crate::services::state::AppState;
+use crate::services::vat_return;
+use chrono::{Local, NaiveDate};
+use tauri::State;
+
+/// UStVA figures for `period` (`2024-03` or `2024-Q1`) on the configured
+/// cash or accrual basis.
+#[tauri::command]
+pub async fn get_vat_return(period: String, state: State<'_, AppState>) -> Result<VatReturn, String> {
+    let settings = state
+        .settings
+        .lock()
+        .map_err(|_| "Settings lock".to_string())?
+        .clone();
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    vat_return::compute(&db, &period, &settings.vat_accounting, &settings.chart_of_accounts)
+        .map_err(|e| e.to_string())
+}
+
+/// Aging of open receivables (`revenue`) or payables as of `as_of`
+/// (`YYYY-MM-DD`, today when missing).
+#[tauri::command]
+pub async fn get_aging_report(
+    category: String,
+    as_of: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<AgingReport, String> {
+    let as_of = match as_of.filter(|value| !value.trim().is_empty()) {
+        Some(value) => NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+            .map_err(|_| format!("Invalid date: {}", value))?,
+        None => Local::now().date_naive(),
+    };
+    let settings = state
+        .settings
+        .lock()
+        .map_err(|_| "Settings lock".to_string())?
+        .clone();
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    aging::compute(&db, &category, as_of, &settings.base_currency).map_err(|e| e.to_string())
+}
+
+/// Cash-basis EÜR of `year`, net of VAT.
+#[tauri::command]
+pub async fn get_euer_report(year: i32, state: State<'_, AppState>) -> Result<EuerReport, String> {
+    let settings = state
+        .settings
+        .lock()
+        .map_err(|_| "Settings lock".to_string())?
+        .clone();
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    euer::compute(&db, year, &settings.chart_of_accounts).map_err(|e| e.to_string())
+}
+
+/// Opens the printable HTML version of the EÜR in the default browser.
+#[tauri::command]
+pub async fn open_euer_report(year: i32, state: State<'_, AppState>) -> Result<(), String> {
+    let report = get_euer_report(year, state).await?;
+    let rendered = euer::render_html_file(&report).map_err(|e| e.to_string())?;
+    open::that(rendered).map_err(|e| e.to_string())?;
+    Ok(())
+}
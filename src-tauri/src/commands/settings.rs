@@ -1,6 +1,12 @@
 use crate::models::Settings;
+use crate::services::accounts;
+use crate::services::counterparties;
 use crate::services::crypto::CryptoService;
+use crate::services::exchange_rates;
+use crate::services::invoicing;
+use crate::services::sepa;
 use crate::services::state::AppState;
+use crate::utils::Money;
 use serde::Deserialize;
 use tauri::{AppHandle, State};
 
@@ -10,6 +16,29 @@ pub struct SettingsPayload {
     pub payable_folder: Option<String>,
     pub openai_api_key: Option<String>,
     pub ocr_language: Option<String>,
+    pub ocr_dpi: Option<u32>,
+    pub base_currency: Option<String>,
+    pub chart_of_accounts: Option<String>,
+    pub datev_consultant_number: Option<String>,
+    pub datev_client_number: Option<String>,
+    pub vat_accounting: Option<String>,
+    pub reporting_basis: Option<String>,
+    pub extraction_backend: Option<String>,
+    pub extraction_base_url: Option<String>,
+    pub extraction_model: Option<String>,
+    pub company_name: Option<String>,
+    pub company_iban: Option<String>,
+    pub company_bic: Option<String>,
+    pub company_address: Option<String>,
+    pub dunning_interval_days: Option<u32>,
+    pub dunning_fee: Option<String>,
+    pub company_vat_id: Option<String>,
+    pub company_tax_number: Option<String>,
+    pub company_email: Option<String>,
+    pub company_phone: Option<String>,
+    pub invoice_number_format: Option<String>,
+    pub invoice_number_start: Option<u32>,
+    pub invoice_payment_days: Option<u32>,
 }
 
 #[tauri::command]
@@ -23,11 +52,97 @@ pub async fn get_settings(state: State<'_, AppState>) -> Result<Settings, String
         .get_setting("ocr_language")
         .map_err(|e| e.to_string())?
         .unwrap_or_else(|| "deu".to_string());
+    let ocr_dpi = db
+        .get_setting("ocr_dpi")
+        .map_err(|e| e.to_string())?
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(300);
+    let base_currency = db
+        .get_setting("base_currency")
+        .map_err(|e| e.to_string())?
+        .unwrap_or_else(|| "EUR".to_string());
+    let chart_of_accounts = db
+        .get_setting("chart_of_accounts")
+        .map_err(|e| e.to_string())?
+        .unwrap_or_else(|| "SKR03".to_string());
+    let datev_consultant_number = db
+        .get_setting("datev_consultant_number")
+        .map_err(|e| e.to_string())?;
+    let datev_client_number = db.get_setting("datev_client_number").map_err(|e| e.to_string())?;
+    let vat_accounting = db
+        .get_setting("vat_accounting")
+        .map_err(|e| e.to_string())?
+        .unwrap_or_else(|| "accrual".to_string());
+    let reporting_basis = db
+        .get_setting("reporting_basis")
+        .map_err(|e| e.to_string())?
+        .unwrap_or_else(|| "accrual".to_string());
+    let extraction_backend = db
+        .get_setting("extraction_backend")
+        .map_err(|e| e.to_string())?
+        .unwrap_or_else(|| "openai".to_string());
+    let extraction_base_url = db.get_setting("extraction_base_url").map_err(|e| e.to_string())?;
+    let extraction_model = db.get_setting("extraction_model").map_err(|e| e.to_string())?;
+    let company_name = db.get_setting("company_name").map_err(|e| e.to_string())?;
+    let company_iban = db.get_setting("company_iban").map_err(|e| e.to_string())?;
+    let company_bic = db.get_setting("company_bic").map_err(|e| e.to_string())?;
+    let company_address = db.get_setting("company_address").map_err(|e| e.to_string())?;
+    let dunning_interval_days = db
+        .get_setting("dunning_interval_days")
+        .map_err(|e| e.to_string())?
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(14);
+    let dunning_fee = db
+        .get_setting("dunning_fee")
+        .map_err(|e| e.to_string())?
+        .and_then(|value| Money::parse(&value).ok())
+        .unwrap_or(Money::from_f64(2.5));
+    let company_vat_id = db.get_setting("company_vat_id").map_err(|e| e.to_string())?;
+    let company_tax_number = db.get_setting("company_tax_number").map_err(|e| e.to_string())?;
+    let company_email = db.get_setting("company_email").map_err(|e| e.to_string())?;
+    let company_phone = db.get_setting("company_phone").map_err(|e| e.to_string())?;
+    let invoice_number_format = db
+        .get_setting("invoice_number_format")
+        .map_err(|e| e.to_string())?
+        .unwrap_or_else(|| invoicing::DEFAULT_NUMBER_FORMAT.to_string());
+    let invoice_number_start = db
+        .get_setting("invoice_number_start")
+        .map_err(|e| e.to_string())?
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(1);
+    let invoice_payment_days = db
+        .get_setting("invoice_payment_days")
+        .map_err(|e| e.to_string())?
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(14);
     Ok(Settings {
         revenue_folder,
         payable_folder,
         openai_api_key,
         ocr_language,
+        ocr_dpi,
+        base_currency,
+        chart_of_accounts,
+        datev_consultant_number,
+        datev_client_number,
+        vat_accounting,
+        reporting_basis,
+        extraction_backend,
+        extraction_base_url,
+        extraction_model,
+        company_name,
+        company_iban,
+        company_bic,
+        company_address,
+        dunning_interval_days,
+        dunning_fee,
+        company_vat_id,
+        company_tax_number,
+        company_email,
+        company_phone,
+        invoice_number_format,
+        invoice_number_start,
+        invoice_payment_days,
     })
 }
 
@@ -49,6 +164,119 @@ pub async fn save_settings(
         if let Some(value) = payload.ocr_language.clone() {
             db.set_setting("ocr_language", &value).map_err(|e| e.to_string())?;
         }
+        if let Some(value) = payload.ocr_dpi {
+            db.set_setting("ocr_dpi", &value.clamp(150, 600).to_string())
+                .map_err(|e| e.to_string())?;
+        }
+        if let Some(value) = payload.base_currency.clone() {
+            let value = value.trim().to_uppercase();
+            if !value.is_empty() {
+                db.set_setting("base_currency", &value).map_err(|e| e.to_string())?;
+                exchange_rates::refresh_base_amounts(&db, &value).map_err(|e| e.to_string())?;
+            }
+        }
+        if let Some(value) = payload.chart_of_accounts.clone() {
+            let value = value.trim().to_uppercase();
+            if !value.is_empty() {
+                db.set_setting("chart_of_accounts", &value).map_err(|e| e.to_string())?;
+                accounts::reassign_all(&db, &value).map_err(|e| e.to_string())?;
+            }
+        }
+        for (key, value) in [
+            ("datev_consultant_number", &payload.datev_consultant_number),
+            ("datev_client_number", &payload.datev_client_number),
+        ] {
+            if let Some(value) = value {
+                let value = value.trim();
+                if !value.chars().all(|c| c.is_ascii_digit()) {
+                    return Err(format!("{} must be numeric", key));
+                }
+                db.set_setting(key, value).map_err(|e| e.to_string())?;
+            }
+        }
+        if let Some(value) = payload.vat_accounting.clone() {
+            if value != "accrual" && value != "cash" {
+                return Err(format!("Unknown VAT accounting basis: {}", value));
+            }
+            db.set_setting("vat_accounting", &value).map_err(|e| e.to_string())?;
+        }
+        if let Some(value) = payload.reporting_basis.clone() {
+            if value != "accrual" && value != "cash" {
+                return Err(format!("Unknown reporting basis: {}", value));
+            }
+            db.set_setting("reporting_basis", &value).map_err(|e| e.to_string())?;
+        }
+        if let Some(value) = payload.extraction_backend.clone() {
+            db.set_setting("extraction_backend", &value).map_err(|e| e.to_string())?;
+        }
+        if let Some(value) = payload.extraction_base_url.clone() {
+            db.set_setting("extraction_base_url", &value).map_err(|e| e.to_string())?;
+        }
+        if let Some(value) = payload.extraction_model.clone() {
+            db.set_setting("extraction_model", &value).map_err(|e| e.to_string())?;
+        }
+        if let Some(value) = payload.company_name.clone() {
+            db.set_setting("company_name", value.trim()).map_err(|e| e.to_string())?;
+        }
+        if let Some(value) = payload.company_iban.clone() {
+            let value = match value.trim() {
+                "" => String::new(),
+                iban => sepa::normalize_iban(iban).ok_or_else(|| format!("Invalid IBAN: {}", iban))?,
+            };
+            db.set_setting("company_iban", &value).map_err(|e| e.to_string())?;
+        }
+        if let Some(value) = payload.company_bic.clone() {
+            let value = match value.trim() {
+                "" => String::new(),
+                bic => sepa::normalize_bic(bic).ok_or_else(|| format!("Invalid BIC: {}", bic))?,
+            };
+            db.set_setting("company_bic", &value).map_err(|e| e.to_string())?;
+        }
+        if let Some(value) = payload.company_address.clone() {
+            db.set_setting("company_address", value.trim()).map_err(|e| e.to_string())?;
+        }
+        if let Some(value) = payload.dunning_interval_days {
+            db.set_setting("dunning_interval_days", &value.clamp(1, 90).to_string())
+                .map_err(|e| e.to_string())?;
+        }
+        if let Some(value) = payload.dunning_fee.clone() {
+            let fee = Money::parse(value.trim().replace(',', ".").as_str())
+                .map_err(|_| format!("Invalid dunning fee: {}", value))?;
+            if fee < Money::ZERO {
+                return Err(format!("Invalid dunning fee: {}", value));
+            }
+            db.set_setting("dunning_fee", &fee.to_string()).map_err(|e| e.to_string())?;
+        }
+        if let Some(value) = payload.company_vat_id.clone() {
+            db.set_setting("company_vat_id", &counterparties::normalize_vat_id(&value))
+                .map_err(|e| e.to_string())?;
+        }
+        if let Some(value) = payload.company_tax_number.clone() {
+            db.set_setting("company_tax_number", value.trim()).map_err(|e| e.to_string())?;
+        }
+        if let Some(value) = payload.company_email.clone() {
+            let value = value.trim();
+            if !value.is_empty() && (!value.contains('@') || value.contains(char::is_whitespace)) {
+                return Err(format!("Invalid email address: {}", value));
+            }
+            db.set_setting("company_email", value).map_err(|e| e.to_string())?;
+        }
+        if let Some(value) = payload.company_phone.clone() {
+            db.set_setting("company_phone", value.trim()).map_err(|e| e.to_string())?;
+        }
+        if let Some(value) = payload.invoice_number_format.clone() {
+            let value = value.trim();
+            invoicing::check_number_format(value).map_err(|e| e.to_string())?;
+            db.set_setting("invoice_number_format", value).map_err(|e| e.to_string())?;
+        }
+        if let Some(value) = payload.invoice_number_start {
+            db.set_setting("invoice_number_start", &value.max(1).to_string())
+                .map_err(|e| e.to_string())?;
+        }
+        if let Some(value) = payload.invoice_payment_days {
+            db.set_setting("invoice_payment_days", &value.min(365).to_string())
+                .map_err(|e| e.to_string())?;
+        }
         if let Some(api_key) = payload.openai_api_key.clone() {
             if !api_key.trim().is_empty() {
                 let encrypted = CryptoService::encrypt_api_key(&api_key).map_err(|e| e.to_string())?;
@@ -82,6 +310,34 @@ pub async fn reprocess_all(state: State<'_, AppState>, app: AppHandle) -> Result
     state.enqueue_scan(&app).map_err(|e| e.to_string())
 }
 
+/// Imports an ECB reference-rate file (CSV or XML) and reconverts all
+/// invoices. Without a path a file dialog is shown.
+#[tauri::command]
+pub async fn import_exchange_rates(path: Option<String>, state: State<'_, AppState>) -> Result<usize, String> {
+    let path = match path {
+        Some(path) => std::path::PathBuf::from(path),
+        None => match rfd::FileDialog::new()
+            .add_filter("ECB reference rates", &["csv", "xml"])
+            .pick_file()
+        {
+            Some(path) => path,
+            None => return Ok(0),
+        },
+    };
+    let rates = exchange_rates::read_ecb_file(&path).map_err(|e| e.to_string())?;
+
+    let base_currency = state
+        .settings
+        .lock()
+        .map_err(|_| "Settings lock".to_string())?
+        .base_currency
+        .clone();
+    let db = state.db.lock().map_err(|_| "DB lock".to_string())?;
+    let imported = db.insert_exchange_rates(&rates, "ecb").map_err(|e| e.to_string())?;
+    exchange_rates::refresh_base_amounts(&db, &base_currency).map_err(|e| e.to_string())?;
+    Ok(imported)
+}
+
 #[tauri::command]
 pub async fn pick_folder() -> Result<Option<String>, String> {
     let selection = rfd::FileDialog::new()
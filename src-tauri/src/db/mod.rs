@@ -1,12 +1,43 @@
-use rusqlite::{params, Connection, OptionalExtension, Result as SqlResult};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqlResult, Transaction, TransactionBehavior};
+use std::ops::Deref;
 use std::path::PathBuf;
 
-use crate::models::{Invoice, InvoiceOverride, InvoiceSummary};
+use crate::models::{
+    Account, AccountRule, AccountTotal, BankMatch, BankTransaction, BaseInterestRate, Counterparty, DunningStep,
+    DunningTemplate, ExchangeRate, ExtractedLineItem, ExtractedVatEntry, FieldEvidence, Invoice, InvoiceLineItem,
+    InvoiceOverride, InvoicePage, InvoiceSummary, InvoiceVatEntry, IssuedInvoice, PaymentDetails, PaymentOrder,
+    ReviewQueueItem,
+};
+use crate::utils::Money;
 
 pub struct Database {
     conn: Connection,
 }
 
+/// A transaction of one method, or nothing when the caller already opened one
+/// with `Database::in_transaction`; committing is then left to the caller.
+struct Writes<'a> {
+    conn: &'a Connection,
+    tx: Option<Transaction<'a>>,
+}
+
+impl Writes<'_> {
+    fn commit(self) -> SqlResult<()> {
+        match self.tx {
+            Some(tx) => tx.commit(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Deref for Writes<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn
+    }
+}
+
 impl Database {
     pub fn new(db_path: PathBuf) -> SqlResult<Self> {
         let conn = Connection::open(db_path)?;
@@ -46,6 +77,104 @@ impl Database {
                     "/../migrations/003_create_processing_logs_table.sql"
                 )),
             ),
+            (
+                "004_create_invoice_pages.sql",
+                include_str!(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/../migrations/004_create_invoice_pages.sql"
+                )),
+            ),
+            (
+                "005_create_invoice_field_evidence.sql",
+                include_str!(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/../migrations/005_create_invoice_field_evidence.sql"
+                )),
+            ),
+            (
+                "006_create_invoice_line_items.sql",
+                include_str!(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/../migrations/006_create_invoice_line_items.sql"
+                )),
+            ),
+            (
+                "007_create_invoice_vat_breakdown.sql",
+                include_str!(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/../migrations/007_create_invoice_vat_breakdown.sql"
+                )),
+            ),
+            (
+                "008_store_amounts_in_minor_units.sql",
+                include_str!(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/../migrations/008_store_amounts_in_minor_units.sql"
+                )),
+            ),
+            (
+                "009_create_exchange_rates.sql",
+                include_str!(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/../migrations/009_create_exchange_rates.sql"
+                )),
+            ),
+            (
+                "010_create_counterparties.sql",
+                include_str!(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/../migrations/010_create_counterparties.sql"
+                )),
+            ),
+            (
+                "011_create_accounts.sql",
+                include_str!(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/../migrations/011_create_accounts.sql"
+                )),
+            ),
+            (
+                "012_add_datev_fields.sql",
+                include_str!(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/../migrations/012_add_datev_fields.sql"
+                )),
+            ),
+            (
+                "013_create_bank_transactions.sql",
+                include_str!(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/../migrations/013_create_bank_transactions.sql"
+                )),
+            ),
+            (
+                "014_create_payment_orders.sql",
+                include_str!(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/../migrations/014_create_payment_orders.sql"
+                )),
+            ),
+            (
+                "015_create_invoice_payment_details.sql",
+                include_str!(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/../migrations/015_create_invoice_payment_details.sql"
+                )),
+            ),
+            (
+                "016_create_dunning.sql",
+                include_str!(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/../migrations/016_create_dunning.sql"
+                )),
+            ),
+            (
+                "017_create_issued_invoices.sql",
+                include_str!(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/../migrations/017_create_issued_invoices.sql"
+                )),
+            ),
         ];
 
         for (name, sql) in migrations {
@@ -72,14 +201,66 @@ impl Database {
         Ok(())
     }
 
+    /// Runs `f` in one transaction, so that either all of its writes are
+    /// stored or none. Methods with a transaction of their own join it. The
+    /// write lock is taken up front, so what `f` reads cannot be changed by
+    /// another connection before it writes.
+    pub fn in_transaction<T, E: From<rusqlite::Error>>(&self, f: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+        let tx = Transaction::new_unchecked(&self.conn, TransactionBehavior::Immediate)?;
+        let value = f()?;
+        tx.commit()?;
+        Ok(value)
+    }
+
+    fn begin(&self) -> SqlResult<Writes<'_>> {
+        let tx = if self.conn.is_autocommit() {
+            Some(self.conn.unchecked_transaction()?)
+        } else {
+            None
+        };
+        Ok(Writes { conn: &self.conn, tx })
+    }
+
+    /// Updates an existing row in place. `INSERT OR REPLACE` would delete it
+    /// first and cascade to its bank matches, overrides and payment orders.
     pub fn upsert_invoice(&self, invoice: &Invoice) -> SqlResult<()> {
         self.conn.execute(
-            "INSERT OR REPLACE INTO invoices (
+            "INSERT INTO invoices (
                 id, category, file_path, file_hash, file_modified_at, ingestion_status,
                 ocr_text, extracted_json, confidence_score, invoice_number, invoice_date,
-                due_date, counterparty_name, total_amount, currency, tax_amount, net_amount,
-                status, paid_at, created_at, updated_at
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21)",
+                due_date, counterparty_name, total_amount_minor, currency, tax_amount_minor, net_amount_minor,
+                status, paid_at, created_at, updated_at, base_currency, exchange_rate, base_total_minor,
+                counterparty_id, account_number, account_source
+            ) VALUES (
+                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21,
+                ?22, ?23, ?24, ?25, ?26, ?27
+            )
+            ON CONFLICT(id) DO UPDATE SET
+                category = excluded.category,
+                file_path = excluded.file_path,
+                file_hash = excluded.file_hash,
+                file_modified_at = excluded.file_modified_at,
+                ingestion_status = excluded.ingestion_status,
+                ocr_text = excluded.ocr_text,
+                extracted_json = excluded.extracted_json,
+                confidence_score = excluded.confidence_score,
+                invoice_number = excluded.invoice_number,
+                invoice_date = excluded.invoice_date,
+                due_date = excluded.due_date,
+                counterparty_name = excluded.counterparty_name,
+                total_amount_minor = excluded.total_amount_minor,
+                currency = excluded.currency,
+                tax_amount_minor = excluded.tax_amount_minor,
+                net_amount_minor = excluded.net_amount_minor,
+                status = excluded.status,
+                paid_at = excluded.paid_at,
+                updated_at = excluded.updated_at,
+                base_currency = excluded.base_currency,
+                exchange_rate = excluded.exchange_rate,
+                base_total_minor = excluded.base_total_minor,
+                counterparty_id = excluded.counterparty_id,
+                account_number = excluded.account_number,
+                account_source = excluded.account_source",
             params![
                 invoice.id,
                 invoice.category,
@@ -101,7 +282,13 @@ impl Database {
                 invoice.status,
                 invoice.paid_at,
                 invoice.created_at,
-                invoice.updated_at
+                invoice.updated_at,
+                invoice.base_currency,
+                invoice.exchange_rate,
+                invoice.base_total_amount,
+                invoice.counterparty_id,
+                invoice.account_number,
+                invoice.account_source
             ],
         )?;
         Ok(())
@@ -111,8 +298,9 @@ impl Database {
         let mut stmt = self.conn.prepare(
             "SELECT id, category, file_path, file_hash, file_modified_at, ingestion_status,
                     ocr_text, extracted_json, confidence_score, invoice_number, invoice_date,
-                    due_date, counterparty_name, total_amount, currency, tax_amount, net_amount,
-                    status, paid_at, created_at, updated_at
+                    due_date, counterparty_name, total_amount_minor, currency, tax_amount_minor, net_amount_minor,
+                    status, paid_at, created_at, updated_at, base_currency, exchange_rate, base_total_minor,
+                    counterparty_id, account_number, account_source
              FROM invoices WHERE id = ?1",
         )?;
 
@@ -139,6 +327,12 @@ impl Database {
                 paid_at: row.get(18)?,
                 created_at: row.get(19)?,
                 updated_at: row.get(20)?,
+                base_currency: row.get(21)?,
+                exchange_rate: row.get(22)?,
+                base_total_amount: row.get(23)?,
+                counterparty_id: row.get(24)?,
+                account_number: row.get(25)?,
+                account_source: row.get(26)?,
             })
         })
         .optional()
@@ -148,8 +342,9 @@ impl Database {
         let mut stmt = self.conn.prepare(
             "SELECT id, category, file_path, file_hash, file_modified_at, ingestion_status,
                     ocr_text, extracted_json, confidence_score, invoice_number, invoice_date,
-                    due_date, counterparty_name, total_amount, currency, tax_amount, net_amount,
-                    status, paid_at, created_at, updated_at
+                    due_date, counterparty_name, total_amount_minor, currency, tax_amount_minor, net_amount_minor,
+                    status, paid_at, created_at, updated_at, base_currency, exchange_rate, base_total_minor,
+                    counterparty_id, account_number, account_source
              FROM invoices WHERE file_path = ?1",
         )?;
 
@@ -176,6 +371,12 @@ impl Database {
                 paid_at: row.get(18)?,
                 created_at: row.get(19)?,
                 updated_at: row.get(20)?,
+                base_currency: row.get(21)?,
+                exchange_rate: row.get(22)?,
+                base_total_amount: row.get(23)?,
+                counterparty_id: row.get(24)?,
+                account_number: row.get(25)?,
+                account_source: row.get(26)?,
             })
         })
         .optional()
@@ -185,8 +386,9 @@ impl Database {
         let mut stmt = self.conn.prepare(
             "SELECT id, category, file_path, file_hash, file_modified_at, ingestion_status,
                     ocr_text, extracted_json, confidence_score, invoice_number, invoice_date,
-                    due_date, counterparty_name, total_amount, currency, tax_amount, net_amount,
-                    status, paid_at, created_at, updated_at
+                    due_date, counterparty_name, total_amount_minor, currency, tax_amount_minor, net_amount_minor,
+                    status, paid_at, created_at, updated_at, base_currency, exchange_rate, base_total_minor,
+                    counterparty_id, account_number, account_source
              FROM invoices
              WHERE category = ?1
              ORDER BY invoice_date DESC",
@@ -215,6 +417,12 @@ impl Database {
                 paid_at: row.get(18)?,
                 created_at: row.get(19)?,
                 updated_at: row.get(20)?,
+                base_currency: row.get(21)?,
+                exchange_rate: row.get(22)?,
+                base_total_amount: row.get(23)?,
+                counterparty_id: row.get(24)?,
+                account_number: row.get(25)?,
+                account_source: row.get(26)?,
             })
         })?;
 
@@ -223,7 +431,8 @@ impl Database {
 
     pub fn get_invoice_summaries(&self, category: &str) -> SqlResult<Vec<InvoiceSummary>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, invoice_date, counterparty_name, total_amount, status, confidence_score, file_path
+            "SELECT id, invoice_date, counterparty_name, total_amount_minor, status, confidence_score, file_path,
+                    currency
              FROM invoices
              WHERE category = ?1
              ORDER BY invoice_date DESC",
@@ -238,6 +447,7 @@ impl Database {
                 status: row.get(4)?,
                 confidence_score: row.get(5)?,
                 file_path: row.get(6)?,
+                currency: row.get(7)?,
             })
         })?;
 
@@ -300,6 +510,278 @@ impl Database {
         Ok(())
     }
 
+    /// Replaces the stored per-page text of an invoice after re-extraction.
+    pub fn replace_invoice_pages(
+        &self,
+        invoice_id: &str,
+        extraction_method: &str,
+        pages: &[(u32, String)],
+    ) -> SqlResult<()> {
+        let tx = self.begin()?;
+        tx.execute(
+            "DELETE FROM invoice_pages WHERE invoice_id = ?1",
+            params![invoice_id],
+        )?;
+        for (page_number, text) in pages {
+            tx.execute(
+                "INSERT INTO invoice_pages (id, invoice_id, page_number, text, extraction_method, created_at)
+                 VALUES (hex(randomblob(16)), ?1, ?2, ?3, ?4, datetime('now'))",
+                params![invoice_id, page_number, text, extraction_method],
+            )?;
+        }
+        tx.commit()
+    }
+
+    pub fn get_invoice_pages(&self, invoice_id: &str) -> SqlResult<Vec<InvoicePage>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, invoice_id, page_number, text, extraction_method, created_at
+             FROM invoice_pages WHERE invoice_id = ?1
+             ORDER BY page_number",
+        )?;
+
+        let rows = stmt.query_map(params![invoice_id], |row| {
+            Ok(InvoicePage {
+                id: row.get(0)?,
+                invoice_id: row.get(1)?,
+                page_number: row.get(2)?,
+                text: row.get(3)?,
+                extraction_method: row.get(4)?,
+                created_at: row.get(5)?,
+            })
+        })?;
+
+        rows.collect()
+    }
+
+    pub fn replace_line_items(&self, invoice_id: &str, items: &[ExtractedLineItem]) -> SqlResult<()> {
+        let tx = self.begin()?;
+        tx.execute(
+            "DELETE FROM invoice_line_items WHERE invoice_id = ?1",
+            params![invoice_id],
+        )?;
+        for (index, item) in items.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO invoice_line_items (
+                    id, invoice_id, position, description, quantity, unit, unit_price_minor,
+                    net_amount_minor, vat_rate, line_total_minor, created_at
+                 ) VALUES (hex(randomblob(16)), ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, datetime('now'))",
+                params![
+                    invoice_id,
+                    index as i64 + 1,
+                    item.description,
+                    item.quantity,
+                    item.unit,
+                    item.unit_price.map(Money::from_f64),
+                    item.net_amount.map(Money::from_f64),
+                    item.vat_rate,
+                    item.line_total.map(Money::from_f64)
+                ],
+            )?;
+        }
+        tx.commit()
+    }
+
+    pub fn get_line_items(&self, invoice_id: &str) -> SqlResult<Vec<InvoiceLineItem>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, invoice_id, position, description, quantity, unit, unit_price_minor,
+                    net_amount_minor, vat_rate, line_total_minor
+             FROM invoice_line_items WHERE invoice_id = ?1
+             ORDER BY position",
+        )?;
+
+        let rows = stmt.query_map(params![invoice_id], |row| {
+            Ok(InvoiceLineItem {
+                id: row.get(0)?,
+                invoice_id: row.get(1)?,
+                position: row.get(2)?,
+                description: row.get(3)?,
+                quantity: row.get(4)?,
+                unit: row.get(5)?,
+                unit_price: row.get(6)?,
+                net_amount: row.get(7)?,
+                vat_rate: row.get(8)?,
+                line_total: row.get(9)?,
+            })
+        })?;
+
+        rows.collect()
+    }
+
+    pub fn replace_vat_breakdown(&self, invoice_id: &str, entries: &[ExtractedVatEntry]) -> SqlResult<()> {
+        let tx = self.begin()?;
+        tx.execute(
+            "DELETE FROM invoice_vat_breakdown WHERE invoice_id = ?1",
+            params![invoice_id],
+        )?;
+        // Subtotals of different tax categories can share a rate, such as 0 %
+        // exempt and 0 % reverse charge; they are summed into one entry.
+        let mut merged: Vec<(f64, Money, Money)> = Vec::new();
+        for entry in entries {
+            let (net, tax) = (Money::from_f64(entry.net_amount), Money::from_f64(entry.tax_amount));
+            match merged.iter_mut().find(|(rate, _, _)| (rate - entry.rate).abs() < 0.005) {
+                Some((_, net_sum, tax_sum)) => {
+                    *net_sum += net;
+                    *tax_sum += tax;
+                }
+                None => merged.push((entry.rate, net, tax)),
+            }
+        }
+        for (rate, net, tax) in merged {
+            tx.execute(
+                "INSERT INTO invoice_vat_breakdown (
+                    id, invoice_id, rate, net_amount_minor, tax_amount_minor, created_at
+                 )
+                 VALUES (hex(randomblob(16)), ?1, ?2, ?3, ?4, datetime('now'))",
+                params![invoice_id, rate, net, tax],
+            )?;
+        }
+        tx.commit()
+    }
+
+    pub fn get_vat_breakdown(&self, invoice_id: &str) -> SqlResult<Vec<InvoiceVatEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, invoice_id, rate, net_amount_minor, tax_amount_minor
+             FROM invoice_vat_breakdown WHERE invoice_id = ?1
+             ORDER BY rate DESC",
+        )?;
+
+        let rows = stmt.query_map(params![invoice_id], |row| {
+            Ok(InvoiceVatEntry {
+                id: row.get(0)?,
+                invoice_id: row.get(1)?,
+                rate: row.get(2)?,
+                net_amount: row.get(3)?,
+                tax_amount: row.get(4)?,
+            })
+        })?;
+
+        rows.collect()
+    }
+
+    pub fn replace_payment_details(&self, invoice_id: &str, details: &PaymentDetails) -> SqlResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO invoice_payment_details (
+                invoice_id, iban, bic, payment_reference, payment_terms, discount_percent, discount_due_date
+             )
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                invoice_id,
+                details.iban,
+                details.bic,
+                details.reference,
+                details.terms,
+                details.discount_percent,
+                details.discount_due_date
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_payment_details(&self, invoice_id: &str) -> SqlResult<Option<PaymentDetails>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT iban, bic, payment_reference, payment_terms, discount_percent, discount_due_date
+             FROM invoice_payment_details WHERE invoice_id = ?1",
+        )?;
+        stmt.query_row(params![invoice_id], |row| {
+            Ok(PaymentDetails {
+                iban: row.get(0)?,
+                bic: row.get(1)?,
+                reference: row.get(2)?,
+                terms: row.get(3)?,
+                discount_percent: row.get(4)?,
+                discount_due_date: row.get(5)?,
+            })
+        })
+        .optional()
+    }
+
+    pub fn replace_field_evidence(&self, invoice_id: &str, evidence: &[FieldEvidence]) -> SqlResult<()> {
+        let tx = self.begin()?;
+        tx.execute(
+            "DELETE FROM invoice_field_evidence WHERE invoice_id = ?1",
+            params![invoice_id],
+        )?;
+        for entry in evidence {
+            tx.execute(
+                "INSERT INTO invoice_field_evidence (
+                    id, invoice_id, field_name, value, confidence, page_number, text_offset,
+                    text_length, snippet, created_at
+                 ) VALUES (hex(randomblob(16)), ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, datetime('now'))",
+                params![
+                    invoice_id,
+                    entry.field_name,
+                    entry.value,
+                    entry.confidence,
+                    entry.page_number,
+                    entry.text_offset,
+                    entry.text_length,
+                    entry.snippet
+                ],
+            )?;
+        }
+        tx.commit()
+    }
+
+    pub fn get_field_evidence(&self, invoice_id: &str) -> SqlResult<Vec<FieldEvidence>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT field_name, value, confidence, page_number, text_offset, text_length, snippet
+             FROM invoice_field_evidence WHERE invoice_id = ?1
+             ORDER BY confidence ASC",
+        )?;
+
+        let rows = stmt.query_map(params![invoice_id], |row| {
+            Ok(FieldEvidence {
+                field_name: row.get(0)?,
+                value: row.get(1)?,
+                confidence: row.get(2)?,
+                page_number: row.get(3)?,
+                text_offset: row.get(4)?,
+                text_length: row.get(5)?,
+                snippet: row.get(6)?,
+            })
+        })?;
+
+        rows.collect()
+    }
+
+    /// Invoices whose weakest field is below `threshold`, weakest first.
+    /// Fields the user has overridden no longer count.
+    pub fn get_review_queue(&self, category: Option<&str>, threshold: f64, limit: usize) -> SqlResult<Vec<ReviewQueueItem>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT i.id, i.category, i.invoice_date, i.counterparty_name, i.total_amount_minor, i.status,
+                    i.file_path, e.field_name, MIN(e.confidence), i.currency
+             FROM invoices i
+             JOIN invoice_field_evidence e ON e.invoice_id = i.id
+             WHERE i.ingestion_status = 'processed'
+               AND (?1 IS NULL OR i.category = ?1)
+               AND NOT EXISTS (
+                   SELECT 1 FROM invoice_overrides o
+                   WHERE o.invoice_id = e.invoice_id AND o.field_name = e.field_name
+               )
+             GROUP BY i.id
+             HAVING MIN(e.confidence) < ?2
+             ORDER BY MIN(e.confidence) ASC, i.invoice_date DESC
+             LIMIT ?3",
+        )?;
+
+        let rows = stmt.query_map(params![category, threshold, limit as i32], |row| {
+            Ok(ReviewQueueItem {
+                id: row.get(0)?,
+                category: row.get(1)?,
+                invoice_date: row.get(2)?,
+                counterparty_name: row.get(3)?,
+                total_amount: row.get(4)?,
+                status: row.get(5)?,
+                file_path: row.get(6)?,
+                weakest_field: row.get(7)?,
+                weakest_confidence: row.get(8)?,
+                currency: row.get(9)?,
+            })
+        })?;
+
+        rows.collect()
+    }
+
     pub fn set_setting(&self, key: &str, value: &str) -> SqlResult<()> {
         self.conn.execute(
             "INSERT OR REPLACE INTO settings (key, value, updated_at) VALUES (?1, ?2, datetime('now'))",
@@ -313,47 +795,514 @@ impl Database {
         stmt.query_row(params![key], |row| row.get(0)).optional()
     }
 
-    pub fn get_monthly_sum(&self, category: &str, year_month: &str) -> SqlResult<f64> {
+    /// Sums are in the base currency; invoices without a conversion into it
+    /// are left out (see `count_unconverted_invoices`). `basis` picks the
+    /// date the invoice is bucketed by (see `reporting_date`).
+    pub fn get_monthly_sum(
+        &self,
+        category: &str,
+        year_month: &str,
+        base_currency: &str,
+        basis: &str,
+    ) -> SqlResult<Money> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT COALESCE(SUM(base_total_minor), 0)
+             FROM invoices
+             WHERE category = ?1 AND substr({}, 1, 7) = ?2 AND base_currency = ?3",
+            reporting_date(basis, "invoices")
+        ))?;
+
+        stmt.query_row(params![category, year_month, base_currency], |row| row.get(0))
+    }
+
+    pub fn get_yearly_sum(&self, category: &str, year: &str, base_currency: &str, basis: &str) -> SqlResult<Money> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT COALESCE(SUM(base_total_minor), 0)
+             FROM invoices
+             WHERE category = ?1 AND substr({}, 1, 4) = ?2 AND base_currency = ?3",
+            reporting_date(basis, "invoices")
+        ))?;
+
+        stmt.query_row(params![category, year, base_currency], |row| row.get(0))
+    }
+
+    /// Paid invoices without a payment date; cash-basis figures cannot
+    /// place them.
+    pub fn count_paid_without_payment_date(&self) -> SqlResult<u32> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT COUNT(*)
+             FROM invoices
+             WHERE ingestion_status = 'processed'
+               AND COALESCE(
+                     (SELECT o.override_value FROM invoice_overrides o
+                      WHERE o.invoice_id = invoices.id AND o.field_name = 'status'),
+                     status
+                   ) = 'paid'
+               AND {} IS NULL",
+            reporting_date("cash", "invoices")
+        ))?;
+
+        stmt.query_row([], |row| row.get(0))
+    }
+
+    /// Payables still to be paid; those marked paid or scheduled by an
+    /// override (reconciliation, payment runs) are left out.
+    pub fn get_open_payables_total(&self, base_currency: &str) -> SqlResult<Money> {
         let mut stmt = self.conn.prepare(
-            "SELECT SUM(CAST(total_amount AS REAL))
+            "SELECT COALESCE(SUM(base_total_minor), 0)
              FROM invoices
-             WHERE category = ?1 AND substr(invoice_date, 1, 7) = ?2",
+             WHERE category = 'payable'
+               AND ingestion_status = 'processed'
+               AND COALESCE(
+                     (SELECT o.override_value FROM invoice_overrides o
+                      WHERE o.invoice_id = invoices.id AND o.field_name = 'status'),
+                     status
+                   ) = 'open'
+               AND base_currency = ?1",
         )?;
 
-        let total: Option<f64> = stmt.query_row(params![category, year_month], |row| row.get(0))?;
-        Ok(total.unwrap_or(0.0))
+        stmt.query_row(params![base_currency], |row| row.get(0))
     }
 
-    pub fn get_yearly_sum(&self, category: &str, year: &str) -> SqlResult<f64> {
+    pub fn count_unconverted_invoices(&self, base_currency: &str) -> SqlResult<u32> {
         let mut stmt = self.conn.prepare(
-            "SELECT SUM(CAST(total_amount AS REAL))
+            "SELECT COUNT(*)
              FROM invoices
-             WHERE category = ?1 AND substr(invoice_date, 1, 4) = ?2",
+             WHERE ingestion_status = 'processed'
+               AND (base_total_minor IS NULL OR base_currency IS NOT ?1)",
+        )?;
+
+        stmt.query_row(params![base_currency], |row| row.get(0))
+    }
+
+    pub fn set_invoice_conversion(&self, invoice: &Invoice) -> SqlResult<()> {
+        self.conn.execute(
+            "UPDATE invoices SET base_currency = ?2, exchange_rate = ?3, base_total_minor = ?4 WHERE id = ?1",
+            params![
+                invoice.id,
+                invoice.base_currency,
+                invoice.exchange_rate,
+                invoice.base_total_amount
+            ],
         )?;
+        Ok(())
+    }
 
-        let total: Option<f64> = stmt.query_row(params![category, year], |row| row.get(0))?;
-        Ok(total.unwrap_or(0.0))
+    pub fn insert_exchange_rates(&self, rates: &[ExchangeRate], source: &str) -> SqlResult<usize> {
+        let tx = self.begin()?;
+        for rate in rates {
+            tx.execute(
+                "INSERT OR REPLACE INTO exchange_rates (id, rate_date, base_currency, quote_currency, rate, source, created_at)
+                 VALUES (
+                    COALESCE(
+                        (SELECT id FROM exchange_rates WHERE base_currency = 'EUR' AND quote_currency = ?2 AND rate_date = ?1),
+                        hex(randomblob(16))
+                    ),
+                    ?1, 'EUR', ?2, ?3, ?4, datetime('now')
+                 )",
+                params![rate.rate_date, rate.currency, rate.rate, source],
+            )?;
+        }
+        tx.commit()?;
+        Ok(rates.len())
     }
 
-    pub fn get_open_payables_total(&self) -> SqlResult<f64> {
+    /// Units of `currency` per 1 EUR from the latest fixing on or before `date`.
+    pub fn get_exchange_rate_on_or_before(&self, currency: &str, date: &str) -> SqlResult<Option<f64>> {
         let mut stmt = self.conn.prepare(
-            "SELECT SUM(CAST(total_amount AS REAL))
-             FROM invoices
-             WHERE category = 'payable' AND status = 'open'",
+            "SELECT rate FROM exchange_rates
+             WHERE base_currency = 'EUR' AND quote_currency = ?1 AND rate_date <= ?2
+             ORDER BY rate_date DESC
+             LIMIT 1",
+        )?;
+        stmt.query_row(params![currency, date], |row| row.get(0)).optional()
+    }
+
+    pub fn find_counterparty_by_vat_id(&self, vat_id: &str) -> SqlResult<Option<String>> {
+        let mut stmt = self.conn.prepare("SELECT id FROM counterparties WHERE vat_id = ?1 LIMIT 1")?;
+        stmt.query_row(params![vat_id], |row| row.get(0)).optional()
+    }
+
+    /// Exact lookup of a normalized name against names and aliases.
+    pub fn find_counterparty_by_name(&self, normalized_name: &str) -> SqlResult<Option<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id FROM counterparties WHERE normalized_name = ?1
+             UNION ALL
+             SELECT counterparty_id FROM counterparty_aliases WHERE normalized_alias = ?1
+             LIMIT 1",
+        )?;
+        stmt.query_row(params![normalized_name], |row| row.get(0)).optional()
+    }
+
+    /// `(counterparty_id, normalized name)` for every name and alias, used for
+    /// fuzzy matching.
+    pub fn get_counterparty_names(&self) -> SqlResult<Vec<(String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, normalized_name FROM counterparties
+             UNION
+             SELECT counterparty_id, normalized_alias FROM counterparty_aliases",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    pub fn insert_counterparty(
+        &self,
+        id: &str,
+        name: &str,
+        normalized_name: &str,
+        vat_id: Option<&str>,
+        default_category: Option<&str>,
+    ) -> SqlResult<()> {
+        self.conn.execute(
+            "INSERT INTO counterparties (
+                id, name, normalized_name, vat_id, default_category, created_at, updated_at
+             ) VALUES (?1, ?2, ?3, ?4, ?5, datetime('now'), datetime('now'))",
+            params![id, name, normalized_name, vat_id, default_category],
+        )?;
+        Ok(())
+    }
+
+    /// Aliases are unique across counterparties; an existing one is kept.
+    pub fn add_counterparty_alias(&self, counterparty_id: &str, alias: &str, normalized_alias: &str) -> SqlResult<()> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO counterparty_aliases (id, counterparty_id, alias, normalized_alias, created_at)
+             SELECT hex(randomblob(16)), ?1, ?2, ?3, datetime('now')
+             WHERE NOT EXISTS (
+                 SELECT 1 FROM counterparties WHERE id = ?1 AND normalized_name = ?3
+             )",
+            params![counterparty_id, alias, normalized_alias],
+        )?;
+        Ok(())
+    }
+
+    pub fn fill_counterparty_vat_id(&self, counterparty_id: &str, vat_id: &str) -> SqlResult<()> {
+        self.conn.execute(
+            "UPDATE counterparties SET vat_id = ?2, updated_at = datetime('now') WHERE id = ?1 AND vat_id IS NULL",
+            params![counterparty_id, vat_id],
+        )?;
+        Ok(())
+    }
+
+    /// Stores the bank account of a counterparty that has none yet.
+    pub fn fill_counterparty_bank_account(&self, counterparty_id: &str, iban: &str, bic: Option<&str>) -> SqlResult<()> {
+        self.conn.execute(
+            "UPDATE counterparties SET iban = ?2, bic = COALESCE(bic, ?3), updated_at = datetime('now')
+             WHERE id = ?1 AND iban IS NULL",
+            params![counterparty_id, iban, bic],
+        )?;
+        Ok(())
+    }
+
+    /// All counterparties, or only `id` when given, ordered by name.
+    pub fn get_counterparties(&self, id: Option<&str>) -> SqlResult<Vec<Counterparty>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT c.id, c.name, c.vat_id, c.iban, c.address, c.default_category, c.created_at, c.updated_at,
+                    (SELECT COUNT(*) FROM invoices i WHERE i.counterparty_id = c.id), c.personal_account, c.bic,
+                    c.consumer, c.email
+             FROM counterparties c
+             WHERE ?1 IS NULL OR c.id = ?1
+             ORDER BY c.name COLLATE NOCASE",
+        )?;
+        let rows = stmt.query_map(params![id], |row| {
+            Ok(Counterparty {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                aliases: Vec::new(),
+                vat_id: row.get(2)?,
+                iban: row.get(3)?,
+                bic: row.get(10)?,
+                address: row.get(4)?,
+                default_category: row.get(5)?,
+                created_at: row.get(6)?,
+                updated_at: row.get(7)?,
+                invoice_count: row.get(8)?,
+                personal_account: row.get(9)?,
+                consumer: row.get(11)?,
+                email: row.get(12)?,
+            })
+        })?;
+        let mut counterparties = rows.collect::<SqlResult<Vec<_>>>()?;
+
+        let mut stmt = self.conn.prepare(
+            "SELECT counterparty_id, alias FROM counterparty_aliases
+             WHERE ?1 IS NULL OR counterparty_id = ?1
+             ORDER BY alias",
         )?;
+        let aliases = stmt.query_map(params![id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+        for alias in aliases {
+            let (counterparty_id, alias) = alias?;
+            if let Some(counterparty) = counterparties.iter_mut().find(|c| c.id == counterparty_id) {
+                counterparty.aliases.push(alias);
+            }
+        }
+        Ok(counterparties)
+    }
 
-        let total: Option<f64> = stmt.query_row([], |row| row.get(0))?;
-        Ok(total.unwrap_or(0.0))
+    pub fn update_counterparty(&self, counterparty: &Counterparty, normalized_name: &str) -> SqlResult<()> {
+        self.conn.execute(
+            "UPDATE counterparties
+             SET name = ?2, normalized_name = ?3, vat_id = ?4, iban = ?5, address = ?6, default_category = ?7,
+                 personal_account = ?8, bic = ?9, consumer = ?10, email = ?11, updated_at = datetime('now')
+             WHERE id = ?1",
+            params![
+                counterparty.id,
+                counterparty.name,
+                normalized_name,
+                counterparty.vat_id,
+                counterparty.iban,
+                counterparty.address,
+                counterparty.default_category,
+                counterparty.personal_account,
+                counterparty.bic,
+                counterparty.consumer,
+                counterparty.email
+            ],
+        )?;
+        self.conn.execute(
+            "DELETE FROM counterparty_aliases WHERE counterparty_id = ?1 AND normalized_alias = ?2",
+            params![counterparty.id, normalized_name],
+        )?;
+        Ok(())
+    }
+
+    /// Moves invoices and aliases of `source_id` to `target_id`, keeps the
+    /// source name as an alias, fills empty master data and deletes the source.
+    pub fn merge_counterparties(&self, target_id: &str, source_id: &str) -> SqlResult<()> {
+        let tx = self.begin()?;
+        tx.execute(
+            "UPDATE invoices SET counterparty_id = ?1 WHERE counterparty_id = ?2",
+            params![target_id, source_id],
+        )?;
+        tx.execute(
+            "UPDATE counterparty_aliases SET counterparty_id = ?1 WHERE counterparty_id = ?2",
+            params![target_id, source_id],
+        )?;
+        tx.execute(
+            "UPDATE account_rules SET counterparty_id = ?1 WHERE counterparty_id = ?2",
+            params![target_id, source_id],
+        )?;
+        tx.execute(
+            "INSERT OR IGNORE INTO counterparty_aliases (id, counterparty_id, alias, normalized_alias, created_at)
+             SELECT hex(randomblob(16)), ?1, source.name, source.normalized_name, datetime('now')
+             FROM counterparties source, counterparties target
+             WHERE source.id = ?2 AND target.id = ?1 AND source.normalized_name != target.normalized_name",
+            params![target_id, source_id],
+        )?;
+        tx.execute(
+            "UPDATE counterparties
+             SET vat_id = COALESCE(vat_id, (SELECT vat_id FROM counterparties WHERE id = ?2)),
+                 iban = COALESCE(iban, (SELECT iban FROM counterparties WHERE id = ?2)),
+                 bic = COALESCE(bic, (SELECT bic FROM counterparties WHERE id = ?2)),
+                 address = COALESCE(address, (SELECT address FROM counterparties WHERE id = ?2)),
+                 default_category = COALESCE(default_category, (SELECT default_category FROM counterparties WHERE id = ?2)),
+                 personal_account = COALESCE(personal_account, (SELECT personal_account FROM counterparties WHERE id = ?2)),
+                 email = COALESCE(email, (SELECT email FROM counterparties WHERE id = ?2)),
+                 updated_at = datetime('now')
+             WHERE id = ?1",
+            params![target_id, source_id],
+        )?;
+        tx.execute("DELETE FROM counterparties WHERE id = ?1", params![source_id])?;
+        tx.commit()
+    }
+
+    pub fn set_invoice_counterparty(&self, invoice_id: &str, counterparty_id: Option<&str>) -> SqlResult<()> {
+        self.conn.execute(
+            "UPDATE invoices SET counterparty_id = ?2 WHERE id = ?1",
+            params![invoice_id, counterparty_id],
+        )?;
+        Ok(())
+    }
+
+    /// Ids of processed invoices with a counterparty name but no counterparty.
+    pub fn get_unlinked_invoice_ids(&self) -> SqlResult<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id FROM invoices
+             WHERE counterparty_id IS NULL AND counterparty_name IS NOT NULL AND ingestion_status = 'processed'
+             ORDER BY created_at",
+        )?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    pub fn get_accounts(&self, chart: &str) -> SqlResult<Vec<Account>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, chart, number, name, category, expense_category, vat_rate, active, automatic_vat
+             FROM accounts WHERE chart = ?1
+             ORDER BY number",
+        )?;
+
+        let rows = stmt.query_map(params![chart], |row| {
+            Ok(Account {
+                id: row.get(0)?,
+                chart: row.get(1)?,
+                number: row.get(2)?,
+                name: row.get(3)?,
+                category: row.get(4)?,
+                expense_category: row.get(5)?,
+                vat_rate: row.get(6)?,
+                active: row.get(7)?,
+                automatic_vat: row.get(8)?,
+            })
+        })?;
+
+        rows.collect()
+    }
+
+    /// Inserts or updates an account, keyed by chart and number. With
+    /// `overwrite` false an existing account is left untouched, so seeding
+    /// keeps the user's edits.
+    pub fn save_account(&self, account: &Account, overwrite: bool) -> SqlResult<()> {
+        let sql = if overwrite {
+            "INSERT INTO accounts (
+                id, chart, number, name, category, expense_category, vat_rate, active, automatic_vat,
+                created_at, updated_at
+             ) VALUES (hex(randomblob(16)), ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, datetime('now'), datetime('now'))
+             ON CONFLICT(chart, number) DO UPDATE SET
+                name = excluded.name,
+                category = excluded.category,
+                expense_category = excluded.expense_category,
+                vat_rate = excluded.vat_rate,
+                active = excluded.active,
+                automatic_vat = excluded.automatic_vat,
+                updated_at = excluded.updated_at"
+        } else {
+            "INSERT OR IGNORE INTO accounts (
+                id, chart, number, name, category, expense_category, vat_rate, active, automatic_vat,
+                created_at, updated_at
+             ) VALUES (hex(randomblob(16)), ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, datetime('now'), datetime('now'))"
+        };
+        self.conn.execute(
+            sql,
+            params![
+                account.chart,
+                account.number,
+                account.name,
+                account.category,
+                account.expense_category,
+                account.vat_rate,
+                account.active,
+                account.automatic_vat
+            ],
+        )?;
+        Ok(())
     }
 
-    pub fn get_recent_invoices(&self, category: &str, limit: usize) -> SqlResult<Vec<InvoiceSummary>> {
+    /// Rules of a chart, highest priority first.
+    pub fn get_account_rules(&self, chart: &str) -> SqlResult<Vec<AccountRule>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, invoice_date, counterparty_name, total_amount, status, confidence_score, file_path
+            "SELECT r.id, r.chart, r.account_number, r.counterparty_id, c.name, r.keyword, r.priority
+             FROM account_rules r
+             LEFT JOIN counterparties c ON c.id = r.counterparty_id
+             WHERE r.chart = ?1
+             ORDER BY r.priority DESC, r.created_at",
+        )?;
+
+        let rows = stmt.query_map(params![chart], |row| {
+            Ok(AccountRule {
+                id: row.get(0)?,
+                chart: row.get(1)?,
+                account_number: row.get(2)?,
+                counterparty_id: row.get(3)?,
+                counterparty_name: row.get(4)?,
+                keyword: row.get(5)?,
+                priority: row.get(6)?,
+            })
+        })?;
+
+        rows.collect()
+    }
+
+    pub fn insert_account_rule(&self, rule: &AccountRule) -> SqlResult<()> {
+        self.conn.execute(
+            "INSERT INTO account_rules (id, chart, account_number, counterparty_id, keyword, priority, created_at)
+             VALUES (hex(randomblob(16)), ?1, ?2, ?3, ?4, ?5, datetime('now'))",
+            params![
+                rule.chart,
+                rule.account_number,
+                rule.counterparty_id,
+                rule.keyword,
+                rule.priority
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_account_rule(&self, id: &str) -> SqlResult<()> {
+        self.conn.execute("DELETE FROM account_rules WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    pub fn set_invoice_account(
+        &self,
+        invoice_id: &str,
+        account_number: Option<&str>,
+        account_source: Option<&str>,
+    ) -> SqlResult<()> {
+        self.conn.execute(
+            "UPDATE invoices SET account_number = ?2, account_source = ?3 WHERE id = ?1",
+            params![invoice_id, account_number, account_source],
+        )?;
+        Ok(())
+    }
+
+    /// Net and gross sums per account for one year, converted into the base
+    /// currency with each invoice's stored rate.
+    pub fn get_account_totals(
+        &self,
+        category: &str,
+        year: &str,
+        chart: &str,
+        base_currency: &str,
+        basis: &str,
+    ) -> SqlResult<Vec<AccountTotal>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT i.account_number, a.name, a.expense_category, COUNT(*),
+                    COALESCE(SUM(CAST(ROUND(COALESCE(i.net_amount_minor, i.total_amount_minor) * i.exchange_rate) AS INTEGER)), 0),
+                    COALESCE(SUM(i.base_total_minor), 0)
+             FROM invoices i
+             LEFT JOIN accounts a ON a.chart = ?3 AND a.number = i.account_number
+             WHERE i.category = ?1
+               AND substr({}, 1, 4) = ?2
+               AND i.ingestion_status = 'processed'
+               AND i.base_currency = ?4
+             GROUP BY i.account_number
+             ORDER BY i.account_number IS NULL, i.account_number",
+            reporting_date(basis, "i")
+        ))?;
+
+        let rows = stmt.query_map(params![category, year, chart, base_currency], |row| {
+            Ok(AccountTotal {
+                account_number: row.get(0)?,
+                account_name: row.get(1)?,
+                expense_category: row.get(2)?,
+                invoice_count: row.get(3)?,
+                net_amount: row.get(4)?,
+                total_amount: row.get(5)?,
+            })
+        })?;
+
+        rows.collect()
+    }
+
+    /// Latest invoices by reporting date; under the cash basis these are the
+    /// latest payments, so unpaid invoices are not listed.
+    pub fn get_recent_invoices(&self, category: &str, limit: usize, basis: &str) -> SqlResult<Vec<InvoiceSummary>> {
+        let date = reporting_date(basis, "invoices");
+        let paid_only = if basis == "cash" {
+            format!("AND {} IS NOT NULL", date)
+        } else {
+            String::new()
+        };
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, invoice_date, counterparty_name, total_amount_minor, status, confidence_score, file_path,
+                    currency
              FROM invoices
-             WHERE category = ?1
-             ORDER BY invoice_date DESC
+             WHERE category = ?1 {}
+             ORDER BY {} DESC
              LIMIT ?2",
-        )?;
+            paid_only, date
+        ))?;
 
         let rows = stmt.query_map(params![category, limit as i32], |row| {
             Ok(InvoiceSummary {
@@ -364,12 +1313,386 @@ impl Database {
                 status: row.get(4)?,
                 confidence_score: row.get(5)?,
                 file_path: row.get(6)?,
+                currency: row.get(7)?,
+            })
+        })?;
+
+        rows.collect()
+    }
+
+    /// Stores a statement line unless one with the same hash exists. Returns
+    /// whether it was added.
+    pub fn insert_bank_transaction(&self, transaction: &BankTransaction, import_hash: &str) -> SqlResult<bool> {
+        let inserted = self.conn.execute(
+            "INSERT OR IGNORE INTO bank_transactions (
+                id, import_hash, source, account_iban, booking_date, value_date, amount_minor, currency,
+                counterparty_name, counterparty_iban, remittance, reference, status, created_at
+             ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, datetime('now'))",
+            params![
+                transaction.id,
+                import_hash,
+                transaction.source,
+                transaction.account_iban,
+                transaction.booking_date,
+                transaction.value_date,
+                transaction.amount,
+                transaction.currency,
+                transaction.counterparty_name,
+                transaction.counterparty_iban,
+                transaction.remittance,
+                transaction.reference,
+                transaction.status,
+            ],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    /// Newest first; all transactions when `status` is `None`.
+    pub fn get_bank_transactions(&self, status: Option<&str>) -> SqlResult<Vec<BankTransaction>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, source, account_iban, booking_date, value_date, amount_minor, currency, counterparty_name,
+                    counterparty_iban, remittance, reference, status, created_at
+             FROM bank_transactions
+             WHERE ?1 IS NULL OR status = ?1
+             ORDER BY booking_date DESC, created_at DESC",
+        )?;
+
+        let rows = stmt.query_map(params![status], |row| {
+            Ok(BankTransaction {
+                id: row.get(0)?,
+                source: row.get(1)?,
+                account_iban: row.get(2)?,
+                booking_date: row.get(3)?,
+                value_date: row.get(4)?,
+                amount: row.get(5)?,
+                currency: row.get(6)?,
+                counterparty_name: row.get(7)?,
+                counterparty_iban: row.get(8)?,
+                remittance: row.get(9)?,
+                reference: row.get(10)?,
+                status: row.get(11)?,
+                created_at: row.get(12)?,
+            })
+        })?;
+
+        rows.collect()
+    }
+
+    pub fn set_bank_transaction_status(&self, id: &str, status: &str) -> SqlResult<()> {
+        self.conn.execute(
+            "UPDATE bank_transactions SET status = ?2 WHERE id = ?1",
+            params![id, status],
+        )?;
+        Ok(())
+    }
+
+    /// Adds or replaces the match of a transaction with an invoice.
+    pub fn upsert_bank_match(
+        &self,
+        transaction_id: &str,
+        invoice_id: &str,
+        amount: Money,
+        confidence: f64,
+        reasons: &str,
+        status: &str,
+    ) -> SqlResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO bank_transaction_matches (
+                id, transaction_id, invoice_id, amount_minor, confidence, reasons, status, created_at
+             ) VALUES (
+                COALESCE(
+                    (SELECT id FROM bank_transaction_matches WHERE transaction_id = ?1 AND invoice_id = ?2),
+                    hex(randomblob(16))
+                ),
+                ?1, ?2, ?3, ?4, ?5, ?6, datetime('now')
+             )",
+            params![transaction_id, invoice_id, amount, confidence, reasons, status],
+        )?;
+        Ok(())
+    }
+
+    /// Matches of one transaction, or of all when `transaction_id` is `None`.
+    pub fn get_bank_matches(&self, transaction_id: Option<&str>) -> SqlResult<Vec<BankMatch>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT m.id, m.transaction_id, m.invoice_id, i.invoice_number, i.counterparty_name, m.amount_minor,
+                    m.confidence, m.reasons, m.status
+             FROM bank_transaction_matches m
+             JOIN invoices i ON i.id = m.invoice_id
+             WHERE ?1 IS NULL OR m.transaction_id = ?1
+             ORDER BY m.status = 'confirmed' DESC, m.confidence DESC",
+        )?;
+
+        let rows = stmt.query_map(params![transaction_id], |row| {
+            Ok(BankMatch {
+                id: row.get(0)?,
+                transaction_id: row.get(1)?,
+                invoice_id: row.get(2)?,
+                invoice_number: row.get(3)?,
+                invoice_counterparty: row.get(4)?,
+                amount: row.get(5)?,
+                confidence: row.get(6)?,
+                reasons: row.get(7)?,
+                status: row.get(8)?,
             })
         })?;
 
         rows.collect()
     }
 
+    pub fn set_bank_match_status(&self, id: &str, status: &str) -> SqlResult<()> {
+        self.conn.execute(
+            "UPDATE bank_transaction_matches SET status = ?2 WHERE id = ?1",
+            params![id, status],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_suggested_bank_matches(&self, transaction_id: &str) -> SqlResult<()> {
+        self.conn.execute(
+            "DELETE FROM bank_transaction_matches WHERE transaction_id = ?1 AND status = 'suggested'",
+            params![transaction_id],
+        )?;
+        Ok(())
+    }
+
+    /// Confirmed payments of an invoice as `(amount, booking date)`.
+    pub fn get_invoice_payments(&self, invoice_id: &str) -> SqlResult<Vec<(Money, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT m.amount_minor, t.booking_date
+             FROM bank_transaction_matches m
+             JOIN bank_transactions t ON t.id = m.transaction_id
+             WHERE m.invoice_id = ?1 AND m.status = 'confirmed'
+             ORDER BY t.booking_date",
+        )?;
+
+        let rows = stmt.query_map(params![invoice_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// Records the orders of one transfer file and marks their invoices as
+    /// `scheduled`, all or nothing.
+    pub fn insert_payment_orders(&self, orders: &[PaymentOrder]) -> SqlResult<()> {
+        let tx = self.begin()?;
+        for order in orders {
+            self.insert_payment_order(order)?;
+            self.set_override(&order.invoice_id, "status", "scheduled")?;
+        }
+        tx.commit()
+    }
+
+    fn insert_payment_order(&self, order: &PaymentOrder) -> SqlResult<()> {
+        self.conn.execute(
+            "INSERT INTO payment_orders (
+                id, message_id, invoice_id, end_to_end_id, creditor_name, creditor_iban, creditor_bic,
+                amount_minor, discount_minor, currency, remittance, execution_date, file_path, created_at
+             ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, datetime('now'))",
+            params![
+                order.id,
+                order.message_id,
+                order.invoice_id,
+                order.end_to_end_id,
+                order.creditor_name,
+                order.creditor_iban,
+                order.creditor_bic,
+                order.amount,
+                order.discount,
+                order.currency,
+                order.remittance,
+                order.execution_date,
+                order.file_path
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Transfers written for an invoice, latest first.
+    pub fn get_payment_orders(&self, invoice_id: &str) -> SqlResult<Vec<PaymentOrder>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, message_id, invoice_id, end_to_end_id, creditor_name, creditor_iban, creditor_bic,
+                    amount_minor, discount_minor, currency, remittance, execution_date, file_path, created_at
+             FROM payment_orders
+             WHERE invoice_id = ?1
+             ORDER BY created_at DESC, execution_date DESC",
+        )?;
+
+        let rows = stmt.query_map(params![invoice_id], |row| {
+            Ok(PaymentOrder {
+                id: row.get(0)?,
+                message_id: row.get(1)?,
+                invoice_id: row.get(2)?,
+                end_to_end_id: row.get(3)?,
+                creditor_name: row.get(4)?,
+                creditor_iban: row.get(5)?,
+                creditor_bic: row.get(6)?,
+                amount: row.get(7)?,
+                discount: row.get(8)?,
+                currency: row.get(9)?,
+                remittance: row.get(10)?,
+                execution_date: row.get(11)?,
+                file_path: row.get(12)?,
+                created_at: row.get(13)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    pub fn insert_dunning_step(&self, step: &DunningStep) -> SqlResult<()> {
+        self.conn.execute(
+            "INSERT INTO dunning_steps (
+                id, invoice_id, level, outstanding_minor, interest_minor, fees_minor, interest_rate,
+                default_since, deadline, file_path, created_at
+             ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, datetime('now'))",
+            params![
+                step.id,
+                step.invoice_id,
+                step.level,
+                step.outstanding,
+                step.interest,
+                step.fees,
+                step.interest_rate,
+                step.default_since,
+                step.deadline,
+                step.file_path
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Reminders sent for one invoice, or for all when `invoice_id` is
+    /// `None`, latest first.
+    pub fn get_dunning_steps(&self, invoice_id: Option<&str>) -> SqlResult<Vec<DunningStep>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, invoice_id, level, outstanding_minor, interest_minor, fees_minor, interest_rate,
+                    default_since, deadline, file_path, created_at
+             FROM dunning_steps
+             WHERE ?1 IS NULL OR invoice_id = ?1
+             ORDER BY created_at DESC, level DESC",
+        )?;
+
+        let rows = stmt.query_map(params![invoice_id], |row| {
+            Ok(DunningStep {
+                id: row.get(0)?,
+                invoice_id: row.get(1)?,
+                level: row.get(2)?,
+                outstanding: row.get(3)?,
+                interest: row.get(4)?,
+                fees: row.get(5)?,
+                interest_rate: row.get(6)?,
+                default_since: row.get(7)?,
+                deadline: row.get(8)?,
+                file_path: row.get(9)?,
+                created_at: row.get(10)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    pub fn delete_dunning_step(&self, step_id: &str) -> SqlResult<usize> {
+        self.conn
+            .execute("DELETE FROM dunning_steps WHERE id = ?1", params![step_id])
+    }
+
+    /// The user's letter text for a level, if it was edited.
+    pub fn get_dunning_template(&self, level: u8) -> SqlResult<Option<DunningTemplate>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT level, subject, body FROM dunning_templates WHERE level = ?1")?;
+        stmt.query_row(params![level], |row| {
+            Ok(DunningTemplate {
+                level: row.get(0)?,
+                subject: row.get(1)?,
+                body: row.get(2)?,
+                customized: true,
+            })
+        })
+        .optional()
+    }
+
+    pub fn save_dunning_template(&self, template: &DunningTemplate) -> SqlResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO dunning_templates (level, subject, body, updated_at)
+             VALUES (?1, ?2, ?3, datetime('now'))",
+            params![template.level, template.subject, template.body],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_dunning_template(&self, level: u8) -> SqlResult<()> {
+        self.conn
+            .execute("DELETE FROM dunning_templates WHERE level = ?1", params![level])?;
+        Ok(())
+    }
+
+    /// Base rates ordered by the date they apply from.
+    pub fn get_base_interest_rates(&self) -> SqlResult<Vec<BaseInterestRate>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT valid_from, rate FROM base_interest_rates ORDER BY valid_from")?;
+        let rows = stmt.query_map([], |row| {
+            Ok(BaseInterestRate {
+                valid_from: row.get(0)?,
+                rate: row.get(1)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    pub fn set_base_interest_rate(&self, valid_from: &str, rate: f64) -> SqlResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO base_interest_rates (valid_from, rate) VALUES (?1, ?2)",
+            params![valid_from, rate],
+        )?;
+        Ok(())
+    }
+
+    /// Stores a written invoice together with its number in one transaction,
+    /// so a number is only taken when the invoice exists.
+    pub fn insert_issued_invoice(&self, invoice: &Invoice, issued: &IssuedInvoice) -> SqlResult<()> {
+        let tx = self.begin()?;
+        self.upsert_invoice(invoice)?;
+        tx.execute(
+            "INSERT INTO issued_invoices (
+                invoice_id, invoice_number, number_scope, sequence, format, draft_json, created_at
+             ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, datetime('now'))",
+            params![
+                issued.invoice_id,
+                issued.invoice_number,
+                issued.number_scope,
+                issued.sequence,
+                issued.format,
+                issued.draft_json
+            ],
+        )?;
+        tx.commit()
+    }
+
+    /// Removes an invoice stored by `insert_issued_invoice` whose file could
+    /// not be written, releasing its number.
+    pub fn delete_issued_invoice(&self, invoice_id: &str) -> SqlResult<()> {
+        let tx = self.begin()?;
+        tx.execute("DELETE FROM issued_invoices WHERE invoice_id = ?1", params![invoice_id])?;
+        tx.execute("DELETE FROM invoices WHERE id = ?1", params![invoice_id])?;
+        tx.commit()
+    }
+
+    pub fn get_last_invoice_sequence(&self, number_scope: &str) -> SqlResult<Option<u32>> {
+        self.conn.query_row(
+            "SELECT MAX(sequence) FROM issued_invoices WHERE number_scope = ?1",
+            params![number_scope],
+            |row| row.get(0),
+        )
+    }
+
+    /// Whether a revenue invoice, written here or imported, has this number.
+    pub fn revenue_invoice_number_exists(&self, invoice_number: &str) -> SqlResult<bool> {
+        self.conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM issued_invoices WHERE invoice_number = ?1)
+                 OR EXISTS (SELECT 1 FROM invoices WHERE category = 'revenue' AND invoice_number = ?1)",
+            params![invoice_number],
+            |row| row.get(0),
+        )
+    }
+
     pub fn log_processing(
         &self,
         invoice_id: Option<&str>,
@@ -386,3 +1709,48 @@ impl Database {
         Ok(())
     }
 }
+
+/// SQL for the date an invoice is reported under: the payment date for the
+/// `cash` basis, the invoice date otherwise. User corrections win over the
+/// extracted value, and the payment date only exists as a correction, so the
+/// override is looked up. `table` is the name or alias of the invoices table.
+fn reporting_date(basis: &str, table: &str) -> String {
+    let field = if basis == "cash" { "paid_at" } else { "invoice_date" };
+    format!(
+        "COALESCE((SELECT o.override_value FROM invoice_overrides o WHERE o.invoice_id = {table}.id AND o.field_name = '{field}'), {table}.{field})"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_transaction_keeps_none_of_its_writes() {
+        let dir = std::env::temp_dir().join(format!("billly-db-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = Database::new(dir.join("test.db")).unwrap();
+        let invoice = Invoice {
+            id: "i1".to_string(),
+            category: "payable".to_string(),
+            ..Invoice::default()
+        };
+
+        let result: anyhow::Result<()> = db.in_transaction(|| {
+            db.upsert_invoice(&invoice)?;
+            db.replace_invoice_pages("i1", "ocr", &[(1, "Seite 1".to_string())])?;
+            Err(anyhow::anyhow!("extraction failed"))
+        });
+        assert!(result.is_err());
+        assert!(db.get_invoice_by_id("i1").unwrap().is_none());
+        assert!(db.get_invoice_pages("i1").unwrap().is_empty());
+
+        db.in_transaction(|| -> SqlResult<()> {
+            db.upsert_invoice(&invoice)?;
+            db.replace_invoice_pages("i1", "ocr", &[(1, "Seite 1".to_string())])
+        })
+        .unwrap();
+        assert_eq!(db.get_invoice_pages("i1").unwrap().len(), 1);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
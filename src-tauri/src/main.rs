@@ -10,7 +10,9 @@ use anyhow::anyhow;
 use tauri::Manager;
 
 use crate::models::Settings;
+use crate::services::invoicing;
 use crate::services::state::AppState;
+use crate::utils::Money;
 
 fn main() {
     tauri::Builder::default()
@@ -23,6 +25,7 @@ fn main() {
 
             let db_path = app_data_dir.join("billly.sqlite");
             let db = db::Database::new(db_path)?;
+            services::counterparties::link_unassigned(&db)?;
             let settings = load_settings(&db);
 
             let state = AppState::new(db, settings);
@@ -37,14 +40,53 @@ fn main() {
             commands::settings::test_openai_key,
             commands::settings::reprocess_all,
             commands::settings::pick_folder,
+            commands::settings::import_exchange_rates,
             commands::dashboard::get_dashboard_stats,
             commands::invoices::get_invoices,
             commands::invoices::get_invoice_detail,
+            commands::invoices::get_review_queue,
             commands::invoices::update_invoice_field,
             commands::invoices::clear_overrides,
             commands::invoices::clear_override,
             commands::invoices::reprocess_invoice,
             commands::invoices::open_invoice_file,
+            commands::counterparties::get_counterparties,
+            commands::counterparties::update_counterparty,
+            commands::counterparties::create_counterparty,
+            commands::counterparties::merge_counterparties,
+            commands::accounts::get_accounts,
+            commands::accounts::save_account,
+            commands::accounts::seed_accounts,
+            commands::accounts::get_account_rules,
+            commands::accounts::add_account_rule,
+            commands::accounts::delete_account_rule,
+            commands::accounts::get_account_totals,
+            commands::exports::export_datev,
+            commands::exports::get_export_columns,
+            commands::exports::export_invoices,
+            commands::bank::get_bank_csv_profiles,
+            commands::bank::import_bank_statement,
+            commands::bank::get_bank_transactions,
+            commands::bank::reconcile_bank_transactions,
+            commands::bank::confirm_bank_match,
+            commands::bank::reject_bank_match,
+            commands::bank::assign_bank_transaction,
+            commands::bank::ignore_bank_transaction,
+            commands::payments::create_sepa_transfer,
+            commands::payments::get_girocode,
+            commands::dunning::get_dunning_overview,
+            commands::dunning::create_dunning_letter,
+            commands::dunning::delete_dunning_step,
+            commands::dunning::get_dunning_templates,
+            commands::dunning::save_dunning_template,
+            commands::dunning::reset_dunning_template,
+            commands::dunning::set_base_interest_rate,
+            commands::invoicing::get_next_invoice_number,
+            commands::invoicing::create_invoice,
+            commands::reports::get_vat_return,
+            commands::reports::get_aging_report,
+            commands::reports::get_euer_report,
+            commands::reports::open_euer_report,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -59,10 +101,105 @@ fn load_settings(db: &db::Database) -> Settings {
         .ok()
         .flatten()
         .unwrap_or_else(|| "deu".to_string());
+    let ocr_dpi = db
+        .get_setting("ocr_dpi")
+        .ok()
+        .flatten()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(300);
+    let base_currency = db
+        .get_setting("base_currency")
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| "EUR".to_string());
+    let chart_of_accounts = db
+        .get_setting("chart_of_accounts")
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| "SKR03".to_string());
+    let datev_consultant_number = db.get_setting("datev_consultant_number").ok().flatten();
+    let datev_client_number = db.get_setting("datev_client_number").ok().flatten();
+    let vat_accounting = db
+        .get_setting("vat_accounting")
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| "accrual".to_string());
+    let reporting_basis = db
+        .get_setting("reporting_basis")
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| "accrual".to_string());
+    let extraction_backend = db
+        .get_setting("extraction_backend")
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| "openai".to_string());
+    let extraction_base_url = db.get_setting("extraction_base_url").ok().flatten();
+    let extraction_model = db.get_setting("extraction_model").ok().flatten();
+    let company_name = db.get_setting("company_name").ok().flatten();
+    let company_iban = db.get_setting("company_iban").ok().flatten();
+    let company_bic = db.get_setting("company_bic").ok().flatten();
+    let company_address = db.get_setting("company_address").ok().flatten();
+    let dunning_interval_days = db
+        .get_setting("dunning_interval_days")
+        .ok()
+        .flatten()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(14);
+    let dunning_fee = db
+        .get_setting("dunning_fee")
+        .ok()
+        .flatten()
+        .and_then(|value| Money::parse(&value).ok())
+        .unwrap_or(Money::from_f64(2.5));
+    let company_vat_id = db.get_setting("company_vat_id").ok().flatten();
+    let company_tax_number = db.get_setting("company_tax_number").ok().flatten();
+    let company_email = db.get_setting("company_email").ok().flatten();
+    let company_phone = db.get_setting("company_phone").ok().flatten();
+    let invoice_number_format = db
+        .get_setting("invoice_number_format")
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| invoicing::DEFAULT_NUMBER_FORMAT.to_string());
+    let invoice_number_start = db
+        .get_setting("invoice_number_start")
+        .ok()
+        .flatten()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(1);
+    let invoice_payment_days = db
+        .get_setting("invoice_payment_days")
+        .ok()
+        .flatten()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(14);
     Settings {
         revenue_folder,
         payable_folder,
         openai_api_key,
         ocr_language,
+        ocr_dpi,
+        base_currency,
+        chart_of_accounts,
+        datev_consultant_number,
+        datev_client_number,
+        vat_accounting,
+        reporting_basis,
+        extraction_backend,
+        extraction_base_url,
+        extraction_model,
+        company_name,
+        company_iban,
+        company_bic,
+        company_address,
+        dunning_interval_days,
+        dunning_fee,
+        company_vat_id,
+        company_tax_number,
+        company_email,
+        company_phone,
+        invoice_number_format,
+        invoice_number_start,
+        invoice_payment_days,
     }
 }
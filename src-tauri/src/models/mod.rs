@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::utils::Money;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Invoice {
     pub id: String,
     pub category: String,
@@ -15,14 +18,20 @@ pub struct Invoice {
     pub invoice_date: Option<String>,
     pub due_date: Option<String>,
     pub counterparty_name: Option<String>,
-    pub total_amount: String,
+    pub total_amount: Money,
     pub currency: String,
-    pub tax_amount: Option<String>,
-    pub net_amount: Option<String>,
+    pub tax_amount: Option<Money>,
+    pub net_amount: Option<Money>,
     pub status: String,
     pub paid_at: Option<String>,
     pub created_at: String,
     pub updated_at: String,
+    pub base_currency: Option<String>,
+    pub exchange_rate: Option<f64>,
+    pub base_total_amount: Option<Money>,
+    pub counterparty_id: Option<String>,
+    pub account_number: Option<String>,
+    pub account_source: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,16 +49,251 @@ pub struct InvoiceSummary {
     pub id: String,
     pub invoice_date: Option<String>,
     pub counterparty_name: Option<String>,
-    pub total_amount: String,
+    pub total_amount: Money,
+    pub currency: String,
     pub status: String,
     pub confidence_score: f64,
     pub file_path: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoicePage {
+    pub id: String,
+    pub invoice_id: String,
+    pub page_number: u32,
+    pub text: String,
+    pub extraction_method: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoiceLineItem {
+    pub id: String,
+    pub invoice_id: String,
+    pub position: u32,
+    pub description: Option<String>,
+    pub quantity: Option<f64>,
+    pub unit: Option<String>,
+    pub unit_price: Option<Money>,
+    pub net_amount: Option<Money>,
+    pub vat_rate: Option<f64>,
+    pub line_total: Option<Money>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoiceVatEntry {
+    pub id: String,
+    pub invoice_id: String,
+    pub rate: f64,
+    pub net_amount: Money,
+    pub tax_amount: Money,
+}
+
+/// Confidence of one extracted field and where in `ocr_text` it was found.
+/// `text_offset` and `text_length` count characters.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldEvidence {
+    pub field_name: String,
+    pub value: Option<String>,
+    pub confidence: f64,
+    pub page_number: Option<u32>,
+    pub text_offset: Option<u32>,
+    pub text_length: Option<u32>,
+    pub snippet: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewQueueItem {
+    pub id: String,
+    pub category: String,
+    pub invoice_date: Option<String>,
+    pub counterparty_name: Option<String>,
+    pub total_amount: Money,
+    pub status: String,
+    pub file_path: Option<String>,
+    pub weakest_field: String,
+    pub weakest_confidence: f64,
+    pub currency: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InvoiceDetail {
     pub invoice: Invoice,
     pub overrides: Vec<InvoiceOverride>,
+    pub pages: Vec<InvoicePage>,
+    pub evidence: Vec<FieldEvidence>,
+    pub line_items: Vec<InvoiceLineItem>,
+    pub vat_breakdown: Vec<InvoiceVatEntry>,
+    pub counterparty: Option<Counterparty>,
+    pub payment: PaymentDetails,
+    pub payment_orders: Vec<PaymentOrder>,
+    pub dunning_steps: Vec<DunningStep>,
+}
+
+/// How a payable is paid: from overrides, the extracted data or, for IBAN
+/// and BIC, the counterparty record. `discount_percent` is Skonto granted
+/// when paying on or before `discount_due_date`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PaymentDetails {
+    pub iban: Option<String>,
+    pub bic: Option<String>,
+    pub reference: Option<String>,
+    pub terms: Option<String>,
+    pub discount_percent: Option<f64>,
+    pub discount_due_date: Option<String>,
+}
+
+/// One credit transfer written to a pain.001 file. `amount` is what was
+/// ordered, `discount` the Skonto deducted from the invoice total.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentOrder {
+    pub id: String,
+    pub message_id: String,
+    pub invoice_id: String,
+    pub end_to_end_id: String,
+    pub creditor_name: String,
+    pub creditor_iban: String,
+    pub creditor_bic: Option<String>,
+    pub amount: Money,
+    pub discount: Money,
+    pub currency: String,
+    pub remittance: String,
+    pub execution_date: String,
+    pub file_path: String,
+    pub created_at: String,
+}
+
+/// One reminder sent for a revenue invoice: level 1 is the payment reminder,
+/// 2 and 3 the first and second Mahnung. Amounts are as claimed in the
+/// letter.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DunningStep {
+    pub id: String,
+    pub invoice_id: String,
+    pub level: u8,
+    pub outstanding: Money,
+    pub interest: Money,
+    pub fees: Money,
+    /// Annual default interest rate in percent.
+    pub interest_rate: Option<f64>,
+    pub default_since: Option<String>,
+    pub deadline: String,
+    pub file_path: String,
+    pub created_at: String,
+}
+
+/// An overdue revenue invoice with the claim as of today.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DunningCase {
+    pub invoice_id: String,
+    pub invoice_number: Option<String>,
+    pub counterparty_name: Option<String>,
+    pub consumer: bool,
+    pub invoice_date: Option<String>,
+    pub due_date: Option<String>,
+    pub currency: String,
+    pub default_since: String,
+    pub days_overdue: i64,
+    pub outstanding: Money,
+    /// Default interest claimed with the next letter; the payment reminder
+    /// claims none.
+    pub interest: Money,
+    /// Base rate plus 5 (consumers) or 9 percentage points, in percent.
+    pub interest_rate: f64,
+    /// Fees claimed with the next letter.
+    pub fees: Money,
+    /// Highest level sent so far, 0 if none.
+    pub level: u8,
+    pub last_deadline: Option<String>,
+    /// `None` once the second Mahnung has been sent.
+    pub next_level: Option<u8>,
+    /// The next letter is due: the invoice is overdue and the deadline of
+    /// the last letter has passed.
+    pub next_due: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BaseInterestRate {
+    pub valid_from: String,
+    pub rate: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DunningOverview {
+    pub cases: Vec<DunningCase>,
+    pub base_rates: Vec<BaseInterestRate>,
+    pub warnings: Vec<String>,
+}
+
+/// Letter text for one level. `{claims}` on a line of its own places the
+/// table of amounts; see `dunning::PLACEHOLDERS` for the other fields.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DunningTemplate {
+    pub level: u8,
+    pub subject: String,
+    pub body: String,
+    #[serde(default)]
+    pub customized: bool,
+}
+
+/// An outgoing invoice as entered, before it gets its number.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoiceDraft {
+    pub counterparty_id: String,
+    pub invoice_date: String,
+    /// Date of delivery or service; the invoice date when empty.
+    #[serde(default)]
+    pub delivery_date: Option<String>,
+    /// Payment term in days; the configured default when empty.
+    #[serde(default)]
+    pub payment_days: Option<u32>,
+    pub currency: String,
+    /// Leitweg-ID or other buyer reference, required for XRechnung.
+    #[serde(default)]
+    pub buyer_reference: Option<String>,
+    /// Why lines carry no VAT: `small_business` (§ 19 UStG),
+    /// `reverse_charge`, `intra_community` or `export`.
+    #[serde(default)]
+    pub vat_exemption: Option<String>,
+    #[serde(default)]
+    pub note: Option<String>,
+    /// `pdf`, `zugferd` (PDF with embedded EN 16931 XML) or `xrechnung`
+    /// (XML only).
+    pub format: String,
+    pub items: Vec<InvoiceDraftItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoiceDraftItem {
+    pub description: String,
+    pub quantity: f64,
+    /// UN/ECE Recommendation 20 code such as `C62` (piece) or `HUR` (hour).
+    pub unit: String,
+    pub unit_price: Money,
+    pub vat_rate: f64,
+}
+
+/// The number an outgoing invoice took and the draft it was written from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssuedInvoice {
+    pub invoice_id: String,
+    pub invoice_number: String,
+    /// The number format with the date filled in; sequences count per scope.
+    pub number_scope: String,
+    pub sequence: u32,
+    pub format: String,
+    pub draft_json: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SepaTransferSummary {
+    pub path: String,
+    pub message_id: String,
+    pub execution_date: String,
+    pub transfers: usize,
+    pub total: Money,
+    pub discount: Money,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -58,23 +302,297 @@ pub struct Settings {
     pub payable_folder: Option<String>,
     pub openai_api_key: Option<String>,
     pub ocr_language: String,
+    pub ocr_dpi: u32,
+    pub base_currency: String,
+    pub chart_of_accounts: String,
+    pub datev_consultant_number: Option<String>,
+    pub datev_client_number: Option<String>,
+    /// `accrual` (Soll-Versteuerung) or `cash` (Ist-Versteuerung).
+    pub vat_accounting: String,
+    /// `accrual` buckets dashboard and report figures by invoice date,
+    /// `cash` by payment date.
+    pub reporting_basis: String,
+    pub extraction_backend: String,
+    pub extraction_base_url: Option<String>,
+    pub extraction_model: Option<String>,
+    /// Debtor of SEPA transfers: account holder name, IBAN and BIC.
+    pub company_name: Option<String>,
+    pub company_iban: Option<String>,
+    pub company_bic: Option<String>,
+    /// Postal address printed on letters, one line per address line.
+    pub company_address: Option<String>,
+    /// Payment deadline given in reminder letters; the next level can be
+    /// sent once it has passed.
+    pub dunning_interval_days: u32,
+    /// Charged to consumers per Mahnung.
+    pub dunning_fee: Money,
+    /// Seller details required on outgoing invoices (§ 14 UStG); email and
+    /// phone are required by XRechnung.
+    pub company_vat_id: Option<String>,
+    pub company_tax_number: Option<String>,
+    pub company_email: Option<String>,
+    pub company_phone: Option<String>,
+    /// Number format of outgoing invoices, e.g. `RE-{YYYY}-{NNNN}`: `{YYYY}`,
+    /// `{YY}` and `{MM}` are taken from the invoice date, the run of `N` is
+    /// the zero-padded sequence. Each distinct date prefix counts on its own.
+    pub invoice_number_format: String,
+    /// First sequence number of a format without issued invoices.
+    pub invoice_number_start: u32,
+    /// Default payment term of outgoing invoices.
+    pub invoice_payment_days: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DashboardStats {
-    pub revenue_month: f64,
-    pub revenue_year: f64,
-    pub payable_month: f64,
-    pub payable_year: f64,
-    pub profit_month: f64,
-    pub profit_year: f64,
-    pub open_payables: f64,
+    pub base_currency: String,
+    pub reporting_basis: String,
+    pub unconverted_invoices: u32,
+    /// Cash basis only: paid invoices left out for lack of a payment date.
+    pub undated_payments: u32,
+    /// Whether scanned PDFs can be OCRed, see `AppState::pdf_ocr_available`.
+    pub pdf_ocr_available: bool,
+    pub revenue_month: Money,
+    pub revenue_year: Money,
+    pub payable_month: Money,
+    pub payable_year: Money,
+    pub profit_month: Money,
+    pub profit_year: Money,
+    pub open_payables: Money,
     pub recent_revenue: Vec<InvoiceSummary>,
     pub recent_payables: Vec<InvoiceSummary>,
     pub chart_months: Vec<String>,
-    pub chart_revenue: Vec<f64>,
-    pub chart_payables: Vec<f64>,
-    pub chart_profit: Vec<f64>,
+    pub chart_revenue: Vec<Money>,
+    pub chart_payables: Vec<Money>,
+    pub chart_profit: Vec<Money>,
+}
+
+/// Master record for a vendor or customer. `aliases` are the other spellings
+/// that were matched or merged into it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Counterparty {
+    pub id: String,
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub vat_id: Option<String>,
+    pub iban: Option<String>,
+    pub bic: Option<String>,
+    pub address: Option<String>,
+    /// Where invoices go; the buyer's electronic address in XRechnung.
+    #[serde(default)]
+    pub email: Option<String>,
+    pub default_category: Option<String>,
+    /// DATEV debtor or creditor number used in exports.
+    pub personal_account: Option<String>,
+    /// Private customer: lower default interest and no flat fee (§ 288 BGB).
+    #[serde(default)]
+    pub consumer: bool,
+    pub invoice_count: u32,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Booking account of a chart (`SKR03`, `SKR04` or a custom one).
+/// `expense_category` groups accounts across charts and is what the LLM
+/// suggests.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Account {
+    pub id: String,
+    pub chart: String,
+    pub number: String,
+    pub name: String,
+    pub category: String,
+    pub expense_category: Option<String>,
+    pub vat_rate: Option<f64>,
+    pub active: bool,
+    /// DATEV automatic account: VAT is derived from the account, so exports
+    /// must not add a BU key.
+    #[serde(default)]
+    pub automatic_vat: bool,
+}
+
+/// Assigns `account_number` to invoices of a counterparty and/or whose text
+/// contains `keyword`. Higher priority wins.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountRule {
+    pub id: String,
+    pub chart: String,
+    pub account_number: String,
+    pub counterparty_id: Option<String>,
+    pub counterparty_name: Option<String>,
+    pub keyword: Option<String>,
+    pub priority: i64,
+}
+
+/// Per-account sums in the base currency; invoices without an account are
+/// reported with `account_number: None`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountTotal {
+    pub account_number: Option<String>,
+    pub account_name: Option<String>,
+    pub expense_category: Option<String>,
+    pub invoice_count: u32,
+    pub net_amount: Money,
+    pub total_amount: Money,
+}
+
+/// Outcome of a file export. `skipped_undated` counts invoices left out
+/// because they have no date to place them in the period.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportSummary {
+    pub path: String,
+    pub invoices: usize,
+    pub records: usize,
+    pub skipped_undated: usize,
+}
+
+/// One Kennzahl of the VAT advance return. `base` is the net amount, `tax`
+/// the VAT; fields that only carry one of them leave the other empty.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VatReturnLine {
+    pub code: String,
+    pub label: String,
+    pub base: Option<Money>,
+    pub tax: Option<Money>,
+}
+
+/// Umsatzsteuer-Voranmeldung for a month or quarter. A positive `balance`
+/// is payable, a negative one refundable.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VatReturn {
+    pub period: String,
+    pub from: String,
+    pub to: String,
+    pub basis: String,
+    pub currency: String,
+    pub lines: Vec<VatReturnLine>,
+    pub output_vat: Money,
+    pub input_vat: Money,
+    pub balance: Money,
+    pub invoice_count: u32,
+    pub warnings: Vec<String>,
+}
+
+/// One line of the EÜR. Non-deductible lines are shown but not subtracted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EuerLine {
+    pub key: String,
+    pub label: String,
+    pub net_amount: Money,
+    pub invoice_count: u32,
+    pub deductible: bool,
+}
+
+/// Einnahmen-Überschuss-Rechnung of one year on a cash basis, net of VAT.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EuerReport {
+    pub year: i32,
+    pub currency: String,
+    pub revenue: Vec<EuerLine>,
+    pub expenses: Vec<EuerLine>,
+    pub total_revenue: Money,
+    pub total_expenses: Money,
+    pub profit: Money,
+    pub vat_received: Money,
+    pub input_vat_paid: Money,
+    pub invoice_count: u32,
+    pub warnings: Vec<String>,
+}
+
+/// Open invoices of one counterparty by days past due. `buckets` follows
+/// `AgingReport::buckets`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgingRow {
+    pub counterparty_id: Option<String>,
+    pub counterparty_name: String,
+    pub buckets: Vec<Money>,
+    pub total: Money,
+    pub open_invoices: u32,
+    pub oldest_days_overdue: i64,
+    pub average_days_to_pay: Option<f64>,
+}
+
+/// Aging of open receivables (`revenue`) or payables as of a day, in the
+/// base currency.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgingReport {
+    pub category: String,
+    pub as_of: String,
+    pub currency: String,
+    pub buckets: Vec<String>,
+    pub rows: Vec<AgingRow>,
+    pub totals: Vec<Money>,
+    pub total: Money,
+    pub open_invoices: u32,
+    pub paid_invoices: u32,
+    pub average_days_to_pay: Option<f64>,
+    pub warnings: Vec<String>,
+}
+
+/// One booked line of a bank statement. `amount` is signed: incoming
+/// payments are positive. `status` is `unmatched`, `suggested`, `matched` or
+/// `ignored`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BankTransaction {
+    pub id: String,
+    pub source: String,
+    pub account_iban: Option<String>,
+    pub booking_date: String,
+    pub value_date: Option<String>,
+    pub amount: Money,
+    pub currency: String,
+    pub counterparty_name: Option<String>,
+    pub counterparty_iban: Option<String>,
+    pub remittance: Option<String>,
+    pub reference: Option<String>,
+    pub status: String,
+    pub created_at: String,
+}
+
+/// Part of a bank transaction assigned to an invoice. Several matches per
+/// transaction are a grouped payment; several per invoice are partial
+/// payments. `status` is `suggested`, `confirmed` or `rejected`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BankMatch {
+    pub id: String,
+    pub transaction_id: String,
+    pub invoice_id: String,
+    pub invoice_number: Option<String>,
+    pub invoice_counterparty: Option<String>,
+    pub amount: Money,
+    pub confidence: f64,
+    pub reasons: String,
+    pub status: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BankTransactionDetail {
+    pub transaction: BankTransaction,
+    pub matches: Vec<BankMatch>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BankImportSummary {
+    pub path: String,
+    pub format: String,
+    pub transactions: usize,
+    pub duplicates: usize,
+    pub matched: usize,
+    pub suggested: usize,
+}
+
+/// A column the generic invoice export can write.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportColumn {
+    pub key: String,
+    pub label: String,
+}
+
+/// ECB-style quote: units of `currency` per 1 EUR on `rate_date`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExchangeRate {
+    pub rate_date: String,
+    pub currency: String,
+    pub rate: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -83,10 +601,53 @@ pub struct ExtractedInvoiceData {
     pub invoice_date: Option<String>,
     pub due_date: Option<String>,
     pub counterparty_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counterparty_vat_id: Option<String>,
     pub total_amount: Option<f64>,
     pub currency: Option<String>,
     pub tax_amount: Option<f64>,
     pub net_amount: Option<f64>,
     pub extraction_notes: String,
     pub confidence_score: Option<f64>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub field_confidence: BTreeMap<String, f64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub line_items: Vec<ExtractedLineItem>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vat_breakdown: Vec<ExtractedVatEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expense_category: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iban: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bic: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_reference: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_terms: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discount_percent: Option<f64>,
+    /// Skonto period in days from the invoice date, when no date is printed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discount_days: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discount_due_date: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractedLineItem {
+    pub description: Option<String>,
+    pub quantity: Option<f64>,
+    pub unit: Option<String>,
+    pub unit_price: Option<f64>,
+    pub net_amount: Option<f64>,
+    pub vat_rate: Option<f64>,
+    pub line_total: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractedVatEntry {
+    pub rate: f64,
+    pub net_amount: f64,
+    pub tax_amount: f64,
 }
@@ -0,0 +1,207 @@
+use anyhow::{anyhow, Result};
+
+use crate::db::Database;
+use crate::models::{Account, AccountRule, ExtractedInvoiceData, Invoice};
+
+/// Expense category used when no rule or suggestion applies to a payable.
+const FALLBACK_EXPENSE_CATEGORY: &str = "other_expenses";
+
+/// `(expense_category, SKR03 number, SKR04 number, name, category, VAT rate)`
+type StandardAccount = (&'static str, &'static str, &'static str, &'static str, &'static str, Option<f64>);
+
+/// Expense categories whose standard accounts are DATEV automatic accounts.
+const AUTOMATIC_VAT_CATEGORIES: [&str; 6] = ["sales_19", "sales_7", "sales_eu", "sales_export", "goods_19", "goods_7"];
+
+/// Commonly used accounts of the DATEV standard charts.
+const STANDARD_ACCOUNTS: [StandardAccount; 36] = [
+    ("sales_19", "8400", "4400", "Erlöse 19 % USt", "revenue", Some(19.0)),
+    ("sales_7", "8300", "4300", "Erlöse 7 % USt", "revenue", Some(7.0)),
+    ("sales_eu", "8125", "4125", "Steuerfreie innergemeinschaftliche Lieferungen § 4 Nr. 1b UStG", "revenue", Some(0.0)),
+    ("sales_export", "8120", "4120", "Steuerfreie Umsätze § 4 Nr. 1a UStG", "revenue", Some(0.0)),
+    ("sales_small_business", "8195", "4185", "Erlöse als Kleinunternehmer i. S. d. § 19 Abs. 1 UStG", "revenue", Some(0.0)),
+    ("goods_19", "3400", "5400", "Wareneingang 19 % Vorsteuer", "payable", Some(19.0)),
+    ("goods_7", "3300", "5300", "Wareneingang 7 % Vorsteuer", "payable", Some(7.0)),
+    ("wages", "4120", "6020", "Gehälter", "payable", None),
+    ("social_security", "4130", "6110", "Gesetzliche soziale Aufwendungen", "payable", None),
+    ("rent", "4210", "6310", "Miete (unbewegliche Wirtschaftsgüter)", "payable", Some(19.0)),
+    ("utilities", "4240", "6325", "Gas, Strom, Wasser", "payable", Some(19.0)),
+    ("insurance", "4360", "6400", "Versicherungen", "payable", Some(0.0)),
+    ("memberships", "4380", "6420", "Beiträge", "payable", Some(0.0)),
+    ("vehicle", "4500", "6500", "Fahrzeugkosten", "payable", Some(19.0)),
+    ("vehicle_running", "4530", "6530", "Laufende Kfz-Betriebskosten", "payable", Some(19.0)),
+    ("advertising", "4600", "6600", "Werbekosten", "payable", Some(19.0)),
+    ("hospitality", "4650", "6640", "Bewirtungskosten", "payable", Some(19.0)),
+    ("travel_employees", "4660", "6650", "Reisekosten Arbeitnehmer", "payable", Some(19.0)),
+    ("travel_owner", "4670", "6670", "Reisekosten Unternehmer", "payable", Some(19.0)),
+    ("repairs", "4805", "6470", "Reparaturen und Instandhaltung von Betriebs- und Geschäftsausstattung", "payable", Some(19.0)),
+    ("maintenance_it", "4806", "6495", "Wartungskosten für Hard- und Software", "payable", Some(19.0)),
+    ("low_value_assets", "4855", "6260", "Sofortabschreibung geringwertiger Wirtschaftsgüter", "payable", Some(19.0)),
+    ("postage", "4910", "6800", "Porto", "payable", Some(0.0)),
+    ("telephone", "4920", "6805", "Telefon", "payable", Some(19.0)),
+    ("internet", "4925", "6810", "Telefax und Internetkosten", "payable", Some(19.0)),
+    ("office_supplies", "4930", "6815", "Bürobedarf", "payable", Some(19.0)),
+    ("literature", "4940", "6820", "Zeitschriften, Bücher", "payable", Some(7.0)),
+    ("training", "4945", "6821", "Fortbildungskosten", "payable", Some(19.0)),
+    ("legal_advice", "4950", "6825", "Rechts- und Beratungskosten", "payable", Some(19.0)),
+    ("accounting", "4955", "6830", "Buchführungskosten", "payable", Some(19.0)),
+    ("audit", "4957", "6827", "Abschluss- und Prüfungskosten", "payable", Some(19.0)),
+    ("licenses", "4964", "6837", "Aufwendungen für die zeitlich befristete Überlassung von Rechten (Lizenzen, Konzessionen)", "payable", Some(19.0)),
+    ("bank_fees", "4970", "6855", "Nebenkosten des Geldverkehrs", "payable", Some(0.0)),
+    ("operating_supplies", "4980", "6850", "Sonstiger Betriebsbedarf", "payable", Some(19.0)),
+    ("other_expenses", "4900", "6300", "Sonstige betriebliche Aufwendungen", "payable", Some(19.0)),
+    ("purchased_services", "3100", "5900", "Fremdleistungen", "payable", Some(19.0)),
+];
+
+/// Expense categories the LLM may suggest; the keys of `STANDARD_ACCOUNTS`.
+pub fn expense_categories() -> Vec<&'static str> {
+    STANDARD_ACCOUNTS.iter().map(|(key, ..)| *key).collect()
+}
+
+/// Adds the standard accounts of `chart`; existing accounts are kept as they
+/// are. Returns the number of accounts in the seed.
+pub fn seed_chart(db: &Database, chart: &str) -> Result<usize> {
+    let use_skr04 = match chart {
+        "SKR03" => false,
+        "SKR04" => true,
+        other => return Err(anyhow!("No standard accounts for chart {}", other)),
+    };
+    for (expense_category, skr03, skr04, name, category, vat_rate) in STANDARD_ACCOUNTS {
+        let account = Account {
+            id: String::new(),
+            chart: chart.to_string(),
+            number: if use_skr04 { skr04 } else { skr03 }.to_string(),
+            name: name.to_string(),
+            category: category.to_string(),
+            expense_category: Some(expense_category.to_string()),
+            vat_rate,
+            active: true,
+            automatic_vat: AUTOMATIC_VAT_CATEGORIES.contains(&expense_category),
+        };
+        db.save_account(&account, false)?;
+    }
+    Ok(STANDARD_ACCOUNTS.len())
+}
+
+/// Checks that an account number exists in `chart`.
+pub fn validate_account(db: &Database, chart: &str, number: &str) -> Result<()> {
+    if db.get_accounts(chart)?.iter().any(|account| account.number == number) {
+        Ok(())
+    } else {
+        Err(anyhow!("Account {} does not exist in {}", number, chart))
+    }
+}
+
+/// Sets `account_number` and `account_source`. A user override wins, then
+/// counterparty rules, keyword rules on the document text, the LLM's expense
+/// category and finally a default by VAT rate or the fallback category.
+pub fn assign(db: &Database, invoice: &mut Invoice, chart: &str) -> Result<()> {
+    let overridden = db
+        .get_overrides(&invoice.id)?
+        .into_iter()
+        .find(|entry| entry.field_name == "account_number")
+        .map(|entry| entry.override_value);
+    let (number, source) = match overridden {
+        Some(number) => (Some(number), Some("override")),
+        None => match suggest(db, invoice, chart)? {
+            Some((number, source)) => (Some(number), Some(source)),
+            None => (None, None),
+        },
+    };
+    invoice.account_number = number;
+    invoice.account_source = source.map(str::to_string);
+    Ok(())
+}
+
+fn suggest(db: &Database, invoice: &Invoice, chart: &str) -> Result<Option<(String, &'static str)>> {
+    let accounts: Vec<Account> = db
+        .get_accounts(chart)?
+        .into_iter()
+        .filter(|account| account.active && account.category == invoice.category)
+        .collect();
+    if accounts.is_empty() {
+        return Ok(None);
+    }
+    let usable = |number: &str| accounts.iter().any(|account| account.number == number);
+    let text = invoice.ocr_text.as_deref().unwrap_or_default().to_lowercase();
+    let keyword_found = |rule: &AccountRule| match rule.keyword.as_deref() {
+        Some(keyword) => text.contains(&keyword.trim().to_lowercase()),
+        None => true,
+    };
+
+    let rules = db.get_account_rules(chart)?;
+    if let Some(counterparty_id) = invoice.counterparty_id.as_deref() {
+        if let Some(rule) = rules.iter().find(|rule| {
+            rule.counterparty_id.as_deref() == Some(counterparty_id)
+                && keyword_found(rule)
+                && usable(&rule.account_number)
+        }) {
+            return Ok(Some((rule.account_number.clone(), "counterparty")));
+        }
+    }
+    if let Some(rule) = rules
+        .iter()
+        .find(|rule| rule.counterparty_id.is_none() && keyword_found(rule) && usable(&rule.account_number))
+    {
+        return Ok(Some((rule.account_number.clone(), "keyword")));
+    }
+
+    let by_expense_category = |key: &str| {
+        accounts
+            .iter()
+            .find(|account| account.expense_category.as_deref() == Some(key))
+            .map(|account| account.number.clone())
+    };
+    let suggested = serde_json::from_str::<ExtractedInvoiceData>(&invoice.extracted_json)
+        .ok()
+        .and_then(|data| data.expense_category);
+    if let Some(number) = suggested.as_deref().and_then(by_expense_category) {
+        return Ok(Some((number, "llm")));
+    }
+
+    let default_key = if invoice.category == "revenue" {
+        revenue_category_by_rate(invoice)
+    } else {
+        Some(FALLBACK_EXPENSE_CATEGORY)
+    };
+    Ok(default_key
+        .and_then(by_expense_category)
+        .map(|number| (number, "default")))
+}
+
+/// Picks the revenue account from the effective VAT rate of the totals.
+fn revenue_category_by_rate(invoice: &Invoice) -> Option<&'static str> {
+    let tax = invoice.tax_amount?;
+    let net = invoice.net_amount.unwrap_or(invoice.total_amount - tax).to_f64();
+    let tax = tax.to_f64();
+    if net <= 0.0 {
+        return None;
+    }
+    let rate = tax / net * 100.0;
+    if (rate - 19.0).abs() < 0.5 {
+        Some("sales_19")
+    } else if (rate - 7.0).abs() < 0.5 {
+        Some("sales_7")
+    } else {
+        None
+    }
+}
+
+/// Re-runs the assignment for all invoices, e.g. after rules or the chart
+/// changed. Returns how many invoices have an account.
+pub fn reassign_all(db: &Database, chart: &str) -> Result<usize> {
+    let mut assigned = 0;
+    for category in ["revenue", "payable"] {
+        for mut invoice in db.get_invoices(category)? {
+            assign(db, &mut invoice, chart)?;
+            db.set_invoice_account(
+                &invoice.id,
+                invoice.account_number.as_deref(),
+                invoice.account_source.as_deref(),
+            )?;
+            if invoice.account_number.is_some() {
+                assigned += 1;
+            }
+        }
+    }
+    Ok(assigned)
+}
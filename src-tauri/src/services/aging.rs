@@ -0,0 +1,155 @@
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+
+use crate::db::Database;
+use crate::models::{AgingReport, AgingRow, Invoice};
+use crate::services::bookkeeping::{load_invoices, parse_date, rate_to, InvoiceFilter};
+use crate::utils::Money;
+
+/// Bucket labels by days past due. An invoice that is not yet due is current.
+const BUCKETS: [&str; 5] = ["Current", "1–30 days", "31–60 days", "61–90 days", "90+ days"];
+
+fn bucket(days_overdue: i64) -> usize {
+    match days_overdue {
+        i64::MIN..=0 => 0,
+        1..=30 => 1,
+        31..=60 => 2,
+        61..=90 => 3,
+        _ => 4,
+    }
+}
+
+#[derive(Default)]
+struct CounterpartyAging {
+    counterparty_id: Option<String>,
+    name: String,
+    buckets: [Money; 5],
+    open_invoices: u32,
+    oldest_days_overdue: i64,
+    days_to_pay: i64,
+    paid_invoices: u32,
+}
+
+/// Open invoices of `category` (receivables for `revenue`, payables for
+/// `payable`) by counterparty and days past due as of `as_of`, in the base
+/// currency. Invoices without a due date are due on their invoice date.
+/// Days-to-pay averages are taken over the paid invoices, from invoice date
+/// to `paid_at`.
+pub fn compute(db: &Database, category: &str, as_of: NaiveDate, base_currency: &str) -> Result<AgingReport> {
+    if category != "revenue" && category != "payable" {
+        return Err(anyhow!("Unknown category: {}", category));
+    }
+    let counterparties = db.get_counterparties(None)?;
+    let filter = InvoiceFilter {
+        category: Some(category.to_string()),
+        ..InvoiceFilter::default()
+    };
+    let (invoices, _) = load_invoices(db, &filter)?;
+
+    let mut by_counterparty: BTreeMap<String, CounterpartyAging> = BTreeMap::new();
+    let mut warnings = Vec::new();
+    for invoice in &invoices {
+        let (key, name) = match invoice
+            .counterparty_id
+            .as_deref()
+            .and_then(|id| counterparties.iter().find(|counterparty| counterparty.id == id))
+        {
+            Some(counterparty) => (counterparty.id.clone(), counterparty.name.clone()),
+            None => {
+                let name = invoice
+                    .counterparty_name
+                    .clone()
+                    .filter(|name| !name.trim().is_empty())
+                    .unwrap_or_else(|| "Unknown".to_string());
+                (format!("name:{}", name.trim().to_lowercase()), name)
+            }
+        };
+        let entry = by_counterparty.entry(key).or_insert_with(|| CounterpartyAging {
+            counterparty_id: invoice.counterparty_id.clone(),
+            name,
+            ..CounterpartyAging::default()
+        });
+
+        if invoice.status == "paid" {
+            if let Some(days) = days_to_pay(invoice) {
+                entry.days_to_pay += days;
+                entry.paid_invoices += 1;
+            }
+            continue;
+        }
+
+        let label = invoice.invoice_number.as_deref().unwrap_or(&invoice.id);
+        let Some(rate) = rate_to(invoice, base_currency) else {
+            warnings.push(format!(
+                "{}: no {} exchange rate for {}, not included",
+                label, base_currency, invoice.currency
+            ));
+            continue;
+        };
+        let due = parse_date(invoice.due_date.as_deref()).or_else(|| parse_date(invoice.invoice_date.as_deref()));
+        let days_overdue = match due {
+            Some(due) => (as_of - due).num_days(),
+            None => {
+                warnings.push(format!("{}: no due or invoice date, counted as current", label));
+                0
+            }
+        };
+        entry.buckets[bucket(days_overdue)] += invoice.total_amount.convert(rate);
+        entry.open_invoices += 1;
+        entry.oldest_days_overdue = entry.oldest_days_overdue.max(days_overdue);
+    }
+
+    let mut totals = [Money::ZERO; 5];
+    let mut days_to_pay = 0;
+    let mut paid_invoices = 0;
+    let mut open_invoices = 0;
+    let mut rows = Vec::new();
+    for aging in by_counterparty.into_values() {
+        days_to_pay += aging.days_to_pay;
+        paid_invoices += aging.paid_invoices;
+        if aging.open_invoices == 0 {
+            continue;
+        }
+        open_invoices += aging.open_invoices;
+        for (total, amount) in totals.iter_mut().zip(aging.buckets) {
+            *total += amount;
+        }
+        rows.push(AgingRow {
+            counterparty_id: aging.counterparty_id,
+            counterparty_name: aging.name,
+            buckets: aging.buckets.to_vec(),
+            total: aging.buckets.into_iter().sum(),
+            open_invoices: aging.open_invoices,
+            oldest_days_overdue: aging.oldest_days_overdue,
+            average_days_to_pay: average(aging.days_to_pay, aging.paid_invoices),
+        });
+    }
+    rows.sort_by(|a, b| b.total.cmp(&a.total).then_with(|| a.counterparty_name.cmp(&b.counterparty_name)));
+
+    Ok(AgingReport {
+        category: category.to_string(),
+        as_of: as_of.format("%Y-%m-%d").to_string(),
+        currency: base_currency.to_string(),
+        buckets: BUCKETS.iter().map(|label| label.to_string()).collect(),
+        rows,
+        totals: totals.to_vec(),
+        total: totals.into_iter().sum(),
+        open_invoices,
+        paid_invoices,
+        average_days_to_pay: average(days_to_pay, paid_invoices),
+        warnings,
+    })
+}
+
+/// Days from invoice date to payment; payments before the invoice date
+/// count as zero.
+fn days_to_pay(invoice: &Invoice) -> Option<i64> {
+    let issued = parse_date(invoice.invoice_date.as_deref())?;
+    let paid = parse_date(invoice.paid_at.as_deref())?;
+    Some((paid - issued).num_days().max(0))
+}
+
+fn average(days: i64, count: u32) -> Option<f64> {
+    (count > 0).then(|| days as f64 / count as f64)
+}
@@ -0,0 +1,864 @@
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, NaiveDate};
+use regex::Regex;
+use roxmltree::{Document, Node};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use crate::services::einvoice::{child, children, path, text_at};
+use crate::utils::Money;
+
+/// SEPA keywords in MT940 purpose texts.
+static SEPA_TAG: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(EREF|KREF|MREF|CRED|DEBT|SVWZ|ABWA|ABWE|COAM|OAMT|IBAN|BIC)\+").unwrap());
+
+/// One booked statement line before it is stored. `amount` is signed:
+/// incoming payments are positive.
+#[derive(Debug, Clone)]
+pub struct StatementLine {
+    pub account_iban: Option<String>,
+    pub booking_date: NaiveDate,
+    pub value_date: Option<NaiveDate>,
+    pub amount: Money,
+    pub currency: String,
+    pub counterparty_name: Option<String>,
+    pub counterparty_iban: Option<String>,
+    pub remittance: Option<String>,
+    pub reference: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatementFormat {
+    Camt053,
+    Mt940,
+    Csv,
+}
+
+impl StatementFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StatementFormat::Camt053 => "camt053",
+            StatementFormat::Mt940 => "mt940",
+            StatementFormat::Csv => "csv",
+        }
+    }
+}
+
+/// Column headers of a CSV export. Alternatives are separated by `|`. Either
+/// `amount` or `debit` and `credit` must be set. `payee_name`, when set, is
+/// the counterparty of outgoing payments and `counterparty_name` that of
+/// incoming ones.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CsvMapping {
+    pub booking_date: String,
+    pub value_date: Option<String>,
+    pub amount: Option<String>,
+    pub debit: Option<String>,
+    pub credit: Option<String>,
+    pub currency: Option<String>,
+    pub counterparty_name: Option<String>,
+    pub payee_name: Option<String>,
+    pub counterparty_iban: Option<String>,
+    pub remittance: Option<String>,
+    pub reference: Option<String>,
+}
+
+/// Column mappings of the CSV exports of common German banks, most specific
+/// first so automatic detection picks the right one.
+fn builtin_profiles() -> Vec<(&'static str, CsvMapping)> {
+    let column = |name: &str| Some(name.to_string());
+    vec![
+        (
+            "sparkasse",
+            CsvMapping {
+                booking_date: "Buchungstag".to_string(),
+                value_date: column("Valutadatum"),
+                amount: column("Betrag"),
+                currency: column("Waehrung"),
+                counterparty_name: column("Beguenstigter/Zahlungspflichtiger"),
+                counterparty_iban: column("Kontonummer/IBAN"),
+                remittance: column("Verwendungszweck"),
+                reference: column("Kundenreferenz (End-to-End)"),
+                ..CsvMapping::default()
+            },
+        ),
+        (
+            "deutsche_bank",
+            CsvMapping {
+                booking_date: "Buchungstag".to_string(),
+                value_date: column("Wert"),
+                debit: column("Soll"),
+                credit: column("Haben"),
+                currency: column("Währung"),
+                counterparty_name: column("Begünstigter / Auftraggeber"),
+                counterparty_iban: column("IBAN"),
+                remittance: column("Verwendungszweck"),
+                reference: column("Kundenreferenz"),
+                ..CsvMapping::default()
+            },
+        ),
+        (
+            "dkb",
+            CsvMapping {
+                booking_date: "Buchungsdatum".to_string(),
+                value_date: column("Wertstellung"),
+                amount: column("Betrag (€)"),
+                counterparty_name: column("Zahlungspflichtige*r"),
+                payee_name: column("Zahlungsempfänger*in"),
+                counterparty_iban: column("IBAN"),
+                remittance: column("Verwendungszweck"),
+                reference: column("Kundenreferenz"),
+                ..CsvMapping::default()
+            },
+        ),
+        (
+            "ing",
+            CsvMapping {
+                booking_date: "Buchung".to_string(),
+                value_date: column("Wertstellungsdatum|Valuta"),
+                amount: column("Betrag"),
+                currency: column("Währung"),
+                counterparty_name: column("Auftraggeber/Empfänger"),
+                remittance: column("Verwendungszweck"),
+                ..CsvMapping::default()
+            },
+        ),
+        (
+            "comdirect",
+            CsvMapping {
+                booking_date: "Buchungstag".to_string(),
+                value_date: column("Wertstellung (Valuta)"),
+                amount: column("Umsatz in EUR"),
+                remittance: column("Buchungstext"),
+                ..CsvMapping::default()
+            },
+        ),
+        (
+            "commerzbank",
+            CsvMapping {
+                booking_date: "Buchungstag".to_string(),
+                value_date: column("Wertstellung"),
+                amount: column("Betrag"),
+                currency: column("Währung"),
+                remittance: column("Buchungstext"),
+                ..CsvMapping::default()
+            },
+        ),
+    ]
+}
+
+/// Names of the built-in CSV profiles, for the import dialog.
+pub fn csv_profiles() -> Vec<String> {
+    builtin_profiles().into_iter().map(|(name, _)| name.to_string()).collect()
+}
+
+/// Reads a bank statement file. The format is recognized from the content;
+/// CSV files use `mapping` when given, otherwise the named `profile` or the
+/// first built-in profile whose columns are all present.
+pub fn parse_file(
+    file: &Path,
+    profile: Option<&str>,
+    mapping: Option<&CsvMapping>,
+) -> Result<(StatementFormat, Vec<StatementLine>)> {
+    let content = decode(&std::fs::read(file)?);
+    let trimmed = content.trim_start_matches('\u{feff}').trim_start();
+    if trimmed.starts_with('<') {
+        return Ok((StatementFormat::Camt053, parse_camt053(trimmed)?));
+    }
+    if trimmed.starts_with(":20:") || trimmed.starts_with("{1:") || content.contains("\n:61:") {
+        return Ok((StatementFormat::Mt940, parse_mt940(&content)?));
+    }
+    let mapping = match (mapping, profile.filter(|profile| !profile.is_empty() && *profile != "auto")) {
+        (Some(mapping), _) => Some(mapping.clone()),
+        (None, Some(name)) => Some(
+            builtin_profiles()
+                .into_iter()
+                .find(|(profile, _)| *profile == name)
+                .map(|(_, mapping)| mapping)
+                .ok_or_else(|| anyhow!("Unknown CSV profile: {}", name))?,
+        ),
+        (None, None) => None,
+    };
+    Ok((StatementFormat::Csv, parse_csv(trimmed, mapping.as_ref())?))
+}
+
+/// Stable key of a statement line, so importing the same statement twice
+/// adds nothing. `occurrence` tells identical lines of one file apart.
+pub fn import_hash(line: &StatementLine, occurrence: usize) -> String {
+    let mut hasher = Sha256::new();
+    for part in [
+        line.account_iban.as_deref().unwrap_or(""),
+        &line.booking_date.to_string(),
+        &line.amount.to_string(),
+        &line.currency,
+        line.counterparty_iban.as_deref().unwrap_or(""),
+        line.remittance.as_deref().unwrap_or(""),
+        line.reference.as_deref().unwrap_or(""),
+        &occurrence.to_string(),
+    ] {
+        hasher.update(part.as_bytes());
+        hasher.update([0x1f]);
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// Bank exports are UTF-8 or Windows-1252.
+fn decode(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes
+            .iter()
+            .map(|&byte| if byte == 0x80 { '€' } else { byte as char })
+            .collect(),
+    }
+}
+
+fn parse_camt053(xml: &str) -> Result<Vec<StatementLine>> {
+    let document = Document::parse(xml)?;
+    let root = document.root_element();
+    let report = child(root, "BkToCstmrStmt").ok_or_else(|| anyhow!("Not a CAMT.053 statement"))?;
+    let mut lines = Vec::new();
+    for statement in children(report, "Stmt") {
+        let account_iban = text_at(statement, &["Acct", "Id", "IBAN"]);
+        let statement_currency = text_at(statement, &["Acct", "Ccy"]);
+        for entry in children(statement, "Ntry") {
+            let status = text_at(entry, &["Sts", "Cd"]).or_else(|| text_at(entry, &["Sts"]));
+            if status.as_deref().is_some_and(|status| status != "BOOK") {
+                continue;
+            }
+            let credit = text_at(entry, &["CdtDbtInd"]).as_deref() == Some("CRDT");
+            let booking_date = camt_date(entry, "BookgDt")
+                .or_else(|| camt_date(entry, "ValDt"))
+                .ok_or_else(|| anyhow!("Entry without booking date"))?;
+            let value_date = camt_date(entry, "ValDt");
+            let (entry_amount, entry_currency) = camt_amount(entry)?;
+            let currency = entry_currency
+                .or_else(|| statement_currency.clone())
+                .unwrap_or_else(|| "EUR".to_string());
+            let entry_info = text_at(entry, &["AddtlNtryInf"]);
+            let bank_reference = text_at(entry, &["AcctSvcrRef"]);
+
+            let details: Vec<Node> = children(entry, "NtryDtls")
+                .flat_map(|batch| children(batch, "TxDtls").collect::<Vec<_>>())
+                .collect();
+            let single = details.len() <= 1;
+            if details.is_empty() {
+                lines.push(StatementLine {
+                    account_iban: account_iban.clone(),
+                    booking_date,
+                    value_date,
+                    amount: signed(entry_amount, credit),
+                    currency,
+                    counterparty_name: None,
+                    counterparty_iban: None,
+                    remittance: entry_info,
+                    reference: bank_reference,
+                });
+                continue;
+            }
+            // A batch booking lists each payment; without single amounts the
+            // booking stays one line.
+            let has_amounts = details.iter().all(|detail| child(*detail, "Amt").is_some());
+            for detail in &details {
+                let amount = if single || !has_amounts {
+                    entry_amount
+                } else {
+                    camt_amount(*detail)?.0
+                };
+                let party = if credit { "Dbtr" } else { "Cdtr" };
+                let party_node = path(*detail, &["RltdPties", party]);
+                let counterparty_name = party_node.and_then(|node| {
+                    text_at(node, &["Nm"]).or_else(|| text_at(node, &["Pty", "Nm"]))
+                });
+                let account = format!("{}Acct", party);
+                let counterparty_iban = path(*detail, &["RltdPties", account.as_str(), "Id", "IBAN"])
+                    .and_then(|node| node.text())
+                    .map(|iban| iban.trim().to_string());
+                let mut remittance: Vec<String> = path(*detail, &["RmtInf"])
+                    .map(|info| {
+                        children(info, "Ustrd")
+                            .filter_map(|node| node.text().map(|text| text.trim().to_string()))
+                            .chain(children(info, "Strd").filter_map(|node| text_at(node, &["CdtrRefInf", "Ref"])))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                if remittance.is_empty() {
+                    remittance.extend(entry_info.clone());
+                }
+                let reference = text_at(*detail, &["Refs", "EndToEndId"])
+                    .filter(|reference| reference != "NOTPROVIDED")
+                    .or_else(|| bank_reference.clone());
+                lines.push(StatementLine {
+                    account_iban: account_iban.clone(),
+                    booking_date,
+                    value_date,
+                    amount: signed(amount, credit),
+                    currency: currency.clone(),
+                    counterparty_name,
+                    counterparty_iban,
+                    remittance: Some(remittance.join(" ")).filter(|text| !text.is_empty()),
+                    reference,
+                });
+                if !has_amounts {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(lines)
+}
+
+fn camt_date(entry: Node, name: &str) -> Option<NaiveDate> {
+    text_at(entry, &[name, "Dt"])
+        .or_else(|| text_at(entry, &[name, "DtTm"]))
+        .and_then(|value| value.get(..10).and_then(|day| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok()))
+}
+
+fn camt_amount(node: Node) -> Result<(Money, Option<String>)> {
+    let amount = child(node, "Amt").ok_or_else(|| anyhow!("Entry without amount"))?;
+    let value = Money::parse(amount.text().unwrap_or_default())?;
+    Ok((value, amount.attribute("Ccy").map(str::to_string)))
+}
+
+fn signed(amount: Money, credit: bool) -> Money {
+    let positive = if amount < Money::ZERO { -amount } else { amount };
+    if credit {
+        positive
+    } else {
+        -positive
+    }
+}
+
+fn parse_mt940(content: &str) -> Result<Vec<StatementLine>> {
+    let mut fields: Vec<(String, String)> = Vec::new();
+    for raw in content.lines() {
+        let line = raw.trim_end_matches('\r');
+        if line.starts_with('-') && line.trim_matches('-').trim().is_empty() {
+            continue;
+        }
+        let tag = line
+            .strip_prefix(':')
+            .and_then(|rest| rest.split_once(':'))
+            .filter(|(tag, _)| tag.len() <= 3 && tag.chars().all(|c| c.is_ascii_alphanumeric()));
+        match (tag, fields.last_mut()) {
+            (Some((tag, value)), _) => fields.push((tag.to_string(), value.to_string())),
+            (None, Some((_, value))) => {
+                value.push('\n');
+                value.push_str(line);
+            }
+            (None, None) => {}
+        }
+    }
+
+    let mut lines: Vec<StatementLine> = Vec::new();
+    let mut account_iban = None;
+    let mut currency = "EUR".to_string();
+    for (tag, value) in fields {
+        match tag.as_str() {
+            "25" => {
+                let account = value.trim().replace(' ', "");
+                account_iban = looks_like_iban(&account).then_some(account);
+            }
+            "60F" | "60M" => {
+                if let Some(code) = value.trim().get(7..10) {
+                    currency = code.to_string();
+                }
+            }
+            "61" => lines.push(mt940_statement_line(&value, account_iban.clone(), &currency)?),
+            "86" => {
+                if let Some(line) = lines.last_mut() {
+                    apply_mt940_details(line, &value);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(lines)
+}
+
+/// `:61:` — value date, optional entry date, mark, amount, type and
+/// references, e.g. `2403150315CR1234,56NTRFNONREF//B4C15`.
+fn mt940_statement_line(value: &str, account_iban: Option<String>, currency: &str) -> Result<StatementLine> {
+    let first = value.lines().next().unwrap_or_default().trim();
+    let invalid = || anyhow!("Invalid :61: line: {}", first);
+    let value_date = NaiveDate::parse_from_str(first.get(..6).ok_or_else(invalid)?, "%y%m%d").map_err(|_| invalid())?;
+    let mut rest = &first[6..];
+    let mut booking_date = value_date;
+    if rest.len() >= 4 && rest[..4].chars().all(|c| c.is_ascii_digit()) {
+        let month: u32 = rest[..2].parse().map_err(|_| invalid())?;
+        let day: u32 = rest[2..4].parse().map_err(|_| invalid())?;
+        let year = match (value_date.month(), month) {
+            (12, 1) => value_date.year() + 1,
+            (1, 12) => value_date.year() - 1,
+            _ => value_date.year(),
+        };
+        booking_date = NaiveDate::from_ymd_opt(year, month, day).ok_or_else(invalid)?;
+        rest = &rest[4..];
+    }
+    let (credit, after_mark) = if let Some(rest) = rest.strip_prefix("RC") {
+        (false, rest)
+    } else if let Some(rest) = rest.strip_prefix("RD") {
+        (true, rest)
+    } else if let Some(rest) = rest.strip_prefix('C') {
+        (true, rest)
+    } else if let Some(rest) = rest.strip_prefix('D') {
+        (false, rest)
+    } else {
+        return Err(invalid());
+    };
+    let after_mark = after_mark.strip_prefix(|c: char| c.is_ascii_alphabetic()).unwrap_or(after_mark);
+    let amount_end = after_mark
+        .find(|c: char| !c.is_ascii_digit() && c != ',')
+        .unwrap_or(after_mark.len());
+    let amount = Money::parse(&after_mark[..amount_end])?;
+    let references = after_mark[amount_end..].get(4..).unwrap_or_default();
+    let reference = references
+        .split("//")
+        .next()
+        .map(str::trim)
+        .filter(|reference| !reference.is_empty() && *reference != "NONREF")
+        .map(str::to_string);
+
+    Ok(StatementLine {
+        account_iban,
+        booking_date,
+        value_date: Some(value_date),
+        amount: signed(amount, credit),
+        currency: currency.to_string(),
+        counterparty_name: None,
+        counterparty_iban: None,
+        remittance: None,
+        reference,
+    })
+}
+
+/// `:86:` — structured German details (`?20`–`?29` and `?60`–`?63`
+/// remittance, `?31` account, `?32`/`?33` name) or free text.
+fn apply_mt940_details(line: &mut StatementLine, value: &str) {
+    let joined: String = value.lines().map(str::trim_end).collect();
+    let separator = joined
+        .get(..3)
+        .filter(|code| code.chars().all(|c| c.is_ascii_digit()))
+        .and_then(|_| joined[3..].chars().next())
+        .filter(|c| !c.is_alphanumeric());
+    let Some(separator) = separator else {
+        line.remittance = Some(joined.trim().to_string()).filter(|text| !text.is_empty());
+        return;
+    };
+
+    let mut subfields: HashMap<u32, String> = HashMap::new();
+    for part in joined[3..].split(separator).skip(1) {
+        let (Some(code), Some(text)) = (part.get(..2).and_then(|code| code.parse().ok()), part.get(2..)) else {
+            continue;
+        };
+        subfields.entry(code).or_default().push_str(text);
+    }
+    let joined_range = |codes: &[u32]| -> String {
+        codes
+            .iter()
+            .filter_map(|code| subfields.get(code))
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join("")
+    };
+    let text = joined_range(&[20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 60, 61, 62, 63]);
+    let (remittance, end_to_end) = split_sepa_tags(&text);
+    let name = joined_range(&[32, 33]);
+    let account = subfields.get(&31).map(|account| account.trim().replace(' ', ""));
+
+    line.remittance = Some(remittance).filter(|text| !text.is_empty());
+    line.counterparty_name = Some(name.trim().to_string()).filter(|name| !name.is_empty());
+    line.counterparty_iban = account.filter(|account| looks_like_iban(account));
+    if end_to_end.is_some() {
+        line.reference = end_to_end;
+    }
+}
+
+/// Separates SEPA keywords (`EREF+`, `SVWZ+`, …) in the purpose text. The
+/// remittance is the `SVWZ+` part, or the whole text without keywords.
+fn split_sepa_tags(text: &str) -> (String, Option<String>) {
+    let starts: Vec<(usize, usize, &str)> = SEPA_TAG
+        .captures_iter(text)
+        .filter_map(|captures| {
+            let whole = captures.get(0)?;
+            Some((whole.start(), whole.end(), captures.get(1)?.as_str()))
+        })
+        .collect();
+    if starts.is_empty() {
+        return (text.trim().to_string(), None);
+    }
+    let mut parts: HashMap<&str, String> = HashMap::new();
+    for (index, (_, end, tag)) in starts.iter().enumerate() {
+        let next = starts.get(index + 1).map(|(start, _, _)| *start).unwrap_or(text.len());
+        parts.insert(tag, text[*end..next].trim().to_string());
+    }
+    let remittance = parts
+        .get("SVWZ")
+        .cloned()
+        .unwrap_or_else(|| text[..starts[0].0].trim().to_string());
+    let end_to_end = parts
+        .get("EREF")
+        .cloned()
+        .filter(|reference| !reference.is_empty() && reference != "NOTPROVIDED");
+    (remittance, end_to_end)
+}
+
+fn looks_like_iban(value: &str) -> bool {
+    value.len() >= 15
+        && value.chars().take(2).all(|c| c.is_ascii_uppercase())
+        && value.chars().skip(2).all(|c| c.is_ascii_alphanumeric())
+}
+
+fn parse_csv(content: &str, mapping: Option<&CsvMapping>) -> Result<Vec<StatementLine>> {
+    let rows: Vec<&str> = content.lines().collect();
+    let split = |row: &str, delimiter: u8| -> Vec<String> {
+        csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(row.as_bytes())
+            .records()
+            .next()
+            .and_then(|record| record.ok())
+            .map(|record| record.iter().map(|cell| cell.trim().to_string()).collect())
+            .unwrap_or_default()
+    };
+
+    // Exports start with account details of varying length; the header is the
+    // first row that has all columns of the mapping.
+    let candidates: Vec<CsvMapping> = match mapping {
+        Some(mapping) => vec![mapping.clone()],
+        None => builtin_profiles().into_iter().map(|(_, mapping)| mapping).collect(),
+    };
+    let mut found = None;
+    'rows: for (index, row) in rows.iter().enumerate().take(40) {
+        for delimiter in [b';', b',', b'\t'] {
+            let headers = split(row, delimiter);
+            if headers.len() < 3 {
+                continue;
+            }
+            for candidate in &candidates {
+                if let Some(columns) = resolve_columns(candidate, &headers) {
+                    found = Some((index, delimiter, headers, columns));
+                    break 'rows;
+                }
+            }
+        }
+    }
+    let (header_index, delimiter, headers, columns) =
+        found.ok_or_else(|| anyhow!("No known bank CSV header found; choose a profile or set the columns"))?;
+    let status_column = headers.iter().position(|header| header.eq_ignore_ascii_case("Status"));
+
+    let mut lines = Vec::new();
+    let body = rows[header_index + 1..].join("\n");
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(body.as_bytes());
+    for record in reader.records() {
+        let record = record?;
+        let cell = |column: Option<usize>| {
+            column
+                .and_then(|column| record.get(column))
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        // Footer rows such as opening and closing balances have no date.
+        let Some(booking_date) = cell(Some(columns.booking_date)).and_then(|value| parse_csv_date(&value)) else {
+            continue;
+        };
+        let pending = record
+            .iter()
+            .any(|value| value.trim().eq_ignore_ascii_case("vorgemerkt") || value.trim().eq_ignore_ascii_case("Umsatz vorgemerkt"));
+        if pending || cell(status_column).is_some_and(|status| status.eq_ignore_ascii_case("Vorgemerkt")) {
+            continue;
+        }
+        let amount = match columns.amount {
+            Some(column) => parse_csv_amount(cell(Some(column)).as_deref())?,
+            None => {
+                let credit = parse_csv_amount(cell(columns.credit).as_deref())?;
+                let debit = parse_csv_amount(cell(columns.debit).as_deref())?;
+                signed(credit, true) + signed(debit, false)
+            }
+        };
+        let name = match columns.payee_name {
+            Some(payee) if amount < Money::ZERO => cell(Some(payee)),
+            _ => cell(columns.counterparty_name),
+        };
+        lines.push(StatementLine {
+            account_iban: None,
+            booking_date,
+            value_date: cell(columns.value_date).and_then(|value| parse_csv_date(&value)),
+            amount,
+            currency: cell(columns.currency).unwrap_or_else(|| "EUR".to_string()),
+            counterparty_name: name,
+            counterparty_iban: cell(columns.counterparty_iban).map(|iban| iban.replace(' ', "")),
+            remittance: cell(columns.remittance),
+            reference: cell(columns.reference).filter(|reference| reference != "NOTPROVIDED"),
+        });
+    }
+    Ok(lines)
+}
+
+struct CsvColumns {
+    booking_date: usize,
+    value_date: Option<usize>,
+    amount: Option<usize>,
+    debit: Option<usize>,
+    credit: Option<usize>,
+    currency: Option<usize>,
+    counterparty_name: Option<usize>,
+    payee_name: Option<usize>,
+    counterparty_iban: Option<usize>,
+    remittance: Option<usize>,
+    reference: Option<usize>,
+}
+
+/// Finds every mapped column in `headers`; `None` if one is missing.
+fn resolve_columns(mapping: &CsvMapping, headers: &[String]) -> Option<CsvColumns> {
+    let find = |names: &str| {
+        names.split('|').find_map(|name| {
+            headers
+                .iter()
+                .position(|header| header.trim_matches('"').eq_ignore_ascii_case(name.trim()))
+        })
+    };
+    let optional = |names: &Option<String>| -> Option<Option<usize>> {
+        match names.as_deref().filter(|names| !names.trim().is_empty()) {
+            Some(names) => find(names).map(Some),
+            None => Some(None),
+        }
+    };
+    let columns = CsvColumns {
+        booking_date: find(&mapping.booking_date)?,
+        value_date: optional(&mapping.value_date)?,
+        amount: optional(&mapping.amount)?,
+        debit: optional(&mapping.debit)?,
+        credit: optional(&mapping.credit)?,
+        currency: optional(&mapping.currency)?,
+        counterparty_name: optional(&mapping.counterparty_name)?,
+        payee_name: optional(&mapping.payee_name)?,
+        counterparty_iban: optional(&mapping.counterparty_iban)?,
+        remittance: optional(&mapping.remittance)?,
+        reference: optional(&mapping.reference)?,
+    };
+    if columns.amount.is_none() && (columns.debit.is_none() || columns.credit.is_none()) {
+        return None;
+    }
+    Some(columns)
+}
+
+/// Two-digit years are tried first: `%Y` would read `24` as the year 24.
+fn parse_csv_date(value: &str) -> Option<NaiveDate> {
+    ["%d.%m.%y", "%d.%m.%Y", "%Y-%m-%d", "%d/%m/%Y"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(value.trim(), format).ok())
+}
+
+fn parse_csv_amount(value: Option<&str>) -> Result<Money> {
+    match value.map(|value| value.trim_start_matches('+')) {
+        Some(value) if !value.is_empty() => Money::parse(value),
+        _ => Ok(Money::ZERO),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(raw: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(raw, "%Y-%m-%d").unwrap()
+    }
+
+    fn amount(raw: &str) -> Money {
+        Money::parse(raw).unwrap()
+    }
+
+    const MT940: &str = ":20:STARTUMS
+:25:DE89370400440532013000
+:28C:00001/001
+:60F:C241230EUR1000,00
+:61:2412310102CR1234,56NTRFNONREF//B4C15
+:86:166?00GUTSCHRIFT?109251?20EREF+E2E-4711?21SVWZ+RE-2024-001 Danke f
+?22uer die Lieferung?30COBADEFFXXX?31DE02120300000000202051
+?32Muster GmbH
+:61:241230RC50,00NMSCNONREF
+:86:Storno Gutschrift
+:61:241230D19,99NDDTKREF-77//B4C16
+:86:177?20Lastschrift Telefon?32Telekom?33 Deutschland GmbH
+:62F:C250102EUR2165,57
+-";
+
+    #[test]
+    fn mt940_reads_marks_dates_and_details() {
+        let lines = parse_mt940(MT940).unwrap();
+        assert_eq!(lines.len(), 3);
+
+        // Entry date in January after a December value date is next year.
+        let credit = &lines[0];
+        assert_eq!(credit.account_iban.as_deref(), Some("DE89370400440532013000"));
+        assert_eq!(credit.value_date, Some(date("2024-12-31")));
+        assert_eq!(credit.booking_date, date("2025-01-02"));
+        assert_eq!(credit.amount, amount("1234.56"));
+        assert_eq!(credit.currency, "EUR");
+        assert_eq!(credit.remittance.as_deref(), Some("RE-2024-001 Danke fuer die Lieferung"));
+        assert_eq!(credit.reference.as_deref(), Some("E2E-4711"));
+        assert_eq!(credit.counterparty_name.as_deref(), Some("Muster GmbH"));
+        assert_eq!(credit.counterparty_iban.as_deref(), Some("DE02120300000000202051"));
+
+        // RC reverses a credit, so money goes out.
+        let reversal = &lines[1];
+        assert_eq!(reversal.amount, amount("-50.00"));
+        assert_eq!(reversal.booking_date, date("2024-12-30"));
+        assert_eq!(reversal.reference, None);
+        assert_eq!(reversal.remittance.as_deref(), Some("Storno Gutschrift"));
+
+        let debit = &lines[2];
+        assert_eq!(debit.amount, amount("-19.99"));
+        assert_eq!(debit.reference.as_deref(), Some("KREF-77"));
+        assert_eq!(debit.remittance.as_deref(), Some("Lastschrift Telefon"));
+        assert_eq!(debit.counterparty_name.as_deref(), Some("Telekom Deutschland GmbH"));
+    }
+
+    #[test]
+    fn mt940_reversed_debit_and_entry_date_before_new_year() {
+        let line = mt940_statement_line("2501021231RD75,00NTRFNONREF", None, "EUR").unwrap();
+        assert_eq!(line.amount, amount("75.00"));
+        assert_eq!(line.value_date, Some(date("2025-01-02")));
+        assert_eq!(line.booking_date, date("2024-12-31"));
+        assert!(mt940_statement_line("2501X", None, "EUR").is_err());
+    }
+
+    #[test]
+    fn sepa_tags_split_purpose_and_end_to_end_id() {
+        let (remittance, reference) = split_sepa_tags("EREF+NOTPROVIDED MREF+M-1 SVWZ+Miete Januar");
+        assert_eq!(remittance, "Miete Januar");
+        assert_eq!(reference, None);
+        let (remittance, reference) = split_sepa_tags("Rechnung 4711 EREF+ABC-1");
+        assert_eq!(remittance, "Rechnung 4711");
+        assert_eq!(reference.as_deref(), Some("ABC-1"));
+        assert_eq!(split_sepa_tags(" Bargeld "), ("Bargeld".to_string(), None));
+    }
+
+    const CAMT053: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.08">
+  <BkToCstmrStmt>
+    <Stmt>
+      <Acct><Id><IBAN>DE89370400440532013000</IBAN></Id><Ccy>EUR</Ccy></Acct>
+      <Ntry>
+        <Amt Ccy="EUR">300.00</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <Sts><Cd>BOOK</Cd></Sts>
+        <BookgDt><Dt>2024-03-15</Dt></BookgDt>
+        <ValDt><Dt>2024-03-16</Dt></ValDt>
+        <AcctSvcrRef>BANK-1</AcctSvcrRef>
+        <NtryDtls>
+          <TxDtls>
+            <Refs><EndToEndId>E2E-A</EndToEndId></Refs>
+            <Amt Ccy="EUR">100.00</Amt>
+            <RltdPties>
+              <Dbtr><Nm>Alpha GmbH</Nm></Dbtr>
+              <DbtrAcct><Id><IBAN>DE02120300000000202051</IBAN></Id></DbtrAcct>
+            </RltdPties>
+            <RmtInf><Ustrd>RE-2024-001</Ustrd></RmtInf>
+          </TxDtls>
+          <TxDtls>
+            <Refs><EndToEndId>NOTPROVIDED</EndToEndId></Refs>
+            <Amt Ccy="EUR">200.00</Amt>
+            <RltdPties><Dbtr><Pty><Nm>Beta AG</Nm></Pty></Dbtr></RltdPties>
+            <RmtInf><Strd><CdtrRefInf><Ref>RF18539007547034</Ref></CdtrRefInf></Strd></RmtInf>
+          </TxDtls>
+        </NtryDtls>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">45.10</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <Sts><Cd>BOOK</Cd></Sts>
+        <BookgDt><DtTm>2024-03-18T10:00:00</DtTm></BookgDt>
+        <AddtlNtryInf>Kontofuehrung</AddtlNtryInf>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">9.99</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <Sts><Cd>PDNG</Cd></Sts>
+        <BookgDt><Dt>2024-03-19</Dt></BookgDt>
+      </Ntry>
+    </Stmt>
+  </BkToCstmrStmt>
+</Document>"#;
+
+    #[test]
+    fn camt053_splits_batches_and_skips_pending_entries() {
+        let lines = parse_camt053(CAMT053).unwrap();
+        assert_eq!(lines.len(), 3);
+
+        let first = &lines[0];
+        assert_eq!(first.amount, amount("100.00"));
+        assert_eq!(first.booking_date, date("2024-03-15"));
+        assert_eq!(first.value_date, Some(date("2024-03-16")));
+        assert_eq!(first.counterparty_name.as_deref(), Some("Alpha GmbH"));
+        assert_eq!(first.counterparty_iban.as_deref(), Some("DE02120300000000202051"));
+        assert_eq!(first.remittance.as_deref(), Some("RE-2024-001"));
+        assert_eq!(first.reference.as_deref(), Some("E2E-A"));
+
+        let second = &lines[1];
+        assert_eq!(second.amount, amount("200.00"));
+        assert_eq!(second.counterparty_name.as_deref(), Some("Beta AG"));
+        assert_eq!(second.remittance.as_deref(), Some("RF18539007547034"));
+        assert_eq!(second.reference.as_deref(), Some("BANK-1"));
+
+        let fee = &lines[2];
+        assert_eq!(fee.amount, amount("-45.10"));
+        assert_eq!(fee.booking_date, date("2024-03-18"));
+        assert_eq!(fee.remittance.as_deref(), Some("Kontofuehrung"));
+    }
+
+    #[test]
+    fn camt053_batch_without_single_amounts_stays_one_line() {
+        let xml = CAMT053.replace("<Amt Ccy=\"EUR\">100.00</Amt>", "");
+        let lines = parse_camt053(&xml).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].amount, amount("300.00"));
+        assert_eq!(lines[0].counterparty_name.as_deref(), Some("Alpha GmbH"));
+    }
+
+    #[test]
+    fn csv_finds_header_after_preamble_and_skips_pending_rows() {
+        let content = "\"Konto\";\"DE89370400440532013000\"
+\"Zeitraum\";\"01.03.2024 - 31.03.2024\"
+
+Buchungstag;Valutadatum;Beguenstigter/Zahlungspflichtiger;Kontonummer/IBAN;Verwendungszweck;Kundenreferenz (End-to-End);Betrag;Waehrung
+15.03.24;16.03.24;Alpha GmbH;DE02 1203 0000 0000 2020 51;RE-2024-001;NOTPROVIDED;1.234,56;EUR
+18.03.24;18.03.24;Telekom;;Rechnung 03/24;KREF-77;-19,99;EUR
+19.03.24;19.03.24;Vorgemerkt;;Umsatz vorgemerkt;;-5,00;EUR
+;;;;Endsaldo;;2.000,00;EUR
+";
+        let lines = parse_csv(content, None).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].booking_date, date("2024-03-15"));
+        assert_eq!(lines[0].amount, amount("1234.56"));
+        assert_eq!(lines[0].counterparty_iban.as_deref(), Some("DE02120300000000202051"));
+        assert_eq!(lines[0].reference, None);
+        assert_eq!(lines[1].amount, amount("-19.99"));
+        assert_eq!(lines[1].reference.as_deref(), Some("KREF-77"));
+    }
+
+    #[test]
+    fn csv_debit_and_credit_columns() {
+        let content = "Buchungstag;Wert;Verwendungszweck;Begünstigter / Auftraggeber;IBAN;Kundenreferenz;Soll;Haben;Währung
+02.04.2024;02.04.2024;Miete;Vermieter KG;;;-800,00;;EUR
+03.04.2024;03.04.2024;RE-7;Kunde AG;;;;+119,00;EUR
+";
+        let lines = parse_csv(content, None).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].amount, amount("-800.00"));
+        assert_eq!(lines[0].counterparty_name.as_deref(), Some("Vermieter KG"));
+        assert_eq!(lines[1].amount, amount("119.00"));
+        assert!(parse_csv("a;b;c\n1;2;3\n", None).is_err());
+    }
+}
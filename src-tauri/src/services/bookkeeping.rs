@@ -0,0 +1,157 @@
+use anyhow::Result;
+use chrono::NaiveDate;
+
+use crate::db::Database;
+use crate::models::{Invoice, InvoiceOverride, InvoiceVatEntry};
+use crate::utils::Money;
+
+/// Replaces extracted values with the user's corrections.
+pub fn apply_overrides(invoice: &mut Invoice, overrides: &[InvoiceOverride]) {
+    for override_entry in overrides {
+        match override_entry.field_name.as_str() {
+            "invoice_number" => invoice.invoice_number = Some(override_entry.override_value.clone()),
+            "invoice_date" => invoice.invoice_date = Some(override_entry.override_value.clone()),
+            "due_date" => invoice.due_date = Some(override_entry.override_value.clone()),
+            "counterparty_name" => invoice.counterparty_name = Some(override_entry.override_value.clone()),
+            "total_amount" => {
+                if let Ok(amount) = Money::parse(&override_entry.override_value) {
+                    invoice.total_amount = amount;
+                }
+            }
+            "currency" => invoice.currency = override_entry.override_value.clone(),
+            "tax_amount" => invoice.tax_amount = Money::parse(&override_entry.override_value).ok(),
+            "net_amount" => invoice.net_amount = Money::parse(&override_entry.override_value).ok(),
+            "status" => invoice.status = override_entry.override_value.clone(),
+            "paid_at" => invoice.paid_at = Some(override_entry.override_value.clone()),
+            "account_number" => invoice.account_number = Some(override_entry.override_value.clone()),
+            _ => {}
+        }
+    }
+}
+
+/// Which processed invoices a report or export covers. Date bounds are
+/// inclusive and checked after overrides are applied, against `paid_at`
+/// when `by_payment_date` is set and the invoice date otherwise.
+#[derive(Debug, Clone, Default)]
+pub struct InvoiceFilter {
+    pub category: Option<String>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub status: Option<String>,
+    pub by_payment_date: bool,
+}
+
+/// Processed invoices matching `filter`, with overrides applied, oldest
+/// first. With a date bound, invoices without a valid invoice date cannot be
+/// placed and are counted instead of returned; unpaid invoices are simply
+/// outside a payment-date period.
+pub fn load_invoices(db: &Database, filter: &InvoiceFilter) -> Result<(Vec<Invoice>, usize)> {
+    let categories = match filter.category.as_deref() {
+        Some(category) => vec![category],
+        None => vec!["revenue", "payable"],
+    };
+    let dated = filter.from.is_some() || filter.to.is_some();
+    let mut selected = Vec::new();
+    let mut skipped_undated = 0;
+    for category in categories {
+        for mut invoice in db.get_invoices(category)? {
+            if invoice.ingestion_status != "processed" {
+                continue;
+            }
+            let overrides = db.get_overrides(&invoice.id)?;
+            apply_overrides(&mut invoice, &overrides);
+            if filter.status.as_deref().is_some_and(|status| status != invoice.status) {
+                continue;
+            }
+            let date = if filter.by_payment_date {
+                parse_date(invoice.paid_at.as_deref())
+            } else {
+                parse_date(invoice.invoice_date.as_deref())
+            };
+            if dated {
+                let Some(date) = date else {
+                    if !filter.by_payment_date {
+                        skipped_undated += 1;
+                    }
+                    continue;
+                };
+                if filter.from.is_some_and(|from| date < from) || filter.to.is_some_and(|to| date > to) {
+                    continue;
+                }
+            }
+            selected.push((date, invoice));
+        }
+    }
+    selected.sort_by_key(|(date, _)| *date);
+    Ok((selected.into_iter().map(|(_, invoice)| invoice).collect(), skipped_undated))
+}
+
+/// Reads the date part of `YYYY-MM-DD` values and timestamps.
+pub fn parse_date(raw: Option<&str>) -> Option<NaiveDate> {
+    raw.map(str::trim)
+        .and_then(|raw| raw.get(..10))
+        .and_then(|day| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok())
+}
+
+/// Net and VAT of an invoice at one rate. `rate` is `None` when the invoice
+/// states neither net nor tax.
+#[derive(Debug, Clone, Copy)]
+pub struct VatPart {
+    pub rate: Option<f64>,
+    pub net: Money,
+    pub tax: Money,
+}
+
+impl VatPart {
+    pub fn gross(&self) -> Money {
+        self.net + self.tax
+    }
+}
+
+/// Splits an invoice by VAT rate. The stored breakdown is used when it adds
+/// up to the total; otherwise the totals form one part at their effective
+/// rate. The parts always add up to `total_amount`.
+pub fn vat_parts(invoice: &Invoice, breakdown: &[InvoiceVatEntry]) -> Vec<VatPart> {
+    let breakdown_gross: Money = breakdown.iter().map(|vat| vat.net_amount + vat.tax_amount).sum();
+    if !breakdown.is_empty() && breakdown_gross == invoice.total_amount {
+        return breakdown
+            .iter()
+            .map(|vat| VatPart {
+                rate: Some(vat.rate),
+                net: vat.net_amount,
+                tax: vat.tax_amount,
+            })
+            .collect();
+    }
+
+    let tax = match (invoice.tax_amount, invoice.net_amount) {
+        (Some(tax), _) => Some(tax),
+        (None, Some(net)) => Some(invoice.total_amount - net),
+        (None, None) => None,
+    };
+    let net = invoice.total_amount - tax.unwrap_or(Money::ZERO);
+    let rate = tax
+        .filter(|_| net != Money::ZERO)
+        .map(|tax| tax.to_f64() / net.to_f64() * 100.0);
+    vec![VatPart {
+        rate,
+        net,
+        tax: tax.unwrap_or(Money::ZERO),
+    }]
+}
+
+/// Rate to convert the invoice's amounts into `currency`: 1 for invoices in
+/// that currency, the stored exchange rate when it is the base currency.
+pub fn rate_to(invoice: &Invoice, currency: &str) -> Option<f64> {
+    if invoice.currency.eq_ignore_ascii_case(currency) {
+        Some(1.0)
+    } else if invoice
+        .base_currency
+        .as_deref()
+        .is_some_and(|base| base.eq_ignore_ascii_case(currency))
+    {
+        invoice.exchange_rate
+    } else {
+        None
+    }
+}
@@ -0,0 +1,307 @@
+use chrono::NaiveDate;
+
+use crate::utils::{escape_xml, Money};
+
+const EN16931_GUIDELINE: &str = "urn:cen.eu:en16931:2017";
+const XRECHNUNG_GUIDELINE: &str = "urn:cen.eu:en16931:2017#compliant#urn:xeinkauf.de:kosit:xrechnung_3.0";
+const PEPPOL_BILLING_PROCESS: &str = "urn:fdc:peppol.eu:2017:poacc:billing:01:1.0";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    /// ZUGFeRD / Factur-X profile EN 16931 (Comfort).
+    En16931,
+    /// XRechnung 3.0, the German CIUS of EN 16931.
+    XRechnung,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Party {
+    pub name: String,
+    pub street: Option<String>,
+    pub postcode: Option<String>,
+    pub city: Option<String>,
+    /// ISO 3166-1 alpha-2.
+    pub country: String,
+    pub vat_id: Option<String>,
+    pub tax_number: Option<String>,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+}
+
+pub struct Line {
+    pub description: String,
+    pub quantity: f64,
+    pub unit_code: String,
+    pub unit_price: Money,
+    pub net_amount: Money,
+    pub tax_category: &'static str,
+    pub vat_rate: f64,
+}
+
+/// One VAT breakdown entry (BG-23).
+pub struct TaxGroup {
+    pub category: &'static str,
+    pub rate: f64,
+    pub basis: Money,
+    pub tax: Money,
+    pub exemption_reason: Option<String>,
+    pub exemption_code: Option<&'static str>,
+}
+
+pub struct CiiInvoice {
+    pub profile: Profile,
+    pub number: String,
+    pub issue_date: NaiveDate,
+    pub delivery_date: NaiveDate,
+    pub due_date: NaiveDate,
+    pub currency: String,
+    pub note: Option<String>,
+    pub buyer_reference: Option<String>,
+    pub seller: Party,
+    pub buyer: Party,
+    pub lines: Vec<Line>,
+    pub taxes: Vec<TaxGroup>,
+    pub net_total: Money,
+    pub tax_total: Money,
+    pub grand_total: Money,
+    pub payment_terms: String,
+    pub iban: Option<String>,
+    pub bic: Option<String>,
+}
+
+/// A commercial invoice (type code 380) in UN/CEFACT CII D16B syntax, with
+/// elements in schema order.
+pub fn render(invoice: &CiiInvoice) -> String {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(
+        "<rsm:CrossIndustryInvoice \
+         xmlns:rsm=\"urn:un:unece:uncefact:data:standard:CrossIndustryInvoice:100\" \
+         xmlns:ram=\"urn:un:unece:uncefact:data:standard:ReusableAggregateBusinessInformationEntity:100\" \
+         xmlns:qdt=\"urn:un:unece:uncefact:data:standard:QualifiedDataType:100\" \
+         xmlns:udt=\"urn:un:unece:uncefact:data:standard:UnqualifiedDataType:100\">\n",
+    );
+
+    xml.push_str("  <rsm:ExchangedDocumentContext>\n");
+    let guideline = match invoice.profile {
+        Profile::En16931 => EN16931_GUIDELINE,
+        Profile::XRechnung => {
+            xml.push_str(&format!(
+                "    <ram:BusinessProcessSpecifiedDocumentContextParameter><ram:ID>{}</ram:ID>\
+                 </ram:BusinessProcessSpecifiedDocumentContextParameter>\n",
+                PEPPOL_BILLING_PROCESS
+            ));
+            XRECHNUNG_GUIDELINE
+        }
+    };
+    xml.push_str(&format!(
+        "    <ram:GuidelineSpecifiedDocumentContextParameter><ram:ID>{}</ram:ID>\
+         </ram:GuidelineSpecifiedDocumentContextParameter>\n",
+        guideline
+    ));
+    xml.push_str("  </rsm:ExchangedDocumentContext>\n");
+
+    xml.push_str("  <rsm:ExchangedDocument>\n");
+    xml.push_str(&format!("    <ram:ID>{}</ram:ID>\n", escape_xml(&invoice.number)));
+    xml.push_str("    <ram:TypeCode>380</ram:TypeCode>\n");
+    xml.push_str(&format!("    <ram:IssueDateTime>{}</ram:IssueDateTime>\n", date(invoice.issue_date)));
+    if let Some(note) = invoice.note.as_deref() {
+        xml.push_str(&format!(
+            "    <ram:IncludedNote><ram:Content>{}</ram:Content></ram:IncludedNote>\n",
+            escape_xml(note)
+        ));
+    }
+    xml.push_str("  </rsm:ExchangedDocument>\n");
+
+    xml.push_str("  <rsm:SupplyChainTradeTransaction>\n");
+    for (index, line) in invoice.lines.iter().enumerate() {
+        xml.push_str("    <ram:IncludedSupplyChainTradeLineItem>\n");
+        xml.push_str(&format!(
+            "      <ram:AssociatedDocumentLineDocument><ram:LineID>{}</ram:LineID>\
+             </ram:AssociatedDocumentLineDocument>\n",
+            index + 1
+        ));
+        xml.push_str(&format!(
+            "      <ram:SpecifiedTradeProduct><ram:Name>{}</ram:Name></ram:SpecifiedTradeProduct>\n",
+            escape_xml(&line.description)
+        ));
+        xml.push_str(&format!(
+            "      <ram:SpecifiedLineTradeAgreement><ram:NetPriceProductTradePrice>\
+             <ram:ChargeAmount>{}</ram:ChargeAmount></ram:NetPriceProductTradePrice>\
+             </ram:SpecifiedLineTradeAgreement>\n",
+            line.unit_price
+        ));
+        xml.push_str(&format!(
+            "      <ram:SpecifiedLineTradeDelivery><ram:BilledQuantity unitCode=\"{}\">{}</ram:BilledQuantity>\
+             </ram:SpecifiedLineTradeDelivery>\n",
+            escape_xml(&line.unit_code),
+            decimal(line.quantity)
+        ));
+        xml.push_str("      <ram:SpecifiedLineTradeSettlement>\n");
+        xml.push_str(&format!(
+            "        <ram:ApplicableTradeTax><ram:TypeCode>VAT</ram:TypeCode><ram:CategoryCode>{}</ram:CategoryCode>\
+             <ram:RateApplicablePercent>{}</ram:RateApplicablePercent></ram:ApplicableTradeTax>\n",
+            line.tax_category,
+            percent(line.vat_rate)
+        ));
+        xml.push_str(&format!(
+            "        <ram:SpecifiedTradeSettlementLineMonetarySummation><ram:LineTotalAmount>{}</ram:LineTotalAmount>\
+             </ram:SpecifiedTradeSettlementLineMonetarySummation>\n",
+            line.net_amount
+        ));
+        xml.push_str("      </ram:SpecifiedLineTradeSettlement>\n");
+        xml.push_str("    </ram:IncludedSupplyChainTradeLineItem>\n");
+    }
+
+    xml.push_str("    <ram:ApplicableHeaderTradeAgreement>\n");
+    if let Some(reference) = invoice.buyer_reference.as_deref() {
+        xml.push_str(&format!("      <ram:BuyerReference>{}</ram:BuyerReference>\n", escape_xml(reference)));
+    }
+    push_party(&mut xml, "SellerTradeParty", &invoice.seller, true);
+    push_party(&mut xml, "BuyerTradeParty", &invoice.buyer, false);
+    xml.push_str("    </ram:ApplicableHeaderTradeAgreement>\n");
+
+    xml.push_str(&format!(
+        "    <ram:ApplicableHeaderTradeDelivery><ram:ActualDeliverySupplyChainEvent>\
+         <ram:OccurrenceDateTime>{}</ram:OccurrenceDateTime></ram:ActualDeliverySupplyChainEvent>\
+         </ram:ApplicableHeaderTradeDelivery>\n",
+        date(invoice.delivery_date)
+    ));
+
+    xml.push_str("    <ram:ApplicableHeaderTradeSettlement>\n");
+    xml.push_str(&format!("      <ram:PaymentReference>{}</ram:PaymentReference>\n", escape_xml(&invoice.number)));
+    xml.push_str(&format!(
+        "      <ram:InvoiceCurrencyCode>{}</ram:InvoiceCurrencyCode>\n",
+        escape_xml(&invoice.currency)
+    ));
+    if let Some(iban) = invoice.iban.as_deref() {
+        xml.push_str("      <ram:SpecifiedTradeSettlementPaymentMeans>\n");
+        xml.push_str("        <ram:TypeCode>58</ram:TypeCode>\n");
+        xml.push_str(&format!(
+            "        <ram:PayeePartyCreditorFinancialAccount><ram:IBANID>{}</ram:IBANID>\
+             </ram:PayeePartyCreditorFinancialAccount>\n",
+            escape_xml(iban)
+        ));
+        if let Some(bic) = invoice.bic.as_deref() {
+            xml.push_str(&format!(
+                "        <ram:PayeeSpecifiedCreditorFinancialInstitution><ram:BICID>{}</ram:BICID>\
+                 </ram:PayeeSpecifiedCreditorFinancialInstitution>\n",
+                escape_xml(bic)
+            ));
+        }
+        xml.push_str("      </ram:SpecifiedTradeSettlementPaymentMeans>\n");
+    }
+    for tax in &invoice.taxes {
+        xml.push_str("      <ram:ApplicableTradeTax>\n");
+        xml.push_str(&format!("        <ram:CalculatedAmount>{}</ram:CalculatedAmount>\n", tax.tax));
+        xml.push_str("        <ram:TypeCode>VAT</ram:TypeCode>\n");
+        if let Some(reason) = tax.exemption_reason.as_deref() {
+            xml.push_str(&format!("        <ram:ExemptionReason>{}</ram:ExemptionReason>\n", escape_xml(reason)));
+        }
+        xml.push_str(&format!("        <ram:BasisAmount>{}</ram:BasisAmount>\n", tax.basis));
+        xml.push_str(&format!("        <ram:CategoryCode>{}</ram:CategoryCode>\n", tax.category));
+        if let Some(code) = tax.exemption_code {
+            xml.push_str(&format!("        <ram:ExemptionReasonCode>{}</ram:ExemptionReasonCode>\n", code));
+        }
+        xml.push_str(&format!(
+            "        <ram:RateApplicablePercent>{}</ram:RateApplicablePercent>\n",
+            percent(tax.rate)
+        ));
+        xml.push_str("      </ram:ApplicableTradeTax>\n");
+    }
+    xml.push_str("      <ram:SpecifiedTradePaymentTerms>\n");
+    xml.push_str(&format!("        <ram:Description>{}</ram:Description>\n", escape_xml(&invoice.payment_terms)));
+    xml.push_str(&format!("        <ram:DueDateDateTime>{}</ram:DueDateDateTime>\n", date(invoice.due_date)));
+    xml.push_str("      </ram:SpecifiedTradePaymentTerms>\n");
+    xml.push_str("      <ram:SpecifiedTradeSettlementHeaderMonetarySummation>\n");
+    xml.push_str(&format!("        <ram:LineTotalAmount>{}</ram:LineTotalAmount>\n", invoice.net_total));
+    xml.push_str(&format!("        <ram:TaxBasisTotalAmount>{}</ram:TaxBasisTotalAmount>\n", invoice.net_total));
+    xml.push_str(&format!(
+        "        <ram:TaxTotalAmount currencyID=\"{}\">{}</ram:TaxTotalAmount>\n",
+        escape_xml(&invoice.currency),
+        invoice.tax_total
+    ));
+    xml.push_str(&format!("        <ram:GrandTotalAmount>{}</ram:GrandTotalAmount>\n", invoice.grand_total));
+    xml.push_str(&format!("        <ram:DuePayableAmount>{}</ram:DuePayableAmount>\n", invoice.grand_total));
+    xml.push_str("      </ram:SpecifiedTradeSettlementHeaderMonetarySummation>\n");
+    xml.push_str("    </ram:ApplicableHeaderTradeSettlement>\n");
+    xml.push_str("  </rsm:SupplyChainTradeTransaction>\n");
+    xml.push_str("</rsm:CrossIndustryInvoice>\n");
+    xml
+}
+
+/// A trade party; the seller also gets a contact (BG-6) with phone and email.
+fn push_party(xml: &mut String, element: &str, party: &Party, seller: bool) {
+    xml.push_str(&format!("      <ram:{}>\n", element));
+    xml.push_str(&format!("        <ram:Name>{}</ram:Name>\n", escape_xml(&party.name)));
+    if seller && (party.phone.is_some() || party.email.is_some()) {
+        xml.push_str("        <ram:DefinedTradeContact>\n");
+        xml.push_str(&format!("          <ram:PersonName>{}</ram:PersonName>\n", escape_xml(&party.name)));
+        if let Some(phone) = party.phone.as_deref() {
+            xml.push_str(&format!(
+                "          <ram:TelephoneUniversalCommunication><ram:CompleteNumber>{}</ram:CompleteNumber>\
+                 </ram:TelephoneUniversalCommunication>\n",
+                escape_xml(phone)
+            ));
+        }
+        if let Some(email) = party.email.as_deref() {
+            xml.push_str(&format!(
+                "          <ram:EmailURIUniversalCommunication><ram:URIID>{}</ram:URIID>\
+                 </ram:EmailURIUniversalCommunication>\n",
+                escape_xml(email)
+            ));
+        }
+        xml.push_str("        </ram:DefinedTradeContact>\n");
+    }
+    xml.push_str("        <ram:PostalTradeAddress>\n");
+    if let Some(postcode) = party.postcode.as_deref() {
+        xml.push_str(&format!("          <ram:PostcodeCode>{}</ram:PostcodeCode>\n", escape_xml(postcode)));
+    }
+    if let Some(street) = party.street.as_deref() {
+        xml.push_str(&format!("          <ram:LineOne>{}</ram:LineOne>\n", escape_xml(street)));
+    }
+    if let Some(city) = party.city.as_deref() {
+        xml.push_str(&format!("          <ram:CityName>{}</ram:CityName>\n", escape_xml(city)));
+    }
+    xml.push_str(&format!("          <ram:CountryID>{}</ram:CountryID>\n", escape_xml(&party.country)));
+    xml.push_str("        </ram:PostalTradeAddress>\n");
+    if let Some(email) = party.email.as_deref() {
+        xml.push_str(&format!(
+            "        <ram:URIUniversalCommunication><ram:URIID schemeID=\"EM\">{}</ram:URIID>\
+             </ram:URIUniversalCommunication>\n",
+            escape_xml(email)
+        ));
+    }
+    if let Some(vat_id) = party.vat_id.as_deref() {
+        xml.push_str(&format!(
+            "        <ram:SpecifiedTaxRegistration><ram:ID schemeID=\"VA\">{}</ram:ID>\
+             </ram:SpecifiedTaxRegistration>\n",
+            escape_xml(vat_id)
+        ));
+    }
+    if let Some(tax_number) = party.tax_number.as_deref() {
+        xml.push_str(&format!(
+            "        <ram:SpecifiedTaxRegistration><ram:ID schemeID=\"FC\">{}</ram:ID>\
+             </ram:SpecifiedTaxRegistration>\n",
+            escape_xml(tax_number)
+        ));
+    }
+    xml.push_str(&format!("      </ram:{}>\n", element));
+}
+
+fn date(date: NaiveDate) -> String {
+    format!(
+        "<udt:DateTimeString format=\"102\">{}</udt:DateTimeString>",
+        date.format("%Y%m%d")
+    )
+}
+
+/// Up to four decimals without trailing zeros.
+fn decimal(value: f64) -> String {
+    let text = format!("{:.4}", value);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn percent(rate: f64) -> String {
+    format!("{:.2}", rate)
+}
@@ -0,0 +1,282 @@
+use anyhow::{anyhow, Result};
+use uuid::Uuid;
+
+use crate::db::Database;
+use crate::models::{Counterparty, ExtractedInvoiceData, Invoice};
+use crate::services::sepa::{normalize_bic, normalize_iban};
+
+/// Jaro-Winkler similarity of two names (without legal form) from which a new
+/// spelling is linked to an existing counterparty instead of creating one.
+/// High enough to keep "Stadtwerke München" and "Stadtwerke Münster" apart.
+const MATCH_THRESHOLD: f64 = 0.95;
+
+/// Spelled-out legal forms and their abbreviation, matched on whole words of
+/// the normalized name. Longer phrases come first.
+const LEGAL_FORM_PHRASES: [(&str, &str); 15] = [
+    ("gesellschaft mit beschraenkter haftung", "gmbh"),
+    ("gesellschaft mbh", "gmbh"),
+    ("g m b h", "gmbh"),
+    ("aktiengesellschaft", "ag"),
+    ("kommanditgesellschaft", "kg"),
+    ("offene handelsgesellschaft", "ohg"),
+    ("eingetragener kaufmann", "ek"),
+    ("eingetragene kauffrau", "ek"),
+    ("e kfm", "ek"),
+    ("e k", "ek"),
+    ("eingetragener verein", "ev"),
+    ("e v", "ev"),
+    ("limited", "ltd"),
+    ("incorporated", "inc"),
+    ("s a r l", "sarl"),
+];
+
+const LEGAL_FORMS: [&str; 20] = [
+    "gmbh", "mbh", "ag", "kg", "ohg", "ug", "haftungsbeschraenkt", "ek", "ev", "gbr", "kgaa", "se", "co", "ltd",
+    "inc", "corp", "llc", "plc", "bv", "sarl",
+];
+
+/// Connectives that are spelled either way ("Müller & Söhne", "Müller und
+/// Söhne") and are ignored when comparing names.
+const CONNECTIVES: [&str; 2] = ["und", "and"];
+
+/// Lowercases, transliterates umlauts, drops punctuation and abbreviates
+/// legal forms, so "ACME GmbH.", "Acme GmbH" and "ACME Gesellschaft mbH" are
+/// all `acme gmbh`.
+pub fn normalize_name(name: &str) -> String {
+    let mut cleaned = String::with_capacity(name.len());
+    for c in name.to_lowercase().chars() {
+        match c {
+            'ä' => cleaned.push_str("ae"),
+            'ö' => cleaned.push_str("oe"),
+            'ü' => cleaned.push_str("ue"),
+            'ß' => cleaned.push_str("ss"),
+            'à' | 'á' | 'â' => cleaned.push('a'),
+            'è' | 'é' | 'ê' => cleaned.push('e'),
+            c if c.is_alphanumeric() => cleaned.push(c),
+            _ => cleaned.push(' '),
+        }
+    }
+
+    let mut padded = format!(" {} ", cleaned.split_whitespace().collect::<Vec<_>>().join(" "));
+    for (phrase, abbreviation) in LEGAL_FORM_PHRASES {
+        padded = padded.replace(&format!(" {} ", phrase), &format!(" {} ", abbreviation));
+    }
+    padded.trim().to_string()
+}
+
+/// VAT IDs are compared without spaces and in upper case.
+pub fn normalize_vat_id(vat_id: &str) -> String {
+    vat_id
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_uppercase()
+}
+
+/// Splits a normalized name into the distinctive part and its legal form.
+fn split_legal_form(normalized: &str) -> (String, String) {
+    let (form, core): (Vec<&str>, Vec<&str>) = normalized
+        .split(' ')
+        .filter(|token| !CONNECTIVES.contains(token))
+        .partition(|token| LEGAL_FORMS.contains(token));
+    (core.join(" "), form.join(" "))
+}
+
+/// Best fuzzy match above `MATCH_THRESHOLD`. Names with different legal forms
+/// ("Acme GmbH" and "Acme AG") are kept apart.
+fn find_similar(db: &Database, normalized: &str) -> Result<Option<String>> {
+    let (core, form) = split_legal_form(normalized);
+    if core.chars().count() < 3 {
+        return Ok(None);
+    }
+
+    let mut best: Option<(String, f64)> = None;
+    for (id, candidate) in db.get_counterparty_names()? {
+        let (candidate_core, candidate_form) = split_legal_form(&candidate);
+        if !form.is_empty() && !candidate_form.is_empty() && form != candidate_form {
+            continue;
+        }
+        let score = strsim::jaro_winkler(&core, &candidate_core);
+        let better = match &best {
+            Some((_, best_score)) => score > *best_score,
+            None => true,
+        };
+        if score >= MATCH_THRESHOLD && better {
+            best = Some((id, score));
+        }
+    }
+    Ok(best.map(|(id, _)| id))
+}
+
+/// Finds the counterparty for a name as printed on an invoice, by VAT ID,
+/// exact normalized name or alias, then fuzzy name match. Unknown names
+/// create a new counterparty; new spellings are remembered as aliases.
+pub fn resolve(db: &Database, name: &str, vat_id: Option<&str>, category: &str) -> Result<Option<String>> {
+    let name = name.trim();
+    let normalized = normalize_name(name);
+    if normalized.is_empty() {
+        return Ok(None);
+    }
+    let vat_id = vat_id.map(normalize_vat_id).filter(|vat_id| !vat_id.is_empty());
+
+    let mut existing = match vat_id.as_deref() {
+        Some(vat_id) => db.find_counterparty_by_vat_id(vat_id)?,
+        None => None,
+    };
+    if existing.is_none() {
+        existing = db.find_counterparty_by_name(&normalized)?;
+    }
+    if existing.is_none() {
+        existing = find_similar(db, &normalized)?;
+    }
+
+    if let Some(id) = existing {
+        db.add_counterparty_alias(&id, name, &normalized)?;
+        if let Some(vat_id) = vat_id.as_deref() {
+            db.fill_counterparty_vat_id(&id, vat_id)?;
+        }
+        return Ok(Some(id));
+    }
+
+    let id = Uuid::new_v4().to_string();
+    db.insert_counterparty(&id, name, &normalized, vat_id.as_deref(), Some(category))?;
+    Ok(Some(id))
+}
+
+/// Sets `counterparty_id` from the counterparty name, preferring a user
+/// override over the extracted name.
+pub fn link_invoice(db: &Database, invoice: &mut Invoice) -> Result<()> {
+    let name = db
+        .get_overrides(&invoice.id)?
+        .into_iter()
+        .find(|entry| entry.field_name == "counterparty_name")
+        .map(|entry| entry.override_value)
+        .or_else(|| invoice.counterparty_name.clone());
+    let vat_id = serde_json::from_str::<ExtractedInvoiceData>(&invoice.extracted_json)
+        .ok()
+        .and_then(|data| data.counterparty_vat_id);
+
+    invoice.counterparty_id = match name {
+        Some(name) => resolve(db, &name, vat_id.as_deref(), &invoice.category)?,
+        None => None,
+    };
+    Ok(())
+}
+
+/// Re-links one stored invoice, e.g. after its counterparty name was edited.
+pub fn relink_invoice(db: &Database, invoice_id: &str) -> Result<()> {
+    let mut invoice = db
+        .get_invoice_by_id(invoice_id)?
+        .ok_or_else(|| anyhow!("Invoice not found"))?;
+    link_invoice(db, &mut invoice)?;
+    db.set_invoice_counterparty(&invoice.id, invoice.counterparty_id.as_deref())?;
+    Ok(())
+}
+
+/// Links invoices processed before counterparties existed. Returns how many
+/// were linked.
+pub fn link_unassigned(db: &Database) -> Result<usize> {
+    let unlinked = db.get_unlinked_invoice_ids()?;
+    for invoice_id in &unlinked {
+        relink_invoice(db, invoice_id)?;
+    }
+    Ok(unlinked.len())
+}
+
+/// Saves edited master data. A renamed counterparty keeps its old name as an
+/// alias so earlier spellings still match.
+pub fn update(db: &Database, counterparty: &Counterparty) -> Result<()> {
+    let existing = db
+        .get_counterparties(Some(&counterparty.id))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("Counterparty not found"))?;
+    let name = counterparty.name.trim();
+    if name.is_empty() {
+        return Err(anyhow!("Counterparty name must not be empty"));
+    }
+    let normalized = normalize_name(name);
+    if let Some(other) = db.find_counterparty_by_name(&normalized)? {
+        if other != counterparty.id {
+            return Err(anyhow!("Another counterparty is already named {}", name));
+        }
+    }
+
+    if existing.name != name {
+        db.add_counterparty_alias(&existing.id, &existing.name, &normalize_name(&existing.name))?;
+    }
+    let mut updated = counterparty.clone();
+    updated.name = name.to_string();
+    updated.vat_id = counterparty
+        .vat_id
+        .as_deref()
+        .map(normalize_vat_id)
+        .filter(|vat_id| !vat_id.is_empty());
+    updated.personal_account = counterparty
+        .personal_account
+        .as_deref()
+        .map(str::trim)
+        .filter(|number| !number.is_empty())
+        .map(str::to_string);
+    if let Some(number) = updated.personal_account.as_deref() {
+        if !number.chars().all(|c| c.is_ascii_digit()) {
+            return Err(anyhow!("Personal account must be numeric"));
+        }
+    }
+    updated.iban = match counterparty.iban.as_deref().map(str::trim).filter(|iban| !iban.is_empty()) {
+        Some(iban) => Some(normalize_iban(iban).ok_or_else(|| anyhow!("Invalid IBAN: {}", iban))?),
+        None => None,
+    };
+    updated.bic = match counterparty.bic.as_deref().map(str::trim).filter(|bic| !bic.is_empty()) {
+        Some(bic) => Some(normalize_bic(bic).ok_or_else(|| anyhow!("Invalid BIC: {}", bic))?),
+        None => None,
+    };
+    updated.email = counterparty
+        .email
+        .as_deref()
+        .map(str::trim)
+        .filter(|email| !email.is_empty())
+        .map(str::to_string);
+    if let Some(email) = updated.email.as_deref() {
+        if !email.contains('@') || email.contains(char::is_whitespace) {
+            return Err(anyhow!("Invalid email address: {}", email));
+        }
+    }
+    db.update_counterparty(&updated, &normalized)?;
+    Ok(())
+}
+
+/// Creates a counterparty entered by hand, e.g. a new customer to invoice.
+pub fn create(db: &Database, name: &str, category: &str) -> Result<Counterparty> {
+    let name = name.trim();
+    let normalized = normalize_name(name);
+    if normalized.is_empty() {
+        return Err(anyhow!("Counterparty name must not be empty"));
+    }
+    if category != "revenue" && category != "payable" {
+        return Err(anyhow!("Unknown category: {}", category));
+    }
+    if db.find_counterparty_by_name(&normalized)?.is_some() {
+        return Err(anyhow!("Another counterparty is already named {}", name));
+    }
+    let id = Uuid::new_v4().to_string();
+    db.insert_counterparty(&id, name, &normalized, None, Some(category))?;
+    db.get_counterparties(Some(&id))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("Counterparty not found"))
+}
+
+/// Merges `source_id` into `target_id`; see `Database::merge_counterparties`.
+pub fn merge(db: &Database, target_id: &str, source_id: &str) -> Result<()> {
+    if target_id == source_id {
+        return Err(anyhow!("Cannot merge a counterparty into itself"));
+    }
+    for id in [target_id, source_id] {
+        if db.get_counterparties(Some(id))?.is_empty() {
+            return Err(anyhow!("Counterparty not found: {}", id));
+        }
+    }
+    db.merge_counterparties(target_id, source_id)?;
+    Ok(())
+}
@@ -0,0 +1,343 @@
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Local, NaiveDate};
+use std::path::Path;
+
+use crate::models::{Account, Invoice, InvoiceVatEntry};
+use crate::services::bookkeeping::{rate_to, vat_parts};
+use crate::utils::{encode_windows_1252, Money};
+
+/// EXTF header: format version 700, category 21 "Buchungsstapel" in format
+/// version 12.
+const EXTF_VERSION: u32 = 700;
+const BOOKING_BATCH_CATEGORY: u32 = 21;
+const BOOKING_BATCH_VERSION: u32 = 12;
+
+/// DATEV posts in EUR; foreign-currency invoices need a conversion to EUR.
+const DATEV_CURRENCY: &str = "EUR";
+
+/// Length of general ledger accounts; debtors and creditors have one digit more.
+const LEDGER_ACCOUNT_LENGTH: u32 = 4;
+
+/// Collective debtor and creditor used for counterparties without their own
+/// personal account.
+const DEFAULT_DEBTOR: &str = "10000";
+const DEFAULT_CREDITOR: &str = "70000";
+
+const DOCUMENT_NUMBER_MAX: usize = 36;
+const BOOKING_TEXT_MAX: usize = 60;
+const DOCUMENT_LINK_MAX: usize = 210;
+
+/// The leading columns of the Buchungsstapel layout up to "Beleglink"; the
+/// optional columns after it are left out.
+const COLUMNS: [&str; 20] = [
+    "Umsatz (ohne Soll/Haben-Kz)",
+    "Soll/Haben-Kennzeichen",
+    "WKZ Umsatz",
+    "Kurs",
+    "Basis-Umsatz",
+    "WKZ Basis-Umsatz",
+    "Konto",
+    "Gegenkonto (ohne BU-Schlüssel)",
+    "BU-Schlüssel",
+    "Belegdatum",
+    "Belegfeld 1",
+    "Belegfeld 2",
+    "Skonto",
+    "Buchungstext",
+    "Postensperre",
+    "Diverse Adressnummer",
+    "Geschäftspartnerbank",
+    "Sachverhalt",
+    "Zinssperre",
+    "Beleglink",
+];
+
+/// Consultant, client and period written into the EXTF header.
+pub struct BatchHeader {
+    pub consultant_number: u32,
+    pub client_number: u32,
+    pub chart: String,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+/// An invoice with overrides applied, together with its VAT breakdown and the
+/// personal account of its counterparty.
+pub struct ExportInvoice {
+    pub invoice: Invoice,
+    pub vat_breakdown: Vec<InvoiceVatEntry>,
+    pub personal_account: Option<String>,
+}
+
+struct Booking {
+    amount: Money,
+    debit: bool,
+    account: String,
+    contra_account: String,
+    tax_key: Option<&'static str>,
+    date: NaiveDate,
+    document_number: String,
+    text: String,
+    link: Option<String>,
+}
+
+/// Checks consultant and client number and that the period lies within one
+/// calendar (fiscal) year, as DATEV requires for a batch.
+pub fn validate_header(header: &BatchHeader) -> Result<()> {
+    if !(1001..=9_999_999).contains(&header.consultant_number) {
+        return Err(anyhow!("DATEV consultant number must be between 1001 and 9999999"));
+    }
+    if !(1..=99_999).contains(&header.client_number) {
+        return Err(anyhow!("DATEV client number must be between 1 and 99999"));
+    }
+    if header.chart != "SKR03" && header.chart != "SKR04" {
+        return Err(anyhow!("DATEV export needs chart SKR03 or SKR04, not {}", header.chart));
+    }
+    if header.from > header.to {
+        return Err(anyhow!("Export period starts after it ends"));
+    }
+    if header.from.year() != header.to.year() {
+        return Err(anyhow!("Export period must lie within one fiscal year"));
+    }
+    Ok(())
+}
+
+/// Writes `invoices` as an EXTF Buchungsstapel (Windows-1252, semicolon
+/// separated). All invoices are validated first; if any lacks a required
+/// field nothing is written and the error lists every offending invoice.
+/// Returns the number of bookings written.
+pub fn write_batch(path: &Path, header: &BatchHeader, invoices: &[ExportInvoice], accounts: &[Account]) -> Result<usize> {
+    validate_header(header)?;
+
+    let mut bookings = Vec::new();
+    let mut problems = Vec::new();
+    for entry in invoices {
+        match bookings_for(entry, accounts) {
+            Ok(mut rows) => bookings.append(&mut rows),
+            Err(problem) => problems.push(format!("{}: {}", label(&entry.invoice), problem)),
+        }
+    }
+    if !problems.is_empty() {
+        return Err(anyhow!(
+            "{} invoice(s) cannot be exported:\n{}",
+            problems.len(),
+            problems.join("\n")
+        ));
+    }
+
+    let mut lines = vec![header_line(header), COLUMNS.map(text).join(";")];
+    lines.extend(bookings.iter().map(booking_line));
+    let mut content = lines.join("\r\n");
+    content.push_str("\r\n");
+    std::fs::write(path, encode_windows_1252(&content))?;
+    Ok(bookings.len())
+}
+
+fn label(invoice: &Invoice) -> String {
+    let number = invoice.invoice_number.as_deref().unwrap_or("without number");
+    match invoice.counterparty_name.as_deref() {
+        Some(name) => format!("{} ({})", number, name),
+        None => number.to_string(),
+    }
+}
+
+/// One booking per VAT rate (see `bookkeeping::vat_parts`).
+fn bookings_for(entry: &ExportInvoice, accounts: &[Account]) -> Result<Vec<Booking>> {
+    let invoice = &entry.invoice;
+    let date = invoice
+        .invoice_date
+        .as_deref()
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        .ok_or_else(|| anyhow!("missing or invalid invoice date"))?;
+    let document_number = document_number(invoice.invoice_number.as_deref())?;
+    let account_number = invoice
+        .account_number
+        .as_deref()
+        .ok_or_else(|| anyhow!("no booking account assigned"))?;
+    let account = accounts
+        .iter()
+        .find(|account| account.number == account_number)
+        .ok_or_else(|| anyhow!("account {} does not exist", account_number))?;
+    let personal_account = match entry.personal_account.as_deref() {
+        Some(number) => number,
+        None if invoice.category == "revenue" => DEFAULT_DEBTOR,
+        None => DEFAULT_CREDITOR,
+    };
+    for number in [account_number, personal_account] {
+        if !number.chars().all(|c| c.is_ascii_digit()) {
+            return Err(anyhow!("account {} is not numeric", number));
+        }
+    }
+    if invoice.total_amount == Money::ZERO {
+        return Err(anyhow!("total amount is zero"));
+    }
+    let rate_to_eur = rate_to(invoice, DATEV_CURRENCY)
+        .ok_or_else(|| anyhow!("no EUR exchange rate for {}", invoice.currency))?;
+    let parts = vat_parts(invoice, &entry.vat_breakdown);
+
+    // Converted parts are rounded individually; the last one takes the
+    // remainder so the bookings add up to the converted total.
+    let total_eur = invoice.total_amount.convert(rate_to_eur);
+    let mut remaining = total_eur;
+    let mut bookings = Vec::with_capacity(parts.len());
+    for (index, part) in parts.iter().enumerate() {
+        let amount = if index + 1 == parts.len() {
+            remaining
+        } else {
+            part.gross().convert(rate_to_eur)
+        };
+        remaining -= amount;
+        if amount == Money::ZERO {
+            continue;
+        }
+        let tax_key = tax_key(&invoice.category, account, part.rate)?;
+        let revenue = invoice.category == "revenue";
+        let negative = amount < Money::ZERO;
+        bookings.push(Booking {
+            amount: if negative { -amount } else { amount },
+            debit: revenue != negative,
+            account: personal_account.to_string(),
+            contra_account: account.number.clone(),
+            tax_key,
+            date,
+            document_number: document_number.clone(),
+            text: invoice
+                .counterparty_name
+                .clone()
+                .unwrap_or_else(|| document_number.clone()),
+            link: invoice
+                .file_path
+                .clone()
+                .filter(|path| path.chars().count() <= DOCUMENT_LINK_MAX),
+        });
+    }
+    Ok(bookings)
+}
+
+/// "Belegfeld 1" allows at most 36 letters, digits and `$ & % * + - /`.
+/// Other characters become `-`, whitespace is dropped.
+fn document_number(raw: Option<&str>) -> Result<String> {
+    let raw = raw
+        .map(str::trim)
+        .filter(|raw| !raw.is_empty())
+        .ok_or_else(|| anyhow!("missing invoice number"))?;
+    let cleaned: String = raw
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "$&%*+-/".contains(c) {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    if cleaned.len() > DOCUMENT_NUMBER_MAX {
+        return Err(anyhow!("invoice number is longer than {} characters", DOCUMENT_NUMBER_MAX));
+    }
+    Ok(cleaned)
+}
+
+/// BU key for a VAT rate: 3/2 for 19/7 % output VAT, 9/8 for 19/7 % input
+/// VAT. Automatic accounts carry the VAT themselves and get no key, so a
+/// rate that differs from theirs cannot be booked on them.
+fn tax_key(category: &str, account: &Account, rate: Option<f64>) -> Result<Option<&'static str>> {
+    let rate = rate.unwrap_or(0.0);
+    let matches = |expected: f64| (rate - expected).abs() < 0.5;
+    if account.automatic_vat {
+        let account_rate = account.vat_rate.unwrap_or(0.0);
+        if !matches(account_rate) {
+            return Err(anyhow!(
+                "automatic account {} is for {} % VAT, not {:.1} %",
+                account.number,
+                account_rate,
+                rate
+            ));
+        }
+        return Ok(None);
+    }
+    let revenue = category == "revenue";
+    if matches(0.0) {
+        Ok(None)
+    } else if matches(19.0) {
+        Ok(Some(if revenue { "3" } else { "9" }))
+    } else if matches(7.0) {
+        Ok(Some(if revenue { "2" } else { "8" }))
+    } else {
+        Err(anyhow!("no BU key for a VAT rate of {:.1} %", rate))
+    }
+}
+
+fn header_line(header: &BatchHeader) -> String {
+    let fiscal_year_start = NaiveDate::from_ymd_opt(header.from.year(), 1, 1).unwrap_or(header.from);
+    let chart_code = header.chart.trim_start_matches("SKR");
+    [
+        text("EXTF"),
+        EXTF_VERSION.to_string(),
+        BOOKING_BATCH_CATEGORY.to_string(),
+        text("Buchungsstapel"),
+        BOOKING_BATCH_VERSION.to_string(),
+        Local::now().format("%Y%m%d%H%M%S%3f").to_string(),
+        String::new(),
+        text("RE"),
+        text(""),
+        text(""),
+        header.consultant_number.to_string(),
+        header.client_number.to_string(),
+        fiscal_year_start.format("%Y%m%d").to_string(),
+        LEDGER_ACCOUNT_LENGTH.to_string(),
+        header.from.format("%Y%m%d").to_string(),
+        header.to.format("%Y%m%d").to_string(),
+        text("Billy"),
+        text(""),
+        "1".to_string(),
+        "0".to_string(),
+        "0".to_string(),
+        text(DATEV_CURRENCY),
+        String::new(),
+        text(""),
+        String::new(),
+        String::new(),
+        text(chart_code),
+        String::new(),
+        String::new(),
+        text(""),
+        text(""),
+    ]
+    .join(";")
+}
+
+fn booking_line(booking: &Booking) -> String {
+    [
+        booking.amount.to_string().replace('.', ","),
+        text(if booking.debit { "S" } else { "H" }),
+        text(DATEV_CURRENCY),
+        String::new(),
+        String::new(),
+        text(""),
+        booking.account.clone(),
+        booking.contra_account.clone(),
+        text(booking.tax_key.unwrap_or_default()),
+        booking.date.format("%d%m").to_string(),
+        text(&booking.document_number),
+        text(""),
+        String::new(),
+        text(&booking.text.chars().take(BOOKING_TEXT_MAX).collect::<String>()),
+        String::new(),
+        text(""),
+        String::new(),
+        String::new(),
+        String::new(),
+        text(booking.link.as_deref().unwrap_or_default()),
+    ]
+    .join(";")
+}
+
+/// Text fields are quoted; quotes are doubled and line breaks removed.
+fn text(value: &str) -> String {
+    let cleaned: String = value
+        .chars()
+        .map(|c| if c == '\r' || c == '\n' { ' ' } else { c })
+        .collect();
+    format!("\"{}\"", cleaned.replace('"', "\"\""))
+}
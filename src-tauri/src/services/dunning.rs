@@ -0,0 +1,744 @@
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Duration, NaiveDate};
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::db::Database;
+use crate::models::{
+    BaseInterestRate, Counterparty, DunningCase, DunningOverview, DunningStep, DunningTemplate, Invoice, Settings,
+};
+use crate::services::bookkeeping::{apply_overrides, load_invoices, parse_date, InvoiceFilter};
+use crate::services::pdf::{Font, PdfWriter, MARGIN_LEFT, MARGIN_RIGHT, PAGE_WIDTH};
+use crate::utils::{format_eur, Money};
+
+/// Payment reminder, 1st and 2nd Mahnung.
+pub const MAX_LEVEL: u8 = 3;
+
+/// Percentage points above the base rate (§ 288 Abs. 1 and 2 BGB).
+const CONSUMER_MARGIN: f64 = 5.0;
+const BUSINESS_MARGIN: f64 = 9.0;
+/// Flat fee a business debtor owes once in default (§ 288 Abs. 5 BGB).
+const BUSINESS_FLAT_FEE: f64 = 40.0;
+/// Without a due date the debtor is in default 30 days after receiving the
+/// invoice (§ 286 Abs. 3 BGB); the invoice date stands in for receipt.
+const DAYS_TO_DEFAULT_WITHOUT_DUE_DATE: i64 = 31;
+
+/// Fields a template can use, written as `{name}`. `{claims}` on a line of
+/// its own places the table of amounts.
+pub const PLACEHOLDERS: [&str; 17] = [
+    "customer",
+    "invoice_number",
+    "invoice_date",
+    "due_date",
+    "outstanding",
+    "interest",
+    "interest_rate",
+    "default_since",
+    "fees",
+    "total",
+    "deadline",
+    "company_name",
+    "iban",
+    "bic",
+    "date",
+    "level",
+    "claims",
+];
+
+pub fn level_name(level: u8) -> &'static str {
+    match level {
+        1 => "Zahlungserinnerung",
+        2 => "1. Mahnung",
+        _ => "2. Mahnung",
+    }
+}
+
+/// Overdue revenue invoices with what is owed today and which letter is
+/// next, most overdue first.
+pub fn overview(db: &Database, settings: &Settings, today: NaiveDate) -> Result<DunningOverview> {
+    let filter = InvoiceFilter {
+        category: Some("revenue".to_string()),
+        ..InvoiceFilter::default()
+    };
+    let (invoices, _) = load_invoices(db, &filter)?;
+    let counterparties = db.get_counterparties(None)?;
+    let steps = db.get_dunning_steps(None)?;
+    let base_rates = db.get_base_interest_rates()?;
+
+    let mut cases = Vec::new();
+    let mut warnings = Vec::new();
+    for invoice in &invoices {
+        if invoice.status == "paid" {
+            continue;
+        }
+        if default_since(invoice).is_none() {
+            warnings.push(format!(
+                "{}: no due or invoice date, cannot tell when it is overdue",
+                invoice.invoice_number.as_deref().unwrap_or(&invoice.id)
+            ));
+            continue;
+        }
+        let invoice_steps: Vec<&DunningStep> = steps.iter().filter(|step| step.invoice_id == invoice.id).collect();
+        if let Some(case) = case_for(db, invoice, &counterparties, &invoice_steps, &base_rates, settings, today)? {
+            cases.push(case);
+        }
+    }
+    cases.sort_by_key(|case| std::cmp::Reverse(case.days_overdue));
+
+    if !cases.is_empty() {
+        if let Some(warning) = base_rate_warning(&base_rates, today) {
+            warnings.push(warning);
+        }
+    }
+    Ok(DunningOverview {
+        cases,
+        base_rates,
+        warnings,
+    })
+}
+
+/// Writes the next letter for an overdue revenue invoice to `path` and logs
+/// it. The payment deadline is `dunning_interval_days` from `today`; the
+/// following level can be sent once it has passed.
+pub fn create_letter(
+    db: &Database,
+    settings: &Settings,
+    invoice_id: &str,
+    today: NaiveDate,
+    path: &Path,
+) -> Result<DunningStep> {
+    let (invoice, counterparty, case) = load_case(db, settings, invoice_id, today)?;
+    let label = case.invoice_number.clone().unwrap_or_else(|| invoice.id.clone());
+    let Some(level) = case.next_level else {
+        return Err(anyhow!("{}: the 2nd Mahnung has already been sent", label));
+    };
+    if !case.next_due {
+        return Err(anyhow!(
+            "{}: the deadline of the {} ({}) has not passed yet",
+            label,
+            level_name(case.level),
+            case.last_deadline.as_deref().map(german_date).unwrap_or_default()
+        ));
+    }
+
+    let deadline = today + Duration::days(settings.dunning_interval_days.max(1) as i64);
+    let template = template(db, level)?;
+    write_letter(&template, &invoice, counterparty.as_ref(), &case, settings, today, deadline, path)?;
+
+    let step = DunningStep {
+        id: Uuid::new_v4().to_string(),
+        invoice_id: invoice.id.clone(),
+        level,
+        outstanding: case.outstanding,
+        interest: case.interest,
+        fees: case.fees,
+        interest_rate: (case.interest > Money::ZERO).then_some(case.interest_rate),
+        default_since: Some(case.default_since.clone()),
+        deadline: deadline.format("%Y-%m-%d").to_string(),
+        file_path: path.to_string_lossy().to_string(),
+        created_at: String::new(),
+    };
+    db.insert_dunning_step(&step)?;
+    Ok(db
+        .get_dunning_steps(Some(&invoice.id))?
+        .into_iter()
+        .find(|logged| logged.id == step.id)
+        .unwrap_or(step))
+}
+
+/// Suggested file name for the next letter, e.g. `1. Mahnung RE-2024-017.pdf`.
+pub fn letter_file_name(db: &Database, settings: &Settings, invoice_id: &str, today: NaiveDate) -> Result<String> {
+    let (invoice, _, case) = load_case(db, settings, invoice_id, today)?;
+    let level = case.next_level.unwrap_or(MAX_LEVEL);
+    let number = invoice.invoice_number.unwrap_or(invoice.id);
+    let number: String = number
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    Ok(format!("{} {}.pdf", level_name(level), number))
+}
+
+/// Letter texts for every level, the user's where edited.
+pub fn templates(db: &Database) -> Result<Vec<DunningTemplate>> {
+    (1..=MAX_LEVEL).map(|level| template(db, level)).collect()
+}
+
+/// Checks the level and placeholders before storing an edited template.
+pub fn save_template(db: &Database, template: &DunningTemplate) -> Result<()> {
+    if !(1..=MAX_LEVEL).contains(&template.level) {
+        return Err(anyhow!("Unknown dunning level: {}", template.level));
+    }
+    if template.subject.trim().is_empty() || template.body.trim().is_empty() {
+        return Err(anyhow!("Subject and text must not be empty"));
+    }
+    for text in [&template.subject, &template.body] {
+        let mut rest = text.as_str();
+        while let Some(start) = rest.find('{') {
+            let Some(end) = rest[start..].find('}') else {
+                break;
+            };
+            let name = &rest[start + 1..start + end];
+            if !PLACEHOLDERS.contains(&name) {
+                return Err(anyhow!("Unknown placeholder {{{}}}", name));
+            }
+            rest = &rest[start + end + 1..];
+        }
+    }
+    db.save_dunning_template(template)?;
+    Ok(())
+}
+
+fn template(db: &Database, level: u8) -> Result<DunningTemplate> {
+    Ok(db.get_dunning_template(level)?.unwrap_or_else(|| default_template(level)))
+}
+
+fn load_case(
+    db: &Database,
+    settings: &Settings,
+    invoice_id: &str,
+    today: NaiveDate,
+) -> Result<(Invoice, Option<Counterparty>, DunningCase)> {
+    let mut invoice = db
+        .get_invoice_by_id(invoice_id)?
+        .ok_or_else(|| anyhow!("Invoice not found: {}", invoice_id))?;
+    apply_overrides(&mut invoice, &db.get_overrides(invoice_id)?);
+    let label = invoice.invoice_number.clone().unwrap_or_else(|| invoice.id.clone());
+    if invoice.category != "revenue" {
+        return Err(anyhow!("{}: only revenue invoices are dunned", label));
+    }
+    if invoice.status == "paid" {
+        return Err(anyhow!("{}: already paid", label));
+    }
+    let counterparties = db.get_counterparties(None)?;
+    let steps = db.get_dunning_steps(Some(invoice_id))?;
+    let steps: Vec<&DunningStep> = steps.iter().collect();
+    let base_rates = db.get_base_interest_rates()?;
+    let case = case_for(db, &invoice, &counterparties, &steps, &base_rates, settings, today)?
+        .ok_or_else(|| anyhow!("{}: not overdue or nothing left to pay", label))?;
+    let counterparty = counterparty_of(&invoice, &counterparties).cloned();
+    Ok((invoice, counterparty, case))
+}
+
+fn counterparty_of<'a>(invoice: &Invoice, counterparties: &'a [Counterparty]) -> Option<&'a Counterparty> {
+    invoice
+        .counterparty_id
+        .as_deref()
+        .and_then(|id| counterparties.iter().find(|counterparty| counterparty.id == id))
+}
+
+/// The first day of default: the day after the due date or, without one,
+/// 31 days after the invoice date.
+fn default_since(invoice: &Invoice) -> Option<NaiveDate> {
+    match parse_date(invoice.due_date.as_deref()) {
+        Some(due) => Some(due + Duration::days(1)),
+        None => parse_date(invoice.invoice_date.as_deref())
+            .map(|date| date + Duration::days(DAYS_TO_DEFAULT_WITHOUT_DUE_DATE)),
+    }
+}
+
+/// The claim on an unpaid revenue invoice as of `today`, or `None` if it
+/// is not overdue or fully paid. Interest and fees are what the next letter
+/// claims: the payment reminder asks for the open amount only.
+fn case_for(
+    db: &Database,
+    invoice: &Invoice,
+    counterparties: &[Counterparty],
+    steps: &[&DunningStep],
+    base_rates: &[BaseInterestRate],
+    settings: &Settings,
+    today: NaiveDate,
+) -> Result<Option<DunningCase>> {
+    let Some(since) = default_since(invoice) else {
+        return Ok(None);
+    };
+    if today < since {
+        return Ok(None);
+    }
+    let paid: Money = db.get_invoice_payments(&invoice.id)?.into_iter().map(|(amount, _)| amount).sum();
+    let outstanding = invoice.total_amount - paid;
+    if outstanding <= Money::ZERO {
+        return Ok(None);
+    }
+
+    let counterparty = counterparty_of(invoice, counterparties);
+    let consumer = counterparty.is_some_and(|counterparty| counterparty.consumer);
+    let margin = if consumer { CONSUMER_MARGIN } else { BUSINESS_MARGIN };
+    let level = steps.iter().map(|step| step.level).max().unwrap_or(0);
+    let last_deadline = steps
+        .iter()
+        .max_by(|a, b| a.level.cmp(&b.level).then(a.created_at.cmp(&b.created_at)))
+        .map(|step| step.deadline.clone());
+    let next_level = (level < MAX_LEVEL).then_some(level + 1);
+    let deadline_passed = match parse_date(last_deadline.as_deref()) {
+        Some(deadline) => today > deadline,
+        None => true,
+    };
+
+    let claim_level = next_level.unwrap_or(level);
+    let interest = if claim_level >= 2 {
+        default_interest(outstanding, since, today, margin, base_rates)
+    } else {
+        Money::ZERO
+    };
+    let fees = if claim_level < 2 {
+        Money::ZERO
+    } else if consumer {
+        settings.dunning_fee.convert((claim_level - 1) as f64)
+    } else {
+        Money::from_f64(BUSINESS_FLAT_FEE)
+    };
+
+    Ok(Some(DunningCase {
+        invoice_id: invoice.id.clone(),
+        invoice_number: invoice.invoice_number.clone(),
+        counterparty_name: counterparty
+            .map(|counterparty| counterparty.name.clone())
+            .or_else(|| invoice.counterparty_name.clone()),
+        consumer,
+        invoice_date: invoice.invoice_date.clone(),
+        due_date: invoice.due_date.clone(),
+        currency: invoice.currency.clone(),
+        default_since: since.format("%Y-%m-%d").to_string(),
+        days_overdue: (today - since).num_days() + 1,
+        outstanding,
+        interest,
+        interest_rate: base_rate_on(base_rates, today).unwrap_or(0.0) + margin,
+        fees,
+        level,
+        last_deadline,
+        next_due: next_level.is_some() && deadline_passed,
+        next_level,
+    }))
+}
+
+/// The base rate in effect on `date`: the latest one published from that
+/// day or before.
+fn base_rate_on(rates: &[BaseInterestRate], date: NaiveDate) -> Option<f64> {
+    rates
+        .iter()
+        .rev()
+        .find(|rate| parse_date(Some(&rate.valid_from)).is_some_and(|from| from <= date))
+        .map(|rate| rate.rate)
+}
+
+/// Simple interest on `outstanding` from `since` through `today` at the
+/// base rate plus `margin`, day by day (act/365) so that a change of the
+/// base rate on 1 January or 1 July applies from that day.
+fn default_interest(
+    outstanding: Money,
+    since: NaiveDate,
+    today: NaiveDate,
+    margin: f64,
+    rates: &[BaseInterestRate],
+) -> Money {
+    let end = today + Duration::days(1);
+    let mut boundaries: Vec<NaiveDate> = rates
+        .iter()
+        .filter_map(|rate| parse_date(Some(&rate.valid_from)))
+        .filter(|from| *from > since && *from < end)
+        .collect();
+    boundaries.push(end);
+
+    let mut factor = 0.0;
+    let mut start = since;
+    for boundary in boundaries {
+        let days = (boundary - start).num_days() as f64;
+        let rate = (base_rate_on(rates, start).unwrap_or(0.0) + margin).max(0.0);
+        factor += days * rate / 100.0 / 365.0;
+        start = boundary;
+    }
+    outstanding.convert(factor)
+}
+
+/// The Bundesbank sets the base rate on 1 January and 1 July; warns when the
+/// current period is missing from the table.
+fn base_rate_warning(rates: &[BaseInterestRate], today: NaiveDate) -> Option<String> {
+    let month = if today.month() >= 7 { 7 } else { 1 };
+    let period = NaiveDate::from_ymd_opt(today.year(), month, 1)?.format("%Y-%m-%d").to_string();
+    if rates.iter().any(|rate| rate.valid_from == period) {
+        return None;
+    }
+    let fallback = rates
+        .last()
+        .map(|rate| format!("the rate from {} ({} %) is used", german_date(&rate.valid_from), rate.rate))
+        .unwrap_or_else(|| "0 % is assumed".to_string());
+    Some(format!(
+        "The base interest rate (Basiszinssatz) from {} is missing; {}. Add it below.",
+        german_date(&period),
+        fallback
+    ))
+}
+
+/// `17.10.2026`; anything that is not an ISO date is returned as is.
+pub fn german_date(value: &str) -> String {
+    match parse_date(Some(value)) {
+        Some(date) => date.format("%d.%m.%Y").to_string(),
+        None => value.to_string(),
+    }
+}
+
+pub fn format_amount(amount: Money, currency: &str) -> String {
+    let formatted = format_eur(amount);
+    if currency.eq_ignore_ascii_case("EUR") {
+        formatted
+    } else {
+        format!("{} {}", formatted.trim_end_matches(" €"), currency)
+    }
+}
+
+pub fn format_percent(value: f64) -> String {
+    format!("{:.2}", value).replace('.', ",")
+}
+
+#[allow(clippy::too_many_arguments)]
+fn write_letter(
+    template: &DunningTemplate,
+    invoice: &Invoice,
+    counterparty: Option<&Counterparty>,
+    case: &DunningCase,
+    settings: &Settings,
+    today: NaiveDate,
+    deadline: NaiveDate,
+    path: &Path,
+) -> Result<()> {
+    let currency = invoice.currency.as_str();
+    let total = case.outstanding + case.interest + case.fees;
+    let company_name = settings.company_name.clone().unwrap_or_default();
+    let company_address: Vec<String> = settings
+        .company_address
+        .as_deref()
+        .unwrap_or_default()
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect();
+    let customer = case.counterparty_name.clone().unwrap_or_default();
+    let iban = settings.company_iban.clone().unwrap_or_default();
+    let bic = settings.company_bic.clone().unwrap_or_default();
+    let values = [
+        ("customer", customer.clone()),
+        (
+            "invoice_number",
+            invoice.invoice_number.clone().unwrap_or_else(|| invoice.id.clone()),
+        ),
+        ("invoice_date", invoice.invoice_date.as_deref().map(german_date).unwrap_or_default()),
+        (
+            "due_date",
+            invoice
+                .due_date
+                .as_deref()
+                .map(german_date)
+                .unwrap_or_else(|| german_date(&case.default_since)),
+        ),
+        ("outstanding", format_amount(case.outstanding, currency)),
+        ("interest", format_amount(case.interest, currency)),
+        ("interest_rate", format_percent(case.interest_rate)),
+        ("default_since", german_date(&case.default_since)),
+        ("fees", format_amount(case.fees, currency)),
+        ("total", format_amount(total, currency)),
+        ("deadline", deadline.format("%d.%m.%Y").to_string()),
+        ("company_name", company_name.clone()),
+        ("iban", iban.clone()),
+        ("bic", bic.clone()),
+        ("date", today.format("%d.%m.%Y").to_string()),
+        ("level", level_name(case.next_level.unwrap_or(MAX_LEVEL)).to_string()),
+    ];
+    let fill = |text: &str| {
+        values
+            .iter()
+            .fold(text.to_string(), |text, (name, value)| text.replace(&format!("{{{}}}", name), value))
+    };
+
+    let right = PAGE_WIDTH - MARGIN_RIGHT;
+    let mut pdf = PdfWriter::new();
+
+    let mut recipient = vec![customer];
+    recipient.extend(
+        counterparty
+            .and_then(|counterparty| counterparty.address.as_deref())
+            .unwrap_or_default()
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty()),
+    );
+    pdf.letterhead(&company_name, &company_address, &recipient);
+
+    pdf.text_right(right, 590.0, 10.0, Font::Regular, &today.format("%d.%m.%Y").to_string());
+    pdf.text(MARGIN_LEFT, 560.0, 11.0, Font::Bold, &fill(&template.subject));
+    pdf.set_cursor(545.0);
+
+    for line in template.body.lines() {
+        if line.trim() == "{claims}" {
+            claims_table(&mut pdf, case, invoice, total);
+        } else {
+            pdf.paragraph(&fill(line), 10.0, Font::Regular);
+        }
+    }
+
+    let mut sender = vec![company_name.clone()];
+    sender.extend(company_address.iter().cloned());
+    let mut footer = vec![sender.join(" · ")];
+    if !iban.is_empty() {
+        let mut account = format!("IBAN {}", iban);
+        if !bic.is_empty() {
+            account.push_str(&format!(" · BIC {}", bic));
+        }
+        footer.push(account);
+    }
+    pdf.set_footer(footer);
+    pdf.save(path)
+}
+
+/// Open amount, interest and fees with the total, amounts right-aligned.
+fn claims_table(pdf: &mut PdfWriter, case: &DunningCase, invoice: &Invoice, total: Money) {
+    let currency = invoice.currency.as_str();
+    let left = MARGIN_LEFT + 20.0;
+    let right = PAGE_WIDTH - MARGIN_RIGHT - 20.0;
+    let mut rows = vec![(
+        format!(
+            "Offener Betrag Rechnung {}",
+            invoice.invoice_number.as_deref().unwrap_or(&invoice.id)
+        ),
+        case.outstanding,
+    )];
+    if case.interest > Money::ZERO {
+        rows.push((
+            format!(
+                "Verzugszinsen {} % p. a. seit {}",
+                format_percent(case.interest_rate),
+                german_date(&case.default_since)
+            ),
+            case.interest,
+        ));
+    }
+    if case.fees > Money::ZERO {
+        let label = if case.consumer {
+            "Mahngebühren"
+        } else {
+            "Verzugspauschale (§ 288 Abs. 5 BGB)"
+        };
+        rows.push((label.to_string(), case.fees));
+    }
+
+    pdf.ensure_space((rows.len() as f32 + 2.0) * 14.0);
+    for (label, amount) in rows {
+        pdf.skip(14.0);
+        let y = pdf.cursor();
+        pdf.text(left, y, 10.0, Font::Regular, &label);
+        pdf.text_right(right, y, 10.0, Font::Regular, &format_amount(amount, currency));
+    }
+    pdf.skip(6.0);
+    let y = pdf.cursor();
+    pdf.line(left, y, right, y);
+    pdf.skip(14.0);
+    let y = pdf.cursor();
+    pdf.text(left, y, 10.0, Font::Bold, "Gesamtbetrag");
+    pdf.text_right(right, y, 10.0, Font::Bold, &format_amount(total, currency));
+}
+
+fn default_template(level: u8) -> DunningTemplate {
+    let (subject, body) = match level {
+        1 => (
+            "Zahlungserinnerung zur Rechnung {invoice_number}",
+            "Sehr geehrte Damen und Herren,\n\
+             \n\
+             sicher ist es Ihrer Aufmerksamkeit entgangen, dass unsere Rechnung {invoice_number} vom {invoice_date} \
+             seit dem {due_date} zur Zahlung fällig ist. Bis heute konnten wir keinen vollständigen Zahlungseingang \
+             feststellen.\n\
+             \n\
+             {claims}\n\
+             \n\
+             Bitte überweisen Sie den offenen Betrag bis zum {deadline} auf unser unten angegebenes Konto. Sollten \
+             Sie die Zahlung bereits veranlasst haben, betrachten Sie dieses Schreiben bitte als gegenstandslos.\n\
+             \n\
+             Mit freundlichen Grüßen\n\
+             {company_name}",
+        ),
+        2 => (
+            "1. Mahnung zur Rechnung {invoice_number}",
+            "Sehr geehrte Damen und Herren,\n\
+             \n\
+             leider konnten wir zu unserer Rechnung {invoice_number} vom {invoice_date} trotz unserer \
+             Zahlungserinnerung noch keinen Zahlungseingang feststellen. Sie befinden sich seit dem {default_since} \
+             in Zahlungsverzug. Gemäß § 288 BGB berechnen wir Verzugszinsen in Höhe von {interest_rate} % p. a. \
+             sowie die folgenden Kosten:\n\
+             \n\
+             {claims}\n\
+             \n\
+             Bitte überweisen Sie den Gesamtbetrag von {total} bis zum {deadline} auf unser unten angegebenes \
+             Konto.\n\
+             \n\
+             Mit freundlichen Grüßen\n\
+             {company_name}",
+        ),
+        _ => (
+            "2. Mahnung zur Rechnung {invoice_number}",
+            "Sehr geehrte Damen und Herren,\n\
+             \n\
+             trotz unserer Mahnung ist unsere Rechnung {invoice_number} vom {invoice_date} weiterhin nicht \
+             beglichen. Sie befinden sich seit dem {default_since} in Zahlungsverzug. Unsere Forderung beläuft sich \
+             inzwischen auf:\n\
+             \n\
+             {claims}\n\
+             \n\
+             Wir fordern Sie letztmalig auf, den Gesamtbetrag von {total} bis zum {deadline} zu überweisen. \
+             Andernfalls werden wir die Forderung ohne weitere Ankündigung gerichtlich geltend machen und die \
+             dadurch entstehenden Kosten Ihnen in Rechnung stellen.\n\
+             \n\
+             Mit freundlichen Grüßen\n\
+             {company_name}",
+        ),
+    };
+    DunningTemplate {
+        level,
+        subject: subject.to_string(),
+        body: body.to_string(),
+        customized: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(raw: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(raw, "%Y-%m-%d").unwrap()
+    }
+
+    fn test_db() -> (std::path::PathBuf, Database) {
+        let dir = std::env::temp_dir().join(format!("billly-dunning-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = Database::new(dir.join("test.db")).unwrap();
+        (dir, db)
+    }
+
+    fn invoice() -> Invoice {
+        Invoice {
+            id: "r1".to_string(),
+            category: "revenue".to_string(),
+            invoice_number: Some("RE-2024-001".to_string()),
+            invoice_date: Some("2024-05-17".to_string()),
+            due_date: Some("2024-05-31".to_string()),
+            counterparty_name: Some("Alpha GmbH".to_string()),
+            total_amount: Money::parse("1000.00").unwrap(),
+            currency: "EUR".to_string(),
+            status: "open".to_string(),
+            counterparty_id: Some("c1".to_string()),
+            ..Invoice::default()
+        }
+    }
+
+    fn counterparty(consumer: bool) -> Counterparty {
+        Counterparty {
+            id: "c1".to_string(),
+            name: "Alpha GmbH".to_string(),
+            aliases: Vec::new(),
+            vat_id: None,
+            iban: None,
+            bic: None,
+            address: None,
+            email: None,
+            default_category: None,
+            personal_account: None,
+            consumer,
+            invoice_count: 1,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    fn reminder() -> DunningStep {
+        DunningStep {
+            id: "s1".to_string(),
+            invoice_id: "r1".to_string(),
+            level: 1,
+            outstanding: Money::parse("1000.00").unwrap(),
+            interest: Money::ZERO,
+            fees: Money::ZERO,
+            interest_rate: None,
+            default_since: Some("2024-06-01".to_string()),
+            deadline: "2024-06-14".to_string(),
+            file_path: String::new(),
+            created_at: "2024-06-03 10:00:00".to_string(),
+        }
+    }
+
+    #[test]
+    fn seeded_base_rates_cover_2026() {
+        let (dir, db) = test_db();
+        let rates = db.get_base_interest_rates().unwrap();
+        assert_eq!(base_rate_on(&rates, date("2024-06-30")), Some(3.62));
+        assert_eq!(base_rate_on(&rates, date("2024-07-01")), Some(3.37));
+        assert_eq!(base_rate_on(&rates, date("2025-01-01")), Some(2.27));
+        assert_eq!(base_rate_on(&rates, date("2026-03-01")), Some(1.27));
+        assert_eq!(base_rate_warning(&rates, date("2026-03-01")), None);
+        assert_eq!(base_rate_warning(&rates, date("2026-10-17")), None);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn interest_follows_base_rate_changes() {
+        let rates = vec![
+            BaseInterestRate { valid_from: "2024-01-01".to_string(), rate: 3.62 },
+            BaseInterestRate { valid_from: "2024-07-01".to_string(), rate: 3.37 },
+            BaseInterestRate { valid_from: "2025-01-01".to_string(), rate: 2.27 },
+        ];
+        let outstanding = Money::parse("1000.00").unwrap();
+        // 30 days at 12.62 %, 184 at 12.37 % and 31 at 11.27 %.
+        let interest = default_interest(outstanding, date("2024-06-01"), date("2025-01-31"), BUSINESS_MARGIN, &rates);
+        assert_eq!(interest.to_string(), "82.30");
+        let interest = default_interest(outstanding, date("2024-06-01"), date("2025-01-31"), CONSUMER_MARGIN, &rates);
+        assert_eq!(interest.to_string(), "55.45");
+        // A single day within one period.
+        let interest = default_interest(outstanding, date("2024-07-01"), date("2024-07-01"), BUSINESS_MARGIN, &rates);
+        assert_eq!(interest.to_string(), "0.34");
+    }
+
+    #[test]
+    fn business_debtor_owes_flat_fee_and_nine_points() {
+        let (dir, db) = test_db();
+        let rates = db.get_base_interest_rates().unwrap();
+        let settings = crate::load_settings(&db);
+        let counterparties = [counterparty(false)];
+        let today = date("2025-01-31");
+
+        let case = case_for(&db, &invoice(), &counterparties, &[], &rates, &settings, today).unwrap().unwrap();
+        assert_eq!(case.default_since, "2024-06-01");
+        assert_eq!(case.days_overdue, 245);
+        assert_eq!(case.next_level, Some(1));
+        assert_eq!(case.interest, Money::ZERO);
+        assert_eq!(case.fees, Money::ZERO);
+
+        let step = reminder();
+        let case = case_for(&db, &invoice(), &counterparties, &[&step], &rates, &settings, today).unwrap().unwrap();
+        assert!(!case.consumer);
+        assert_eq!(case.next_level, Some(2));
+        assert!(case.next_due);
+        assert_eq!(case.interest.to_string(), "82.30");
+        assert!((case.interest_rate - 11.27).abs() < 1e-9);
+        assert_eq!(case.fees.to_string(), "40.00");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn consumer_owes_five_points_and_dunning_fee() {
+        let (dir, db) = test_db();
+        let rates = db.get_base_interest_rates().unwrap();
+        let settings = crate::load_settings(&db);
+        let counterparties = [counterparty(true)];
+        let step = reminder();
+
+        let case = case_for(&db, &invoice(), &counterparties, &[&step], &rates, &settings, date("2025-01-31"))
+            .unwrap()
+            .unwrap();
+        assert!(case.consumer);
+        assert_eq!(case.interest.to_string(), "55.45");
+        assert!((case.interest_rate - 7.27).abs() < 1e-9);
+        assert_eq!(case.fees, settings.dunning_fee);
+
+        assert!(case_for(&db, &invoice(), &counterparties, &[], &rates, &settings, date("2024-05-31"))
+            .unwrap()
+            .is_none());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
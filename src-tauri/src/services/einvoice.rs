@@ -0,0 +1,781 @@
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use lopdf::{Document, Object};
+use roxmltree::Node;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::models::{ExtractedInvoiceData, ExtractedLineItem, ExtractedVatEntry};
+use crate::utils::{escape_html, round_cents, sha256_file};
+
+const CII_ROOT: &str = "CrossIndustryInvoice";
+const UBL_INVOICE_ROOT: &str = "Invoice";
+const UBL_CREDIT_NOTE_ROOT: &str = "CreditNote";
+/// UNTDID 1001 document type of a CII credit note.
+const CII_CREDIT_NOTE_TYPE: &str = "381";
+
+/// Syntax-independent view of a structured e-invoice. Keeps both parties so
+/// the counterparty can be chosen per category and both can be rendered.
+#[derive(Debug, Clone)]
+pub struct EInvoiceDocument {
+    pub syntax: &'static str,
+    pub invoice_number: Option<String>,
+    pub invoice_date: Option<String>,
+    pub due_date: Option<String>,
+    pub seller_name: Option<String>,
+    pub buyer_name: Option<String>,
+    pub seller_vat_id: Option<String>,
+    pub buyer_vat_id: Option<String>,
+    pub currency: Option<String>,
+    pub total_amount: Option<f64>,
+    pub tax_amount: Option<f64>,
+    pub net_amount: Option<f64>,
+    pub line_items: Vec<ExtractedLineItem>,
+    pub vat_breakdown: Vec<ExtractedVatEntry>,
+    pub payment: EInvoicePayment,
+}
+
+/// Payee account and payment terms (BG-16, BG-17, BT-83, BT-20).
+#[derive(Debug, Clone, Default)]
+pub struct EInvoicePayment {
+    pub iban: Option<String>,
+    pub bic: Option<String>,
+    pub reference: Option<String>,
+    pub terms: Option<String>,
+    pub discount_percent: Option<f64>,
+    pub discount_days: Option<u32>,
+    pub discount_due_date: Option<String>,
+}
+
+/// Reads structured e-invoices (ZUGFeRD / Factur-X, XRechnung in CII or UBL)
+/// so no OCR or LLM is needed.
+pub struct EInvoiceParser;
+
+impl EInvoiceParser {
+    /// Returns the embedded XML of a hybrid PDF, if there is one.
+    pub fn find_embedded_xml(path: &Path) -> Result<Option<String>> {
+        let doc = Document::load(path).map_err(|e| anyhow!("PDF load: {}", e))?;
+
+        for object in doc.objects.values() {
+            let Ok(dict) = object.as_dict() else {
+                continue;
+            };
+            let Ok(embedded) = dict.get(b"EF").and_then(Object::as_dict) else {
+                continue;
+            };
+
+            let file_name = [b"UF".as_slice(), b"F".as_slice()]
+                .iter()
+                .find_map(|key| dict.get(key).ok().and_then(|name| name.as_str().ok()))
+                .map(decode_pdf_string)
+                .unwrap_or_default();
+            if !file_name.to_lowercase().ends_with(".xml") {
+                continue;
+            }
+
+            let Ok(stream_ref) = embedded.get(b"F").and_then(Object::as_reference) else {
+                continue;
+            };
+            let Ok(stream) = doc.get_object(stream_ref).and_then(Object::as_stream) else {
+                continue;
+            };
+            let content = stream
+                .decompressed_content()
+                .unwrap_or_else(|_| stream.content.clone());
+            let xml = String::from_utf8_lossy(&content).trim_start_matches('\u{feff}').to_string();
+            if root_name(&xml).as_deref() == Some(CII_ROOT) {
+                return Ok(Some(xml));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Parses a CII or UBL document, detected by its root element.
+    pub fn read(xml: &str) -> Result<EInvoiceDocument> {
+        let xml = xml.trim_start_matches('\u{feff}');
+        let doc = roxmltree::Document::parse(xml).map_err(|e| anyhow!("XML parse: {}", e))?;
+        let root = doc.root_element();
+        match root.tag_name().name() {
+            CII_ROOT => parse_cii(root),
+            UBL_INVOICE_ROOT | UBL_CREDIT_NOTE_ROOT => parse_ubl(root),
+            other => Err(anyhow!("Unsupported e-invoice format: {}", other)),
+        }
+    }
+
+    /// Whether the XML file at `path` is a CII or UBL invoice, judged by its
+    /// root element. Payment files and other XML are not.
+    pub fn is_einvoice_file(path: &Path) -> bool {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|xml| root_name(xml.trim_start_matches('\u{feff}')))
+            .is_some_and(|root| [CII_ROOT, UBL_INVOICE_ROOT, UBL_CREDIT_NOTE_ROOT].contains(&root.as_str()))
+    }
+
+    /// Writes an HTML rendering of a standalone XML invoice to the temp
+    /// directory and returns its path, so it can be opened like a PDF.
+    pub fn render_html_file(path: &Path) -> Result<PathBuf> {
+        let xml = std::fs::read_to_string(path)?;
+        let document = Self::read(&xml)?;
+        let dir = std::env::temp_dir().join("billly");
+        std::fs::create_dir_all(&dir)?;
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| "invoice".to_string());
+        // Files with the same name in different folders must not overwrite
+        // each other's rendering.
+        let hash = sha256_file(path)?;
+        let target = dir.join(format!("{}-{}.html", stem, &hash[..12]));
+        std::fs::write(&target, document.render_html())?;
+        Ok(target)
+    }
+}
+
+impl EInvoiceDocument {
+    /// `category` decides whether the seller or the buyer is the counterparty.
+    pub fn into_extracted(self, category: &str) -> ExtractedInvoiceData {
+        let (counterparty_name, counterparty_vat_id) = if category == "revenue" {
+            (self.buyer_name, self.buyer_vat_id)
+        } else {
+            (self.seller_name, self.seller_vat_id)
+        };
+
+        let mut field_confidence = BTreeMap::new();
+        let present = [
+            ("invoice_number", self.invoice_number.is_some()),
+            ("invoice_date", self.invoice_date.is_some()),
+            ("due_date", self.due_date.is_some()),
+            ("counterparty_name", counterparty_name.is_some()),
+            ("total_amount", self.total_amount.is_some()),
+            ("currency", self.currency.is_some()),
+            ("tax_amount", self.tax_amount.is_some()),
+            ("net_amount", self.net_amount.is_some()),
+            ("iban", self.payment.iban.is_some()),
+            ("bic", self.payment.bic.is_some()),
+            ("payment_reference", self.payment.reference.is_some()),
+            ("payment_terms", self.payment.terms.is_some()),
+            ("discount_percent", self.payment.discount_percent.is_some()),
+        ];
+        for (field, found) in present {
+            if found {
+                field_confidence.insert(field.to_string(), 1.0);
+            }
+        }
+
+        ExtractedInvoiceData {
+            invoice_number: self.invoice_number,
+            invoice_date: self.invoice_date,
+            due_date: self.due_date,
+            counterparty_name,
+            counterparty_vat_id,
+            total_amount: self.total_amount,
+            currency: Some(self.currency.unwrap_or_else(|| "EUR".to_string())),
+            tax_amount: self.tax_amount,
+            net_amount: self.net_amount,
+            extraction_notes: format!("structured e-invoice: {}", self.syntax),
+            confidence_score: Some(1.0),
+            field_confidence,
+            line_items: self.line_items,
+            vat_breakdown: self.vat_breakdown,
+            expense_category: None,
+            iban: self.payment.iban,
+            bic: self.payment.bic,
+            payment_reference: self.payment.reference,
+            payment_terms: self.payment.terms,
+            discount_percent: self.payment.discount_percent,
+            discount_days: self.payment.discount_days,
+            discount_due_date: self.payment.discount_due_date,
+        }
+    }
+
+    /// Credit notes state their amounts as positive values; they are stored
+    /// negative so they reduce revenue, expenses and VAT.
+    fn negate_amounts(&mut self) {
+        for amount in [&mut self.total_amount, &mut self.tax_amount, &mut self.net_amount] {
+            *amount = amount.map(|value| -value);
+        }
+        for item in &mut self.line_items {
+            item.net_amount = item.net_amount.map(|value| -value);
+            item.line_total = item.line_total.map(|value| -value);
+        }
+        for entry in &mut self.vat_breakdown {
+            entry.net_amount = -entry.net_amount;
+            entry.tax_amount = -entry.tax_amount;
+        }
+    }
+
+    /// Plain-text rendering stored as `ocr_text` for XML-only invoices.
+    pub fn render_text(&self) -> String {
+        let currency = self.currency.clone().unwrap_or_else(|| "EUR".to_string());
+        let mut lines = vec![
+            format!("E-Rechnung ({})", self.syntax),
+            format!("Rechnungsnummer: {}", display(&self.invoice_number)),
+            format!("Rechnungsdatum: {}", display(&self.invoice_date)),
+            format!("Fällig am: {}", display(&self.due_date)),
+            format!("Verkäufer: {}", display(&self.seller_name)),
+            format!("Käufer: {}", display(&self.buyer_name)),
+            String::new(),
+        ];
+        for item in &self.line_items {
+            lines.push(format!(
+                "{} | {} {} x {} | {} {}",
+                item.description.clone().unwrap_or_default(),
+                display_amount(item.quantity),
+                item.unit.clone().unwrap_or_default(),
+                display_amount(item.unit_price),
+                display_amount(item.net_amount),
+                currency
+            ));
+        }
+        lines.push(String::new());
+        for entry in &self.vat_breakdown {
+            lines.push(format!(
+                "USt {:.2}% auf {:.2}: {:.2} {}",
+                entry.rate, entry.net_amount, entry.tax_amount, currency
+            ));
+        }
+        lines.push(format!("Summe netto: {} {}", display_amount(self.net_amount), currency));
+        lines.push(format!("Umsatzsteuer: {} {}", display_amount(self.tax_amount), currency));
+        lines.push(format!("Gesamtbetrag: {} {}", display_amount(self.total_amount), currency));
+        let payment = self.payment_lines();
+        if !payment.is_empty() {
+            lines.push(String::new());
+            lines.extend(payment);
+        }
+        lines.join("\n")
+    }
+
+    pub fn render_html(&self) -> String {
+        let currency = escape_html(&self.currency.clone().unwrap_or_else(|| "EUR".to_string()));
+        let rows = self
+            .line_items
+            .iter()
+            .map(|item| {
+                format!(
+                    "<tr><td>{}</td><td class=\"num\">{} {}</td><td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td></tr>",
+                    escape_html(&item.description.clone().unwrap_or_default()),
+                    display_amount(item.quantity),
+                    escape_html(&item.unit.clone().unwrap_or_default()),
+                    display_amount(item.unit_price),
+                    item.vat_rate.map(|rate| format!("{:.2}%", rate)).unwrap_or_default(),
+                    display_amount(item.net_amount),
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let vat_rows = self
+            .vat_breakdown
+            .iter()
+            .map(|entry| {
+                format!(
+                    "<tr><td>USt {:.2}% auf {:.2}</td><td class=\"num\">{:.2} {}</td></tr>",
+                    entry.rate, entry.net_amount, entry.tax_amount, currency
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let payment = self
+            .payment_lines()
+            .iter()
+            .map(|line| escape_html(line))
+            .collect::<Vec<_>>()
+            .join("<br />");
+
+        format!(
+            r#"<!doctype html>
+<html lang="de">
+<head>
+<meta charset="utf-8" />
+<title>Rechnung {number}</title>
+<style>
+body {{ font-family: sans-serif; margin: 2rem; color: #111; }}
+table {{ border-collapse: collapse; width: 100%; margin-top: 1rem; }}
+th, td {{ border-bottom: 1px solid #ddd; padding: 0.4rem; text-align: left; }}
+.num {{ text-align: right; }}
+.parties {{ display: flex; gap: 4rem; }}
+</style>
+</head>
+<body>
+<h1>Rechnung {number}</h1>
+<p>E-Rechnung ({syntax})</p>
+<div class="parties">
+<div><h3>Verkäufer</h3><p>{seller}</p></div>
+<div><h3>Käufer</h3><p>{buyer}</p></div>
+</div>
+<p>Rechnungsdatum: {date}<br />Fällig am: {due}</p>
+<table>
+<thead><tr><th>Beschreibung</th><th class="num">Menge</th><th class="num">Einzelpreis</th><th class="num">USt</th><th class="num">Netto</th></tr></thead>
+<tbody>
+{rows}
+</tbody>
+</table>
+<table>
+<tr><td>Summe netto</td><td class="num">{net} {currency}</td></tr>
+{vat_rows}
+<tr><td>Umsatzsteuer</td><td class="num">{tax} {currency}</td></tr>
+<tr><th>Gesamtbetrag</th><th class="num">{total} {currency}</th></tr>
+</table>
+<p>{payment}</p>
+</body>
+</html>
+"#,
+            number = escape_html(&display(&self.invoice_number)),
+            syntax = self.syntax,
+            seller = escape_html(&display(&self.seller_name)),
+            buyer = escape_html(&display(&self.buyer_name)),
+            date = escape_html(&display(&self.invoice_date)),
+            due = escape_html(&display(&self.due_date)),
+            rows = rows,
+            vat_rows = vat_rows,
+            net = display_amount(self.net_amount),
+            tax = display_amount(self.tax_amount),
+            total = display_amount(self.total_amount),
+            currency = currency,
+            payment = payment,
+        )
+    }
+
+    fn payment_lines(&self) -> Vec<String> {
+        let payment = &self.payment;
+        let mut lines = Vec::new();
+        if let Some(iban) = &payment.iban {
+            lines.push(format!("IBAN: {}", iban));
+        }
+        if let Some(bic) = &payment.bic {
+            lines.push(format!("BIC: {}", bic));
+        }
+        if let Some(reference) = &payment.reference {
+            lines.push(format!("Verwendungszweck: {}", reference));
+        }
+        if let Some(terms) = &payment.terms {
+            lines.push(format!("Zahlungsbedingungen: {}", terms));
+        }
+        if let Some(percent) = payment.discount_percent {
+            let period = match (&payment.discount_due_date, payment.discount_days) {
+                (Some(date), _) => format!(" bis {}", date),
+                (None, Some(days)) => format!(" innerhalb {} Tagen", days),
+                (None, None) => String::new(),
+            };
+            lines.push(format!("Skonto: {}%{}", percent, period));
+        }
+        lines
+    }
+}
+
+fn parse_cii(root: Node) -> Result<EInvoiceDocument> {
+    let transaction = child(root, "SupplyChainTradeTransaction")
+        .ok_or_else(|| anyhow!("CII: SupplyChainTradeTransaction missing"))?;
+    let agreement = child(transaction, "ApplicableHeaderTradeAgreement");
+    let settlement = child(transaction, "ApplicableHeaderTradeSettlement")
+        .ok_or_else(|| anyhow!("CII: ApplicableHeaderTradeSettlement missing"))?;
+    let summation = child(settlement, "SpecifiedTradeSettlementHeaderMonetarySummation")
+        .ok_or_else(|| anyhow!("CII: monetary summation missing"))?;
+
+    let currency = text_at(settlement, &["InvoiceCurrencyCode"]);
+
+    let line_items = children(transaction, "IncludedSupplyChainTradeLineItem")
+        .map(|line| {
+            let net_amount = amount_at(
+                line,
+                &[
+                    "SpecifiedLineTradeSettlement",
+                    "SpecifiedTradeSettlementLineMonetarySummation",
+                    "LineTotalAmount",
+                ],
+            );
+            let vat_rate = amount_at(
+                line,
+                &["SpecifiedLineTradeSettlement", "ApplicableTradeTax", "RateApplicablePercent"],
+            );
+            let quantity_node = path(line, &["SpecifiedLineTradeDelivery", "BilledQuantity"]);
+            ExtractedLineItem {
+                description: text_at(line, &["SpecifiedTradeProduct", "Name"]),
+                quantity: quantity_node.and_then(node_amount),
+                unit: quantity_node.and_then(|node| node.attribute("unitCode")).map(str::to_string),
+                unit_price: amount_at(
+                    line,
+                    &["SpecifiedLineTradeAgreement", "NetPriceProductTradePrice", "ChargeAmount"],
+                ),
+                net_amount,
+                vat_rate,
+                line_total: gross_amount(net_amount, vat_rate),
+            }
+        })
+        .collect();
+
+    let vat_breakdown = children(settlement, "ApplicableTradeTax")
+        .filter_map(|tax| {
+            Some(ExtractedVatEntry {
+                rate: amount_at(tax, &["RateApplicablePercent"]).unwrap_or(0.0),
+                net_amount: amount_at(tax, &["BasisAmount"])?,
+                tax_amount: amount_at(tax, &["CalculatedAmount"])?,
+            })
+        })
+        .collect();
+
+    let payment_terms = child(settlement, "SpecifiedTradePaymentTerms");
+    let description = payment_terms.and_then(|terms| text_at(terms, &["Description"]));
+    let discount_terms = payment_terms.and_then(|terms| child(terms, "ApplicableTradePaymentDiscountTerms"));
+    let mut payment = EInvoicePayment {
+        iban: children(settlement, "SpecifiedTradeSettlementPaymentMeans")
+            .find_map(|means| text_at(means, &["PayeePartyCreditorFinancialAccount", "IBANID"])),
+        bic: children(settlement, "SpecifiedTradeSettlementPaymentMeans")
+            .find_map(|means| text_at(means, &["PayeeSpecifiedCreditorFinancialInstitution", "BICID"])),
+        reference: text_at(settlement, &["PaymentReference"]),
+        terms: description.as_deref().and_then(terms_text),
+        discount_percent: discount_terms.and_then(|terms| amount_at(terms, &["CalculationPercent"])),
+        discount_days: discount_terms
+            .and_then(|terms| amount_at(terms, &["BasisPeriodMeasure"]))
+            .map(|days| days as u32),
+        discount_due_date: None,
+    };
+    if payment.discount_percent.is_none() {
+        if let Some((days, percent)) = description.as_deref().and_then(xrechnung_skonto) {
+            payment.discount_days = Some(days);
+            payment.discount_percent = Some(percent);
+        }
+    }
+
+    let mut document = EInvoiceDocument {
+        syntax: "CII",
+        invoice_number: text_at(root, &["ExchangedDocument", "ID"]),
+        invoice_date: text_at(root, &["ExchangedDocument", "IssueDateTime", "DateTimeString"])
+            .and_then(parse_xml_date),
+        due_date: text_at(settlement, &["SpecifiedTradePaymentTerms", "DueDateDateTime", "DateTimeString"])
+            .and_then(parse_xml_date),
+        seller_name: agreement.and_then(|node| text_at(node, &["SellerTradeParty", "Name"])),
+        buyer_name: agreement.and_then(|node| text_at(node, &["BuyerTradeParty", "Name"])),
+        seller_vat_id: agreement.and_then(|node| cii_vat_id(node, "SellerTradeParty")),
+        buyer_vat_id: agreement.and_then(|node| cii_vat_id(node, "BuyerTradeParty")),
+        total_amount: amount_at(summation, &["GrandTotalAmount"]),
+        tax_amount: currency_amount(summation, "TaxTotalAmount", &currency),
+        net_amount: amount_at(summation, &["TaxBasisTotalAmount"]),
+        currency,
+        line_items,
+        vat_breakdown,
+        payment,
+    };
+    if text_at(root, &["ExchangedDocument", "TypeCode"]).as_deref() == Some(CII_CREDIT_NOTE_TYPE) {
+        document.negate_amounts();
+    }
+    Ok(document)
+}
+
+fn parse_ubl(root: Node) -> Result<EInvoiceDocument> {
+    let is_credit_note = root.tag_name().name() == UBL_CREDIT_NOTE_ROOT;
+    let (line_name, quantity_name) = if is_credit_note {
+        ("CreditNoteLine", "CreditedQuantity")
+    } else {
+        ("InvoiceLine", "InvoicedQuantity")
+    };
+    let totals = child(root, "LegalMonetaryTotal").ok_or_else(|| anyhow!("UBL: LegalMonetaryTotal missing"))?;
+    let currency = text_at(root, &["DocumentCurrencyCode"]);
+
+    let line_items = children(root, line_name)
+        .map(|line| {
+            let net_amount = amount_at(line, &["LineExtensionAmount"]);
+            let vat_rate = amount_at(line, &["Item", "ClassifiedTaxCategory", "Percent"]);
+            let quantity_node = child(line, quantity_name);
+            ExtractedLineItem {
+                description: text_at(line, &["Item", "Name"]),
+                quantity: quantity_node.and_then(node_amount),
+                unit: quantity_node.and_then(|node| node.attribute("unitCode")).map(str::to_string),
+                unit_price: amount_at(line, &["Price", "PriceAmount"]),
+                net_amount,
+                vat_rate,
+                line_total: gross_amount(net_amount, vat_rate),
+            }
+        })
+        .collect();
+
+    // A second TaxTotal without subtotals may carry the accounting currency.
+    let tax_total = children(root, "TaxTotal").find(|total| match (&currency, child(*total, "TaxAmount")) {
+        (Some(expected), Some(amount)) => amount.attribute("currencyID").map(|id| id == expected).unwrap_or(true),
+        _ => true,
+    });
+    let vat_breakdown = tax_total
+        .map(|total| {
+            children(total, "TaxSubtotal")
+                .filter_map(|subtotal| {
+                    Some(ExtractedVatEntry {
+                        rate: amount_at(subtotal, &["TaxCategory", "Percent"]).unwrap_or(0.0),
+                        net_amount: amount_at(subtotal, &["TaxableAmount"])?,
+                        tax_amount: amount_at(subtotal, &["TaxAmount"])?,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let due_date = text_at(root, &["DueDate"])
+        .or_else(|| text_at(root, &["PaymentMeans", "PaymentDueDate"]))
+        .and_then(parse_xml_date);
+
+    let note = children(root, "PaymentTerms")
+        .filter_map(|terms| text_at(terms, &["Note"]))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let skonto = xrechnung_skonto(&note);
+    let payment = EInvoicePayment {
+        iban: children(root, "PaymentMeans").find_map(|means| text_at(means, &["PayeeFinancialAccount", "ID"])),
+        bic: children(root, "PaymentMeans")
+            .find_map(|means| text_at(means, &["PayeeFinancialAccount", "FinancialInstitutionBranch", "ID"])),
+        reference: children(root, "PaymentMeans").find_map(|means| text_at(means, &["PaymentID"])),
+        terms: terms_text(&note),
+        discount_percent: skonto.map(|(_, percent)| percent),
+        discount_days: skonto.map(|(days, _)| days),
+        discount_due_date: None,
+    };
+
+    let mut document = EInvoiceDocument {
+        syntax: if is_credit_note { "UBL CreditNote" } else { "UBL" },
+        invoice_number: text_at(root, &["ID"]),
+        invoice_date: text_at(root, &["IssueDate"]).and_then(parse_xml_date),
+        due_date,
+        seller_name: ubl_party_name(root, "AccountingSupplierParty"),
+        buyer_name: ubl_party_name(root, "AccountingCustomerParty"),
+        seller_vat_id: ubl_vat_id(root, "AccountingSupplierParty"),
+        buyer_vat_id: ubl_vat_id(root, "AccountingCustomerParty"),
+        total_amount: amount_at(totals, &["TaxInclusiveAmount"]).or_else(|| amount_at(totals, &["PayableAmount"])),
+        tax_amount: tax_total.and_then(|total| amount_at(total, &["TaxAmount"])),
+        net_amount: amount_at(totals, &["TaxExclusiveAmount"]),
+        currency,
+        line_items,
+        vat_breakdown,
+        payment,
+    };
+    if is_credit_note {
+        document.negate_amounts();
+    }
+    Ok(document)
+}
+
+/// Payment terms as text, without XRechnung `#SKONTO#` code lines.
+fn terms_text(terms: &str) -> Option<String> {
+    let text = terms
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+/// First XRechnung Skonto code, `#SKONTO#TAGE=14#PROZENT=2.00#`, as days and
+/// percent.
+fn xrechnung_skonto(terms: &str) -> Option<(u32, f64)> {
+    terms.lines().map(str::trim).find_map(|line| {
+        let code = line.strip_prefix("#SKONTO#")?;
+        let mut days = None;
+        let mut percent = None;
+        for part in code.split('#') {
+            match part.split_once('=') {
+                Some(("TAGE", value)) => days = value.trim().parse().ok(),
+                Some(("PROZENT", value)) => percent = value.trim().parse().ok(),
+                _ => {}
+            }
+        }
+        Some((days?, percent?))
+    })
+}
+
+fn ubl_party_name(root: Node, role: &str) -> Option<String> {
+    let party = path(root, &[role, "Party"])?;
+    text_at(party, &["PartyName", "Name"]).or_else(|| text_at(party, &["PartyLegalEntity", "RegistrationName"]))
+}
+
+/// `SpecifiedTaxRegistration/ID` with scheme `VA` (VAT ID, BT-31 / BT-48).
+fn cii_vat_id(agreement: Node, party: &str) -> Option<String> {
+    let party = child(agreement, party)?;
+    children(party, "SpecifiedTaxRegistration")
+        .filter_map(|registration| child(registration, "ID"))
+        .find(|id| id.attribute("schemeID") == Some("VA"))
+        .and_then(|id| id.text())
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+}
+
+fn ubl_vat_id(root: Node, role: &str) -> Option<String> {
+    let party = path(root, &[role, "Party"])?;
+    text_at(party, &["PartyTaxScheme", "CompanyID"])
+}
+
+/// Picks the amount element in the invoice currency when it is repeated.
+fn currency_amount(node: Node, name: &str, currency: &Option<String>) -> Option<f64> {
+    children(node, name)
+        .find(|found| match (currency, found.attribute("currencyID")) {
+            (Some(expected), Some(id)) => expected == id,
+            _ => true,
+        })
+        .and_then(node_amount)
+}
+
+fn display(value: &Option<String>) -> String {
+    value.clone().unwrap_or_else(|| "-".to_string())
+}
+
+fn display_amount(value: Option<f64>) -> String {
+    value.map(|amount| format!("{:.2}", amount)).unwrap_or_else(|| "-".to_string())
+}
+
+fn root_name(xml: &str) -> Option<String> {
+    roxmltree::Document::parse(xml)
+        .ok()
+        .map(|doc| doc.root_element().tag_name().name().to_string())
+}
+
+/// PDF text strings are either PDFDocEncoding or UTF-16BE with a BOM.
+fn decode_pdf_string(bytes: &[u8]) -> String {
+    if bytes.starts_with(&[0xFE, 0xFF]) {
+        let units: Vec<u16> = bytes[2..]
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+        return String::from_utf16_lossy(&units);
+    }
+    String::from_utf8_lossy(bytes).to_string()
+}
+
+fn parse_xml_date(raw: String) -> Option<String> {
+    let raw = raw.trim();
+    ["%Y%m%d", "%Y-%m-%d"]
+        .iter()
+        .find_map(|fmt| NaiveDate::parse_from_str(raw, fmt).ok())
+        .map(|date| date.format("%Y-%m-%d").to_string())
+}
+
+fn gross_amount(net: Option<f64>, rate: Option<f64>) -> Option<f64> {
+    let net = net?;
+    let rate = rate.unwrap_or(0.0);
+    Some(round_cents(net * (100.0 + rate) / 100.0))
+}
+
+pub fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children()
+        .find(|candidate| candidate.is_element() && candidate.tag_name().name() == name)
+}
+
+pub fn children<'a, 'input: 'a>(node: Node<'a, 'input>, name: &'a str) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children()
+        .filter(move |candidate| candidate.is_element() && candidate.tag_name().name() == name)
+}
+
+pub fn path<'a, 'input>(node: Node<'a, 'input>, names: &[&str]) -> Option<Node<'a, 'input>> {
+    names.iter().try_fold(node, |current, name| child(current, name))
+}
+
+pub fn text_at(node: Node, names: &[&str]) -> Option<String> {
+    path(node, names)
+        .and_then(|found| found.text())
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+}
+
+fn amount_at(node: Node, names: &[&str]) -> Option<f64> {
+    path(node, names).and_then(node_amount)
+}
+
+fn node_amount(node: Node) -> Option<f64> {
+    node.text().and_then(|text| text.trim().parse::<f64>().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UBL_CREDIT_NOTE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<CreditNote xmlns="urn:oasis:names:specification:ubl:schema:xsd:CreditNote-2"
+    xmlns:cac="urn:oasis:names:specification:ubl:schema:xsd:CommonAggregateComponents-2"
+    xmlns:cbc="urn:oasis:names:specification:ubl:schema:xsd:CommonBasicComponents-2">
+  <cbc:ID>GS-2024-17</cbc:ID>
+  <cbc:IssueDate>2024-05-02</cbc:IssueDate>
+  <cbc:CreditNoteTypeCode>381</cbc:CreditNoteTypeCode>
+  <cbc:DocumentCurrencyCode>EUR</cbc:DocumentCurrencyCode>
+  <cac:AccountingSupplierParty><cac:Party>
+    <cac:PartyName><cbc:Name>Lieferant GmbH</cbc:Name></cac:PartyName>
+  </cac:Party></cac:AccountingSupplierParty>
+  <cac:AccountingCustomerParty><cac:Party>
+    <cac:PartyName><cbc:Name>Billly Test GmbH</cbc:Name></cac:PartyName>
+  </cac:Party></cac:AccountingCustomerParty>
+  <cac:TaxTotal>
+    <cbc:TaxAmount currencyID="EUR">19.00</cbc:TaxAmount>
+    <cac:TaxSubtotal>
+      <cbc:TaxableAmount currencyID="EUR">100.00</cbc:TaxableAmount>
+      <cbc:TaxAmount currencyID="EUR">19.00</cbc:TaxAmount>
+      <cac:TaxCategory><cbc:ID>S</cbc:ID><cbc:Percent>19</cbc:Percent></cac:TaxCategory>
+    </cac:TaxSubtotal>
+  </cac:TaxTotal>
+  <cac:LegalMonetaryTotal>
+    <cbc:LineExtensionAmount currencyID="EUR">100.00</cbc:LineExtensionAmount>
+    <cbc:TaxExclusiveAmount currencyID="EUR">100.00</cbc:TaxExclusiveAmount>
+    <cbc:TaxInclusiveAmount currencyID="EUR">119.00</cbc:TaxInclusiveAmount>
+    <cbc:PayableAmount currencyID="EUR">119.00</cbc:PayableAmount>
+  </cac:LegalMonetaryTotal>
+  <cac:CreditNoteLine>
+    <cbc:ID>1</cbc:ID>
+    <cbc:CreditedQuantity unitCode="C62">2</cbc:CreditedQuantity>
+    <cbc:LineExtensionAmount currencyID="EUR">100.00</cbc:LineExtensionAmount>
+    <cac:Item>
+      <cbc:Name>Retoure Toner</cbc:Name>
+      <cac:ClassifiedTaxCategory><cbc:ID>S</cbc:ID><cbc:Percent>19</cbc:Percent></cac:ClassifiedTaxCategory>
+    </cac:Item>
+    <cac:Price><cbc:PriceAmount currencyID="EUR">50.00</cbc:PriceAmount></cac:Price>
+  </cac:CreditNoteLine>
+</CreditNote>"#;
+
+    #[test]
+    fn ubl_credit_note_is_stored_negative() {
+        let document = EInvoiceParser::read(UBL_CREDIT_NOTE).unwrap();
+        assert_eq!(document.syntax, "UBL CreditNote");
+        assert_eq!(document.total_amount, Some(-119.0));
+        assert_eq!(document.net_amount, Some(-100.0));
+        assert_eq!(document.tax_amount, Some(-19.0));
+
+        let data = document.into_extracted("payable");
+        assert_eq!(data.counterparty_name.as_deref(), Some("Lieferant GmbH"));
+        let line = &data.line_items[0];
+        assert_eq!((line.quantity, line.unit_price), (Some(2.0), Some(50.0)));
+        assert_eq!((line.net_amount, line.line_total), (Some(-100.0), Some(-119.0)));
+        let entry = &data.vat_breakdown[0];
+        assert_eq!((entry.rate, entry.net_amount, entry.tax_amount), (19.0, -100.0, -19.0));
+    }
+
+    #[test]
+    fn ubl_invoice_stays_positive() {
+        let xml = UBL_CREDIT_NOTE
+            .replace("CreditNote-2", "Invoice-2")
+            .replace("CreditNoteLine", "InvoiceLine")
+            .replace("CreditedQuantity", "InvoicedQuantity")
+            .replace("CreditNoteTypeCode>381</cbc:CreditNoteTypeCode", "InvoiceTypeCode>380</cbc:InvoiceTypeCode")
+            .replace("<CreditNote ", "<Invoice ")
+            .replace("</CreditNote>", "</Invoice>");
+        let document = EInvoiceParser::read(&xml).unwrap();
+        assert_eq!(document.syntax, "UBL");
+        assert_eq!(document.total_amount, Some(119.0));
+        assert_eq!(document.vat_breakdown[0].tax_amount, 19.0);
+    }
+
+    #[test]
+    fn only_invoice_roots_count_as_einvoice_files() {
+        let dir = std::env::temp_dir().join(format!("billly-einvoice-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let files = [
+            ("credit-note.xml", UBL_CREDIT_NOTE.to_string(), true),
+            (
+                "pain.xml",
+                r#"<Document xmlns="urn:iso:std:iso:20022:tech:xsd:pain.001.001.09"><CstmrCdtTrfInitn/></Document>"#
+                    .to_string(),
+                false,
+            ),
+            ("broken.xml", "<Invoice".to_string(), false),
+        ];
+        for (name, content, expected) in files {
+            let path = dir.join(name);
+            std::fs::write(&path, content).unwrap();
+            assert_eq!(EInvoiceParser::is_einvoice_file(&path), expected, "{}", name);
+        }
+        assert!(!EInvoiceParser::is_einvoice_file(&dir.join("missing.xml")));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,308 @@
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use crate::db::Database;
+use crate::models::{EuerLine, EuerReport};
+use crate::services::bookkeeping::{load_invoices, rate_to, vat_parts, InvoiceFilter};
+use crate::utils::{escape_html, format_eur, Money};
+
+/// The EÜR is filed in EUR.
+const REPORT_CURRENCY: &str = "EUR";
+
+/// Share of business meals that is deductible (§ 4 Abs. 5 Nr. 2 EStG).
+const HOSPITALITY_DEDUCTIBLE_PERCENT: f64 = 70.0;
+
+/// `(key, label, category)` of the report lines in the order of the
+/// Anlage EÜR.
+const GROUPS: [(&str, &str, &str); 22] = [
+    ("taxable_revenue", "Umsatzsteuerpflichtige Betriebseinnahmen", "revenue"),
+    ("tax_free_revenue", "Umsatzsteuerfreie, nicht umsatzsteuerbare Betriebseinnahmen", "revenue"),
+    ("small_business_revenue", "Betriebseinnahmen als umsatzsteuerlicher Kleinunternehmer", "revenue"),
+    ("goods", "Waren, Rohstoffe und Hilfsstoffe einschließlich Nebenkosten", "payable"),
+    ("purchased_services", "Bezogene Fremdleistungen", "payable"),
+    ("personnel", "Ausgaben für eigenes Personal", "payable"),
+    ("low_value_assets", "Aufwendungen für geringwertige Wirtschaftsgüter", "payable"),
+    ("premises", "Raumkosten und sonstige Grundstücksaufwendungen", "payable"),
+    ("licenses", "Aufwendungen für die zeitlich befristete Überlassung von Rechten (z. B. Lizenzen)", "payable"),
+    ("telecommunication", "Aufwendungen für Telekommunikation und Porto", "payable"),
+    ("travel", "Übernachtungs- und Reisenebenkosten bei Geschäftsreisen", "payable"),
+    ("training", "Fortbildungskosten", "payable"),
+    ("advisory", "Kosten für Rechts- und Steuerberatung, Buchführung", "payable"),
+    ("insurance", "Beiträge, Gebühren, Abgaben und Versicherungen", "payable"),
+    ("repairs", "Laufende EDV-Kosten, Instandhaltung und Wartung", "payable"),
+    ("advertising", "Werbeaufwendungen", "payable"),
+    ("vehicle", "Kraftfahrzeugkosten und andere Fahrtkosten", "payable"),
+    ("hospitality", "Bewirtungsaufwendungen (abziehbar)", "payable"),
+    ("hospitality_non_deductible", "Nicht abziehbare Bewirtungsaufwendungen", "payable"),
+    ("office", "Bürobedarf und sonstiger Betriebsbedarf", "payable"),
+    ("other_expenses", "Übrige unbeschränkt abziehbare Betriebsausgaben", "payable"),
+    ("unassigned_revenue", "Sonstige Betriebseinnahmen", "revenue"),
+];
+
+/// Which report line an expense category of the standard charts belongs to.
+const CATEGORY_GROUPS: [(&str, &str); 36] = [
+    ("sales_19", "taxable_revenue"),
+    ("sales_7", "taxable_revenue"),
+    ("sales_eu", "tax_free_revenue"),
+    ("sales_export", "tax_free_revenue"),
+    ("sales_small_business", "small_business_revenue"),
+    ("goods_19", "goods"),
+    ("goods_7", "goods"),
+    ("purchased_services", "purchased_services"),
+    ("wages", "personnel"),
+    ("social_security", "personnel"),
+    ("rent", "premises"),
+    ("utilities", "premises"),
+    ("insurance", "insurance"),
+    ("memberships", "insurance"),
+    ("vehicle", "vehicle"),
+    ("vehicle_running", "vehicle"),
+    ("advertising", "advertising"),
+    ("hospitality", "hospitality"),
+    ("travel_employees", "travel"),
+    ("travel_owner", "travel"),
+    ("repairs", "repairs"),
+    ("maintenance_it", "repairs"),
+    ("low_value_assets", "low_value_assets"),
+    ("postage", "telecommunication"),
+    ("telephone", "telecommunication"),
+    ("internet", "telecommunication"),
+    ("office_supplies", "office"),
+    ("literature", "office"),
+    ("operating_supplies", "office"),
+    ("training", "training"),
+    ("legal_advice", "advisory"),
+    ("accounting", "advisory"),
+    ("audit", "advisory"),
+    ("licenses", "licenses"),
+    ("bank_fees", "other_expenses"),
+    ("other_expenses", "other_expenses"),
+];
+
+#[derive(Default)]
+struct GroupTotal {
+    net_amount: Money,
+    invoices: BTreeSet<String>,
+}
+
+/// Cash-basis profit statement for a calendar year: invoices count in the
+/// year they were paid (`paid_at`), net of VAT and in EUR. Unpaid invoices
+/// are left out. VAT received and input VAT paid are reported separately.
+pub fn compute(db: &Database, year: i32, chart: &str) -> Result<EuerReport> {
+    let from = NaiveDate::from_ymd_opt(year, 1, 1).ok_or_else(|| anyhow!("Invalid year: {}", year))?;
+    let to = NaiveDate::from_ymd_opt(year, 12, 31).ok_or_else(|| anyhow!("Invalid year: {}", year))?;
+    let accounts = db.get_accounts(chart)?;
+    let filter = InvoiceFilter {
+        from: Some(from),
+        to: Some(to),
+        by_payment_date: true,
+        ..InvoiceFilter::default()
+    };
+    let (invoices, _) = load_invoices(db, &filter)?;
+
+    let mut groups: BTreeMap<&'static str, GroupTotal> = BTreeMap::new();
+    let mut vat_received = Money::ZERO;
+    let mut input_vat_paid = Money::ZERO;
+    let mut warnings = Vec::new();
+    let mut invoice_count = 0;
+    for invoice in &invoices {
+        let Some(to_eur) = rate_to(invoice, REPORT_CURRENCY) else {
+            warnings.push(format!(
+                "{}: no EUR exchange rate for {}, not included",
+                invoice.invoice_number.as_deref().unwrap_or(&invoice.id),
+                invoice.currency
+            ));
+            continue;
+        };
+        let account = invoice
+            .account_number
+            .as_deref()
+            .and_then(|number| accounts.iter().find(|account| account.number == number));
+        let mapped = account
+            .and_then(|account| account.expense_category.as_deref())
+            .and_then(|category| {
+                CATEGORY_GROUPS
+                    .iter()
+                    .find(|(key, _)| *key == category)
+                    .map(|(_, group)| *group)
+            });
+        invoice_count += 1;
+
+        let breakdown = db.get_vat_breakdown(&invoice.id)?;
+        for part in vat_parts(invoice, &breakdown) {
+            if part.rate.is_none() {
+                warnings.push(format!(
+                    "{}: states neither net nor VAT, counted in full without VAT",
+                    invoice.invoice_number.as_deref().unwrap_or(&invoice.id)
+                ));
+            }
+            let net = part.net.convert(to_eur);
+            let tax = part.tax.convert(to_eur);
+            let revenue = invoice.category == "revenue";
+            if revenue {
+                vat_received += tax;
+            } else {
+                input_vat_paid += tax;
+            }
+
+            let group = match mapped {
+                Some(group) => group,
+                None if revenue && tax != Money::ZERO => "taxable_revenue",
+                None if revenue => "unassigned_revenue",
+                None => "other_expenses",
+            };
+            if group == "hospitality" {
+                let deductible = net.convert(HOSPITALITY_DEDUCTIBLE_PERCENT / 100.0);
+                add(&mut groups, "hospitality", deductible, &invoice.id);
+                add(&mut groups, "hospitality_non_deductible", net - deductible, &invoice.id);
+            } else {
+                add(&mut groups, group, net, &invoice.id);
+            }
+        }
+    }
+
+    let mut revenue = Vec::new();
+    let mut expenses = Vec::new();
+    for (key, label, category) in GROUPS {
+        let Some(total) = groups.get(key) else {
+            continue;
+        };
+        let line = EuerLine {
+            key: key.to_string(),
+            label: label.to_string(),
+            net_amount: total.net_amount,
+            invoice_count: total.invoices.len() as u32,
+            deductible: key != "hospitality_non_deductible",
+        };
+        if category == "revenue" {
+            revenue.push(line);
+        } else {
+            expenses.push(line);
+        }
+    }
+    let total_revenue: Money = revenue.iter().map(|line| line.net_amount).sum();
+    let total_expenses: Money = expenses
+        .iter()
+        .filter(|line| line.deductible)
+        .map(|line| line.net_amount)
+        .sum();
+
+    Ok(EuerReport {
+        year,
+        currency: REPORT_CURRENCY.to_string(),
+        revenue,
+        expenses,
+        total_revenue,
+        total_expenses,
+        profit: total_revenue - total_expenses,
+        vat_received,
+        input_vat_paid,
+        invoice_count,
+        warnings,
+    })
+}
+
+fn add(groups: &mut BTreeMap<&'static str, GroupTotal>, key: &'static str, net: Money, invoice_id: &str) {
+    let total = groups.entry(key).or_default();
+    total.net_amount += net;
+    total.invoices.insert(invoice_id.to_string());
+}
+
+/// Writes a printable HTML rendering to the temp directory and returns its
+/// path; the browser's print dialog turns it into a PDF.
+pub fn render_html_file(report: &EuerReport) -> Result<PathBuf> {
+    let dir = std::env::temp_dir().join("billly");
+    std::fs::create_dir_all(&dir)?;
+    let target = dir.join(format!("euer_{}.html", report.year));
+    std::fs::write(&target, render_html(report))?;
+    Ok(target)
+}
+
+fn render_html(report: &EuerReport) -> String {
+    let rows = |lines: &[EuerLine]| {
+        lines
+            .iter()
+            .map(|line| {
+                format!(
+                    "<tr{}><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td></tr>",
+                    if line.deductible { "" } else { " class=\"muted\"" },
+                    escape_html(&line.label),
+                    line.invoice_count,
+                    format_eur(line.net_amount)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let warnings = if report.warnings.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "<h2>Hinweise</h2>\n<ul>\n{}\n</ul>",
+            report
+                .warnings
+                .iter()
+                .map(|warning| format!("<li>{}</li>", escape_html(warning)))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )
+    };
+
+    format!(
+        r#"<!doctype html>
+<html lang="de">
+<head>
+<meta charset="utf-8" />
+<title>Einnahmen-Überschuss-Rechnung {year}</title>
+<style>
+body {{ font-family: sans-serif; margin: 2rem; color: #111; }}
+table {{ border-collapse: collapse; width: 100%; margin-top: 1rem; }}
+th, td {{ border-bottom: 1px solid #ddd; padding: 0.4rem; text-align: left; }}
+.num {{ text-align: right; }}
+.muted {{ color: #777; }}
+@media print {{ body {{ margin: 0; }} }}
+</style>
+</head>
+<body>
+<h1>Einnahmen-Überschuss-Rechnung {year}</h1>
+<p>Zufluss-/Abflussprinzip (§ 11 EStG), Nettobeträge in {currency}, {count} Belege</p>
+<h2>Betriebseinnahmen</h2>
+<table>
+<thead><tr><th>Position</th><th class="num">Belege</th><th class="num">Netto</th></tr></thead>
+<tbody>
+{revenue_rows}
+<tr><th>Summe Betriebseinnahmen</th><th></th><th class="num">{total_revenue}</th></tr>
+</tbody>
+</table>
+<h2>Betriebsausgaben</h2>
+<table>
+<thead><tr><th>Position</th><th class="num">Belege</th><th class="num">Netto</th></tr></thead>
+<tbody>
+{expense_rows}
+<tr><th>Summe abziehbare Betriebsausgaben</th><th></th><th class="num">{total_expenses}</th></tr>
+</tbody>
+</table>
+<table>
+<tr><th>Gewinn / Verlust</th><th class="num">{profit}</th></tr>
+<tr><td>Vereinnahmte Umsatzsteuer</td><td class="num">{vat_received}</td></tr>
+<tr><td>Gezahlte Vorsteuerbeträge</td><td class="num">{input_vat_paid}</td></tr>
+</table>
+{warnings}
+</body>
+</html>
+"#,
+        year = report.year,
+        currency = escape_html(&report.currency),
+        count = report.invoice_count,
+        revenue_rows = rows(&report.revenue),
+        expense_rows = rows(&report.expenses),
+        total_revenue = format_eur(report.total_revenue),
+        total_expenses = format_eur(report.total_expenses),
+        profit = format_eur(report.profit),
+        vat_received = format_eur(report.vat_received),
+        input_vat_paid = format_eur(report.input_vat_paid),
+        warnings = warnings,
+    )
+}
@@ -0,0 +1,167 @@
+use regex::Regex;
+use std::ops::Range;
+use std::sync::LazyLock;
+
+use crate::models::{ExtractedInvoiceData, FieldEvidence};
+use crate::services::rule_based::{locate_amount, locate_date, locate_iban};
+
+/// Fields below this confidence are highlighted and put in the review queue.
+pub const REVIEW_THRESHOLD: f64 = 0.7;
+
+/// Used when the extractor gave no per-field score but the value is in the text.
+const FOUND_CONFIDENCE: f64 = 0.85;
+/// A value that cannot be found in the document text is treated as a guess.
+const UNSUPPORTED_CONFIDENCE: f64 = 0.5;
+const SNIPPET_CONTEXT: usize = 40;
+
+static PAGE_MARKER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"--- Page (\d+) ---").unwrap());
+
+/// Fields that get an entry even when nothing was extracted, so a missing
+/// total or date shows up in the review queue.
+const REQUIRED_FIELDS: [&str; 3] = ["invoice_date", "counterparty_name", "total_amount"];
+
+/// Scores every extracted field and locates the text it was taken from.
+/// Offsets and lengths are in characters of `text` (the stored `ocr_text`).
+pub fn collect(data: &ExtractedInvoiceData, text: &str) -> Vec<FieldEvidence> {
+    let fields = [
+        ("invoice_number", data.invoice_number.clone()),
+        ("invoice_date", data.invoice_date.clone()),
+        ("due_date", data.due_date.clone()),
+        ("counterparty_name", data.counterparty_name.clone()),
+        ("total_amount", data.total_amount.map(|value| format!("{:.2}", value))),
+        ("currency", data.currency.clone()),
+        ("tax_amount", data.tax_amount.map(|value| format!("{:.2}", value))),
+        ("net_amount", data.net_amount.map(|value| format!("{:.2}", value))),
+        ("iban", data.iban.clone()),
+        ("bic", data.bic.clone()),
+        ("payment_reference", data.payment_reference.clone()),
+        ("payment_terms", data.payment_terms.clone()),
+        ("discount_percent", data.discount_percent.map(|value| value.to_string())),
+        // A deadline counted from the Skonto days is not printed anywhere.
+        (
+            "discount_due_date",
+            data.discount_due_date.clone().filter(|_| data.discount_days.is_none()),
+        ),
+    ];
+
+    let fallback = data.confidence_score.unwrap_or(UNSUPPORTED_CONFIDENCE);
+    let mut evidence = Vec::new();
+
+    for (field, value) in fields {
+        let Some(value) = value.filter(|value| !value.trim().is_empty()) else {
+            if REQUIRED_FIELDS.contains(&field) {
+                evidence.push(FieldEvidence {
+                    field_name: field.to_string(),
+                    value: None,
+                    confidence: 0.0,
+                    page_number: None,
+                    text_offset: None,
+                    text_length: None,
+                    snippet: None,
+                });
+            }
+            continue;
+        };
+
+        let explicit = data.field_confidence.get(field).copied();
+        let range = locate(field, &value, data, text);
+        let confidence = match &range {
+            Some(_) => explicit.unwrap_or(FOUND_CONFIDENCE),
+            None => explicit.unwrap_or(fallback).min(UNSUPPORTED_CONFIDENCE),
+        };
+
+        let mut entry = FieldEvidence {
+            field_name: field.to_string(),
+            value: Some(value),
+            confidence: confidence.clamp(0.0, 1.0),
+            page_number: None,
+            text_offset: None,
+            text_length: None,
+            snippet: None,
+        };
+        if let Some(range) = range {
+            entry.page_number = Some(page_at(text, range.start));
+            entry.text_offset = Some(text[..range.start].chars().count() as u32);
+            entry.text_length = Some(text[range.clone()].chars().count() as u32);
+            entry.snippet = Some(snippet(text, range));
+        }
+        evidence.push(entry);
+    }
+
+    evidence
+}
+
+fn locate(field: &str, value: &str, data: &ExtractedInvoiceData, text: &str) -> Option<Range<usize>> {
+    match field {
+        "total_amount" => data.total_amount.and_then(|amount| locate_amount(text, amount)),
+        "tax_amount" => data.tax_amount.and_then(|amount| locate_amount(text, amount)),
+        "net_amount" => data.net_amount.and_then(|amount| locate_amount(text, amount)),
+        "invoice_date" | "due_date" | "discount_due_date" => locate_date(text, value),
+        "iban" => locate_iban(text, value),
+        "discount_percent" => locate_percent(text, value),
+        "currency" => locate_text(text, value)
+            .or_else(|| currency_symbol(value).and_then(|symbol| locate_text(text, symbol))),
+        _ => locate_text(text, value),
+    }
+}
+
+/// Case-insensitive search that tolerates differing whitespace.
+fn locate_text(text: &str, value: &str) -> Option<Range<usize>> {
+    let pattern = value
+        .split_whitespace()
+        .map(regex::escape)
+        .collect::<Vec<_>>()
+        .join(r"\s+");
+    if pattern.is_empty() {
+        return None;
+    }
+    Regex::new(&format!("(?i){}", pattern))
+        .ok()?
+        .find(text)
+        .map(|m| m.range())
+}
+
+/// A percentage such as `2.5` printed as "2,5 %" or "2,50%".
+fn locate_percent(text: &str, value: &str) -> Option<Range<usize>> {
+    let number = regex::escape(value).replace(r"\.", "[.,]");
+    let fraction = if value.contains('.') { "0*" } else { "(?:[.,]0+)?" };
+    Regex::new(&format!(r"\b{}{}\s*%", number, fraction))
+        .ok()?
+        .find(text)
+        .map(|m| m.range())
+}
+
+fn currency_symbol(code: &str) -> Option<&'static str> {
+    match code.to_uppercase().as_str() {
+        "EUR" => Some("€"),
+        "USD" => Some("$"),
+        "GBP" => Some("£"),
+        _ => None,
+    }
+}
+
+/// Multi-page text carries `--- Page N ---` markers; the last one before the
+/// match names its page.
+fn page_at(text: &str, offset: usize) -> u32 {
+    PAGE_MARKER
+        .captures_iter(&text[..offset])
+        .last()
+        .and_then(|caps| caps[1].parse().ok())
+        .unwrap_or(1)
+}
+
+fn snippet(text: &str, range: Range<usize>) -> String {
+    let before: String = text[..range.start]
+        .chars()
+        .rev()
+        .take(SNIPPET_CONTEXT)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    let after: String = text[range.end..].chars().take(SNIPPET_CONTEXT).collect();
+    format!("{}{}{}", before, &text[range], after)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
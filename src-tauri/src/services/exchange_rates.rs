@@ -0,0 +1,216 @@
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use std::path::Path;
+
+use crate::db::Database;
+use crate::models::{ExchangeRate, Invoice};
+use crate::services::bookkeeping::apply_overrides;
+
+/// ECB reference rates are quoted as units of currency per 1 EUR.
+pub const ECB_BASE_CURRENCY: &str = "EUR";
+
+/// Reads an ECB reference-rate file (`eurofxref*.csv` or `eurofxref*.xml`).
+pub fn read_ecb_file(path: &Path) -> Result<Vec<ExchangeRate>> {
+    let content = std::fs::read_to_string(path)?;
+    let rates = if content.trim_start().starts_with('<') {
+        parse_ecb_xml(&content)?
+    } else {
+        parse_ecb_csv(&content)?
+    };
+    if rates.is_empty() {
+        return Err(anyhow!("No exchange rates found in {}", path.display()));
+    }
+    Ok(rates)
+}
+
+/// `<Cube time="2024-03-14"><Cube currency="USD" rate="1.0942"/>…</Cube>`
+fn parse_ecb_xml(content: &str) -> Result<Vec<ExchangeRate>> {
+    let document = roxmltree::Document::parse(content).map_err(|e| anyhow!("ECB XML: {}", e))?;
+    let mut rates = Vec::new();
+    for day in document
+        .descendants()
+        .filter(|node| node.tag_name().name() == "Cube" && node.has_attribute("time"))
+    {
+        let Some(rate_date) = day.attribute("time").and_then(parse_ecb_date) else {
+            continue;
+        };
+        for quote in day.children().filter(|node| node.is_element()) {
+            let (Some(currency), Some(rate)) = (quote.attribute("currency"), quote.attribute("rate")) else {
+                continue;
+            };
+            if let Ok(rate) = rate.trim().parse::<f64>() {
+                rates.push(ExchangeRate {
+                    rate_date: rate_date.clone(),
+                    currency: currency.trim().to_uppercase(),
+                    rate,
+                });
+            }
+        }
+    }
+    Ok(rates)
+}
+
+/// Header row `Date, USD, JPY, …` followed by one row per day; missing
+/// quotes are `N/A`.
+fn parse_ecb_csv(content: &str) -> Result<Vec<ExchangeRate>> {
+    let mut lines = content.lines().filter(|line| !line.trim().is_empty());
+    let header: Vec<String> = lines
+        .next()
+        .ok_or_else(|| anyhow!("ECB CSV is empty"))?
+        .split(',')
+        .map(|cell| cell.trim().to_uppercase())
+        .collect();
+    if header.first().map(|cell| cell.as_str()) != Some("DATE") {
+        return Err(anyhow!("ECB CSV header must start with Date"));
+    }
+
+    let mut rates = Vec::new();
+    for line in lines {
+        let cells: Vec<&str> = line.split(',').map(|cell| cell.trim()).collect();
+        let Some(rate_date) = cells.first().and_then(|cell| parse_ecb_date(cell)) else {
+            continue;
+        };
+        for (currency, cell) in header.iter().zip(cells.iter()).skip(1) {
+            if currency.is_empty() {
+                continue;
+            }
+            if let Ok(rate) = cell.parse::<f64>() {
+                rates.push(ExchangeRate {
+                    rate_date: rate_date.clone(),
+                    currency: currency.clone(),
+                    rate,
+                });
+            }
+        }
+    }
+    Ok(rates)
+}
+
+fn parse_ecb_date(raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    ["%Y-%m-%d", "%d %B %Y"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(raw, format).ok())
+        .map(|date| date.format("%Y-%m-%d").to_string())
+}
+
+/// Rate to multiply an amount in `from` by to get `to`, using the latest ECB
+/// fixing on or before `date` (there are none on weekends and holidays).
+pub fn conversion_rate(db: &Database, from: &str, to: &str, date: &str) -> Result<Option<f64>> {
+    let from = from.trim().to_uppercase();
+    let to = to.trim().to_uppercase();
+    if from == to {
+        return Ok(Some(1.0));
+    }
+    let per_euro = |currency: &str| -> Result<Option<f64>> {
+        if currency == ECB_BASE_CURRENCY {
+            return Ok(Some(1.0));
+        }
+        Ok(db.get_exchange_rate_on_or_before(currency, date)?)
+    };
+    match (per_euro(&from)?, per_euro(&to)?) {
+        (Some(from_rate), Some(to_rate)) if from_rate > 0.0 => Ok(Some(to_rate / from_rate)),
+        _ => Ok(None),
+    }
+}
+
+/// Fills the base-currency fields of an invoice. Without an invoice date or a
+/// matching rate the converted amount stays empty and the invoice is left out
+/// of base-currency sums.
+pub fn apply_conversion(db: &Database, invoice: &mut Invoice, base_currency: &str) -> Result<()> {
+    let rate = match invoice.invoice_date.as_deref() {
+        Some(date) => conversion_rate(db, &invoice.currency, base_currency, date)?,
+        None if invoice.currency.eq_ignore_ascii_case(base_currency) => Some(1.0),
+        None => None,
+    };
+    invoice.base_currency = Some(base_currency.to_string());
+    invoice.exchange_rate = rate;
+    invoice.base_total_amount = rate.map(|rate| invoice.total_amount.convert(rate));
+    Ok(())
+}
+
+/// Like `apply_conversion`, but converts the total, currency and date as
+/// corrected by the user's overrides; the stored invoice keeps its extracted
+/// values.
+pub fn apply_corrected_conversion(db: &Database, invoice: &mut Invoice, base_currency: &str) -> Result<()> {
+    let mut corrected = invoice.clone();
+    apply_overrides(&mut corrected, &db.get_overrides(&invoice.id)?);
+    apply_conversion(db, &mut corrected, base_currency)?;
+    invoice.base_currency = corrected.base_currency;
+    invoice.exchange_rate = corrected.exchange_rate;
+    invoice.base_total_amount = corrected.base_total_amount;
+    Ok(())
+}
+
+/// Recomputes the converted amount of one invoice after its total, currency
+/// or date was corrected.
+pub fn refresh_base_amount(db: &Database, invoice_id: &str, base_currency: &str) -> Result<()> {
+    let mut invoice = db
+        .get_invoice_by_id(invoice_id)?
+        .ok_or_else(|| anyhow!("Invoice not found: {}", invoice_id))?;
+    apply_corrected_conversion(db, &mut invoice, base_currency)?;
+    db.set_invoice_conversion(&invoice)?;
+    Ok(())
+}
+
+/// Recomputes the converted amounts of all invoices, e.g. after importing
+/// rates or changing the base currency. Returns how many could be converted.
+pub fn refresh_base_amounts(db: &Database, base_currency: &str) -> Result<usize> {
+    let mut converted = 0;
+    for category in ["revenue", "payable"] {
+        for mut invoice in db.get_invoices(category)? {
+            apply_corrected_conversion(db, &mut invoice, base_currency)?;
+            db.set_invoice_conversion(&invoice)?;
+            if invoice.base_total_amount.is_some() {
+                converted += 1;
+            }
+        }
+    }
+    Ok(converted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::Money;
+
+    #[test]
+    fn corrected_total_and_currency_are_converted() {
+        let dir = std::env::temp_dir().join(format!("billly-rates-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = Database::new(dir.join("test.db")).unwrap();
+        let rate = ExchangeRate {
+            rate_date: "2024-03-14".to_string(),
+            currency: "USD".to_string(),
+            rate: 1.25,
+        };
+        db.insert_exchange_rates(&[rate], "test").unwrap();
+        let mut invoice = Invoice {
+            id: "p1".to_string(),
+            category: "payable".to_string(),
+            ingestion_status: "processed".to_string(),
+            invoice_date: Some("2024-03-15".to_string()),
+            total_amount: Money::parse("100.00").unwrap(),
+            currency: "USD".to_string(),
+            status: "open".to_string(),
+            ..Invoice::default()
+        };
+        apply_conversion(&db, &mut invoice, "EUR").unwrap();
+        db.upsert_invoice(&invoice).unwrap();
+        assert_eq!(invoice.base_total_amount, Some(Money::parse("80.00").unwrap()));
+
+        db.set_override("p1", "total_amount", "150.00").unwrap();
+        refresh_base_amount(&db, "p1", "EUR").unwrap();
+        let stored = db.get_invoice_by_id("p1").unwrap().unwrap();
+        assert_eq!(stored.total_amount, Money::parse("100.00").unwrap());
+        assert_eq!(stored.base_total_amount, Some(Money::parse("120.00").unwrap()));
+
+        db.set_override("p1", "currency", "EUR").unwrap();
+        refresh_base_amount(&db, "p1", "EUR").unwrap();
+        let stored = db.get_invoice_by_id("p1").unwrap().unwrap();
+        assert_eq!(stored.exchange_rate, Some(1.0));
+        assert_eq!(stored.base_total_amount, Some(Money::parse("150.00").unwrap()));
+        assert_eq!(db.count_unconverted_invoices("EUR").unwrap(), 0);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
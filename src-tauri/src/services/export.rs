@@ -0,0 +1,282 @@
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use rust_xlsxwriter::{Format, Workbook};
+use serde_json::{Map, Value};
+use std::io::Write;
+use std::path::Path;
+
+use crate::models::{ExportColumn, Invoice};
+use crate::utils::Money;
+
+/// `(key, label)` of the exportable invoice fields, in the order offered.
+const COLUMNS: [(&str, &str); 19] = [
+    ("id", "ID"),
+    ("category", "Category"),
+    ("invoice_number", "Invoice Number"),
+    ("invoice_date", "Invoice Date"),
+    ("due_date", "Due Date"),
+    ("counterparty_name", "Counterparty"),
+    ("net_amount", "Net"),
+    ("tax_amount", "Tax"),
+    ("total_amount", "Total"),
+    ("currency", "Currency"),
+    ("base_total_amount", "Total (Base Currency)"),
+    ("base_currency", "Base Currency"),
+    ("exchange_rate", "Exchange Rate"),
+    ("status", "Status"),
+    ("paid_at", "Paid At"),
+    ("account_number", "Account"),
+    ("confidence_score", "Confidence"),
+    ("file_path", "File"),
+    ("created_at", "Imported At"),
+];
+
+/// Columns written when none are chosen.
+const DEFAULT_COLUMNS: [&str; 9] = [
+    "invoice_date",
+    "invoice_number",
+    "counterparty_name",
+    "category",
+    "net_amount",
+    "tax_amount",
+    "total_amount",
+    "currency",
+    "status",
+];
+
+/// UTF-8 byte order mark; without it Excel reads CSV files as ANSI.
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Xlsx,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn parse(raw: &str) -> Result<Self> {
+        match raw.trim().to_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "xlsx" => Ok(ExportFormat::Xlsx),
+            "json" => Ok(ExportFormat::Json),
+            other => Err(anyhow!("Unknown export format: {}", other)),
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Xlsx => "xlsx",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+/// Number and date formatting of CSV cells and XLSX date formats. German
+/// Excel expects `;` separators, decimal commas and `31.01.2024`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    German,
+    English,
+}
+
+impl Locale {
+    pub fn parse(raw: Option<&str>) -> Self {
+        match raw.map(|raw| raw.trim().to_lowercase()) {
+            Some(raw) if raw.starts_with("en") => Locale::English,
+            _ => Locale::German,
+        }
+    }
+}
+
+enum Cell {
+    Empty,
+    Text(String),
+    Amount(Money),
+    Number(f64),
+    Date(NaiveDate),
+}
+
+pub fn columns() -> Vec<ExportColumn> {
+    COLUMNS
+        .iter()
+        .map(|(key, label)| ExportColumn {
+            key: key.to_string(),
+            label: label.to_string(),
+        })
+        .collect()
+}
+
+/// Checks the requested column keys; an empty selection means the defaults.
+pub fn resolve_columns(keys: &[String]) -> Result<Vec<&'static str>> {
+    if keys.is_empty() {
+        return Ok(DEFAULT_COLUMNS.to_vec());
+    }
+    keys.iter()
+        .map(|key| {
+            COLUMNS
+                .iter()
+                .find(|(known, _)| known == key)
+                .map(|(known, _)| *known)
+                .ok_or_else(|| anyhow!("Unknown export column: {}", key))
+        })
+        .collect()
+}
+
+/// Writes `invoices` (overrides already applied) with the given columns.
+pub fn write(
+    path: &Path,
+    format: ExportFormat,
+    locale: Locale,
+    columns: &[&str],
+    invoices: &[Invoice],
+) -> Result<()> {
+    match format {
+        ExportFormat::Csv => write_csv(path, locale, columns, invoices),
+        ExportFormat::Xlsx => write_xlsx(path, locale, columns, invoices),
+        ExportFormat::Json => write_json(path, columns, invoices),
+    }
+}
+
+fn label(key: &str) -> &'static str {
+    COLUMNS
+        .iter()
+        .find(|(known, _)| *known == key)
+        .map(|(_, label)| *label)
+        .unwrap_or_default()
+}
+
+fn cell(invoice: &Invoice, key: &str) -> Cell {
+    let text = |value: Option<&str>| match value {
+        Some(value) if !value.is_empty() => Cell::Text(value.to_string()),
+        _ => Cell::Empty,
+    };
+    let amount = |value: Option<Money>| value.map(Cell::Amount).unwrap_or(Cell::Empty);
+    match key {
+        "id" => text(Some(&invoice.id)),
+        "category" => text(Some(&invoice.category)),
+        "invoice_number" => text(invoice.invoice_number.as_deref()),
+        "invoice_date" => date(invoice.invoice_date.as_deref()),
+        "due_date" => date(invoice.due_date.as_deref()),
+        "counterparty_name" => text(invoice.counterparty_name.as_deref()),
+        "net_amount" => amount(invoice.net_amount),
+        "tax_amount" => amount(invoice.tax_amount),
+        "total_amount" => amount(Some(invoice.total_amount)),
+        "currency" => text(Some(&invoice.currency)),
+        "base_total_amount" => amount(invoice.base_total_amount),
+        "base_currency" => text(invoice.base_currency.as_deref()),
+        "exchange_rate" => invoice.exchange_rate.map(Cell::Number).unwrap_or(Cell::Empty),
+        "status" => text(Some(&invoice.status)),
+        "paid_at" => date(invoice.paid_at.as_deref()),
+        "account_number" => text(invoice.account_number.as_deref()),
+        "confidence_score" => Cell::Number(invoice.confidence_score),
+        "file_path" => text(invoice.file_path.as_deref()),
+        "created_at" => date(Some(&invoice.created_at)),
+        _ => Cell::Empty,
+    }
+}
+
+/// Dates are stored as `YYYY-MM-DD`, timestamps with a time after it; values
+/// that do not parse are exported as text.
+fn date(raw: Option<&str>) -> Cell {
+    let Some(raw) = raw.map(str::trim).filter(|raw| !raw.is_empty()) else {
+        return Cell::Empty;
+    };
+    raw.get(..10)
+        .and_then(|day| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok())
+        .map(Cell::Date)
+        .unwrap_or_else(|| Cell::Text(raw.to_string()))
+}
+
+fn write_csv(path: &Path, locale: Locale, columns: &[&str], invoices: &[Invoice]) -> Result<()> {
+    let (delimiter, decimal, date_format) = match locale {
+        Locale::German => (b';', ",", "%d.%m.%Y"),
+        Locale::English => (b',', ".", "%Y-%m-%d"),
+    };
+    let mut file = std::fs::File::create(path)?;
+    file.write_all(UTF8_BOM)?;
+    let mut writer = csv::WriterBuilder::new().delimiter(delimiter).from_writer(file);
+    writer.write_record(columns.iter().map(|key| label(key)))?;
+    for invoice in invoices {
+        let record: Vec<String> = columns
+            .iter()
+            .map(|key| match cell(invoice, key) {
+                Cell::Empty => String::new(),
+                Cell::Text(value) => value,
+                Cell::Amount(value) => value.to_string().replace('.', decimal),
+                Cell::Number(value) => value.to_string().replace('.', decimal),
+                Cell::Date(value) => value.format(date_format).to_string(),
+            })
+            .collect();
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Amounts and dates are written as native cells, so Excel shows them in
+/// the user's own locale; only the date format differs.
+fn write_xlsx(path: &Path, locale: Locale, columns: &[&str], invoices: &[Invoice]) -> Result<()> {
+    let header_format = Format::new().set_bold();
+    let amount_format = Format::new().set_num_format("#,##0.00");
+    let date_format = Format::new().set_num_format(match locale {
+        Locale::German => "dd.mm.yyyy",
+        Locale::English => "yyyy-mm-dd",
+    });
+
+    let mut workbook = Workbook::new();
+    let sheet = workbook.add_worksheet();
+    sheet.set_name("Invoices")?;
+    for (col, key) in columns.iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, label(key), &header_format)?;
+    }
+    for (index, invoice) in invoices.iter().enumerate() {
+        let row = index as u32 + 1;
+        for (col, key) in columns.iter().enumerate() {
+            let col = col as u16;
+            match cell(invoice, key) {
+                Cell::Empty => {}
+                Cell::Text(value) => {
+                    sheet.write_string(row, col, value)?;
+                }
+                Cell::Amount(value) => {
+                    sheet.write_number_with_format(row, col, value.to_f64(), &amount_format)?;
+                }
+                Cell::Number(value) => {
+                    sheet.write_number(row, col, value)?;
+                }
+                Cell::Date(value) => {
+                    sheet.write_date_with_format(row, col, value, &date_format)?;
+                }
+            }
+        }
+    }
+    sheet.set_freeze_panes(1, 0)?;
+    sheet.autofit();
+    workbook.save(path)?;
+    Ok(())
+}
+
+/// Amounts are decimal strings as everywhere else in the API, dates ISO.
+fn write_json(path: &Path, columns: &[&str], invoices: &[Invoice]) -> Result<()> {
+    let rows: Vec<Value> = invoices
+        .iter()
+        .map(|invoice| {
+            let mut row = Map::new();
+            for key in columns {
+                let value = match cell(invoice, key) {
+                    Cell::Empty => Value::Null,
+                    Cell::Text(value) => Value::String(value),
+                    Cell::Amount(value) => Value::String(value.to_string()),
+                    Cell::Number(value) => Value::from(value),
+                    Cell::Date(value) => Value::String(value.format("%Y-%m-%d").to_string()),
+                };
+                row.insert(key.to_string(), value);
+            }
+            Value::Object(row)
+        })
+        .collect();
+    std::fs::write(path, serde_json::to_string_pretty(&rows)?)?;
+    Ok(())
+}
@@ -0,0 +1,233 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use jsonschema::JSONSchema;
+use serde_json::{json, Value};
+use std::future::Future;
+
+use crate::models::{ExtractedInvoiceData, Settings};
+use crate::services::accounts;
+use crate::services::crypto::CryptoService;
+use crate::services::ollama::OllamaExtractor;
+use crate::services::openai::OpenAIExtractor;
+use crate::services::rule_based::RuleBasedExtractor;
+
+pub const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+pub const OPENAI_DEFAULT_MODEL: &str = "gpt-4o-mini";
+pub const OLLAMA_BASE_URL: &str = "http://localhost:11434";
+pub const OLLAMA_DEFAULT_MODEL: &str = "llama3.1";
+
+#[async_trait]
+pub trait InvoiceExtractor: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn extract_invoice_data(&self, text: &str) -> Result<(ExtractedInvoiceData, String)>;
+}
+
+pub fn extractor_from_settings(settings: &Settings, category: &str) -> Result<Box<dyn InvoiceExtractor>> {
+    let model = settings
+        .extraction_model
+        .clone()
+        .filter(|value| !value.trim().is_empty());
+    let base_url = settings
+        .extraction_base_url
+        .clone()
+        .filter(|value| !value.trim().is_empty());
+
+    match settings.extraction_backend.as_str() {
+        "openai" => {
+            // Without a key we still ingest, just without the LLM.
+            let Some(api_key) = settings.openai_api_key.as_ref() else {
+                return Ok(Box::new(RuleBasedExtractor::new(settings, category)));
+            };
+            let decrypted_key = CryptoService::decrypt_api_key(api_key)?;
+            Ok(Box::new(OpenAIExtractor::new(
+                OPENAI_BASE_URL.to_string(),
+                Some(decrypted_key),
+                model.unwrap_or_else(|| OPENAI_DEFAULT_MODEL.to_string()),
+            )))
+        }
+        "openai_compatible" => {
+            let base_url = base_url.ok_or_else(|| anyhow!("Extraction base URL missing"))?;
+            let model = model.ok_or_else(|| anyhow!("Extraction model missing"))?;
+            let api_key = match settings.openai_api_key.as_ref() {
+                Some(key) => Some(CryptoService::decrypt_api_key(key)?),
+                None => None,
+            };
+            Ok(Box::new(OpenAIExtractor::new(base_url, api_key, model)))
+        }
+        "ollama" => Ok(Box::new(OllamaExtractor::new(
+            base_url.unwrap_or_else(|| OLLAMA_BASE_URL.to_string()),
+            model.unwrap_or_else(|| OLLAMA_DEFAULT_MODEL.to_string()),
+        ))),
+        "rules" => Ok(Box::new(RuleBasedExtractor::new(settings, category))),
+        other => Err(anyhow!("Unknown extraction backend: {}", other)),
+    }
+}
+
+/// Runs the prompt / validate / repair cycle shared by all chat-based backends.
+/// `call` receives the system prompt and the user prompt and returns the raw model output.
+pub async fn extract_with_chat<F, Fut>(text: &str, call: F) -> Result<(ExtractedInvoiceData, String)>
+where
+    F: Fn(String, String) -> Fut,
+    Fut: Future<Output = Result<String>>,
+{
+    let schema = extraction_schema();
+    let prompt = system_prompt();
+    let user = format!("Invoice text:\n{}", text);
+
+    let mut raw = call(prompt.clone(), user).await?;
+    let mut value = parse_json(&raw)?;
+
+    if !validate_json(&schema, &value) {
+        let fix_prompt = format!(
+            "Fixe dieses JSON so, dass es exakt dem Schema entspricht. Nur JSON ausgeben. JSON:\n{}",
+            raw
+        );
+        raw = call(prompt, fix_prompt).await?;
+        value = parse_json(&raw)?;
+        if !validate_json(&schema, &value) {
+            return Err(anyhow!("JSON validation failed"));
+        }
+    }
+
+    let mut data: ExtractedInvoiceData = serde_json::from_value(value)?;
+    if data.currency.is_none() {
+        data.currency = Some("EUR".to_string());
+    }
+    if data.extraction_notes.trim().is_empty() {
+        data.extraction_notes = "notes missing".to_string();
+    }
+
+    if data.confidence_score.is_none() {
+        data.confidence_score = Some(compute_confidence(&data));
+    }
+
+    Ok((data, raw))
+}
+
+fn parse_json(raw: &str) -> Result<Value> {
+    serde_json::from_str::<Value>(raw).map_err(|e| anyhow!("Invalid JSON: {}", e))
+}
+
+fn extraction_schema() -> JSONSchema {
+    let schema = json!({
+        "type": "object",
+        "additionalProperties": false,
+        "required": ["total_amount", "currency", "invoice_date", "extraction_notes"],
+        "properties": {
+            "invoice_number": {"type": ["string", "null"]},
+            "invoice_date": {"type": ["string", "null"]},
+            "due_date": {"type": ["string", "null"]},
+            "counterparty_name": {"type": ["string", "null"]},
+            "counterparty_vat_id": {"type": ["string", "null"]},
+            "total_amount": {"type": ["number", "null"]},
+            "currency": {"type": ["string", "null"]},
+            "tax_amount": {"type": ["number", "null"]},
+            "net_amount": {"type": ["number", "null"]},
+            "iban": {"type": ["string", "null"]},
+            "bic": {"type": ["string", "null"]},
+            "payment_reference": {"type": ["string", "null"]},
+            "payment_terms": {"type": ["string", "null"]},
+            "discount_percent": {"type": ["number", "null"], "exclusiveMinimum": 0, "exclusiveMaximum": 100},
+            "discount_days": {"type": ["integer", "null"], "minimum": 0},
+            "discount_due_date": {"type": ["string", "null"]},
+            "extraction_notes": {"type": "string"},
+            "confidence_score": {"type": ["number", "null"]},
+            "field_confidence": {
+                "type": "object",
+                "additionalProperties": {"type": "number", "minimum": 0, "maximum": 1}
+            },
+            "line_items": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "additionalProperties": false,
+                    "properties": {
+                        "description": {"type": ["string", "null"]},
+                        "quantity": {"type": ["number", "null"]},
+                        "unit": {"type": ["string", "null"]},
+                        "unit_price": {"type": ["number", "null"]},
+                        "net_amount": {"type": ["number", "null"]},
+                        "vat_rate": {"type": ["number", "null"]},
+                        "line_total": {"type": ["number", "null"]}
+                    }
+                }
+            },
+            "expense_category": {"type": ["string", "null"]},
+            "vat_breakdown": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "additionalProperties": false,
+                    "required": ["rate", "net_amount", "tax_amount"],
+                    "properties": {
+                        "rate": {"type": "number"},
+                        "net_amount": {"type": "number"},
+                        "tax_amount": {"type": "number"}
+                    }
+                }
+            }
+        }
+    });
+
+    JSONSchema::compile(&schema).expect("Invalid JSON schema")
+}
+
+fn validate_json(schema: &JSONSchema, value: &Value) -> bool {
+    schema.is_valid(value)
+}
+
+fn compute_confidence(data: &ExtractedInvoiceData) -> f64 {
+    let mut score: f64 = 0.4;
+    if data.invoice_number.is_some() {
+        score += 0.1;
+    }
+    if data.invoice_date.is_some() {
+        score += 0.1;
+    }
+    if data.counterparty_name.is_some() {
+        score += 0.1;
+    }
+    if data.total_amount.is_some() {
+        score += 0.1;
+    }
+    if data.tax_amount.is_some() || data.net_amount.is_some() {
+        score += 0.05;
+    }
+    score.clamp(0.0, 1.0)
+}
+
+fn system_prompt() -> String {
+    let mut prompt = r#"You are an invoice extraction system. Return JSON only and match the schema exactly.
+Fields:
+- invoice_number (string|null)
+- invoice_date (YYYY-MM-DD|null)
+- due_date (YYYY-MM-DD|null)
+- counterparty_name (string|null)
+- counterparty_vat_id (string|null, the counterparty's VAT ID such as DE123456789, optional)
+- total_amount (number|null)
+- currency (string|null)
+- tax_amount (number|null)
+- net_amount (number|null)
+- iban (string|null, optional): the payee's IBAN as printed in the bank details
+- bic (string|null, optional): the payee's BIC/SWIFT code
+- payment_reference (string|null, optional): the reference to quote when paying (Verwendungszweck), if stated
+- payment_terms (string|null, optional): the payment terms as printed, e.g. "Zahlbar innerhalb 30 Tagen netto"
+- discount_percent (number|null, optional): early payment discount (Skonto) in percent, e.g. 2
+- discount_days (integer|null, optional): days from the invoice date within which the discount applies
+- discount_due_date (YYYY-MM-DD|null, optional): last day for the discount when a date is printed
+- extraction_notes (string, short)
+- confidence_score (number|null)
+- field_confidence (object, optional): confidence 0..1 per field name above, e.g. {"total_amount": 0.95}
+- line_items (array, optional): one object per invoice position with description (string|null),
+  quantity (number|null), unit (string|null), unit_price (number|null, net), net_amount (number|null),
+  vat_rate (percent number|null, e.g. 19) and line_total (number|null, gross)
+- vat_breakdown (array, optional): one object per VAT rate with rate (percent number), net_amount and tax_amount
+"#
+    .to_string();
+    prompt.push_str(&format!(
+        "- expense_category (string|null, optional): what the invoice is for, one of {}\n",
+        accounts::expense_categories().join(", ")
+    ));
+    prompt
+}
@@ -0,0 +1,684 @@
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Duration, NaiveDate};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::db::Database;
+use crate::models::{Counterparty, Invoice, InvoiceDraft, IssuedInvoice, Settings};
+use crate::services::bookkeeping::parse_date;
+use crate::services::cii::{self, CiiInvoice, Line, Party, Profile, TaxGroup};
+use crate::services::counterparties::normalize_vat_id;
+use crate::services::dunning::{format_amount, format_percent};
+use crate::services::einvoice::EInvoiceParser;
+use crate::services::pdf::{self, Font, PdfWriter, MARGIN_LEFT, MARGIN_RIGHT, PAGE_WIDTH};
+use crate::services::processor;
+use crate::services::text_extraction::ExtractedText;
+use crate::utils::{modified_time_rfc3339, now_rfc3339, sha256_file, Money};
+
+pub const DEFAULT_NUMBER_FORMAT: &str = "RE-{YYYY}-{NNNN}";
+
+/// UN/ECE Recommendation 20 unit codes offered for line items, with the
+/// abbreviation printed on the PDF.
+pub const UNITS: [(&str, &str); 9] = [
+    ("C62", "Stk."),
+    ("HUR", "Std."),
+    ("DAY", "Tag"),
+    ("MON", "Monat"),
+    ("LS", "pauschal"),
+    ("KGM", "kg"),
+    ("MTR", "m"),
+    ("KMT", "km"),
+    ("LTR", "l"),
+];
+
+/// How lines without VAT are exempt: EN 16931 tax category, exemption
+/// reason code and the note printed on the invoice.
+fn exemption(kind: &str) -> Option<(&'static str, Option<&'static str>, &'static str)> {
+    match kind {
+        "small_business" => Some(("E", None, "Gemäß § 19 UStG wird keine Umsatzsteuer berechnet.")),
+        "reverse_charge" => Some((
+            "AE",
+            Some("VATEX-EU-AE"),
+            "Steuerschuldnerschaft des Leistungsempfängers (§ 13b UStG).",
+        )),
+        "intra_community" => Some((
+            "K",
+            Some("VATEX-EU-IC"),
+            "Steuerfreie innergemeinschaftliche Lieferung (§ 4 Nr. 1b UStG).",
+        )),
+        "export" => Some(("G", Some("VATEX-EU-G"), "Steuerfreie Ausfuhrlieferung (§ 4 Nr. 1a UStG).")),
+        _ => None,
+    }
+}
+
+/// Checks that a number format has exactly one `{N…}` sequence token and
+/// otherwise only `{YYYY}`, `{YY}` and `{MM}`.
+pub fn check_number_format(format: &str) -> Result<()> {
+    let mut sequences = 0;
+    let mut rest = format;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| anyhow!("Unclosed token in invoice number format: {}", format))?;
+        let token = &rest[start + 1..start + end];
+        match token {
+            "YYYY" | "YY" | "MM" => {}
+            _ if !token.is_empty() && token.chars().all(|c| c == 'N') => sequences += 1,
+            _ => return Err(anyhow!("Unknown token {{{}}} in invoice number format", token)),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    if sequences != 1 {
+        return Err(anyhow!("The invoice number format needs exactly one sequence token such as {{NNNN}}"));
+    }
+    if format.contains(['/', '\\']) {
+        return Err(anyhow!("The invoice number is used as file name and must not contain slashes"));
+    }
+    Ok(())
+}
+
+/// The format with the date filled in; sequences count per scope, so
+/// `RE-{YYYY}-{NNNN}` starts again each year.
+fn number_scope(format: &str, date: NaiveDate) -> String {
+    format
+        .replace("{YYYY}", &format!("{:04}", date.year()))
+        .replace("{YY}", &format!("{:02}", date.year() % 100))
+        .replace("{MM}", &format!("{:02}", date.month()))
+}
+
+fn format_number(scope: &str, sequence: u32) -> String {
+    let start = scope.find("{N").unwrap_or(scope.len());
+    let end = scope[start..].find('}').map(|end| start + end + 1).unwrap_or(scope.len());
+    let width = end.saturating_sub(start + 2);
+    format!("{}{:0width$}{}", &scope[..start], sequence, &scope[end..], width = width)
+}
+
+/// The next free number in the scope of `date`: one after the last issued
+/// invoice, or the configured start. Numbers of imported revenue invoices,
+/// e.g. written before Billly, are skipped; they fill their place in the
+/// sequence.
+fn next_number(db: &Database, settings: &Settings, date: NaiveDate) -> Result<(String, u32, String)> {
+    check_number_format(&settings.invoice_number_format)?;
+    let scope = number_scope(&settings.invoice_number_format, date);
+    let mut sequence = match db.get_last_invoice_sequence(&scope)? {
+        Some(last) => last + 1,
+        None => settings.invoice_number_start.max(1),
+    };
+    let mut number = format_number(&scope, sequence);
+    while db.revenue_invoice_number_exists(&number)? {
+        sequence += 1;
+        number = format_number(&scope, sequence);
+    }
+    Ok((scope, sequence, number))
+}
+
+/// The number the next invoice dated `date` would get.
+pub fn preview_number(db: &Database, settings: &Settings, date: NaiveDate) -> Result<String> {
+    next_number(db, settings, date).map(|(_, _, number)| number)
+}
+
+/// Splits a free-text address ("Hauptstr. 1\n12345 Berlin", or comma
+/// separated) into street, postcode, city and country. The country is a
+/// two-letter code or known name after the city, else the VAT ID prefix,
+/// else Germany.
+fn parse_address(name: &str, address: &str, vat_id: Option<&str>) -> Party {
+    let parts: Vec<&str> = address
+        .split(['\n', ','])
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .collect();
+    let city_index = parts.iter().position(|part| postcode_and_city(part).is_some());
+    let (postcode, city) = match city_index.and_then(|index| postcode_and_city(parts[index])) {
+        Some((postcode, city)) => (Some(postcode), Some(city)),
+        None => (None, None),
+    };
+    let street = parts[..city_index.unwrap_or(parts.len())]
+        .iter()
+        .rev()
+        .find(|part| part.chars().any(|c| c.is_ascii_digit()))
+        .or_else(|| parts.first().filter(|_| city_index != Some(0)))
+        .map(|part| part.to_string());
+    let country = city_index
+        .and_then(|index| parts.get(index + 1..))
+        .and_then(|rest| rest.iter().find_map(|part| country_code(part)))
+        .or_else(|| {
+            vat_id
+                .filter(|vat_id| vat_id.len() > 2 && vat_id[..2].chars().all(|c| c.is_ascii_uppercase()))
+                .map(|vat_id| match &vat_id[..2] {
+                    "EL" => "GR".to_string(),
+                    prefix => prefix.to_string(),
+                })
+        })
+        .unwrap_or_else(|| "DE".to_string());
+    Party {
+        name: name.to_string(),
+        street,
+        postcode,
+        city,
+        country,
+        ..Party::default()
+    }
+}
+
+/// `12345 Berlin`, also with a `D-` country prefix or four-digit postcodes.
+fn postcode_and_city(part: &str) -> Option<(String, String)> {
+    let part = part.trim_start_matches("D-").trim_start_matches("A-").trim_start_matches("CH-");
+    let (postcode, city) = part.split_once(' ')?;
+    let valid = (4..=5).contains(&postcode.len()) && postcode.chars().all(|c| c.is_ascii_digit());
+    (valid && !city.trim().is_empty()).then(|| (postcode.to_string(), city.trim().to_string()))
+}
+
+fn country_code(part: &str) -> Option<String> {
+    let code = match part.to_lowercase().as_str() {
+        "deutschland" | "germany" => "DE",
+        "österreich" | "austria" => "AT",
+        "schweiz" | "switzerland" => "CH",
+        "frankreich" | "france" => "FR",
+        "niederlande" | "netherlands" => "NL",
+        "belgien" | "belgium" => "BE",
+        "luxemburg" | "luxembourg" => "LU",
+        "italien" | "italy" => "IT",
+        "spanien" | "spain" => "ES",
+        "polen" | "poland" => "PL",
+        "dänemark" | "denmark" => "DK",
+        "tschechien" | "czech republic" => "CZ",
+        "vereinigtes königreich" | "united kingdom" | "großbritannien" => "GB",
+        "usa" | "vereinigte staaten" | "united states" => "US",
+        _ if part.len() == 2 && part.chars().all(|c| c.is_ascii_uppercase()) => part,
+        _ => return None,
+    };
+    Some(code.to_string())
+}
+
+fn lines(text: Option<&str>) -> Vec<String> {
+    text.unwrap_or_default()
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value.map(str::trim).filter(|value| !value.is_empty()).map(str::to_string)
+}
+
+/// Checks the draft and the seller and buyer data that § 14 UStG (and
+/// XRechnung, if chosen) require, and computes the invoice without a number.
+fn prepare(draft: &InvoiceDraft, counterparty: &Counterparty, settings: &Settings) -> Result<CiiInvoice> {
+    let profile = match draft.format.as_str() {
+        "pdf" | "zugferd" => Profile::En16931,
+        "xrechnung" => Profile::XRechnung,
+        other => return Err(anyhow!("Unknown invoice format: {}", other)),
+    };
+    let xrechnung = profile == Profile::XRechnung;
+    let issue_date =
+        parse_date(Some(&draft.invoice_date)).ok_or_else(|| anyhow!("Invalid invoice date: {}", draft.invoice_date))?;
+    let delivery_date = match non_empty(draft.delivery_date.as_deref()) {
+        Some(date) => parse_date(Some(&date)).ok_or_else(|| anyhow!("Invalid delivery date: {}", date))?,
+        None => issue_date,
+    };
+    let payment_days = draft.payment_days.unwrap_or(settings.invoice_payment_days).min(365);
+    let due_date = issue_date + Duration::days(payment_days as i64);
+    let currency = draft.currency.trim().to_uppercase();
+    if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_uppercase()) {
+        return Err(anyhow!("Invalid currency: {}", draft.currency));
+    }
+
+    let company_name = non_empty(settings.company_name.as_deref())
+        .ok_or_else(|| anyhow!("Set the company name in the settings first"))?;
+    let company_address = settings.company_address.as_deref().unwrap_or_default();
+    if company_address.trim().is_empty() {
+        return Err(anyhow!("Set the company address in the settings first"));
+    }
+    let mut seller = parse_address(&company_name, company_address, None);
+    seller.vat_id = non_empty(settings.company_vat_id.as_deref());
+    seller.tax_number = non_empty(settings.company_tax_number.as_deref());
+    seller.email = non_empty(settings.company_email.as_deref());
+    seller.phone = non_empty(settings.company_phone.as_deref());
+    if seller.vat_id.is_none() && seller.tax_number.is_none() {
+        return Err(anyhow!("Set the VAT ID or tax number in the settings first"));
+    }
+
+    let buyer_address = counterparty.address.as_deref().unwrap_or_default();
+    if buyer_address.trim().is_empty() {
+        return Err(anyhow!("{} has no address", counterparty.name));
+    }
+    let buyer_vat_id = counterparty.vat_id.as_deref().map(normalize_vat_id).filter(|id| !id.is_empty());
+    let mut buyer = parse_address(&counterparty.name, buyer_address, buyer_vat_id.as_deref());
+    buyer.vat_id = buyer_vat_id;
+    buyer.email = non_empty(counterparty.email.as_deref());
+
+    let buyer_reference = non_empty(draft.buyer_reference.as_deref());
+    if xrechnung {
+        let mut missing = Vec::new();
+        if buyer_reference.is_none() {
+            missing.push("the Leitweg-ID or buyer reference".to_string());
+        }
+        if seller.email.is_none() || seller.phone.is_none() {
+            missing.push("the company email and phone".to_string());
+        }
+        if settings.company_iban.is_none() {
+            missing.push("the company IBAN".to_string());
+        }
+        if seller.postcode.is_none() {
+            missing.push("postcode and city in the company address".to_string());
+        }
+        if buyer.email.is_none() {
+            missing.push(format!("an email address of {}", counterparty.name));
+        }
+        if buyer.postcode.is_none() {
+            missing.push(format!("postcode and city in the address of {}", counterparty.name));
+        }
+        if !missing.is_empty() {
+            return Err(anyhow!("XRechnung needs {}", missing.join(", ")));
+        }
+    }
+
+    if draft.items.is_empty() {
+        return Err(anyhow!("Add at least one line item"));
+    }
+    let exemption_kind = non_empty(draft.vat_exemption.as_deref());
+    let exempt = match exemption_kind.as_deref() {
+        Some(kind) => Some(exemption(kind).ok_or_else(|| anyhow!("Unknown VAT exemption: {}", kind))?),
+        None => None,
+    };
+    let mut lines = Vec::new();
+    for (index, item) in draft.items.iter().enumerate() {
+        let position = index + 1;
+        let description = item.description.trim();
+        if description.is_empty() {
+            return Err(anyhow!("Line {} has no description", position));
+        }
+        if !item.quantity.is_finite() || item.quantity <= 0.0 {
+            return Err(anyhow!("Line {} needs a positive quantity", position));
+        }
+        if !UNITS.iter().any(|(code, _)| *code == item.unit) {
+            return Err(anyhow!("Line {} has an unknown unit: {}", position, item.unit));
+        }
+        if !item.vat_rate.is_finite() || !(0.0..=100.0).contains(&item.vat_rate) {
+            return Err(anyhow!("Line {} has an invalid VAT rate", position));
+        }
+        let tax_category = if item.vat_rate > 0.0 {
+            if exemption_kind.as_deref() == Some("small_business") {
+                return Err(anyhow!("Small businesses under § 19 UStG charge no VAT (line {})", position));
+            }
+            "S"
+        } else {
+            exempt
+                .map(|(category, _, _)| category)
+                .ok_or_else(|| anyhow!("Line {} has no VAT; choose the reason for the exemption", position))?
+        };
+        lines.push(Line {
+            description: description.to_string(),
+            quantity: item.quantity,
+            unit_code: item.unit.clone(),
+            unit_price: item.unit_price,
+            net_amount: item.unit_price.convert(item.quantity),
+            tax_category,
+            vat_rate: item.vat_rate,
+        });
+    }
+    if exempt.is_some() && lines.iter().all(|line| line.tax_category == "S") {
+        return Err(anyhow!("A VAT exemption is chosen but every line carries VAT"));
+    }
+    if lines.iter().any(|line| line.tax_category != "S" && line.tax_category != "E") && seller.vat_id.is_none() {
+        return Err(anyhow!("Tax-free supplies need the company VAT ID"));
+    }
+    if lines.iter().any(|line| line.tax_category == "AE" || line.tax_category == "K") && buyer.vat_id.is_none() {
+        return Err(anyhow!("Reverse charge and intra-community supplies need the VAT ID of {}", counterparty.name));
+    }
+
+    let mut groups: BTreeMap<(&'static str, i64), Money> = BTreeMap::new();
+    for line in &lines {
+        let key = (line.tax_category, (line.vat_rate * 100.0).round() as i64);
+        *groups.entry(key).or_insert(Money::ZERO) += line.net_amount;
+    }
+    let taxes: Vec<TaxGroup> = groups
+        .into_iter()
+        .map(|((category, rate), basis)| {
+            let rate = rate as f64 / 100.0;
+            let exemption = (category != "S").then_some(exempt).flatten();
+            TaxGroup {
+                category,
+                rate,
+                basis,
+                tax: basis.convert(rate / 100.0),
+                exemption_reason: exemption.map(|(_, _, note)| note.to_string()),
+                exemption_code: exemption.and_then(|(_, code, _)| code),
+            }
+        })
+        .collect();
+    let net_total: Money = lines.iter().map(|line| line.net_amount).sum();
+    let tax_total: Money = taxes.iter().map(|tax| tax.tax).sum();
+    let grand_total = net_total + tax_total;
+    if grand_total <= Money::ZERO {
+        return Err(anyhow!("The invoice total must be positive"));
+    }
+
+    let payment_terms = if payment_days == 0 {
+        "Zahlbar sofort ohne Abzug.".to_string()
+    } else {
+        format!("Zahlbar ohne Abzug bis {}.", due_date.format("%d.%m.%Y"))
+    };
+    Ok(CiiInvoice {
+        profile,
+        number: String::new(),
+        issue_date,
+        delivery_date,
+        due_date,
+        currency,
+        note: non_empty(draft.note.as_deref()),
+        buyer_reference,
+        seller,
+        buyer,
+        lines,
+        taxes,
+        net_total,
+        tax_total,
+        grand_total,
+        payment_terms,
+        iban: non_empty(settings.company_iban.as_deref()),
+        bic: non_empty(settings.company_bic.as_deref()),
+    })
+}
+
+/// Writes an outgoing invoice into the revenue folder and registers it as a
+/// processed revenue invoice. PDF and ZUGFeRD are rendered as PDF (the
+/// latter with the CII XML embedded), XRechnung as XML only. The number is
+/// read and stored in one transaction, so two invoices cannot get the same
+/// one; if storing fails the file is removed again, so numbers have no gaps.
+pub fn create(db: &Database, settings: &Settings, draft: &InvoiceDraft) -> Result<Invoice> {
+    let folder = non_empty(settings.revenue_folder.as_deref())
+        .map(PathBuf::from)
+        .ok_or_else(|| anyhow!("Choose a revenue folder in the settings first"))?;
+    if !folder.is_dir() {
+        return Err(anyhow!("The revenue folder {} does not exist", folder.display()));
+    }
+    let counterparty = db
+        .get_counterparties(Some(&draft.counterparty_id))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("Counterparty not found"))?;
+
+    let mut document = prepare(draft, &counterparty, settings)?;
+    let extension = if draft.format == "xrechnung" { "xml" } else { "pdf" };
+    let id = Uuid::new_v4().to_string();
+    let partial = folder.join(format!("{}.{}.part", id, extension));
+    // The row goes in before the file shows up in the watched folder, so the
+    // watcher finds it instead of processing the file as a new invoice.
+    let stored = db.in_transaction(|| -> Result<(Invoice, String, PathBuf)> {
+        let (scope, sequence, number) = next_number(db, settings, document.issue_date)?;
+        document.number = number.clone();
+        let xml = cii::render(&document);
+
+        let file_name: String = number
+            .chars()
+            .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '_' })
+            .collect();
+        let path = folder.join(format!("{}.{}", file_name, extension));
+        if path.exists() {
+            return Err(anyhow!("{} already exists", path.display()));
+        }
+        match draft.format.as_str() {
+            "xrechnung" => std::fs::write(&partial, &xml)?,
+            format => {
+                let embedded = (format == "zugferd").then(|| xml.clone());
+                write_pdf(&document, &counterparty, settings, embedded, &partial)?
+            }
+        }
+
+        let now = now_rfc3339();
+        let invoice = Invoice {
+            id: id.clone(),
+            category: "revenue".to_string(),
+            file_path: Some(path.to_string_lossy().to_string()),
+            file_hash: sha256_file(&partial)?,
+            file_modified_at: modified_time_rfc3339(&partial)?,
+            ingestion_status: "pending".to_string(),
+            ocr_text: None,
+            extracted_json: "{}".to_string(),
+            confidence_score: 0.0,
+            invoice_number: Some(number.clone()),
+            invoice_date: Some(document.issue_date.format("%Y-%m-%d").to_string()),
+            due_date: Some(document.due_date.format("%Y-%m-%d").to_string()),
+            counterparty_name: Some(counterparty.name.clone()),
+            total_amount: document.grand_total,
+            currency: document.currency.clone(),
+            tax_amount: Some(document.tax_total),
+            net_amount: Some(document.net_total),
+            status: "open".to_string(),
+            paid_at: None,
+            created_at: now.clone(),
+            updated_at: now,
+            base_currency: None,
+            exchange_rate: None,
+            base_total_amount: None,
+            counterparty_id: Some(counterparty.id.clone()),
+            account_number: None,
+            account_source: None,
+        };
+        let issued = IssuedInvoice {
+            invoice_id: invoice.id.clone(),
+            invoice_number: number,
+            number_scope: scope,
+            sequence,
+            format: draft.format.clone(),
+            draft_json: serde_json::to_string(draft)?,
+            created_at: String::new(),
+        };
+        db.insert_issued_invoice(&invoice, &issued)?;
+        Ok((invoice, xml, path))
+    });
+    let (mut invoice, xml, path) = match stored {
+        Ok(stored) => stored,
+        Err(err) => {
+            let _ = std::fs::remove_file(&partial);
+            return Err(err);
+        }
+    };
+    if let Err(err) = std::fs::rename(&partial, &path) {
+        let _ = std::fs::remove_file(&partial);
+        db.delete_issued_invoice(&invoice.id)?;
+        return Err(err.into());
+    }
+
+    // The data comes from the XML just written, as if the file were read.
+    let parsed = EInvoiceParser::read(&xml)?;
+    let text = ExtractedText::single_page(parsed.render_text(), "einvoice");
+    let data = parsed.into_extracted("revenue");
+    let raw_json = serde_json::to_string(&data)?;
+    processor::store_extracted(db, &mut invoice, data, raw_json, text, settings, "create")?;
+    if invoice.counterparty_id.as_deref() != Some(counterparty.id.as_str()) {
+        db.set_invoice_counterparty(&invoice.id, Some(&counterparty.id))?;
+        invoice.counterparty_id = Some(counterparty.id);
+    }
+    Ok(invoice)
+}
+
+fn write_pdf(
+    document: &CiiInvoice,
+    counterparty: &Counterparty,
+    settings: &Settings,
+    factur_x: Option<String>,
+    path: &Path,
+) -> Result<()> {
+    let currency = document.currency.as_str();
+    let right = PAGE_WIDTH - MARGIN_RIGHT;
+    let company_address = lines(settings.company_address.as_deref());
+    let mut recipient = vec![counterparty.name.clone()];
+    recipient.extend(
+        lines(counterparty.address.as_deref())
+            .into_iter()
+            .filter(|line| !line.eq_ignore_ascii_case(&counterparty.name)),
+    );
+
+    let mut pdf = PdfWriter::new();
+    pdf.letterhead(&document.seller.name, &company_address, &recipient);
+
+    let date = |date: NaiveDate| date.format("%d.%m.%Y").to_string();
+    let mut info = vec![
+        ("Rechnungsnummer", document.number.clone()),
+        ("Rechnungsdatum", date(document.issue_date)),
+        ("Leistungsdatum", date(document.delivery_date)),
+    ];
+    if let Some(account) = counterparty.personal_account.as_deref() {
+        info.push(("Kundennummer", account.to_string()));
+    }
+    if let Some(reference) = document.buyer_reference.as_deref() {
+        info.push(("Ihre Referenz", reference.to_string()));
+    }
+    if let Some(vat_id) = document.buyer.vat_id.as_deref() {
+        info.push(("Ihre USt-IdNr.", vat_id.to_string()));
+    }
+    let mut y = 686.0;
+    for (label, value) in &info {
+        pdf.text(354.0, y, 9.0, Font::Regular, label);
+        pdf.text_right(right, y, 9.0, Font::Regular, value);
+        y -= 13.0;
+    }
+
+    pdf.text(MARGIN_LEFT, 560.0, 13.0, Font::Bold, &format!("Rechnung {}", document.number));
+    pdf.set_cursor(545.0);
+    if let Some(note) = document.note.as_deref() {
+        pdf.paragraph(note, 10.0, Font::Regular);
+        pdf.skip(8.0);
+    }
+
+    // Columns: position, description, quantity, unit, unit price, VAT, amount.
+    let description_x = MARGIN_LEFT + 22.0;
+    let description_width = 205.0;
+    let quantity_right = 335.0;
+    let unit_x = 341.0;
+    let price_right = 435.0;
+    let rate_right = 472.0;
+    let header = |pdf: &mut PdfWriter| {
+        pdf.skip(14.0);
+        let y = pdf.cursor();
+        pdf.text(MARGIN_LEFT, y, 9.0, Font::Bold, "Pos.");
+        pdf.text(description_x, y, 9.0, Font::Bold, "Beschreibung");
+        pdf.text_right(quantity_right, y, 9.0, Font::Bold, "Menge");
+        pdf.text(unit_x, y, 9.0, Font::Bold, "Einheit");
+        pdf.text_right(price_right, y, 9.0, Font::Bold, "Einzelpreis");
+        pdf.text_right(rate_right, y, 9.0, Font::Bold, "USt");
+        pdf.text_right(right, y, 9.0, Font::Bold, "Betrag");
+        pdf.skip(5.0);
+        let y = pdf.cursor();
+        pdf.line(MARGIN_LEFT, y, right, y);
+    };
+    header(&mut pdf);
+    for (index, line) in document.lines.iter().enumerate() {
+        let wrapped: Vec<String> = line
+            .description
+            .lines()
+            .flat_map(|text| pdf::wrap(text, description_width, 9.0, Font::Regular))
+            .collect();
+        let height = wrapped.len() as f32 * 12.0 + 4.0;
+        if pdf.cursor() - height < 100.0 {
+            pdf.new_page();
+            header(&mut pdf);
+        }
+        pdf.skip(14.0);
+        let y = pdf.cursor();
+        let unit = UNITS
+            .iter()
+            .find(|(code, _)| *code == line.unit_code)
+            .map(|(_, label)| *label)
+            .unwrap_or(line.unit_code.as_str());
+        pdf.text(MARGIN_LEFT, y, 9.0, Font::Regular, &(index + 1).to_string());
+        pdf.text_right(quantity_right, y, 9.0, Font::Regular, &quantity(line.quantity));
+        pdf.text(unit_x, y, 9.0, Font::Regular, unit);
+        pdf.text_right(price_right, y, 9.0, Font::Regular, &format_amount(line.unit_price, currency));
+        pdf.text_right(rate_right, y, 9.0, Font::Regular, &format!("{} %", rate(line.vat_rate)));
+        pdf.text_right(right, y, 9.0, Font::Regular, &format_amount(line.net_amount, currency));
+        for (row, text) in wrapped.iter().enumerate() {
+            pdf.text(description_x, y - row as f32 * 12.0, 9.0, Font::Regular, text);
+        }
+        pdf.set_cursor(y - (wrapped.len() as f32 - 1.0) * 12.0);
+    }
+    pdf.skip(6.0);
+    let y = pdf.cursor();
+    pdf.line(MARGIN_LEFT, y, right, y);
+
+    let mut totals = vec![("Summe netto".to_string(), document.net_total, Font::Regular)];
+    for tax in &document.taxes {
+        let label = if tax.category == "S" {
+            format!("zzgl. USt {} % auf {}", rate(tax.rate), format_amount(tax.basis, currency))
+        } else {
+            format!("USt 0 % auf {}", format_amount(tax.basis, currency))
+        };
+        totals.push((label, tax.tax, Font::Regular));
+    }
+    totals.push(("Rechnungsbetrag".to_string(), document.grand_total, Font::Bold));
+    pdf.ensure_space(totals.len() as f32 * 14.0 + 10.0);
+    for (label, amount, font) in totals {
+        pdf.skip(14.0);
+        let y = pdf.cursor();
+        pdf.text(price_right - 150.0, y, 10.0, font, &label);
+        pdf.text_right(right, y, 10.0, font, &format_amount(amount, currency));
+    }
+
+    pdf.skip(16.0);
+    let mut notes: Vec<&str> = document
+        .taxes
+        .iter()
+        .filter_map(|tax| tax.exemption_reason.as_deref())
+        .collect();
+    notes.dedup();
+    for note in notes {
+        pdf.paragraph(note, 10.0, Font::Regular);
+    }
+    pdf.paragraph(&document.payment_terms, 10.0, Font::Regular);
+    if document.iban.is_some() {
+        pdf.paragraph(
+            &format!(
+                "Bitte überweisen Sie den Betrag unter Angabe der Rechnungsnummer {} auf das unten genannte Konto.",
+                document.number
+            ),
+            10.0,
+            Font::Regular,
+        );
+    }
+
+    let mut sender = vec![document.seller.name.clone()];
+    sender.extend(company_address);
+    let mut footer = vec![sender.join(" · ")];
+    let mut registration = Vec::new();
+    if let Some(vat_id) = document.seller.vat_id.as_deref() {
+        registration.push(format!("USt-IdNr. {}", vat_id));
+    }
+    if let Some(tax_number) = document.seller.tax_number.as_deref() {
+        registration.push(format!("Steuernummer {}", tax_number));
+    }
+    if let Some(phone) = document.seller.phone.as_deref() {
+        registration.push(format!("Tel. {}", phone));
+    }
+    if let Some(email) = document.seller.email.as_deref() {
+        registration.push(email.to_string());
+    }
+    footer.push(registration.join(" · "));
+    if let Some(iban) = document.iban.as_deref() {
+        let mut account = format!("IBAN {}", iban);
+        if let Some(bic) = document.bic.as_deref() {
+            account.push_str(&format!(" · BIC {}", bic));
+        }
+        footer.push(account);
+    }
+    pdf.set_footer(footer);
+    if let Some(xml) = factur_x {
+        pdf.embed_factur_x(xml);
+    }
+    pdf.save(path)
+}
+
+/// `1,5` or `2`; up to three decimals.
+fn quantity(value: f64) -> String {
+    let text = format!("{:.3}", value);
+    text.trim_end_matches('0').trim_end_matches('.').replace('.', ",")
+}
+
+/// `19` or `5,5`.
+fn rate(value: f64) -> String {
+    let text = format_percent(value);
+    text.trim_end_matches('0').trim_end_matches(',').to_string()
+}
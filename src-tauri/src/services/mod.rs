@@ -1,6 +1,31 @@
+pub mod accounts;
+pub mod aging;
+pub mod bank_import;
+pub mod bookkeeping;
+pub mod cii;
+pub mod counterparties;
 pub mod crypto;
+pub mod datev;
+pub mod dunning;
+pub mod einvoice;
+pub mod euer;
+pub mod evidence;
+pub mod exchange_rates;
+pub mod export;
+pub mod extraction;
+pub mod girocode;
+pub mod invoicing;
+pub mod ocr;
+pub mod ollama;
 pub mod openai;
+pub mod payments;
+pub mod pdf;
 pub mod processor;
+pub mod reconciliation;
+pub mod rule_based;
+pub mod sepa;
 pub mod state;
 pub mod text_extraction;
+pub mod validation;
+pub mod vat_return;
 pub mod watcher;
@@ -0,0 +1,100 @@
+use anyhow::{anyhow, Result};
+use image::imageops::FilterType;
+use image::{DynamicImage, GrayImage, Luma};
+use imageproc::contrast::{otsu_level, threshold, ThresholdType};
+use imageproc::geometric_transformations::{rotate_about_center, Interpolation};
+
+/// Tesseract works best with glyphs around 30px high, which roughly means
+/// 1500px across for a receipt or A4 page.
+const MIN_OCR_WIDTH: u32 = 1500;
+const MAX_UPSCALE: u32 = 4;
+const SKEW_PROBE_WIDTH: u32 = 800;
+const MAX_SKEW_DEGREES: f32 = 10.0;
+const SKEW_STEP_DEGREES: f32 = 0.5;
+
+/// Grayscale, upscale, deskew and binarize an image before OCR. Returns the
+/// factor it was upscaled by, which multiplies its resolution.
+pub fn preprocess(image: DynamicImage) -> (GrayImage, u32) {
+    let mut gray = image.to_luma8();
+
+    let mut scale = 1;
+    if gray.width() > 0 && gray.width() < MIN_OCR_WIDTH {
+        let factor = MIN_OCR_WIDTH.div_ceil(gray.width()).min(MAX_UPSCALE);
+        scale = factor;
+        gray = image::imageops::resize(
+            &gray,
+            gray.width() * factor,
+            gray.height() * factor,
+            FilterType::CatmullRom,
+        );
+    }
+
+    let angle = estimate_skew(&gray);
+    if angle.abs() >= SKEW_STEP_DEGREES {
+        gray = rotate_about_center(&gray, -angle.to_radians(), Interpolation::Bilinear, Luma([255]));
+    }
+
+    let level = otsu_level(&gray);
+    (threshold(&gray, level, ThresholdType::Binary), scale)
+}
+
+/// Runs Tesseract on an already preprocessed grayscale image.
+pub fn recognize(image: &GrayImage, language: &str, dpi: i32) -> Result<String> {
+    let width = image.width() as i32;
+    let height = image.height() as i32;
+    let text = tesseract::Tesseract::new(None, Some(language))
+        .map_err(|e| anyhow!("Tesseract init: {}", e))?
+        .set_frame(image.as_raw(), width, height, 1, width)
+        .map_err(|e| anyhow!("Tesseract image: {}", e))?
+        .set_source_resolution(dpi)
+        .recognize()
+        .map_err(|e| anyhow!("Tesseract recognize: {}", e))?
+        .get_text()
+        .map_err(|e| anyhow!("OCR text: {}", e))?;
+    Ok(text)
+}
+
+/// Finds the rotation (in degrees) at which text lines are most horizontal,
+/// measured by the variance of dark pixels per row on a small binarized copy.
+fn estimate_skew(gray: &GrayImage) -> f32 {
+    if gray.width() == 0 || gray.height() == 0 {
+        return 0.0;
+    }
+    let probe = if gray.width() > SKEW_PROBE_WIDTH {
+        let height = (gray.height() as u64 * SKEW_PROBE_WIDTH as u64 / gray.width() as u64).max(1) as u32;
+        image::imageops::resize(gray, SKEW_PROBE_WIDTH, height, FilterType::Triangle)
+    } else {
+        gray.clone()
+    };
+    let level = otsu_level(&probe);
+    let probe = threshold(&probe, level, ThresholdType::Binary);
+
+    let steps = (MAX_SKEW_DEGREES / SKEW_STEP_DEGREES) as i32;
+    let mut best_angle = 0.0;
+    let mut best_score = row_profile_variance(&probe);
+    for step in -steps..=steps {
+        if step == 0 {
+            continue;
+        }
+        let angle = step as f32 * SKEW_STEP_DEGREES;
+        let rotated = rotate_about_center(&probe, -angle.to_radians(), Interpolation::Nearest, Luma([255]));
+        let score = row_profile_variance(&rotated);
+        if score > best_score {
+            best_score = score;
+            best_angle = angle;
+        }
+    }
+    best_angle
+}
+
+fn row_profile_variance(image: &GrayImage) -> f64 {
+    let rows: Vec<f64> = image
+        .rows()
+        .map(|row| row.filter(|pixel| pixel.0[0] < 128).count() as f64)
+        .collect();
+    if rows.is_empty() {
+        return 0.0;
+    }
+    let mean = rows.iter().sum::<f64>() / rows.len() as f64;
+    rows.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / rows.len() as f64
+}
@@ -0,0 +1,91 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::models::ExtractedInvoiceData;
+use crate::services::extraction::{extract_with_chat, InvoiceExtractor};
+
+#[derive(Serialize)]
+struct ChatRequest {
+    model: String,
+    messages: Vec<Message>,
+    stream: bool,
+    format: String,
+    options: ChatOptions,
+}
+
+#[derive(Serialize)]
+struct ChatOptions {
+    temperature: f32,
+}
+
+#[derive(Serialize)]
+struct Message {
+    role: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    message: ResponseMessage,
+}
+
+#[derive(Deserialize)]
+struct ResponseMessage {
+    content: String,
+}
+
+/// Uses Ollama's native `/api/chat` endpoint with JSON mode enabled.
+pub struct OllamaExtractor {
+    base_url: String,
+    model: String,
+}
+
+impl OllamaExtractor {
+    pub fn new(base_url: String, model: String) -> Self {
+        OllamaExtractor { base_url, model }
+    }
+
+    async fn call_ollama(&self, system_prompt: String, user_prompt: String) -> Result<String> {
+        let client = reqwest::Client::new();
+        let request = ChatRequest {
+            model: self.model.clone(),
+            messages: vec![
+                Message {
+                    role: "system".to_string(),
+                    content: system_prompt,
+                },
+                Message {
+                    role: "user".to_string(),
+                    content: user_prompt,
+                },
+            ],
+            stream: false,
+            format: "json".to_string(),
+            options: ChatOptions { temperature: 0.1 },
+        };
+
+        let url = format!("{}/api/chat", self.base_url.trim_end_matches('/'));
+        let response = client.post(url).json(&request).send().await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Ollama error {}: {}", status, body));
+        }
+
+        let body: ChatResponse = response.json().await?;
+        Ok(body.message.content.trim().to_string())
+    }
+}
+
+#[async_trait]
+impl InvoiceExtractor for OllamaExtractor {
+    fn name(&self) -> &'static str {
+        "ollama"
+    }
+
+    async fn extract_invoice_data(&self, text: &str) -> Result<(ExtractedInvoiceData, String)> {
+        extract_with_chat(text, |system, user| self.call_ollama(system, user)).await
+    }
+}
@@ -1,9 +1,9 @@
 use anyhow::{anyhow, Result};
-use jsonschema::JSONSchema;
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
 
 use crate::models::ExtractedInvoiceData;
+use crate::services::extraction::{extract_with_chat, InvoiceExtractor};
 
 #[derive(Serialize)]
 struct ChatRequest {
@@ -40,153 +40,76 @@ struct ResponseMessage {
     content: String,
 }
 
-pub struct OpenAIExtractor;
+/// Talks to the OpenAI chat completions API or any server exposing the same
+/// `/chat/completions` endpoint (llama.cpp server, vLLM, LM Studio).
+pub struct OpenAIExtractor {
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+}
 
 impl OpenAIExtractor {
-    pub async fn extract_invoice_data(api_key: &str, text: &str) -> Result<(ExtractedInvoiceData, String)> {
-        let schema = extraction_schema();
-        let prompt = system_prompt();
-        let user = format!("Invoice text:\n{}", text);
-
-        let mut raw = call_openai(api_key, &prompt, &user).await?;
-        let mut value = parse_json(&raw)?;
-
-        if !validate_json(&schema, &value) {
-            let fix_prompt = format!(
-                "Fixe dieses JSON so, dass es exakt dem Schema entspricht. Nur JSON ausgeben. JSON:\n{}",
-                raw
-            );
-            raw = call_openai(api_key, &prompt, &fix_prompt).await?;
-            value = parse_json(&raw)?;
-            if !validate_json(&schema, &value) {
-                return Err(anyhow!("JSON validation failed"));
-            }
-        }
-
-        let mut data: ExtractedInvoiceData = serde_json::from_value(value)?;
-        if data.currency.is_none() {
-            data.currency = Some("EUR".to_string());
-        }
-        if data.extraction_notes.trim().is_empty() {
-            data.extraction_notes = "notes missing".to_string();
+    pub fn new(base_url: String, api_key: Option<String>, model: String) -> Self {
+        OpenAIExtractor {
+            base_url,
+            api_key,
+            model,
         }
-
-        if data.confidence_score.is_none() {
-            data.confidence_score = Some(compute_confidence(&data));
-        }
-
-        Ok((data, raw))
     }
-}
 
-async fn call_openai(api_key: &str, system_prompt: &str, user_prompt: &str) -> Result<String> {
-    let client = reqwest::Client::new();
-    let request = ChatRequest {
-        model: "gpt-4o-mini".to_string(),
-        temperature: 0.1,
-        messages: vec![
-            Message {
-                role: "system".to_string(),
-                content: system_prompt.to_string(),
+    async fn call_openai(&self, system_prompt: String, user_prompt: String) -> Result<String> {
+        let client = reqwest::Client::new();
+        let request = ChatRequest {
+            model: self.model.clone(),
+            temperature: 0.1,
+            messages: vec![
+                Message {
+                    role: "system".to_string(),
+                    content: system_prompt,
+                },
+                Message {
+                    role: "user".to_string(),
+                    content: user_prompt,
+                },
+            ],
+            response_format: ResponseFormat {
+                format_type: "json_object".to_string(),
             },
-            Message {
-                role: "user".to_string(),
-                content: user_prompt.to_string(),
-            },
-        ],
-        response_format: ResponseFormat {
-            format_type: "json_object".to_string(),
-        },
-    };
-
-    let response = client
-        .post("https://api.openai.com/v1/chat/completions")
-        .bearer_auth(api_key)
-        .json(&request)
-        .send()
-        .await?;
-
-    if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_default();
-        return Err(anyhow!("OpenAI error {}: {}", status, body));
-    }
-
-    let body: ChatResponse = response.json().await?;
-    let content = body
-        .choices
-        .get(0)
-        .ok_or_else(|| anyhow!("Empty response"))?
-        .message
-        .content
-        .trim()
-        .to_string();
-    Ok(content)
-}
+        };
 
-fn parse_json(raw: &str) -> Result<Value> {
-    serde_json::from_str::<Value>(raw).map_err(|e| anyhow!("Invalid JSON: {}", e))
-}
-
-fn extraction_schema() -> JSONSchema {
-    let schema = json!({
-        "type": "object",
-        "additionalProperties": false,
-        "required": ["total_amount", "currency", "invoice_date", "extraction_notes"],
-        "properties": {
-            "invoice_number": {"type": ["string", "null"]},
-            "invoice_date": {"type": ["string", "null"]},
-            "due_date": {"type": ["string", "null"]},
-            "counterparty_name": {"type": ["string", "null"]},
-            "total_amount": {"type": ["number", "null"]},
-            "currency": {"type": ["string", "null"]},
-            "tax_amount": {"type": ["number", "null"]},
-            "net_amount": {"type": ["number", "null"]},
-            "extraction_notes": {"type": "string"},
-            "confidence_score": {"type": ["number", "null"]}
+        let url = format!("{}/chat/completions", self.base_url.trim_end_matches('/'));
+        let mut builder = client.post(url).json(&request);
+        if let Some(api_key) = &self.api_key {
+            builder = builder.bearer_auth(api_key);
         }
-    });
+        let response = builder.send().await?;
 
-    JSONSchema::compile(&schema).expect("Invalid JSON schema")
-}
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!("OpenAI error {}: {}", status, body));
+        }
 
-fn validate_json(schema: &JSONSchema, value: &Value) -> bool {
-    schema.is_valid(value)
+        let body: ChatResponse = response.json().await?;
+        let content = body
+            .choices
+            .first()
+            .ok_or_else(|| anyhow!("Empty response"))?
+            .message
+            .content
+            .trim()
+            .to_string();
+        Ok(content)
+    }
 }
 
-fn compute_confidence(data: &ExtractedInvoiceData) -> f64 {
-    let mut score: f64 = 0.4;
-    if data.invoice_number.is_some() {
-        score += 0.1;
-    }
-    if data.invoice_date.is_some() {
-        score += 0.1;
-    }
-    if data.counterparty_name.is_some() {
-        score += 0.1;
-    }
-    if data.total_amount.is_some() {
-        score += 0.1;
+#[async_trait]
+impl InvoiceExtractor for OpenAIExtractor {
+    fn name(&self) -> &'static str {
+        "openai"
     }
-    if data.tax_amount.is_some() || data.net_amount.is_some() {
-        score += 0.05;
-    }
-    score.clamp(0.0, 1.0)
-}
 
-fn system_prompt() -> String {
-    r#"You are an invoice extraction system. Return JSON only and match the schema exactly.
-Fields:
-- invoice_number (string|null)
-- invoice_date (YYYY-MM-DD|null)
-- due_date (YYYY-MM-DD|null)
-- counterparty_name (string|null)
-- total_amount (number|null)
-- currency (string|null)
-- tax_amount (number|null)
-- net_amount (number|null)
-- extraction_notes (string, short)
-- confidence_score (number|null)
-"#
-        .to_string()
+    async fn extract_invoice_data(&self, text: &str) -> Result<(ExtractedInvoiceData, String)> {
+        extract_with_chat(text, |system, user| self.call_openai(system, user)).await
+    }
 }
@@ -0,0 +1,316 @@
+use anyhow::{anyhow, Result};
+use chrono::{Local, NaiveDate};
+use std::collections::HashSet;
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::db::Database;
+use crate::models::{
+    Counterparty, ExtractedInvoiceData, Invoice, InvoiceOverride, PaymentDetails, PaymentOrder, SepaTransferSummary,
+};
+use crate::services::bookkeeping::{apply_overrides, parse_date};
+use crate::services::girocode;
+use crate::services::sepa::{self, CreditTransfer, Debtor};
+use crate::utils::Money;
+
+/// Payment details as extracted, before overrides and counterparty data.
+pub fn from_extracted(data: &ExtractedInvoiceData) -> PaymentDetails {
+    PaymentDetails {
+        iban: data.iban.clone(),
+        bic: data.bic.clone(),
+        reference: data.payment_reference.clone(),
+        terms: data.payment_terms.clone(),
+        discount_percent: data.discount_percent,
+        discount_due_date: data.discount_due_date.clone(),
+    }
+}
+
+/// Payment details of an invoice. Overrides (`iban`, `bic`,
+/// `payment_reference`, `payment_terms`, `discount_percent`,
+/// `discount_due_date`) win over the `extracted` details; IBAN and BIC fall
+/// back to the counterparty record. An IBAN or BIC that fails validation is
+/// dropped.
+pub fn payment_details(
+    extracted: Option<&PaymentDetails>,
+    overrides: &[InvoiceOverride],
+    counterparty: Option<&Counterparty>,
+) -> PaymentDetails {
+    let overridden = |field: &str| {
+        overrides
+            .iter()
+            .find(|entry| entry.field_name == field)
+            .map(|entry| entry.override_value.trim().to_string())
+    };
+    let extracted_field = |get: fn(&PaymentDetails) -> Option<String>| extracted.and_then(get);
+
+    let iban = overridden("iban")
+        .or_else(|| extracted_field(|details| details.iban.clone()))
+        .and_then(|iban| sepa::normalize_iban(&iban))
+        .or_else(|| counterparty.and_then(|counterparty| counterparty.iban.as_deref()).and_then(sepa::normalize_iban));
+    let bic = overridden("bic")
+        .or_else(|| extracted_field(|details| details.bic.clone()))
+        .and_then(|bic| sepa::normalize_bic(&bic))
+        .or_else(|| counterparty.and_then(|counterparty| counterparty.bic.as_deref()).and_then(sepa::normalize_bic));
+    let discount_percent = match overridden("discount_percent") {
+        Some(value) => value.replace(',', ".").parse().ok(),
+        None => extracted.and_then(|details| details.discount_percent),
+    };
+    PaymentDetails {
+        iban,
+        bic,
+        reference: overridden("payment_reference")
+            .or_else(|| extracted_field(|details| details.reference.clone()))
+            .filter(|reference| !reference.trim().is_empty()),
+        terms: overridden("payment_terms")
+            .or_else(|| extracted_field(|details| details.terms.clone()))
+            .filter(|terms| !terms.trim().is_empty()),
+        discount_percent: discount_percent.filter(|percent: &f64| *percent > 0.0 && *percent < 100.0),
+        discount_due_date: overridden("discount_due_date")
+            .or_else(|| extracted_field(|details| details.discount_due_date.clone())),
+    }
+}
+
+/// Skonto on `total` when paying on `on`: the discount percent if its
+/// deadline has not passed, otherwise nothing.
+pub fn discount_for(total: Money, details: &PaymentDetails, on: NaiveDate) -> Money {
+    match (details.discount_percent, parse_date(details.discount_due_date.as_deref())) {
+        (Some(percent), Some(deadline)) if on <= deadline => total.convert(percent / 100.0),
+        _ => Money::ZERO,
+    }
+}
+
+/// An open payable with what is owed on a given day.
+struct Payable {
+    invoice: Invoice,
+    label: String,
+    details: PaymentDetails,
+    creditor_name: Option<String>,
+    amount: Money,
+    discount: Money,
+}
+
+impl Payable {
+    /// Skonto is deducted when paying on `on` is within its deadline and
+    /// nothing has been paid yet; confirmed bank payments are deducted too.
+    fn load(db: &Database, invoice_id: &str, counterparties: &[Counterparty], on: NaiveDate) -> Result<Self> {
+        let mut invoice = db
+            .get_invoice_by_id(invoice_id)?
+            .ok_or_else(|| anyhow!("Invoice not found: {}", invoice_id))?;
+        let overrides = db.get_overrides(invoice_id)?;
+        apply_overrides(&mut invoice, &overrides);
+        let counterparty = invoice
+            .counterparty_id
+            .as_deref()
+            .and_then(|id| counterparties.iter().find(|counterparty| counterparty.id == id));
+        let extracted = db.get_payment_details(invoice_id)?;
+        let details = payment_details(extracted.as_ref(), &overrides, counterparty);
+
+        let paid: Money = db.get_invoice_payments(invoice_id)?.into_iter().map(|(amount, _)| amount).sum();
+        let discount = if paid == Money::ZERO {
+            discount_for(invoice.total_amount, &details, on)
+        } else {
+            Money::ZERO
+        };
+        Ok(Payable {
+            label: invoice.invoice_number.clone().unwrap_or_else(|| invoice.id.clone()),
+            creditor_name: counterparty
+                .map(|counterparty| counterparty.name.clone())
+                .or_else(|| invoice.counterparty_name.clone())
+                .filter(|name| !name.trim().is_empty()),
+            amount: invoice.total_amount - discount - paid,
+            discount,
+            details,
+            invoice,
+        })
+    }
+
+    /// Why the payable cannot be paid by SEPA transfer, if it cannot.
+    fn problem(&self) -> Option<&'static str> {
+        if self.invoice.category != "payable" {
+            Some("not a payable")
+        } else if self.invoice.status == "paid" {
+            Some("already paid")
+        } else if self.invoice.status == "scheduled" {
+            Some("payment already scheduled")
+        } else if !self.invoice.currency.eq_ignore_ascii_case("EUR") {
+            Some("SEPA transfers are in EUR only")
+        } else if self.amount <= Money::ZERO {
+            Some("nothing left to pay")
+        } else if self.details.iban.is_none() {
+            Some("no valid IBAN")
+        } else if self.creditor_name.is_none() {
+            Some("no payee name")
+        } else {
+            None
+        }
+    }
+
+    /// The payment reference, or the invoice number and any Skonto taken.
+    fn remittance(&self) -> String {
+        self.details.reference.clone().unwrap_or_else(|| {
+            if self.discount > Money::ZERO {
+                format!("Rechnung {} abzgl. {} Skonto", self.label, self.discount)
+            } else {
+                format!("Rechnung {}", self.label)
+            }
+        })
+    }
+}
+
+/// Writes a pain.001.001.09 file paying the open payables `invoice_ids` on
+/// `execution_date` from the `debtor` account, records one payment order per
+/// invoice and marks the invoices as `scheduled`. Skonto is deducted where
+/// its deadline is on or after the execution date; confirmed bank payments
+/// are deducted as well. An invoice selected twice is paid once. Nothing is
+/// written unless every invoice can be paid.
+pub fn schedule_transfers(
+    db: &Database,
+    invoice_ids: &[String],
+    execution_date: NaiveDate,
+    debtor: &Debtor,
+    path: &Path,
+) -> Result<SepaTransferSummary> {
+    if invoice_ids.is_empty() {
+        return Err(anyhow!("Select at least one payable"));
+    }
+    if execution_date < Local::now().date_naive() {
+        return Err(anyhow!("The execution date {} is in the past", execution_date));
+    }
+    let counterparties = db.get_counterparties(None)?;
+    let message_id = Uuid::new_v4().simple().to_string();
+
+    let mut orders = Vec::new();
+    let mut transfers = Vec::new();
+    let mut problems = Vec::new();
+    let mut seen = HashSet::new();
+    for invoice_id in invoice_ids.iter().filter(|invoice_id| seen.insert(invoice_id.as_str())) {
+        let payable = Payable::load(db, invoice_id, &counterparties, execution_date)?;
+        if let Some(problem) = payable.problem() {
+            problems.push(format!("{}: {}", payable.label, problem));
+            continue;
+        }
+
+        let order_id = Uuid::new_v4();
+        let transfer = CreditTransfer {
+            end_to_end_id: order_id.simple().to_string(),
+            creditor_name: payable.creditor_name.clone().unwrap_or_default(),
+            iban: payable.details.iban.clone().unwrap_or_default(),
+            bic: payable.details.bic.clone(),
+            amount: payable.amount,
+            remittance: payable.remittance(),
+        };
+        orders.push(PaymentOrder {
+            id: order_id.to_string(),
+            message_id: message_id.clone(),
+            invoice_id: payable.invoice.id.clone(),
+            end_to_end_id: transfer.end_to_end_id.clone(),
+            creditor_name: transfer.creditor_name.clone(),
+            creditor_iban: transfer.iban.clone(),
+            creditor_bic: transfer.bic.clone(),
+            amount: payable.amount,
+            discount: payable.discount,
+            currency: "EUR".to_string(),
+            remittance: transfer.remittance.clone(),
+            execution_date: execution_date.format("%Y-%m-%d").to_string(),
+            file_path: path.to_string_lossy().to_string(),
+            created_at: String::new(),
+        });
+        transfers.push(transfer);
+    }
+    if !problems.is_empty() {
+        return Err(anyhow!("Cannot create the transfer: {}", problems.join("; ")));
+    }
+
+    let xml = sepa::render_pain001(
+        &message_id,
+        Local::now().naive_local(),
+        execution_date,
+        debtor,
+        &transfers,
+    );
+    std::fs::write(path, xml)?;
+    if let Err(err) = db.insert_payment_orders(&orders) {
+        let _ = std::fs::remove_file(path);
+        return Err(err.into());
+    }
+
+    Ok(SepaTransferSummary {
+        path: path.to_string_lossy().to_string(),
+        message_id,
+        execution_date: execution_date.format("%Y-%m-%d").to_string(),
+        transfers: orders.len(),
+        total: orders.iter().map(|order| order.amount).sum(),
+        discount: orders.iter().map(|order| order.discount).sum(),
+    })
+}
+
+/// GiroCode (EPC069-12 QR code) for paying an open payable on `on`, as a
+/// PNG image. The amount is what is still owed, less Skonto while its
+/// deadline holds.
+pub fn girocode(db: &Database, invoice_id: &str, on: NaiveDate) -> Result<Vec<u8>> {
+    let counterparties = db.get_counterparties(None)?;
+    let payable = Payable::load(db, invoice_id, &counterparties, on)?;
+    if let Some(problem) = payable.problem() {
+        return Err(anyhow!("No GiroCode for {}: {}", payable.label, problem));
+    }
+    let payload = girocode::payload(
+        payable.creditor_name.as_deref().unwrap_or_default(),
+        payable.details.iban.as_deref().unwrap_or_default(),
+        payable.details.bic.as_deref(),
+        payable.amount,
+        &payable.remittance(),
+    )?;
+    girocode::render_png(&payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invoice_selected_twice_is_paid_once() {
+        let dir = std::env::temp_dir().join(format!("billly-payments-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = Database::new(dir.join("test.db")).unwrap();
+        let total = Money::parse("119.00").unwrap();
+        db.upsert_invoice(&Invoice {
+            id: "p1".to_string(),
+            category: "payable".to_string(),
+            ingestion_status: "processed".to_string(),
+            invoice_number: Some("4711".to_string()),
+            counterparty_name: Some("Telekom Deutschland GmbH".to_string()),
+            total_amount: total,
+            currency: "EUR".to_string(),
+            status: "open".to_string(),
+            base_currency: Some("EUR".to_string()),
+            base_total_amount: Some(total),
+            ..Invoice::default()
+        })
+        .unwrap();
+        let details = PaymentDetails {
+            iban: Some("DE02120300000000202051".to_string()),
+            bic: None,
+            reference: None,
+            terms: None,
+            discount_percent: None,
+            discount_due_date: None,
+        };
+        db.replace_payment_details("p1", &details).unwrap();
+        let debtor = Debtor {
+            name: "Billly Test GmbH".to_string(),
+            iban: "DE75512108001245126199".to_string(),
+            bic: None,
+        };
+        let path = dir.join("pain.xml");
+        let ids = vec!["p1".to_string(), "p1".to_string()];
+
+        let summary = schedule_transfers(&db, &ids, Local::now().date_naive(), &debtor, &path).unwrap();
+        assert_eq!(summary.transfers, 1);
+        assert_eq!(summary.total, total);
+        let xml = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(xml.matches("<CdtTrfTxInf>").count(), 1);
+        assert_eq!(db.get_payment_orders("p1").unwrap().len(), 1);
+        assert_eq!(db.get_open_payables_total("EUR").unwrap(), Money::ZERO);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,354 @@
+use anyhow::Result;
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Document, Object, Stream, StringFormat};
+use std::path::Path;
+
+use crate::utils::encode_windows_1252;
+
+/// A4 in points.
+pub const PAGE_WIDTH: f32 = 595.0;
+pub const PAGE_HEIGHT: f32 = 842.0;
+/// Left and right margins as in DIN 5008 (25 mm and 20 mm).
+pub const MARGIN_LEFT: f32 = 71.0;
+pub const MARGIN_RIGHT: f32 = 57.0;
+const MARGIN_TOP: f32 = 57.0;
+/// Leaves room for the footer.
+const MARGIN_BOTTOM: f32 = 85.0;
+const FOOTER_Y: f32 = 50.0;
+const FOOTER_SIZE: f32 = 7.5;
+const LINE_HEIGHT: f32 = 1.4;
+const FACTUR_X_FILE_NAME: &str = "factur-x.xml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Font {
+    Regular,
+    Bold,
+}
+
+impl Font {
+    fn resource(self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+        }
+    }
+}
+
+/// A simple document with the standard Helvetica fonts: absolute text and
+/// lines, plus paragraphs that flow down the page from a cursor and break
+/// onto new pages. Coordinates are in points from the bottom left.
+pub struct PdfWriter {
+    pages: Vec<Vec<Operation>>,
+    cursor: f32,
+    footer: Vec<String>,
+    factur_x: Option<String>,
+}
+
+impl Default for PdfWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PdfWriter {
+    pub fn new() -> Self {
+        PdfWriter {
+            pages: vec![Vec::new()],
+            cursor: PAGE_HEIGHT - MARGIN_TOP,
+            footer: Vec::new(),
+            factur_x: None,
+        }
+    }
+
+    /// Where the next paragraph starts.
+    pub fn cursor(&self) -> f32 {
+        self.cursor
+    }
+
+    pub fn set_cursor(&mut self, y: f32) {
+        self.cursor = y;
+    }
+
+    /// Lines printed small at the bottom of every page.
+    pub fn set_footer(&mut self, lines: Vec<String>) {
+        self.footer = lines;
+    }
+
+    /// Embeds a CII invoice as `factur-x.xml` with the Factur-X / ZUGFeRD
+    /// XMP metadata (profile EN 16931), so readers take the data from the
+    /// XML. The fonts are not embedded, so the file is not a validated
+    /// PDF/A-3.
+    pub fn embed_factur_x(&mut self, xml: String) {
+        self.factur_x = Some(xml);
+    }
+
+    /// Letter header after DIN 5008 form B: the sender top right, the
+    /// sender line above the address field, then the recipient.
+    pub fn letterhead(&mut self, sender_name: &str, sender_address: &[String], recipient: &[String]) {
+        let right = PAGE_WIDTH - MARGIN_RIGHT;
+        let mut y = 785.0;
+        self.text_right(right, y, 12.0, Font::Bold, sender_name);
+        for line in sender_address {
+            y -= 12.0;
+            self.text_right(right, y, 9.0, Font::Regular, line);
+        }
+
+        let mut sender = vec![sender_name.to_string()];
+        sender.extend(sender_address.iter().cloned());
+        self.text(MARGIN_LEFT, 705.0, 7.0, Font::Regular, &sender.join(" · "));
+        self.line(MARGIN_LEFT, 702.0, MARGIN_LEFT + 240.0, 702.0);
+        let mut y = 686.0;
+        for line in recipient {
+            self.text(MARGIN_LEFT, y, 10.0, Font::Regular, line);
+            y -= 13.0;
+        }
+    }
+
+    pub fn text(&mut self, x: f32, y: f32, size: f32, font: Font, text: &str) {
+        let operations = self.pages.last_mut().expect("at least one page");
+        operations.extend(text_operations(x, y, size, font, text));
+    }
+
+    /// Text ending at `x`, for amounts in columns.
+    pub fn text_right(&mut self, x: f32, y: f32, size: f32, font: Font, text: &str) {
+        self.text(x - text_width(text, size, font), y, size, font, text);
+    }
+
+    pub fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32) {
+        let operations = self.pages.last_mut().expect("at least one page");
+        operations.push(Operation::new("w", vec![0.5.into()]));
+        operations.push(Operation::new("m", vec![x1.into(), y1.into()]));
+        operations.push(Operation::new("l", vec![x2.into(), y2.into()]));
+        operations.push(Operation::new("S", vec![]));
+    }
+
+    /// Writes `text` at the cursor between the margins, wrapping at word
+    /// boundaries. Line breaks in `text` are kept; empty lines add space.
+    pub fn paragraph(&mut self, text: &str, size: f32, font: Font) {
+        let width = PAGE_WIDTH - MARGIN_LEFT - MARGIN_RIGHT;
+        for source_line in text.lines() {
+            for line in wrap(source_line, width, size, font) {
+                self.ensure_space(size * LINE_HEIGHT);
+                self.cursor -= size * LINE_HEIGHT;
+                self.text(MARGIN_LEFT, self.cursor, size, font, &line);
+            }
+        }
+    }
+
+    /// Moves the cursor down, starting a new page when it would run into the
+    /// footer.
+    pub fn skip(&mut self, height: f32) {
+        self.ensure_space(height);
+        self.cursor -= height;
+    }
+
+    /// Starts a new page unless `height` still fits above the bottom margin.
+    pub fn ensure_space(&mut self, height: f32) {
+        if self.cursor - height < MARGIN_BOTTOM {
+            self.new_page();
+        }
+    }
+
+    pub fn new_page(&mut self) {
+        self.pages.push(Vec::new());
+        self.cursor = PAGE_HEIGHT - MARGIN_TOP;
+    }
+
+    pub fn save(mut self, path: &Path) -> Result<()> {
+        let page_count = self.pages.len();
+        for (index, operations) in self.pages.iter_mut().enumerate() {
+            for (line_index, line) in self.footer.iter().enumerate() {
+                let y = FOOTER_Y - line_index as f32 * FOOTER_SIZE * LINE_HEIGHT;
+                operations.extend(text_operations(MARGIN_LEFT, y, FOOTER_SIZE, Font::Regular, line));
+            }
+            if page_count > 1 {
+                let number = format!("{} / {}", index + 1, page_count);
+                let x = PAGE_WIDTH - MARGIN_RIGHT - text_width(&number, FOOTER_SIZE, Font::Regular);
+                operations.extend(text_operations(x, FOOTER_Y, FOOTER_SIZE, Font::Regular, &number));
+            }
+        }
+
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let font = |base: &str| {
+            dictionary! {
+                "Type" => "Font",
+                "Subtype" => "Type1",
+                "BaseFont" => base,
+                "Encoding" => "WinAnsiEncoding",
+            }
+        };
+        let regular_id = doc.add_object(font("Helvetica"));
+        let bold_id = doc.add_object(font("Helvetica-Bold"));
+        let resources_id = doc.add_object(dictionary! {
+            "Font" => dictionary! {
+                "F1" => regular_id,
+                "F2" => bold_id,
+            },
+        });
+
+        let mut kids = Vec::new();
+        for operations in self.pages {
+            let content = Content { operations };
+            let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode()?));
+            let page_id = doc.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "Contents" => content_id,
+            });
+            kids.push(page_id.into());
+        }
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Count" => kids.len() as i64,
+                "Kids" => kids,
+                "Resources" => resources_id,
+                "MediaBox" => vec![0.into(), 0.into(), PAGE_WIDTH.into(), PAGE_HEIGHT.into()],
+            }),
+        );
+        let mut catalog = dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        };
+        if let Some(xml) = self.factur_x.take() {
+            let size = xml.len() as i64;
+            let file_id = doc.add_object(Stream::new(
+                dictionary! {
+                    "Type" => "EmbeddedFile",
+                    "Subtype" => Object::Name(b"text/xml".to_vec()),
+                    "Params" => dictionary! { "Size" => size },
+                },
+                xml.into_bytes(),
+            ));
+            let spec_id = doc.add_object(dictionary! {
+                "Type" => "Filespec",
+                "F" => Object::string_literal(FACTUR_X_FILE_NAME),
+                "UF" => Object::string_literal(FACTUR_X_FILE_NAME),
+                "Desc" => Object::string_literal("Factur-X/ZUGFeRD invoice"),
+                "AFRelationship" => "Alternative",
+                "EF" => dictionary! { "F" => file_id, "UF" => file_id },
+            });
+            catalog.set(
+                "Names",
+                dictionary! {
+                    "EmbeddedFiles" => dictionary! {
+                        "Names" => vec![Object::string_literal(FACTUR_X_FILE_NAME), spec_id.into()],
+                    },
+                },
+            );
+            catalog.set("AF", vec![spec_id.into()]);
+            let metadata = Stream::new(
+                dictionary! { "Type" => "Metadata", "Subtype" => "XML" },
+                factur_x_metadata().into_bytes(),
+            )
+            .with_compression(false);
+            catalog.set("Metadata", doc.add_object(metadata));
+        }
+        let catalog_id = doc.add_object(catalog);
+        doc.trailer.set("Root", catalog_id);
+        doc.compress();
+        doc.save(path)?;
+        Ok(())
+    }
+}
+
+/// XMP packet with the Factur-X extension fields readers look for.
+fn factur_x_metadata() -> String {
+    format!(
+        "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n\
+         <x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n\
+         <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n\
+         <rdf:Description rdf:about=\"\" xmlns:fx=\"urn:factur-x:pdfa:CrossIndustryDocument:invoice:1p0#\">\n\
+         <fx:DocumentType>INVOICE</fx:DocumentType>\n\
+         <fx:DocumentFileName>{}</fx:DocumentFileName>\n\
+         <fx:Version>1.0</fx:Version>\n\
+         <fx:ConformanceLevel>EN 16931</fx:ConformanceLevel>\n\
+         </rdf:Description>\n\
+         </rdf:RDF>\n\
+         </x:xmpmeta>\n\
+         <?xpacket end=\"w\"?>",
+        FACTUR_X_FILE_NAME
+    )
+}
+
+fn text_operations(x: f32, y: f32, size: f32, font: Font, text: &str) -> [Operation; 5] {
+    [
+        Operation::new("BT", vec![]),
+        Operation::new("Tf", vec![font.resource().into(), size.into()]),
+        Operation::new("Td", vec![x.into(), y.into()]),
+        Operation::new("Tj", vec![Object::String(encode_windows_1252(text), StringFormat::Literal)]),
+        Operation::new("ET", vec![]),
+    ]
+}
+
+/// Width of `text` in points, from the Helvetica font metrics. Accented
+/// letters are as wide as their base letter.
+pub fn text_width(text: &str, size: f32, font: Font) -> f32 {
+    let units: u32 = text.chars().map(|c| char_width(c, font)).sum();
+    units as f32 * size / 1000.0
+}
+
+/// Splits a line into pieces no wider than `width`; a single word that is
+/// too long stays on its own line.
+pub fn wrap(line: &str, width: f32, size: f32, font: Font) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in line.split_whitespace() {
+        let candidate = if current.is_empty() {
+            word.to_string()
+        } else {
+            format!("{} {}", current, word)
+        };
+        if !current.is_empty() && text_width(&candidate, size, font) > width {
+            lines.push(std::mem::replace(&mut current, word.to_string()));
+        } else {
+            current = candidate;
+        }
+    }
+    lines.push(current);
+    lines
+}
+
+/// Helvetica and Helvetica-Bold advance widths for ASCII 32 to 126, in
+/// 1/1000 em.
+const REGULAR_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556, 556, 556, 556, 556,
+    556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833,
+    722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556,
+    556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334,
+    260, 334, 584,
+];
+const BOLD_WIDTHS: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556, 556, 556, 556, 556,
+    556, 556, 556, 333, 333, 584, 584, 584, 611, 975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833,
+    722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556, 333, 556, 611, 556, 611,
+    556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611, 611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389,
+    280, 389, 584,
+];
+
+fn char_width(c: char, font: Font) -> u32 {
+    let base = match c {
+        'ä' | 'á' | 'à' | 'â' => 'a',
+        'Ä' | 'Á' | 'À' | 'Â' => 'A',
+        'ö' | 'ó' | 'ò' | 'ô' => 'o',
+        'Ö' | 'Ó' | 'Ò' | 'Ô' => 'O',
+        'ü' | 'ú' | 'ù' | 'û' => 'u',
+        'Ü' | 'Ú' | 'Ù' | 'Û' => 'U',
+        'é' | 'è' | 'ê' => 'e',
+        'É' | 'È' | 'Ê' => 'E',
+        'ß' => return 611,
+        '–' => return 556,
+        '—' | '…' => return 1000,
+        c => c,
+    };
+    let widths = match font {
+        Font::Regular => &REGULAR_WIDTHS,
+        Font::Bold => &BOLD_WIDTHS,
+    };
+    match base as u32 {
+        code @ 32..=126 => widths[(code - 32) as usize] as u32,
+        _ => 556,
+    }
+}
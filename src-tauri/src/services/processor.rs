@@ -4,10 +4,18 @@ use std::sync::{Arc, Mutex};
 
 use crate::db::Database;
 use crate::models::{ExtractedInvoiceData, Invoice, Settings};
-use crate::services::crypto::CryptoService;
-use crate::services::openai::OpenAIExtractor;
-use crate::services::text_extraction::TextExtractor;
-use crate::utils::{format_decimal, modified_time_rfc3339, normalize_date, now_rfc3339, sha256_file};
+use crate::services::accounts;
+use crate::services::counterparties;
+use crate::services::einvoice::EInvoiceParser;
+use crate::services::evidence;
+use crate::services::exchange_rates;
+use crate::services::extraction::extractor_from_settings;
+use crate::services::payments;
+use crate::services::rule_based::{cross_check, RuleBasedExtractor};
+use crate::services::text_extraction::{ExtractedText, TextExtractor};
+use crate::services::validation;
+use crate::services::watcher::{is_pdf, is_xml};
+use crate::utils::{modified_time_rfc3339, normalize_date, now_rfc3339, sha256_file, Money};
 
 pub async fn process_invoice(
     db: &Arc<Mutex<Database>>,
@@ -45,7 +53,7 @@ pub async fn process_invoice(
         invoice_date: None,
         due_date: None,
         counterparty_name: None,
-        total_amount: "0.00".to_string(),
+        total_amount: Money::ZERO,
         currency: "EUR".to_string(),
         tax_amount: None,
         net_amount: None,
@@ -53,6 +61,12 @@ pub async fn process_invoice(
         paid_at: None,
         created_at: now.clone(),
         updated_at: now.clone(),
+        base_currency: None,
+        exchange_rate: None,
+        base_total_amount: None,
+        counterparty_id: None,
+        account_number: None,
+        account_source: None,
     });
 
     invoice.file_hash = file_hash.clone();
@@ -66,33 +80,138 @@ pub async fn process_invoice(
         db.upsert_invoice(&invoice)?;
     }
 
-    let text = TextExtractor::extract_from_pdf(path, &settings.ocr_language)?;
-    invoice.ocr_text = Some(text.clone());
+    let (data, raw_json, text) = extract_data(db, &invoice, path, category, settings).await?;
+    {
+        let db = db.lock().map_err(|_| anyhow!("DB lock poisoned"))?;
+        store_extracted(&db, &mut invoice, data, raw_json, text, settings, "process")?;
+    }
 
-    let api_key = settings
-        .openai_api_key
-        .as_ref()
-        .ok_or_else(|| anyhow!("OpenAI API key missing"))?;
-    let decrypted_key = CryptoService::decrypt_api_key(api_key)?;
+    Ok(invoice)
+}
 
-    let (data, raw_json) = OpenAIExtractor::extract_invoice_data(&decrypted_key, &text).await?;
-    apply_extracted(&mut invoice, data, raw_json);
+/// Validates extracted data and stores it with the invoice: amounts and
+/// dates, currency conversion, counterparty, account, pages, evidence, line
+/// items, VAT breakdown and payment details, all or nothing. Logs `action` as
+/// successful.
+pub fn store_extracted(
+    db: &Database,
+    invoice: &mut Invoice,
+    mut data: ExtractedInvoiceData,
+    raw_json: String,
+    text: ExtractedText,
+    settings: &Settings,
+    action: &str,
+) -> Result<()> {
+    validation::check_line_items(&mut data);
+    validation::check_vat_breakdown(&mut data);
+    validation::check_payment_details(&mut data);
+    let full_text = text.full_text();
+    let evidence = evidence::collect(&data, &full_text);
+    let line_items = data.line_items.clone();
+    let vat_breakdown = data.vat_breakdown.clone();
+    let payment = payments::from_extracted(&data);
+    invoice.ocr_text = Some(full_text);
+    apply_extracted(invoice, data, raw_json);
     invoice.ingestion_status = "processed".to_string();
     invoice.updated_at = now_rfc3339();
 
-    {
-        let db = db.lock().map_err(|_| anyhow!("DB lock poisoned"))?;
-        db.upsert_invoice(&invoice)?;
-        db.log_processing(
-            Some(&invoice.id),
-            Some(&invoice.file_hash),
-            "process",
-            "success",
-            None,
-        )?;
+    // One transaction, so a failure cannot leave a half-stored invoice for
+    // reconciliation and dunning to act on.
+    db.in_transaction(|| {
+        exchange_rates::apply_corrected_conversion(db, invoice, &settings.base_currency)?;
+        counterparties::link_invoice(db, invoice)?;
+        if let (Some(counterparty_id), Some(iban)) = (invoice.counterparty_id.as_deref(), payment.iban.as_deref()) {
+            if invoice.category == "payable" {
+                db.fill_counterparty_bank_account(counterparty_id, iban, payment.bic.as_deref())?;
+            }
+        }
+        accounts::assign(db, invoice, &settings.chart_of_accounts)?;
+        db.upsert_invoice(invoice)?;
+        let pages: Vec<(u32, String)> = text
+            .pages
+            .into_iter()
+            .map(|page| (page.page_number, page.text))
+            .collect();
+        db.replace_invoice_pages(&invoice.id, text.method, &pages)?;
+        db.replace_field_evidence(&invoice.id, &evidence)?;
+        db.replace_line_items(&invoice.id, &line_items)?;
+        db.replace_vat_breakdown(&invoice.id, &vat_breakdown)?;
+        db.replace_payment_details(&invoice.id, &payment)?;
+        db.log_processing(Some(&invoice.id), Some(&invoice.file_hash), action, "success", None)?;
+        Ok(())
+    })
+}
+
+/// Structured e-invoices (standalone XML or embedded in a hybrid PDF) are
+/// parsed directly; PDFs and images go through text extraction and the
+/// configured extractor. The document text is returned page by page.
+async fn extract_data(
+    db: &Arc<Mutex<Database>>,
+    invoice: &Invoice,
+    path: &Path,
+    category: &str,
+    settings: &Settings,
+) -> Result<(ExtractedInvoiceData, String, ExtractedText)> {
+    if is_xml(path) {
+        let xml = std::fs::read_to_string(path)?;
+        let document = EInvoiceParser::read(&xml)?;
+        let text = ExtractedText::single_page(document.render_text(), "einvoice");
+        let data = document.into_extracted(category);
+        let raw_json = serde_json::to_string(&data)?;
+        return Ok((data, raw_json, text));
     }
 
-    Ok(invoice)
+    if is_pdf(path) {
+        if let Some((data, rendered)) = extract_embedded_einvoice(db, invoice, path, category) {
+            let text = TextExtractor::extract_text_layer(path)
+                .unwrap_or_else(|| ExtractedText::single_page(rendered, "einvoice"));
+            let raw_json = serde_json::to_string(&data)?;
+            return Ok((data, raw_json, text));
+        }
+    }
+
+    let text = TextExtractor::extract(path, &settings.ocr_language, settings.ocr_dpi)?;
+    let full_text = text.full_text();
+
+    let extractor = extractor_from_settings(settings, category)?;
+    let (mut data, raw_json) = extractor.extract_invoice_data(&full_text).await?;
+    if extractor.name() != "rules" {
+        cross_check(&mut data, &RuleBasedExtractor::new(settings, category).extract(&full_text));
+    }
+    Ok((data, raw_json, text))
+}
+
+/// Hybrid PDFs carry exact data in an embedded XML; parse failures are logged
+/// and processing falls back to text extraction.
+fn extract_embedded_einvoice(
+    db: &Arc<Mutex<Database>>,
+    invoice: &Invoice,
+    path: &Path,
+    category: &str,
+) -> Option<(ExtractedInvoiceData, String)> {
+    let result = EInvoiceParser::find_embedded_xml(path).and_then(|xml| match xml {
+        Some(xml) => EInvoiceParser::read(&xml).map(|document| {
+            let text = document.render_text();
+            Some((document.into_extracted(category), text))
+        }),
+        None => Ok(None),
+    });
+
+    match result {
+        Ok(found) => found,
+        Err(err) => {
+            if let Ok(db) = db.lock() {
+                let _ = db.log_processing(
+                    Some(&invoice.id),
+                    Some(&invoice.file_hash),
+                    "einvoice",
+                    "failed",
+                    Some(&err.to_string()),
+                );
+            }
+            None
+        }
+    }
 }
 
 pub fn mark_failed(db: &Arc<Mutex<Database>>, invoice: &mut Invoice, message: &str) -> Result<()> {
@@ -117,12 +236,12 @@ fn apply_extracted(invoice: &mut Invoice, data: ExtractedInvoiceData, raw_json:
     invoice.due_date = normalize_date(data.due_date);
     invoice.counterparty_name = data.counterparty_name;
     if let Some(total) = data.total_amount {
-        invoice.total_amount = format_decimal(total);
+        invoice.total_amount = Money::from_f64(total);
     }
     if let Some(currency) = data.currency {
         invoice.currency = currency;
     }
-    invoice.tax_amount = data.tax_amount.map(format_decimal);
-    invoice.net_amount = data.net_amount.map(format_decimal);
+    invoice.tax_amount = data.tax_amount.map(Money::from_f64);
+    invoice.net_amount = data.net_amount.map(Money::from_f64);
     invoice.confidence_score = data.confidence_score.unwrap_or(0.5);
 }
@@ -0,0 +1,672 @@
+use anyhow::{anyhow, Result};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::db::Database;
+use crate::models::{BankImportSummary, BankTransaction, Invoice};
+use crate::services::bank_import::{self, CsvMapping};
+use crate::services::bookkeeping::{apply_overrides, load_invoices, InvoiceFilter};
+use crate::services::counterparties::normalize_name;
+use crate::services::payments;
+use crate::utils::Money;
+
+const REFERENCE_WEIGHT: f64 = 0.5;
+const IBAN_WEIGHT: f64 = 0.3;
+const NAME_WEIGHT: f64 = 0.2;
+const AMOUNT_WEIGHT: f64 = 0.3;
+
+/// Matches at or above this confidence are confirmed without review, e.g.
+/// exact amount plus invoice number, or exact amount plus IBAN and name.
+const AUTOMATIC_CONFIDENCE: f64 = 0.8;
+const SUGGESTION_CONFIDENCE: f64 = 0.5;
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Most open invoices of one counterparty searched for a grouped payment
+/// without invoice numbers.
+const MAX_GROUP_CANDIDATES: usize = 12;
+
+/// Imports a statement file and reconciles all open transactions.
+pub fn import_statement(
+    db: &Database,
+    file: &Path,
+    profile: Option<&str>,
+    mapping: Option<&CsvMapping>,
+) -> Result<BankImportSummary> {
+    let (format, lines) = bank_import::parse_file(file, profile, mapping)?;
+    let mut occurrences: HashMap<String, usize> = HashMap::new();
+    let mut transactions = 0;
+    let mut duplicates = 0;
+    for line in &lines {
+        let occurrence = occurrences.entry(bank_import::import_hash(line, 0)).or_insert(0);
+        let hash = bank_import::import_hash(line, *occurrence);
+        *occurrence += 1;
+        let transaction = BankTransaction {
+            id: Uuid::new_v4().to_string(),
+            source: format.as_str().to_string(),
+            account_iban: line.account_iban.clone(),
+            booking_date: line.booking_date.format("%Y-%m-%d").to_string(),
+            value_date: line.value_date.map(|date| date.format("%Y-%m-%d").to_string()),
+            amount: line.amount,
+            currency: line.currency.to_uppercase(),
+            counterparty_name: line.counterparty_name.clone(),
+            counterparty_iban: line.counterparty_iban.clone(),
+            remittance: line.remittance.clone(),
+            reference: line.reference.clone(),
+            status: "unmatched".to_string(),
+            created_at: String::new(),
+        };
+        if db.insert_bank_transaction(&transaction, &hash)? {
+            transactions += 1;
+        } else {
+            duplicates += 1;
+        }
+    }
+
+    let (matched, suggested) = reconcile(db)?;
+    Ok(BankImportSummary {
+        path: file.to_string_lossy().to_string(),
+        format: format.as_str().to_string(),
+        transactions,
+        duplicates,
+        matched,
+        suggested,
+    })
+}
+
+/// An open invoice as seen from the bank account. `remaining` is the unpaid
+/// part after Skonto and confirmed payments; `incoming` tells whether it is
+/// settled by a credit (revenue, or a credit note from a supplier).
+struct OpenInvoice {
+    invoice: Invoice,
+    remaining: Money,
+    incoming: bool,
+    number: Option<String>,
+    names: Vec<String>,
+    iban: Option<String>,
+}
+
+struct Candidate {
+    index: usize,
+    confidence: f64,
+    reasons: Vec<&'static str>,
+    referenced: bool,
+    identified: bool,
+    exact: bool,
+}
+
+struct Allocation {
+    index: usize,
+    amount: Money,
+    confidence: f64,
+    reasons: String,
+}
+
+enum Outcome {
+    Automatic(Vec<Allocation>),
+    Suggested(Vec<Allocation>),
+    Unmatched,
+}
+
+/// Matches unmatched and suggested transactions, oldest first, against
+/// open invoices. Suggestions are recomputed; rejected pairs stay rejected.
+/// Returns the number of transactions matched automatically and those with
+/// new suggestions.
+pub fn reconcile(db: &Database) -> Result<(usize, usize)> {
+    let mut open = open_invoices(db)?;
+    let mut pending = db.get_bank_transactions(Some("unmatched"))?;
+    pending.extend(db.get_bank_transactions(Some("suggested"))?);
+    pending.sort_by(|a, b| a.booking_date.cmp(&b.booking_date));
+
+    let mut matched = 0;
+    let mut suggested = 0;
+    for transaction in pending {
+        db.delete_suggested_bank_matches(&transaction.id)?;
+        let rejected: HashSet<String> = db
+            .get_bank_matches(Some(&transaction.id))?
+            .into_iter()
+            .filter(|existing| existing.status == "rejected")
+            .map(|existing| existing.invoice_id)
+            .collect();
+        let (status, allocations) = match match_transaction(&transaction, &open, &rejected) {
+            Outcome::Automatic(allocations) => ("confirmed", allocations),
+            Outcome::Suggested(allocations) => ("suggested", allocations),
+            Outcome::Unmatched => {
+                db.set_bank_transaction_status(&transaction.id, "unmatched")?;
+                continue;
+            }
+        };
+        for allocation in &allocations {
+            let invoice_id = &open[allocation.index].invoice.id;
+            db.upsert_bank_match(
+                &transaction.id,
+                invoice_id,
+                allocation.amount,
+                allocation.confidence,
+                &allocation.reasons,
+                status,
+            )?;
+        }
+        if status == "confirmed" {
+            for allocation in &allocations {
+                open[allocation.index].remaining -= allocation.amount;
+                settle_invoice(db, &open[allocation.index].invoice.id)?;
+            }
+            db.set_bank_transaction_status(&transaction.id, "matched")?;
+            matched += 1;
+        } else {
+            db.set_bank_transaction_status(&transaction.id, "suggested")?;
+            suggested += 1;
+        }
+    }
+    Ok((matched, suggested))
+}
+
+fn open_invoices(db: &Database) -> Result<Vec<OpenInvoice>> {
+    let counterparties = db.get_counterparties(None)?;
+    let (invoices, _) = load_invoices(db, &InvoiceFilter::default())?;
+    let mut open = Vec::new();
+    for invoice in invoices {
+        if invoice.status == "paid" {
+            continue;
+        }
+        let paid: Money = db.get_invoice_payments(&invoice.id)?.into_iter().map(|(amount, _)| amount).sum();
+        let remaining = amount_due(db, &invoice)? - paid;
+        if remaining <= Money::ZERO {
+            continue;
+        }
+        let counterparty = invoice
+            .counterparty_id
+            .as_deref()
+            .and_then(|id| counterparties.iter().find(|counterparty| counterparty.id == id));
+        let mut names: Vec<String> = invoice.counterparty_name.iter().map(|name| normalize_name(name)).collect();
+        if let Some(counterparty) = counterparty {
+            names.push(normalize_name(&counterparty.name));
+            names.extend(counterparty.aliases.iter().map(|alias| normalize_name(alias)));
+        }
+        names.retain(|name| !name.is_empty());
+        names.dedup();
+        // Our own account is printed on revenue invoices, so only a payable's
+        // IBAN identifies the counterparty.
+        let iban = if invoice.category == "payable" {
+            let extracted = db.get_payment_details(&invoice.id)?;
+            let overrides = db.get_overrides(&invoice.id)?;
+            payments::payment_details(extracted.as_ref(), &overrides, counterparty).iban
+        } else {
+            counterparty.and_then(|counterparty| counterparty.iban.as_deref()).map(compact)
+        };
+        open.push(OpenInvoice {
+            incoming: (invoice.category == "revenue") == (invoice.total_amount > Money::ZERO),
+            remaining,
+            number: invoice.invoice_number.as_deref().map(compact).filter(|number| number.len() >= 3),
+            names,
+            iban,
+            invoice,
+        });
+    }
+    Ok(open)
+}
+
+fn match_transaction(transaction: &BankTransaction, open: &[OpenInvoice], rejected: &HashSet<String>) -> Outcome {
+    let amount = magnitude(transaction.amount);
+    let candidates: Vec<Candidate> = open
+        .iter()
+        .enumerate()
+        .filter(|(_, invoice)| !rejected.contains(&invoice.invoice.id))
+        .filter_map(|(index, invoice)| score(transaction, invoice, index))
+        .collect();
+    let allocate = |candidate: &Candidate, amount: Money, note: Option<&str>| Allocation {
+        index: candidate.index,
+        amount,
+        confidence: candidate.confidence,
+        reasons: candidate.reasons.iter().copied().chain(note).collect::<Vec<_>>().join(", "),
+    };
+
+    // One invoice with the exact open amount.
+    let mut exact: Vec<&Candidate> = candidates
+        .iter()
+        .filter(|candidate| candidate.exact && (candidate.referenced || candidate.identified))
+        .collect();
+    exact.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    if let Some(best) = exact.first() {
+        let unique = exact.get(1).is_none_or(|next| next.confidence < best.confidence);
+        if best.confidence >= AUTOMATIC_CONFIDENCE && unique {
+            return Outcome::Automatic(vec![allocate(best, amount, None)]);
+        }
+        if best.confidence >= SUGGESTION_CONFIDENCE {
+            return Outcome::Suggested(
+                exact
+                    .iter()
+                    .take(MAX_SUGGESTIONS)
+                    .map(|candidate| allocate(candidate, amount, None))
+                    .collect(),
+            );
+        }
+    }
+
+    // Several invoices named in the remittance and paid together.
+    let referenced: Vec<&Candidate> = candidates.iter().filter(|candidate| candidate.referenced).collect();
+    if referenced.len() >= 2 {
+        let total: Money = referenced.iter().map(|candidate| open[candidate.index].remaining).sum();
+        if total == amount {
+            return Outcome::Automatic(
+                referenced
+                    .iter()
+                    .map(|candidate| allocate(candidate, open[candidate.index].remaining, Some("grouped payment")))
+                    .collect(),
+            );
+        }
+    }
+
+    // Part of one invoice, or more than is open.
+    if let [candidate] = referenced.as_slice() {
+        let remaining = open[candidate.index].remaining;
+        if amount < remaining {
+            let allocation = allocate(candidate, amount, Some("partial payment"));
+            return if candidate.identified {
+                Outcome::Automatic(vec![allocation])
+            } else {
+                Outcome::Suggested(vec![allocation])
+            };
+        }
+        return Outcome::Suggested(vec![allocate(candidate, remaining, Some("overpayment"))]);
+    }
+
+    // Several invoices of the same counterparty without invoice numbers.
+    let identified: Vec<&Candidate> = candidates
+        .iter()
+        .filter(|candidate| candidate.identified)
+        .take(MAX_GROUP_CANDIDATES)
+        .collect();
+    let amounts: Vec<Money> = identified.iter().map(|candidate| open[candidate.index].remaining).collect();
+    if let Some(subset) = subset_with_sum(&amounts, amount).filter(|subset| subset.len() >= 2) {
+        return Outcome::Suggested(
+            subset
+                .into_iter()
+                .map(|position| allocate(identified[position], amounts[position], Some("grouped payment")))
+                .collect(),
+        );
+    }
+
+    Outcome::Unmatched
+}
+
+fn score(transaction: &BankTransaction, open: &OpenInvoice, index: usize) -> Option<Candidate> {
+    let incoming = transaction.amount > Money::ZERO;
+    if open.remaining <= Money::ZERO
+        || incoming != open.incoming
+        || !transaction.currency.eq_ignore_ascii_case(&open.invoice.currency)
+    {
+        return None;
+    }
+
+    let text = [transaction.remittance.as_deref(), transaction.reference.as_deref()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let referenced = open.number.as_deref().is_some_and(|number| mentions(&text, number));
+    let iban = match (transaction.counterparty_iban.as_deref(), open.iban.as_deref()) {
+        (Some(paid_by), Some(known)) => compact(paid_by) == known,
+        _ => false,
+    };
+    let name = transaction
+        .counterparty_name
+        .as_deref()
+        .map(normalize_name)
+        .is_some_and(|paid_by| open.names.iter().any(|known| similar_names(&paid_by, known)))
+        || open
+            .names
+            .iter()
+            .any(|known| known.len() >= 5 && normalize_name(&text).contains(known.as_str()));
+    let exact = magnitude(transaction.amount) == open.remaining;
+
+    let mut confidence = 0.0;
+    let mut reasons = Vec::new();
+    for (hit, weight, reason) in [
+        (referenced, REFERENCE_WEIGHT, "invoice number"),
+        (iban, IBAN_WEIGHT, "IBAN"),
+        (name, NAME_WEIGHT, "name"),
+        (exact, AMOUNT_WEIGHT, "amount"),
+    ] {
+        if hit {
+            confidence += weight;
+            reasons.push(reason);
+        }
+    }
+    Some(Candidate {
+        index,
+        confidence: f64::min(confidence, 1.0),
+        reasons,
+        referenced,
+        identified: iban || name,
+        exact,
+    })
+}
+
+/// Whether `text` contains the compacted invoice number: as a word of its
+/// own, or inside a longer word when the number is long enough not to
+/// appear there by chance.
+fn mentions(text: &str, number: &str) -> bool {
+    text.split(|c: char| !c.is_alphanumeric() && c != '-' && c != '/')
+        .any(|word| compact(word) == number)
+        || (number.len() >= 6 && compact(text).contains(number))
+}
+
+fn similar_names(a: &str, b: &str) -> bool {
+    a == b
+        || strsim::jaro_winkler(a, b) >= 0.9
+        || (a.len().min(b.len()) >= 5 && (a.contains(b) || b.contains(a)))
+}
+
+/// Upper-case letters and digits only, so `RE-2024/001` and `re2024001`
+/// compare equal.
+fn compact(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+fn magnitude(amount: Money) -> Money {
+    if amount < Money::ZERO {
+        -amount
+    } else {
+        amount
+    }
+}
+
+/// Positions of some amounts adding up to `target`, trying smaller groups
+/// first.
+fn subset_with_sum(amounts: &[Money], target: Money) -> Option<Vec<usize>> {
+    let count = amounts.len();
+    let mut masks: Vec<u32> = (1..(1u32 << count)).collect();
+    masks.sort_by_key(|mask| mask.count_ones());
+    masks.into_iter().find_map(|mask| {
+        let positions: Vec<usize> = (0..count).filter(|position| mask & (1 << position) != 0).collect();
+        let total: Money = positions.iter().map(|position| amounts[*position]).sum();
+        (total == target).then_some(positions)
+    })
+}
+
+/// Confirms a suggested match and settles the invoice if it is now paid.
+pub fn confirm_match(db: &Database, match_id: &str) -> Result<()> {
+    let found = find_match(db, match_id)?;
+    db.set_bank_match_status(match_id, "confirmed")?;
+    settle_invoice(db, &found.invoice_id)?;
+    refresh_transaction(db, &found.transaction_id)
+}
+
+/// Rejects a match so it is not suggested again. A confirmed match is taken
+/// back, and an invoice it had marked as paid is open again.
+pub fn reject_match(db: &Database, match_id: &str) -> Result<()> {
+    let found = find_match(db, match_id)?;
+    db.set_bank_match_status(match_id, "rejected")?;
+    if found.status == "confirmed" {
+        reopen_invoice(db, &found.invoice_id)?;
+    }
+    refresh_transaction(db, &found.transaction_id)
+}
+
+/// Assigns a transaction to invoices by hand. The amount is spread over the
+/// invoices in the given order, each up to its open amount; the last one
+/// takes the rest.
+pub fn assign(db: &Database, transaction_id: &str, invoice_ids: &[String]) -> Result<()> {
+    let transaction = db
+        .get_bank_transactions(None)?
+        .into_iter()
+        .find(|transaction| transaction.id == transaction_id)
+        .ok_or_else(|| anyhow!("Bank transaction not found"))?;
+    let mut left = magnitude(transaction.amount);
+    for invoice_id in invoice_ids {
+        if left <= Money::ZERO {
+            break;
+        }
+        let invoice = load_invoice(db, invoice_id)?;
+        let paid: Money = db.get_invoice_payments(invoice_id)?.into_iter().map(|(amount, _)| amount).sum();
+        let open = amount_due(db, &invoice)? - paid;
+        let amount = if invoice_id == invoice_ids.last().unwrap_or(invoice_id) {
+            left
+        } else {
+            open.min(left)
+        };
+        db.upsert_bank_match(transaction_id, invoice_id, amount, 1.0, "manual", "confirmed")?;
+        settle_invoice(db, invoice_id)?;
+        left -= amount;
+    }
+    refresh_transaction(db, transaction_id)
+}
+
+/// Marks a transaction as not related to any invoice (fees, transfers).
+pub fn ignore(db: &Database, transaction_id: &str) -> Result<()> {
+    db.delete_suggested_bank_matches(transaction_id)?;
+    db.set_bank_transaction_status(transaction_id, "ignored")?;
+    Ok(())
+}
+
+fn find_match(db: &Database, match_id: &str) -> Result<crate::models::BankMatch> {
+    db.get_bank_matches(None)?
+        .into_iter()
+        .find(|candidate| candidate.id == match_id)
+        .ok_or_else(|| anyhow!("Match not found"))
+}
+
+fn load_invoice(db: &Database, invoice_id: &str) -> Result<Invoice> {
+    let mut invoice = db
+        .get_invoice_by_id(invoice_id)?
+        .ok_or_else(|| anyhow!("Invoice not found"))?;
+    let overrides = db.get_overrides(invoice_id)?;
+    apply_overrides(&mut invoice, &overrides);
+    Ok(invoice)
+}
+
+/// Marks an invoice paid on the date of its last confirmed payment once the
+/// payments cover it.
+fn settle_invoice(db: &Database, invoice_id: &str) -> Result<()> {
+    let invoice = load_invoice(db, invoice_id)?;
+    let payments = db.get_invoice_payments(invoice_id)?;
+    let paid: Money = payments.iter().map(|(amount, _)| *amount).sum();
+    let due = amount_due(db, &invoice)?;
+    if let Some((_, date)) = payments.last().filter(|_| paid >= due) {
+        db.set_override(invoice_id, "status", "paid")?;
+        db.set_override(invoice_id, "paid_at", date)?;
+    }
+    Ok(())
+}
+
+/// Takes back the paid status once a confirmed payment was withdrawn and
+/// the rest no longer covers the invoice. A payable with a transfer on file
+/// goes back to `scheduled`.
+fn reopen_invoice(db: &Database, invoice_id: &str) -> Result<()> {
+    let invoice = load_invoice(db, invoice_id)?;
+    let paid: Money = db.get_invoice_payments(invoice_id)?.into_iter().map(|(amount, _)| amount).sum();
+    if invoice.status == "paid" && paid < amount_due(db, &invoice)? {
+        if db.get_payment_orders(invoice_id)?.is_empty() {
+            db.clear_override(invoice_id, "status")?;
+        } else {
+            db.set_override(invoice_id, "status", "scheduled")?;
+        }
+        db.clear_override(invoice_id, "paid_at")?;
+    }
+    Ok(())
+}
+
+/// What settles an invoice: its total, less Skonto deducted in the latest
+/// SEPA transfer ordered for it.
+fn amount_due(db: &Database, invoice: &Invoice) -> Result<Money> {
+    let discount = db
+        .get_payment_orders(&invoice.id)?
+        .first()
+        .map(|order| order.discount)
+        .unwrap_or(Money::ZERO);
+    Ok(magnitude(invoice.total_amount) - discount)
+}
+
+/// Drops other suggestions once confirmed matches cover the transaction and
+/// derives its status from its matches.
+fn refresh_transaction(db: &Database, transaction_id: &str) -> Result<()> {
+    let transaction = db
+        .get_bank_transactions(None)?
+        .into_iter()
+        .find(|transaction| transaction.id == transaction_id)
+        .ok_or_else(|| anyhow!("Bank transaction not found"))?;
+    let matches = db.get_bank_matches(Some(transaction_id))?;
+    let confirmed: Money = matches
+        .iter()
+        .filter(|found| found.status == "confirmed")
+        .map(|found| found.amount)
+        .sum();
+    if confirmed >= magnitude(transaction.amount) {
+        db.delete_suggested_bank_matches(transaction_id)?;
+    }
+    let status = if confirmed > Money::ZERO {
+        "matched"
+    } else if matches.iter().any(|found| found.status == "suggested") {
+        "suggested"
+    } else {
+        "unmatched"
+    };
+    db.set_bank_transaction_status(transaction_id, status)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(id: &str, category: &str, number: Option<&str>, name: &str, total: &str) -> OpenInvoice {
+        let total = Money::parse(total).unwrap();
+        let invoice = Invoice {
+            id: id.to_string(),
+            category: category.to_string(),
+            invoice_number: number.map(str::to_string),
+            counterparty_name: Some(name.to_string()),
+            total_amount: total,
+            currency: "EUR".to_string(),
+            status: "open".to_string(),
+            ..Invoice::default()
+        };
+        OpenInvoice {
+            remaining: total,
+            incoming: category == "revenue",
+            number: number.map(compact),
+            names: vec![normalize_name(name)],
+            iban: None,
+            invoice,
+        }
+    }
+
+    fn transaction(amount: &str, name: Option<&str>, remittance: &str) -> BankTransaction {
+        BankTransaction {
+            id: "t1".to_string(),
+            source: "camt053".to_string(),
+            account_iban: None,
+            booking_date: "2024-03-15".to_string(),
+            value_date: None,
+            amount: Money::parse(amount).unwrap(),
+            currency: "EUR".to_string(),
+            counterparty_name: name.map(str::to_string),
+            counterparty_iban: None,
+            remittance: Some(remittance.to_string()),
+            reference: None,
+            status: "unmatched".to_string(),
+            created_at: String::new(),
+        }
+    }
+
+    fn summary(allocations: &[Allocation]) -> Vec<(usize, String)> {
+        allocations.iter().map(|allocation| (allocation.index, allocation.amount.to_string())).collect()
+    }
+
+    fn revenue() -> Vec<OpenInvoice> {
+        vec![
+            open("r1", "revenue", Some("RE-2024-001"), "Alpha GmbH", "119.00"),
+            open("r2", "revenue", Some("RE-2024-002"), "Alpha GmbH", "81.00"),
+        ]
+    }
+
+    #[test]
+    fn exact_payment_is_matched_automatically() {
+        let payment = transaction("119.00", Some("ALPHA GMBH"), "Rechnung RE 2024/001");
+        match match_transaction(&payment, &revenue(), &HashSet::new()) {
+            Outcome::Automatic(allocations) => {
+                assert_eq!(summary(&allocations), vec![(0, "119.00".to_string())]);
+                assert_eq!(allocations[0].reasons, "invoice number, name, amount");
+            }
+            _ => panic!("expected an automatic match"),
+        }
+    }
+
+    #[test]
+    fn rejected_and_opposite_direction_do_not_match() {
+        let refund = transaction("-119.00", Some("Alpha GmbH"), "RE-2024-001");
+        assert!(matches!(match_transaction(&refund, &revenue(), &HashSet::new()), Outcome::Unmatched));
+
+        let payment = transaction("119.00", Some("Alpha GmbH"), "RE-2024-001");
+        let rejected = HashSet::from(["r1".to_string()]);
+        assert!(matches!(match_transaction(&payment, &revenue(), &rejected), Outcome::Unmatched));
+    }
+
+    #[test]
+    fn partial_payment_needs_a_known_payer_to_be_automatic() {
+        let payment = transaction("50.00", Some("Alpha GmbH"), "Anzahlung RE-2024-001");
+        match match_transaction(&payment, &revenue(), &HashSet::new()) {
+            Outcome::Automatic(allocations) => {
+                assert_eq!(summary(&allocations), vec![(0, "50.00".to_string())]);
+                assert!(allocations[0].reasons.ends_with("partial payment"));
+            }
+            _ => panic!("expected an automatic partial payment"),
+        }
+
+        let anonymous = transaction("50.00", None, "Anzahlung RE-2024-001");
+        assert!(matches!(
+            match_transaction(&anonymous, &revenue(), &HashSet::new()),
+            Outcome::Suggested(allocations) if summary(&allocations) == vec![(0, "50.00".to_string())]
+        ));
+
+        let overpaid = transaction("150.00", None, "RE-2024-001");
+        assert!(matches!(
+            match_transaction(&overpaid, &revenue(), &HashSet::new()),
+            Outcome::Suggested(allocations) if allocations[0].reasons.ends_with("overpayment")
+        ));
+    }
+
+    #[test]
+    fn referenced_invoices_paid_together_are_split() {
+        let payment = transaction("200.00", None, "RE-2024-001, RE-2024-002");
+        match match_transaction(&payment, &revenue(), &HashSet::new()) {
+            Outcome::Automatic(allocations) => assert_eq!(
+                summary(&allocations),
+                vec![(0, "119.00".to_string()), (1, "81.00".to_string())]
+            ),
+            _ => panic!("expected an automatic grouped payment"),
+        }
+    }
+
+    #[test]
+    fn payments_to_one_supplier_without_numbers_are_suggested_as_group() {
+        let bills = vec![
+            open("p1", "payable", None, "Office Shop", "30.00"),
+            open("p2", "payable", None, "Office Shop", "70.00"),
+            open("p3", "payable", None, "Office Shop", "45.00"),
+            open("p4", "payable", None, "Telekom", "75.00"),
+        ];
+        let transfer = transaction("-75.00", Some("Office Shop GmbH"), "Sammelueberweisung");
+        match match_transaction(&transfer, &bills, &HashSet::new()) {
+            Outcome::Suggested(allocations) => assert_eq!(
+                summary(&allocations),
+                vec![(0, "30.00".to_string()), (2, "45.00".to_string())]
+            ),
+            _ => panic!("expected a grouped suggestion"),
+        }
+    }
+
+    #[test]
+    fn subset_prefers_fewest_amounts() {
+        let amounts: Vec<Money> = ["10.00", "20.00", "30.00"].iter().map(|raw| Money::parse(raw).unwrap()).collect();
+        assert_eq!(subset_with_sum(&amounts, Money::parse("30.00").unwrap()), Some(vec![2]));
+        assert_eq!(subset_with_sum(&amounts, Money::parse("40.00").unwrap()), Some(vec![0, 2]));
+        assert_eq!(subset_with_sum(&amounts, Money::parse("45.00").unwrap()), None);
+    }
+}
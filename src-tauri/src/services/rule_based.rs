@@ -0,0 +1,920 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use regex::{Match, Regex};
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::LazyLock;
+
+use crate::models::{ExtractedInvoiceData, ExtractedVatEntry, Settings};
+use crate::services::extraction::InvoiceExtractor;
+use crate::services::sepa;
+use crate::utils::{normalize_date, round_cents};
+
+const HEADER_LINES: usize = 25;
+const AMOUNT_TOLERANCE: f64 = 0.015;
+const MISMATCH_CONFIDENCE: f64 = 0.3;
+
+/// Deterministic extractor for common German/EU invoice layouts.
+/// Works fully offline and doubles as a plausibility check for LLM output.
+#[derive(Default)]
+pub struct RuleBasedExtractor {
+    /// On revenue invoices our own letterhead comes first, so it is not taken
+    /// as the counterparty.
+    own_company: Option<String>,
+}
+
+/// Compiled once; extraction and every evidence lookup use them.
+static PATTERNS: LazyLock<Patterns> = LazyLock::new(Patterns::new);
+
+struct Patterns {
+    amount: Regex,
+    percent: Regex,
+    date_numeric: Regex,
+    date_iso: Regex,
+    date_slash: Regex,
+    date_named: Regex,
+    invoice_number: Regex,
+    invoice_date_label: Regex,
+    date_weak_label: Regex,
+    due_date_label: Regex,
+    total_label: Regex,
+    total_weak_label: Regex,
+    net_label: Regex,
+    tax_label: Regex,
+    inclusive_label: Regex,
+    legal_form: Regex,
+    iban: Regex,
+    iban_label: Regex,
+    bic: Regex,
+    reference_label: Regex,
+    terms_label: Regex,
+    discount_label: Regex,
+    discount_days: Regex,
+}
+
+impl Patterns {
+    fn new() -> Self {
+        Patterns {
+            amount: Regex::new(r"-?\d{1,3}(?:[.,]\d{3})+[.,]\d{2}\b|-?\d+[.,]\d{2}\b").unwrap(),
+            percent: Regex::new(r"(\d{1,2}(?:[.,]\d{1,2})?)\s*%").unwrap(),
+            date_numeric: Regex::new(r"\b(\d{1,2})\.\s?(\d{1,2})\.\s?(\d{4}|\d{2})\b").unwrap(),
+            date_iso: Regex::new(r"\b(\d{4})-(\d{2})-(\d{2})\b").unwrap(),
+            date_slash: Regex::new(r"\b(\d{1,2})/(\d{1,2})/(\d{4})\b").unwrap(),
+            date_named: Regex::new(
+                r"(?i)\b(\d{1,2})\.?\s+(januar|februar|märz|maerz|april|mai|juni|juli|august|september|oktober|november|dezember|january|february|march|may|june|july|october|december|jan|feb|mär|mar|apr|jun|jul|aug|sept|sep|okt|oct|nov|dez|dec)\.?\s+(\d{4})\b",
+            )
+            .unwrap(),
+            invoice_number: Regex::new(
+                r"(?i)(?:rechnungs\s*-?\s*(?:nummer|nr\.?)|rechnung\s+nr\.?|invoice\s*(?:no\.?|number|nr\.?|#)|beleg\s*-?\s*(?:nummer|nr\.?)|re\.\s*-?\s*nr\.?)\s*[:#.]?\s*([A-Za-z0-9][A-Za-z0-9\-/_.]*[A-Za-z0-9])",
+            )
+            .unwrap(),
+            invoice_date_label: Regex::new(r"(?i)rechnungsdatum|belegdatum|invoice\s+date").unwrap(),
+            date_weak_label: Regex::new(r"(?i)\bdatum\b|\bdate\b").unwrap(),
+            due_date_label: Regex::new(
+                r"(?i)fällig(?:keitsdatum|\s+am|\s+bis)?|zahlbar\s+bis|zahlungsziel|due\s+date|payable\s+by|due\s+on",
+            )
+            .unwrap(),
+            total_label: Regex::new(
+                r"(?i)gesamtbetrag|rechnungsbetrag|bruttobetrag|endbetrag|gesamtsumme|summe\s+brutto|brutto\s*summe|gesamt\s+brutto|zu\s+zahlen|zahlbetrag|total\s+amount|amount\s+due|grand\s+total|total\s+due",
+            )
+            .unwrap(),
+            total_weak_label: Regex::new(r"(?i)\b(?:gesamt|total|summe|brutto)\b").unwrap(),
+            net_label: Regex::new(
+                r"(?i)nettobetrag|summe\s+netto|netto\s*summe|gesamt\s+netto|zwischensumme|subtotal|net\s+amount|total\s+net|\bnetto\b",
+            )
+            .unwrap(),
+            tax_label: Regex::new(
+                r"(?i)umsatzsteuer|mehrwertsteuer|\bmwst\b|\bust\b|\bvat\b|sales\s+tax|\btax\b",
+            )
+            .unwrap(),
+            inclusive_label: Regex::new(r"(?i)\binkl\b|\bincl\b|\binklusive\b|\bincluding\b").unwrap(),
+            legal_form: Regex::new(
+                r"\b(?:GmbH|mbH|AG|UG|KG|OHG|GbR|e\.\s?K\.|e\.\s?V\.|Ltd\.?|Limited|Inc\.?|LLC|S\.A\.|SARL|S\.r\.l\.|B\.V\.|N\.V\.|SE)(?:\s|$|,|&)",
+            )
+            .unwrap(),
+            iban: Regex::new(r"\b[A-Z]{2}\d{2}(?:\s?[A-Z0-9]{4}){2,7}(?:\s?[A-Z0-9]{1,3})?\b").unwrap(),
+            iban_label: Regex::new(r"(?i)\biban\b").unwrap(),
+            bic: Regex::new(
+                r"(?i:\bbic\b|\bswift\b)(?:\s*[/-]?\s*(?i:bic|swift|code))?\s*[:.]?\s*([A-Z]{6}[A-Z0-9]{2}(?:[A-Z0-9]{3})?)\b",
+            )
+            .unwrap(),
+            reference_label: Regex::new(
+                r"(?i)verwendungszweck|zahlungsreferenz|überweisungsreferenz|payment\s+reference",
+            )
+            .unwrap(),
+            terms_label: Regex::new(
+                r"(?i)zahlungsbedingung(?:en)?|zahlungsziel|zahlbar\s+(?:innerhalb|binnen|sofort|ohne|rein|netto)|payment\s+terms|payable\s+within",
+            )
+            .unwrap(),
+            discount_label: Regex::new(r"(?i)skonto|early\s+payment\s+discount|cash\s+discount").unwrap(),
+            discount_days: Regex::new(r"(?i)(?:innerhalb|binnen|within)\s+(?:von\s+)?(\d{1,3})\s+(?:tagen|tage|days)")
+                .unwrap(),
+        }
+    }
+}
+
+impl RuleBasedExtractor {
+    pub fn new(settings: &Settings, category: &str) -> Self {
+        let own_company = settings
+            .company_name
+            .as_deref()
+            .map(|name| name.trim().to_lowercase())
+            .filter(|name| category == "revenue" && !name.is_empty());
+        RuleBasedExtractor { own_company }
+    }
+
+    pub fn extract(&self, text: &str) -> ExtractedInvoiceData {
+        let patterns = &*PATTERNS;
+        let lines: Vec<&str> = text.lines().map(|line| line.trim()).collect();
+        let mut confidence = BTreeMap::new();
+
+        let invoice_number = patterns
+            .invoice_number
+            .captures_iter(text)
+            .filter_map(|caps| caps.get(1).map(|m| m.as_str().to_string()))
+            .find(|value| value.chars().any(|c| c.is_ascii_digit()));
+        if invoice_number.is_some() {
+            confidence.insert("invoice_number".to_string(), 0.8);
+        }
+
+        let strong_date = labeled_date(patterns, &lines, &patterns.invoice_date_label, None);
+        let weak_date = || labeled_date(patterns, &lines, &patterns.date_weak_label, Some(&patterns.due_date_label));
+        let invoice_date = match strong_date {
+            Some(date) => {
+                confidence.insert("invoice_date".to_string(), 0.85);
+                Some(date)
+            }
+            None => match weak_date() {
+                Some(date) => {
+                    confidence.insert("invoice_date".to_string(), 0.65);
+                    Some(date)
+                }
+                None => {
+                    let first = lines.iter().find_map(|line| find_dates(patterns, line).into_iter().next());
+                    if first.is_some() {
+                        confidence.insert("invoice_date".to_string(), 0.4);
+                    }
+                    first.map(|(_, date)| date)
+                }
+            },
+        };
+
+        let due_date = labeled_date(patterns, &lines, &patterns.due_date_label, Some(&patterns.discount_label));
+        if due_date.is_some() {
+            confidence.insert("due_date".to_string(), 0.75);
+        }
+
+        let (total_amount, total_confidence) = match labeled_amounts(patterns, &lines, &patterns.total_label, None)
+            .into_iter()
+            .reduce(f64::max)
+        {
+            Some(total) => (Some(total), 0.7),
+            None => {
+                let weak = labeled_amounts(patterns, &lines, &patterns.total_weak_label, Some(&patterns.net_label))
+                    .into_iter()
+                    .reduce(f64::max);
+                (weak, 0.45)
+            }
+        };
+
+        let net_amount = labeled_amounts(patterns, &lines, &patterns.net_label, Some(&patterns.tax_label))
+            .into_iter()
+            .next();
+
+        let tax_candidates: Vec<f64> = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| {
+                patterns.tax_label.is_match(line)
+                    && !patterns.total_label.is_match(line)
+                    && !patterns.net_label.is_match(line)
+                    && !patterns.inclusive_label.is_match(line)
+            })
+            .filter_map(|(index, _)| amount_near(patterns, &lines, index))
+            .collect();
+
+        let mut tax_amount = None;
+        let mut tax_confidence = 0.0;
+        match (total_amount, net_amount) {
+            (Some(total), Some(net)) => {
+                let expected = total - net;
+                let candidate_sum: f64 = tax_candidates.iter().sum();
+                if let Some(candidate) = tax_candidates.iter().find(|c| amounts_equal(**c, expected)) {
+                    tax_amount = Some(*candidate);
+                    tax_confidence = 0.95;
+                } else if !tax_candidates.is_empty() && amounts_equal(candidate_sum, expected) {
+                    tax_amount = Some(round_cents(candidate_sum));
+                    tax_confidence = 0.85;
+                } else if expected >= 0.0 {
+                    tax_amount = Some(round_cents(expected));
+                    tax_confidence = 0.5;
+                }
+            }
+            _ => {
+                if let Some(candidate) = tax_candidates.last() {
+                    tax_amount = Some(*candidate);
+                    tax_confidence = 0.55;
+                }
+            }
+        }
+
+        let mut total_amount = total_amount;
+        let mut net_amount = net_amount;
+        let mut total_confidence = total_confidence;
+        let mut net_confidence = 0.6;
+        match (total_amount, net_amount, tax_amount) {
+            (Some(total), Some(net), Some(tax)) if amounts_equal(net + tax, total) && tax_confidence > 0.5 => {
+                total_confidence = 0.95;
+                net_confidence = 0.95;
+            }
+            (None, Some(net), Some(tax)) => {
+                total_amount = Some(round_cents(net + tax));
+                total_confidence = 0.5;
+            }
+            (Some(total), None, Some(tax)) => {
+                net_amount = Some(round_cents(total - tax));
+                net_confidence = 0.5;
+            }
+            _ => {}
+        }
+        if total_amount.is_some() {
+            confidence.insert("total_amount".to_string(), total_confidence);
+        }
+        if net_amount.is_some() {
+            confidence.insert("net_amount".to_string(), net_confidence);
+        }
+        if tax_amount.is_some() {
+            confidence.insert("tax_amount".to_string(), tax_confidence);
+        }
+
+        let vat_breakdown = vat_breakdown(patterns, &lines, net_amount);
+
+        let currency = detect_currency(text);
+        confidence.insert(
+            "currency".to_string(),
+            if currency.is_some() { 0.9 } else { 0.3 },
+        );
+
+        let counterparty_name = lines
+            .iter()
+            .take(HEADER_LINES)
+            .filter(|line| patterns.legal_form.is_match(&format!("{} ", line)))
+            .map(|line| clean_company_line(line))
+            .find(|name| !self.is_own_company(name));
+        if counterparty_name.is_some() {
+            confidence.insert("counterparty_name".to_string(), 0.5);
+        }
+
+        let payment = payment_fields(patterns, &lines);
+        for (field, value, weight) in [
+            ("iban", payment.iban.is_some(), payment.iban_confidence),
+            ("bic", payment.bic.is_some(), 0.8),
+            ("payment_reference", payment.reference.is_some(), 0.7),
+            ("payment_terms", payment.terms.is_some(), 0.7),
+            ("discount_percent", payment.discount_percent.is_some(), 0.75),
+        ] {
+            if value {
+                confidence.insert(field.to_string(), weight);
+            }
+        }
+
+        let key_fields = ["invoice_number", "invoice_date", "counterparty_name", "total_amount", "currency"];
+        let score = key_fields
+            .iter()
+            .map(|field| confidence.get(*field).copied().unwrap_or(0.0))
+            .sum::<f64>()
+            / key_fields.len() as f64;
+
+        let missing: Vec<&str> = key_fields
+            .iter()
+            .filter(|field| !confidence.contains_key(**field))
+            .copied()
+            .collect();
+        let extraction_notes = if missing.is_empty() {
+            "rule-based extraction".to_string()
+        } else {
+            format!("rule-based extraction, missing: {}", missing.join(", "))
+        };
+
+        ExtractedInvoiceData {
+            invoice_number,
+            invoice_date,
+            due_date,
+            counterparty_name,
+            counterparty_vat_id: None,
+            total_amount,
+            currency: Some(currency.unwrap_or_else(|| "EUR".to_string())),
+            tax_amount,
+            net_amount,
+            extraction_notes,
+            confidence_score: Some(score.clamp(0.0, 1.0)),
+            field_confidence: confidence,
+            line_items: Vec::new(),
+            vat_breakdown,
+            expense_category: None,
+            iban: payment.iban,
+            bic: payment.bic,
+            payment_reference: payment.reference,
+            payment_terms: payment.terms,
+            discount_percent: payment.discount_percent,
+            discount_days: payment.discount_days,
+            discount_due_date: payment.discount_due_date,
+        }
+    }
+
+    fn is_own_company(&self, name: &str) -> bool {
+        self.own_company
+            .as_deref()
+            .is_some_and(|own| name.to_lowercase().contains(own))
+    }
+}
+
+#[async_trait]
+impl InvoiceExtractor for RuleBasedExtractor {
+    fn name(&self) -> &'static str {
+        "rules"
+    }
+
+    async fn extract_invoice_data(&self, text: &str) -> Result<(ExtractedInvoiceData, String)> {
+        let data = self.extract(text);
+        let raw = serde_json::to_string(&data)?;
+        Ok((data, raw))
+    }
+}
+
+/// Compares LLM output against the rule-based result. Fields the rules are
+/// reasonably sure about and that disagree lower the overall confidence.
+pub fn cross_check(data: &mut ExtractedInvoiceData, reference: &ExtractedInvoiceData) {
+    let trusted = |field: &str| reference.field_confidence.get(field).copied().unwrap_or(0.0) >= 0.7;
+    let mut mismatches = Vec::new();
+
+    let amounts = [
+        ("total_amount", data.total_amount, reference.total_amount),
+        ("net_amount", data.net_amount, reference.net_amount),
+        ("tax_amount", data.tax_amount, reference.tax_amount),
+    ];
+    for (field, value, expected) in amounts {
+        if let (Some(value), Some(expected)) = (value, expected) {
+            if trusted(field) && !amounts_equal(value, expected) {
+                mismatches.push((field, format!("{} {:.2} vs {:.2}", field, value, expected)));
+            }
+        }
+    }
+
+    if let (Some(value), Some(expected)) = (&data.invoice_date, &reference.invoice_date) {
+        if trusted("invoice_date") && normalize_date(Some(value.clone())) != Some(expected.clone()) {
+            mismatches.push(("invoice_date", format!("invoice_date {} vs {}", value, expected)));
+        }
+    }
+
+    if let (Some(value), Some(expected)) = (&data.invoice_number, &reference.invoice_number) {
+        if trusted("invoice_number") && !value.trim().eq_ignore_ascii_case(expected) {
+            mismatches.push(("invoice_number", format!("invoice_number {} vs {}", value, expected)));
+        }
+    }
+
+    if let (Some(value), Some(expected)) = (&data.iban, &reference.iban) {
+        if let Some(value) = sepa::normalize_iban(value) {
+            if trusted("iban") && value != *expected {
+                mismatches.push(("iban", format!("iban {} vs {}", value, expected)));
+            }
+        }
+    }
+
+    if mismatches.is_empty() {
+        return;
+    }
+
+    let penalty = 0.15 * mismatches.len() as f64;
+    let score = data.confidence_score.unwrap_or(0.5) - penalty;
+    data.confidence_score = Some(score.clamp(0.1, 1.0));
+    for (field, _) in &mismatches {
+        let current = data.field_confidence.get(*field).copied().unwrap_or(1.0);
+        data.field_confidence
+            .insert(field.to_string(), current.min(MISMATCH_CONFIDENCE));
+    }
+    let messages: Vec<String> = mismatches.into_iter().map(|(_, message)| message).collect();
+    data.extraction_notes = format!(
+        "{}; rule check mismatch: {}",
+        data.extraction_notes,
+        messages.join(", ")
+    );
+}
+
+struct PaymentFields {
+    iban: Option<String>,
+    iban_confidence: f64,
+    bic: Option<String>,
+    reference: Option<String>,
+    terms: Option<String>,
+    discount_percent: Option<f64>,
+    discount_days: Option<u32>,
+    discount_due_date: Option<String>,
+}
+
+/// Bank details and payment terms. An IBAN counts only if its check digits
+/// are valid; one on a line labelled "IBAN" wins over a bare one. Skonto is
+/// read from the line mentioning it: "2 % Skonto innerhalb von 10 Tagen" or
+/// "bei Zahlung bis 12.04.2024 3% Skonto".
+fn payment_fields(patterns: &Patterns, lines: &[&str]) -> PaymentFields {
+    let ibans: Vec<(bool, String)> = lines
+        .iter()
+        .flat_map(|line| {
+            let labelled = patterns.iban_label.is_match(line);
+            find_ibans(patterns, line).into_iter().map(move |(_, iban)| (labelled, iban))
+        })
+        .collect();
+    let labelled_iban = ibans.iter().find(|(labelled, _)| *labelled).map(|(_, iban)| iban.clone());
+    let (iban, iban_confidence) = match labelled_iban {
+        Some(iban) => (Some(iban), 0.9),
+        None => (ibans.into_iter().next().map(|(_, iban)| iban), 0.7),
+    };
+
+    let bic = lines.iter().find_map(|line| {
+        patterns
+            .bic
+            .captures(line)
+            .and_then(|caps| sepa::normalize_bic(&caps[1]))
+    });
+
+    let reference = labeled_text(lines, &patterns.reference_label)
+        .map(|text| text.chars().take(140).collect::<String>())
+        .filter(|text| text.chars().any(|c| c.is_ascii_alphanumeric()));
+    let terms = lines
+        .iter()
+        .enumerate()
+        .find_map(|(index, line)| {
+            let found = patterns.terms_label.find(line)?;
+            let label = found.as_str().to_lowercase();
+            let text = if label.starts_with("zahlbar") || label.starts_with("payable") {
+                line.to_string()
+            } else {
+                labeled_text(&lines[index..], &patterns.terms_label)?
+            };
+            Some(text.chars().take(200).collect::<String>())
+        })
+        .filter(|text| !text.trim().is_empty());
+
+    let mut discount_percent = None;
+    let mut discount_days = None;
+    let mut discount_due_date = None;
+    if let Some((line, label)) = lines.iter().find_map(|line| {
+        let label = patterns.discount_label.find(line)?;
+        patterns.percent.is_match(line).then_some((line, label.start()))
+    }) {
+        // Net terms often share the line ("30 Tage netto, 10 Tage 2 % Skonto"),
+        // so each value is taken from the match closest to the Skonto label.
+        let distance = |range: Range<usize>| range.start.abs_diff(label).min(range.end.abs_diff(label));
+        discount_percent = patterns
+            .percent
+            .captures_iter(line)
+            .min_by_key(|caps| distance(caps.get(0).map(|m| m.range()).unwrap_or_default()))
+            .and_then(|caps| caps[1].replace(',', ".").parse::<f64>().ok())
+            .filter(|percent| *percent > 0.0 && *percent < 100.0);
+        discount_days = patterns
+            .discount_days
+            .captures_iter(line)
+            .min_by_key(|caps| distance(caps.get(0).map(|m| m.range()).unwrap_or_default()))
+            .and_then(|caps| caps[1].parse().ok());
+        discount_due_date = find_dates(patterns, line)
+            .into_iter()
+            .min_by_key(|(range, _)| distance(range.clone()))
+            .map(|(_, date)| date);
+    }
+    if discount_percent.is_none() {
+        discount_days = None;
+        discount_due_date = None;
+    }
+
+    PaymentFields {
+        iban,
+        iban_confidence,
+        bic,
+        reference,
+        terms,
+        discount_percent,
+        discount_days,
+        discount_due_date,
+    }
+}
+
+/// Text after the label on its line without leading separators, or the next
+/// line when the label stands alone.
+fn labeled_text(lines: &[&str], label: &Regex) -> Option<String> {
+    lines.iter().enumerate().find_map(|(index, line)| {
+        let found = label.find(line)?;
+        let rest = line[found.end()..].trim_start_matches(|c: char| c == ':' || c == '-' || c.is_whitespace());
+        if !rest.is_empty() {
+            Some(rest.trim().to_string())
+        } else {
+            lines.get(index + 1).map(|next| next.trim().to_string()).filter(|next| !next.is_empty())
+        }
+    })
+}
+
+/// IBANs with valid check digits in `line`. Printed IBANs are often followed
+/// by more text, so trailing groups are dropped until the checksum fits.
+fn find_ibans(patterns: &Patterns, line: &str) -> Vec<(Range<usize>, String)> {
+    let mut found = Vec::new();
+    for candidate in patterns.iban.find_iter(line) {
+        let groups: Vec<(usize, &str)> = candidate
+            .as_str()
+            .split_whitespace()
+            .scan(0, |offset, group| {
+                let start = candidate.as_str()[*offset..].find(group).unwrap_or(0) + *offset;
+                *offset = start + group.len();
+                Some((start, group))
+            })
+            .collect();
+        for count in (1..=groups.len()).rev() {
+            let (start, last) = groups[count - 1];
+            let end = start + last.len();
+            if let Some(iban) = sepa::normalize_iban(&candidate.as_str()[..end]) {
+                found.push((candidate.start()..candidate.start() + end, iban));
+                break;
+            }
+        }
+    }
+    found
+}
+
+fn labeled_date(patterns: &Patterns, lines: &[&str], label: &Regex, exclude: Option<&Regex>) -> Option<String> {
+    for (index, line) in lines.iter().enumerate() {
+        let Some(found) = label.find(line) else {
+            continue;
+        };
+        if exclude.map(|re| re.is_match(line)).unwrap_or(false) {
+            continue;
+        }
+        if let Some((_, date)) = find_dates(patterns, &line[found.end()..]).into_iter().next() {
+            return Some(date);
+        }
+        if let Some(next) = lines.get(index + 1) {
+            if let Some((_, date)) = find_dates(patterns, next).into_iter().next() {
+                return Some(date);
+            }
+        }
+    }
+    None
+}
+
+fn labeled_amounts(patterns: &Patterns, lines: &[&str], label: &Regex, exclude: Option<&Regex>) -> Vec<f64> {
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| label.is_match(line))
+        .filter(|(_, line)| exclude.map(|re| !re.is_match(line)).unwrap_or(true))
+        .filter_map(|(index, _)| amount_near(patterns, lines, index))
+        .collect()
+}
+
+/// Takes the last amount on the labelled line, or the first one on the next
+/// line for layouts where the value sits below its label.
+fn amount_near(patterns: &Patterns, lines: &[&str], index: usize) -> Option<f64> {
+    find_amounts(patterns, lines[index]).last().copied().or_else(|| {
+        lines
+            .get(index + 1)
+            .and_then(|next| find_amounts(patterns, next).first().copied())
+    })
+}
+
+/// Collects one entry per VAT rate from lines like "zzgl. 19% MwSt. 228,00"
+/// or "USt 7% auf 100,00: 7,00". With a single rate the net amount is the base.
+fn vat_breakdown(patterns: &Patterns, lines: &[&str], net_amount: Option<f64>) -> Vec<ExtractedVatEntry> {
+    let mut entries: Vec<ExtractedVatEntry> = Vec::new();
+
+    for (index, line) in lines.iter().enumerate() {
+        if !patterns.tax_label.is_match(line) || patterns.total_label.is_match(line) {
+            continue;
+        }
+        let Some(rate) = patterns
+            .percent
+            .captures(line)
+            .and_then(|caps| caps[1].replace(',', ".").parse::<f64>().ok())
+        else {
+            continue;
+        };
+        if entries.iter().any(|entry| entry.rate == rate) {
+            continue;
+        }
+
+        let mut amounts = find_amounts(patterns, line);
+        if amounts.is_empty() {
+            if let Some(next) = lines.get(index + 1) {
+                amounts = find_amounts(patterns, next);
+            }
+        }
+        let entry = match amounts.as_slice() {
+            [] => None,
+            [tax] => Some(ExtractedVatEntry {
+                rate,
+                net_amount: if rate > 0.0 { round_cents(tax * 100.0 / rate) } else { 0.0 },
+                tax_amount: *tax,
+            }),
+            [first, .., last] => [(*first, *last), (*last, *first)]
+                .into_iter()
+                .find(|(base, tax)| amounts_equal(round_cents(base * rate / 100.0), *tax))
+                .map(|(base, tax)| ExtractedVatEntry {
+                    rate,
+                    net_amount: base,
+                    tax_amount: tax,
+                }),
+        };
+        entries.extend(entry);
+    }
+
+    if let ([entry], Some(net)) = (entries.as_mut_slice(), net_amount) {
+        if amounts_equal(round_cents(net * entry.rate / 100.0), entry.tax_amount) {
+            entry.net_amount = net;
+        }
+    }
+    entries
+}
+
+/// Amount matches outside of dates, so "15.01.2025" does not yield 15.01.
+fn amount_matches<'a>(patterns: &'a Patterns, text: &'a str) -> impl Iterator<Item = Match<'a>> {
+    let dates: Vec<Range<usize>> = find_dates(patterns, text).into_iter().map(|(range, _)| range).collect();
+    patterns
+        .amount
+        .find_iter(text)
+        .filter(move |m| !dates.iter().any(|date| m.start() < date.end && date.start < m.end()))
+}
+
+fn find_amounts(patterns: &Patterns, line: &str) -> Vec<f64> {
+    amount_matches(patterns, line)
+        .filter(|m| !line[m.end()..].trim_start().starts_with('%'))
+        .filter_map(|m| parse_amount(m.as_str()))
+        .collect()
+}
+
+pub fn parse_amount(raw: &str) -> Option<f64> {
+    let cleaned: String = raw.chars().filter(|c| !c.is_whitespace()).collect();
+    let separator = cleaned.rfind([',', '.']);
+    let normalized = match separator {
+        Some(index) if cleaned.len() - index - 1 == 2 => {
+            let (int_part, frac_part) = cleaned.split_at(index);
+            let int_part: String = int_part.chars().filter(|c| *c != '.' && *c != ',').collect();
+            format!("{}.{}", int_part, &frac_part[1..])
+        }
+        _ => cleaned.chars().filter(|c| *c != '.' && *c != ',').collect(),
+    };
+    normalized.parse::<f64>().ok()
+}
+
+fn find_dates(patterns: &Patterns, line: &str) -> Vec<(Range<usize>, String)> {
+    let mut dates = Vec::new();
+
+    for caps in patterns.date_numeric.captures_iter(line) {
+        let year: i32 = caps[3].parse().unwrap_or(0);
+        let year = if caps[3].len() == 2 { 2000 + year } else { year };
+        if let Some(date) = build_date(year, &caps[2], &caps[1]) {
+            dates.push((caps.get(0).map(|m| m.range()).unwrap_or_default(), date));
+        }
+    }
+    for caps in patterns.date_iso.captures_iter(line) {
+        if let Some(date) = build_date(caps[1].parse().unwrap_or(0), &caps[2], &caps[3]) {
+            dates.push((caps.get(0).map(|m| m.range()).unwrap_or_default(), date));
+        }
+    }
+    for caps in patterns.date_slash.captures_iter(line) {
+        if let Some(date) = build_date(caps[3].parse().unwrap_or(0), &caps[2], &caps[1]) {
+            dates.push((caps.get(0).map(|m| m.range()).unwrap_or_default(), date));
+        }
+    }
+    for caps in patterns.date_named.captures_iter(line) {
+        let month = month_number(&caps[2].to_lowercase());
+        if let Some(date) = month.and_then(|m| build_date(caps[3].parse().unwrap_or(0), &m.to_string(), &caps[1])) {
+            dates.push((caps.get(0).map(|m| m.range()).unwrap_or_default(), date));
+        }
+    }
+
+    dates.sort_by_key(|(range, _)| range.start);
+    dates
+}
+
+/// Byte range of the first amount in `text` equal to `value`, in any of the
+/// notations the extractor understands.
+pub fn locate_amount(text: &str, value: f64) -> Option<Range<usize>> {
+    let patterns = &*PATTERNS;
+    let found = amount_matches(patterns, text).find(|m| {
+        parse_amount(m.as_str())
+            .map(|amount| amounts_equal(amount.abs(), value.abs()))
+            .unwrap_or(false)
+    });
+    found.map(|m| m.range())
+}
+
+/// Byte range of the first date in `text` matching the ISO date `date`.
+pub fn locate_date(text: &str, date: &str) -> Option<Range<usize>> {
+    let target = normalize_date(Some(date.to_string()))?;
+    let patterns = &*PATTERNS;
+    find_dates(patterns, text)
+        .into_iter()
+        .find(|(_, found)| *found == target)
+        .map(|(range, _)| range)
+}
+
+/// Byte range of `iban` in `text`, printed with or without spaces.
+pub fn locate_iban(text: &str, iban: &str) -> Option<Range<usize>> {
+    let patterns = &*PATTERNS;
+    text.split('\n')
+        .scan(0, |offset, line| {
+            let start = *offset;
+            *offset += line.len() + 1;
+            Some((start, line))
+        })
+        .find_map(|(start, line)| {
+            find_ibans(patterns, line)
+                .into_iter()
+                .find(|(_, found)| found == iban)
+                .map(|(range, _)| range.start + start..range.end + start)
+        })
+}
+
+fn build_date(year: i32, month: &str, day: &str) -> Option<String> {
+    let month: u32 = month.parse().ok()?;
+    let day: u32 = day.parse().ok()?;
+    if !(1990..=2100).contains(&year) {
+        return None;
+    }
+    NaiveDate::from_ymd_opt(year, month, day).map(|date| date.format("%Y-%m-%d").to_string())
+}
+
+fn month_number(name: &str) -> Option<u32> {
+    let month = match name {
+        "januar" | "january" | "jan" => 1,
+        "februar" | "february" | "feb" => 2,
+        "märz" | "maerz" | "march" | "mär" | "mar" => 3,
+        "april" | "apr" => 4,
+        "mai" | "may" => 5,
+        "juni" | "june" | "jun" => 6,
+        "juli" | "july" | "jul" => 7,
+        "august" | "aug" => 8,
+        "september" | "sept" | "sep" => 9,
+        "oktober" | "october" | "okt" | "oct" => 10,
+        "november" | "nov" => 11,
+        "dezember" | "december" | "dez" | "dec" => 12,
+        _ => return None,
+    };
+    Some(month)
+}
+
+fn detect_currency(text: &str) -> Option<String> {
+    let upper = text.to_uppercase();
+    let counts = [
+        ("EUR", upper.matches("EUR").count() + text.matches('€').count()),
+        ("USD", upper.matches("USD").count() + text.matches('$').count()),
+        ("GBP", upper.matches("GBP").count() + text.matches('£').count()),
+        ("CHF", upper.matches("CHF").count()),
+    ];
+    counts
+        .iter()
+        .filter(|(_, count)| *count > 0)
+        .max_by_key(|(_, count)| *count)
+        .map(|(code, _)| code.to_string())
+}
+
+fn clean_company_line(line: &str) -> String {
+    let name = line
+        .split(['·', '|', '•', ',', '\t'])
+        .next()
+        .unwrap_or(line)
+        .split("  ")
+        .next()
+        .unwrap_or(line)
+        .trim();
+    name.chars().take(80).collect()
+}
+
+fn amounts_equal(left: f64, right: f64) -> bool {
+    (left - right).abs() < AMOUNT_TOLERANCE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Case {
+        name: &'static str,
+        text: &'static str,
+        invoice_date: Option<&'static str>,
+        total: Option<f64>,
+        net: Option<f64>,
+        tax: Option<f64>,
+        counterparty: Option<&'static str>,
+    }
+
+    const CASES: &[Case] = &[
+        Case {
+            name: "german totals",
+            text: "Bürobedarf Schulz GmbH
+Lindenstraße 4, 10115 Berlin
+
+Rechnung Nr. RE-2025-0042
+Rechnungsdatum: 15.01.2025
+
+Druckerpapier A4              1.000,00
+Nettobetrag                   1.000,00 EUR
+zzgl. 19% MwSt.                 190,00 EUR
+Gesamtbetrag                  1.190,00 EUR",
+            invoice_date: Some("2025-01-15"),
+            total: Some(1190.0),
+            net: Some(1000.0),
+            tax: Some(190.0),
+            counterparty: Some("Bürobedarf Schulz GmbH"),
+        },
+        Case {
+            name: "english totals",
+            text: "Northwind Ltd.
+1 High Street, London
+
+Invoice No. INV-7781
+Invoice date: 3 March 2025
+
+Subtotal          250.00
+VAT 20%            50.00
+Total amount due  300.00 GBP",
+            invoice_date: Some("2025-03-03"),
+            total: Some(300.0),
+            net: Some(250.0),
+            tax: Some(50.0),
+            counterparty: Some("Northwind Ltd."),
+        },
+        Case {
+            name: "credit note",
+            text: "Hosting Nord GmbH
+
+Gutschrift Nr. GS-2025-007
+Datum: 02.04.2025
+
+Nettobetrag       -50,00 EUR
+19% USt            -9,50 EUR
+Gesamtbetrag      -59,50 EUR",
+            invoice_date: Some("2025-04-02"),
+            total: Some(-59.5),
+            net: Some(-50.0),
+            tax: Some(-9.5),
+            counterparty: Some("Hosting Nord GmbH"),
+        },
+        Case {
+            name: "dates next to amounts",
+            text: "Stadtwerke Musterstadt AG
+
+Rechnung Nr. 2025-118
+Rechnungsdatum 28.02.2025
+Leistungszeitraum 01.01.25 - 31.01.25
+
+Nettobetrag 70,76 EUR
+19% USt 13,44 EUR
+Gesamtbetrag fällig am 15.03.2025
+84,20 EUR",
+            invoice_date: Some("2025-02-28"),
+            total: Some(84.2),
+            net: Some(70.76),
+            tax: Some(13.44),
+            counterparty: Some("Stadtwerke Musterstadt AG"),
+        },
+    ];
+
+    #[test]
+    fn extracts_sample_invoices() {
+        for case in CASES {
+            let data = RuleBasedExtractor::default().extract(case.text);
+            assert_eq!(data.invoice_date.as_deref(), case.invoice_date, "{}: invoice date", case.name);
+            assert_eq!(data.total_amount, case.total, "{}: total", case.name);
+            assert_eq!(data.net_amount, case.net, "{}: net", case.name);
+            assert_eq!(data.tax_amount, case.tax, "{}: tax", case.name);
+            assert_eq!(data.counterparty_name.as_deref(), case.counterparty, "{}: counterparty", case.name);
+        }
+    }
+
+    #[test]
+    fn amounts_inside_dates_are_ignored() {
+        let patterns = &*PATTERNS;
+        assert_eq!(find_amounts(patterns, "Lieferung 15.01.2025 12,50"), vec![12.5]);
+        assert_eq!(find_amounts(patterns, "vom 01.02.25 bis 28.02.25"), Vec::<f64>::new());
+        assert_eq!(locate_amount("Datum 15.01.2025, Betrag 15.01 EUR", 15.01), Some(25..30));
+    }
+
+    #[test]
+    fn own_company_is_not_the_revenue_counterparty() {
+        let text = "Muster Software GmbH · Hauptstraße 1 · 80331 München
+Kunde: Beispiel Handels KG
+Rechnung Nr. RE-2025-0003
+Gesamtbetrag 119,00 EUR";
+        let dir = std::env::temp_dir().join(format!("billly-rules-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = crate::db::Database::new(dir.join("test.db")).unwrap();
+        let mut settings = crate::load_settings(&db);
+        settings.company_name = Some("Muster Software GmbH".to_string());
+
+        let revenue = RuleBasedExtractor::new(&settings, "revenue").extract(text);
+        assert_eq!(revenue.counterparty_name.as_deref(), Some("Kunde: Beispiel Handels KG"));
+
+        let payable = RuleBasedExtractor::new(&settings, "payable").extract(text);
+        assert_eq!(payable.counterparty_name.as_deref(), Some("Muster Software GmbH"));
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
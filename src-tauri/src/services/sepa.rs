@@ -0,0 +1,300 @@
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::utils::{escape_xml, Money};
+
+/// Longest `MsgId`, `PmtInfId` and `EndToEndId`.
+const MAX_ID_LENGTH: usize = 35;
+const MAX_NAME_LENGTH: usize = 70;
+const MAX_REMITTANCE_LENGTH: usize = 140;
+
+/// The IBAN in electronic format (no spaces, upper case) if its ISO 13616
+/// check digits are valid.
+pub fn normalize_iban(raw: &str) -> Option<String> {
+    let iban: String = raw
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_uppercase)
+        .collect();
+    let bytes = iban.as_bytes();
+    if !(15..=34).contains(&bytes.len())
+        || !bytes[..2].iter().all(u8::is_ascii_uppercase)
+        || !bytes[2..4].iter().all(u8::is_ascii_digit)
+        || !bytes.iter().all(u8::is_ascii_alphanumeric)
+    {
+        return None;
+    }
+    check_digits_valid(&iban).then_some(iban)
+}
+
+/// The ISO 11649 creditor reference ("RF18 5390 0754 7034") without spaces
+/// and in upper case if its check digits are valid.
+pub fn normalize_creditor_reference(raw: &str) -> Option<String> {
+    let reference: String = raw
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_uppercase)
+        .collect();
+    let valid = (5..=25).contains(&reference.len())
+        && reference.starts_with("RF")
+        && reference.bytes().all(|b| b.is_ascii_alphanumeric())
+        && check_digits_valid(&reference);
+    valid.then_some(reference)
+}
+
+/// ISO 7064 MOD 97-10 with the first four characters moved to the end, as
+/// used by IBANs and creditor references.
+fn check_digits_valid(value: &str) -> bool {
+    let mut remainder = 0u32;
+    for c in value[4..].chars().chain(value[..4].chars()) {
+        let Some(digit) = c.to_digit(36) else {
+            return false;
+        };
+        remainder = if digit >= 10 {
+            (remainder * 100 + digit) % 97
+        } else {
+            (remainder * 10 + digit) % 97
+        };
+    }
+    remainder == 1
+}
+
+/// The BIC (ISO 9362) without spaces and in upper case: 8 or 11 characters
+/// with the country code in positions 5 and 6.
+pub fn normalize_bic(raw: &str) -> Option<String> {
+    let bic: String = raw
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_uppercase)
+        .collect();
+    let bytes = bic.as_bytes();
+    let valid = (bytes.len() == 8 || bytes.len() == 11)
+        && bytes.iter().all(u8::is_ascii_alphanumeric)
+        && bytes[..6].iter().all(u8::is_ascii_uppercase);
+    valid.then_some(bic)
+}
+
+/// Reduces text to the SEPA character set (Latin letters, digits and
+/// `/ - ? : ( ) . , ' +`), spelling out umlauts, and cuts it to `max`
+/// characters.
+pub fn sepa_text(value: &str, max: usize) -> String {
+    let mut text = String::new();
+    for c in value.chars() {
+        match c {
+            'ä' => text.push_str("ae"),
+            'ö' => text.push_str("oe"),
+            'ü' => text.push_str("ue"),
+            'Ä' => text.push_str("Ae"),
+            'Ö' => text.push_str("Oe"),
+            'Ü' => text.push_str("Ue"),
+            'ß' => text.push_str("ss"),
+            '&' => text.push('+'),
+            c if c.is_ascii_alphanumeric() || "/-?:().,'+ ".contains(c) => text.push(c),
+            _ => text.push(' '),
+        }
+    }
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(max)
+        .collect()
+}
+
+/// The ordering account.
+pub struct Debtor {
+    pub name: String,
+    pub iban: String,
+    pub bic: Option<String>,
+}
+
+pub struct CreditTransfer {
+    pub end_to_end_id: String,
+    pub creditor_name: String,
+    pub iban: String,
+    pub bic: Option<String>,
+    pub amount: Money,
+    pub remittance: String,
+}
+
+/// A pain.001.001.09 customer credit transfer initiation with one batch
+/// of EUR transfers executed on `execution_date`.
+pub fn render_pain001(
+    message_id: &str,
+    created_at: NaiveDateTime,
+    execution_date: NaiveDate,
+    debtor: &Debtor,
+    transfers: &[CreditTransfer],
+) -> String {
+    let total: Money = transfers.iter().map(|transfer| transfer.amount).sum();
+    let agent = |bic: Option<&str>| match bic {
+        Some(bic) => format!("<FinInstnId><BICFI>{}</BICFI></FinInstnId>", escape_xml(bic)),
+        None => "<FinInstnId><Othr><Id>NOTPROVIDED</Id></Othr></FinInstnId>".to_string(),
+    };
+
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(
+        "<Document xmlns=\"urn:iso:std:iso:20022:tech:xsd:pain.001.001.09\" \
+         xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">\n",
+    );
+    xml.push_str("  <CstmrCdtTrfInitn>\n");
+    xml.push_str("    <GrpHdr>\n");
+    xml.push_str(&format!("      <MsgId>{}</MsgId>\n", escape_xml(message_id)));
+    xml.push_str(&format!(
+        "      <CreDtTm>{}</CreDtTm>\n",
+        created_at.format("%Y-%m-%dT%H:%M:%S")
+    ));
+    xml.push_str(&format!("      <NbOfTxs>{}</NbOfTxs>\n", transfers.len()));
+    xml.push_str(&format!("      <CtrlSum>{}</CtrlSum>\n", total));
+    xml.push_str(&format!(
+        "      <InitgPty><Nm>{}</Nm></InitgPty>\n",
+        escape_xml(&sepa_text(&debtor.name, MAX_NAME_LENGTH))
+    ));
+    xml.push_str("    </GrpHdr>\n");
+    xml.push_str("    <PmtInf>\n");
+    xml.push_str(&format!(
+        "      <PmtInfId>{}</PmtInfId>\n",
+        escape_xml(&format!("{}-1", message_id).chars().take(MAX_ID_LENGTH).collect::<String>())
+    ));
+    xml.push_str("      <PmtMtd>TRF</PmtMtd>\n");
+    xml.push_str("      <BtchBookg>true</BtchBookg>\n");
+    xml.push_str(&format!("      <NbOfTxs>{}</NbOfTxs>\n", transfers.len()));
+    xml.push_str(&format!("      <CtrlSum>{}</CtrlSum>\n", total));
+    xml.push_str("      <PmtTpInf><SvcLvl><Cd>SEPA</Cd></SvcLvl></PmtTpInf>\n");
+    xml.push_str(&format!(
+        "      <ReqdExctnDt><Dt>{}</Dt></ReqdExctnDt>\n",
+        execution_date.format("%Y-%m-%d")
+    ));
+    xml.push_str(&format!(
+        "      <Dbtr><Nm>{}</Nm></Dbtr>\n",
+        escape_xml(&sepa_text(&debtor.name, MAX_NAME_LENGTH))
+    ));
+    xml.push_str(&format!(
+        "      <DbtrAcct><Id><IBAN>{}</IBAN></Id></DbtrAcct>\n",
+        escape_xml(&debtor.iban)
+    ));
+    xml.push_str(&format!("      <DbtrAgt>{}</DbtrAgt>\n", agent(debtor.bic.as_deref())));
+    xml.push_str("      <ChrgBr>SLEV</ChrgBr>\n");
+    for transfer in transfers {
+        xml.push_str("      <CdtTrfTxInf>\n");
+        xml.push_str(&format!(
+            "        <PmtId><EndToEndId>{}</EndToEndId></PmtId>\n",
+            escape_xml(&sepa_text(&transfer.end_to_end_id, MAX_ID_LENGTH))
+        ));
+        xml.push_str(&format!(
+            "        <Amt><InstdAmt Ccy=\"EUR\">{}</InstdAmt></Amt>\n",
+            transfer.amount
+        ));
+        if let Some(bic) = transfer.bic.as_deref() {
+            xml.push_str(&format!("        <CdtrAgt>{}</CdtrAgt>\n", agent(Some(bic))));
+        }
+        xml.push_str(&format!(
+            "        <Cdtr><Nm>{}</Nm></Cdtr>\n",
+            escape_xml(&sepa_text(&transfer.creditor_name, MAX_NAME_LENGTH))
+        ));
+        xml.push_str(&format!(
+            "        <CdtrAcct><Id><IBAN>{}</IBAN></Id></CdtrAcct>\n",
+            escape_xml(&transfer.iban)
+        ));
+        xml.push_str(&format!(
+            "        <RmtInf><Ustrd>{}</Ustrd></RmtInf>\n",
+            escape_xml(&sepa_text(&transfer.remittance, MAX_REMITTANCE_LENGTH))
+        ));
+        xml.push_str("      </CdtTrfTxInf>\n");
+    }
+    xml.push_str("    </PmtInf>\n");
+    xml.push_str("  </CstmrCdtTrfInitn>\n");
+    xml.push_str("</Document>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ibans_with_valid_check_digits_are_normalized() {
+        let valid = [
+            ("DE89 3704 0044 0532 0130 00", "DE89370400440532013000"),
+            ("de02120300000000202051", "DE02120300000000202051"),
+            ("GB82 WEST 1234 5698 7654 32", "GB82WEST12345698765432"),
+            ("AT611904300234573201", "AT611904300234573201"),
+            ("NL91ABNA0417164300", "NL91ABNA0417164300"),
+        ];
+        for (raw, expected) in valid {
+            assert_eq!(normalize_iban(raw).as_deref(), Some(expected), "{}", raw);
+        }
+        let invalid = [
+            "DE89370400440532013001",
+            "DE98370400440532013000",
+            "DE89-3704-0044-0532-0130-00",
+            "8989370400440532013000",
+            "DE8937040044",
+            "",
+        ];
+        for raw in invalid {
+            assert_eq!(normalize_iban(raw), None, "{}", raw);
+        }
+    }
+
+    #[test]
+    fn creditor_references_need_valid_check_digits() {
+        assert_eq!(normalize_creditor_reference("rf18 5390 0754 7034").as_deref(), Some("RF18539007547034"));
+        assert_eq!(
+            normalize_creditor_reference("RF18000000000539007547034").as_deref(),
+            Some("RF18000000000539007547034")
+        );
+        for raw in ["RF19539007547034", "XX18539007547034", "RF18 5390 0754 7034 0000 0000 00", "RF18", "RE-2024-001"] {
+            assert_eq!(normalize_creditor_reference(raw), None, "{}", raw);
+        }
+    }
+
+    #[test]
+    fn bics_have_eight_or_eleven_characters() {
+        assert_eq!(normalize_bic("cobadeff xxx").as_deref(), Some("COBADEFFXXX"));
+        assert_eq!(normalize_bic("COBADEFF").as_deref(), Some("COBADEFF"));
+        for raw in ["COBA1EFF", "COBADEF", "COBADEFFXX", "COBADEFF-XX"] {
+            assert_eq!(normalize_bic(raw), None, "{}", raw);
+        }
+    }
+
+    #[test]
+    fn pain001_totals_match_the_transfers() {
+        let debtor = Debtor {
+            name: "Müller & Söhne GmbH".to_string(),
+            iban: "DE75512108001245126199".to_string(),
+            bic: Some("SOGEDEFFXXX".to_string()),
+        };
+        let transfer = |id: &str, amount: &str, bic: Option<&str>| CreditTransfer {
+            end_to_end_id: id.to_string(),
+            creditor_name: "Telekom <Deutschland>".to_string(),
+            iban: "DE02120300000000202051".to_string(),
+            bic: bic.map(str::to_string),
+            amount: Money::parse(amount).unwrap(),
+            remittance: "Rechnung 4711 abzgl. 2.38 Skonto".to_string(),
+        };
+        let transfers = [transfer("E2E-1", "116.62", None), transfer("E2E-2", "0.01", Some("BYLADEM1001"))];
+        let created_at = NaiveDate::from_ymd_opt(2024, 3, 14).unwrap().and_hms_opt(9, 30, 0).unwrap();
+        let execution_date = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
+        let xml = render_pain001("MSG-1", created_at, execution_date, &debtor, &transfers);
+
+        let document = roxmltree::Document::parse(&xml).unwrap();
+        let texts = |name: &str| -> Vec<String> {
+            document
+                .descendants()
+                .filter(|node| node.tag_name().name() == name)
+                .filter_map(|node| node.text().map(str::to_string))
+                .collect()
+        };
+        assert_eq!(texts("NbOfTxs"), ["2", "2"]);
+        assert_eq!(texts("CtrlSum"), ["116.63", "116.63"]);
+        assert_eq!(texts("InstdAmt"), ["116.62", "0.01"]);
+        assert_eq!(texts("EndToEndId"), ["E2E-1", "E2E-2"]);
+        assert_eq!(texts("BICFI"), ["SOGEDEFFXXX", "BYLADEM1001"]);
+        assert_eq!(texts("Dt"), ["2024-03-15"]);
+        assert_eq!(texts("CreDtTm"), ["2024-03-14T09:30:00"]);
+        assert_eq!(texts("Nm")[0], "Mueller + Soehne GmbH");
+        assert_eq!(texts("Nm")[2], "Telekom Deutschland");
+        assert_eq!(texts("Ustrd")[0], "Rechnung 4711 abzgl. 2.38 Skonto");
+    }
+}
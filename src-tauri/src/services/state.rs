@@ -1,5 +1,5 @@
 use anyhow::{anyhow, Result};
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::sync::{mpsc, Arc, Mutex};
 
 use tauri::{AppHandle, Emitter};
@@ -7,11 +7,16 @@ use tauri::{AppHandle, Emitter};
 use crate::db::Database;
 use crate::models::Settings;
 use crate::services::processor::{mark_failed, process_invoice};
-use crate::services::watcher::{debounce_file_event, FileEvent, FileEventKind, WatcherService};
+use crate::services::text_extraction::TextExtractor;
+use crate::services::watcher::{
+    debounce_file_event, is_invoice_file, FileEvent, FileEventKind, WatcherService,
+};
 
 pub struct AppState {
     pub db: Arc<Mutex<Database>>,
     pub settings: Arc<Mutex<Settings>>,
+    /// Checked once at startup; without it scanned PDFs cannot be read.
+    pub pdf_ocr_available: bool,
     watcher: Mutex<Option<WatcherService>>,
 }
 
@@ -20,6 +25,7 @@ impl AppState {
         AppState {
             db: Arc::new(Mutex::new(db)),
             settings: Arc::new(Mutex::new(settings)),
+            pdf_ocr_available: TextExtractor::pdf_rasterizer_available(),
             watcher: Mutex::new(None),
         }
     }
@@ -75,7 +81,7 @@ impl AppState {
             .into_iter()
             .filter_map(|e| e.ok())
             .filter(|e| e.path().is_file())
-            .filter(|e| is_pdf(e.path()))
+            .filter(|e| is_invoice_file(e.path()))
             .map(|e| e.path().to_path_buf())
             .collect::<Vec<_>>();
 
@@ -118,7 +124,7 @@ fn handle_event(event: FileEvent, db: &Arc<Mutex<Database>>, settings: &Arc<Mute
             }
         }
         _ => {
-            if !debounce_file_event(&event.path, 700) {
+            if !debounce_file_event(&event.path, 700) || !is_invoice_file(&event.path) {
                 return;
             }
 
@@ -157,10 +163,3 @@ fn handle_event(event: FileEvent, db: &Arc<Mutex<Database>>, settings: &Arc<Mute
         }
     }
 }
-
-fn is_pdf(path: &Path) -> bool {
-    path.extension()
-        .and_then(|ext| ext.to_str())
-        .map(|ext| ext.eq_ignore_ascii_case("pdf"))
-        .unwrap_or(false)
-}
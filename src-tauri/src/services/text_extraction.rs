@@ -1,28 +1,152 @@
 use anyhow::{anyhow, Result};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::services::ocr;
+use crate::services::watcher::is_image;
+
+const IMAGE_OCR_DPI: i32 = 300;
+
+#[derive(Debug, Clone)]
+pub struct PageText {
+    pub page_number: u32,
+    pub text: String,
+}
+
+/// Text of a document split by page, plus how it was obtained
+/// (`text_layer`, `ocr` or `einvoice`).
+#[derive(Debug, Clone)]
+pub struct ExtractedText {
+    pub pages: Vec<PageText>,
+    pub method: &'static str,
+}
+
+impl ExtractedText {
+    pub fn single_page(text: String, method: &'static str) -> Self {
+        ExtractedText {
+            pages: vec![PageText { page_number: 1, text }],
+            method,
+        }
+    }
+
+    /// Joins all pages; multi-page documents get a `--- Page N ---` marker
+    /// before each page so offsets in the result can be mapped back.
+    pub fn full_text(&self) -> String {
+        if self.pages.len() == 1 {
+            return self.pages[0].text.clone();
+        }
+        self.pages
+            .iter()
+            .map(|page| format!("{}\n{}", page_marker(page.page_number), page.text.trim_end()))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+pub fn page_marker(page_number: u32) -> String {
+    format!("--- Page {} ---", page_number)
+}
 
 pub struct TextExtractor;
 
 impl TextExtractor {
-    pub fn extract_from_pdf(path: &Path, ocr_language: &str) -> Result<String> {
-        if let Ok(text) = pdf_extract::extract_text(path) {
-            if !text.trim().is_empty() {
-                return Ok(text);
+    pub fn extract(path: &Path, ocr_language: &str, ocr_dpi: u32) -> Result<ExtractedText> {
+        if is_image(path) {
+            return Self::extract_from_image(path, ocr_language);
+        }
+        Self::extract_from_pdf(path, ocr_language, ocr_dpi)
+    }
+
+    /// Photos and scans go straight to Tesseract after preprocessing.
+    pub fn extract_from_image(path: &Path, ocr_language: &str) -> Result<ExtractedText> {
+        let image = image::open(path).map_err(|e| anyhow!("Image decode: {}", e))?;
+        let (prepared, scale) = ocr::preprocess(image);
+        let text = ocr::recognize(&prepared, ocr_language, IMAGE_OCR_DPI * scale as i32)?;
+        Ok(ExtractedText::single_page(text, "ocr"))
+    }
+
+    pub fn extract_from_pdf(path: &Path, ocr_language: &str, ocr_dpi: u32) -> Result<ExtractedText> {
+        if let Some(layer) = Self::extract_text_layer(path) {
+            if Self::validate_text_quality(&layer.full_text()) {
+                return Ok(layer);
             }
         }
-        Self::extract_via_ocr(path, ocr_language)
+        Self::extract_via_ocr(path, ocr_language, ocr_dpi)
+    }
+
+    /// Returns the embedded text layer only, without falling back to OCR.
+    pub fn extract_text_layer(path: &Path) -> Option<ExtractedText> {
+        let pages = pdf_extract::extract_text_by_pages(path).ok()?;
+        if pages.iter().all(|page| page.trim().is_empty()) {
+            return None;
+        }
+        Some(ExtractedText {
+            pages: pages
+                .into_iter()
+                .enumerate()
+                .map(|(index, text)| PageText {
+                    page_number: index as u32 + 1,
+                    text,
+                })
+                .collect(),
+            method: "text_layer",
+        })
+    }
+
+    /// Rasterizes every page with `pdftoppm` and runs OCR page by page.
+    fn extract_via_ocr(path: &Path, language: &str, dpi: u32) -> Result<ExtractedText> {
+        let work_dir = std::env::temp_dir().join(format!("billly-ocr-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&work_dir)?;
+        let result = Self::ocr_pages(path, &work_dir, language, dpi);
+        let _ = std::fs::remove_dir_all(&work_dir);
+        result
+    }
+
+    fn ocr_pages(path: &Path, work_dir: &Path, language: &str, dpi: u32) -> Result<ExtractedText> {
+        let output = Command::new("pdftoppm")
+            .arg("-r")
+            .arg(dpi.to_string())
+            .arg("-gray")
+            .arg("-png")
+            .arg(path)
+            .arg(work_dir.join("page"))
+            .output()
+            .map_err(|e| anyhow!("pdftoppm (Poppler) is needed to OCR scanned PDFs: {}", e))?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "pdftoppm failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        // pdftoppm zero-pads page numbers consistently, so name order is page order.
+        let mut images: Vec<PathBuf> = std::fs::read_dir(work_dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|file| file.extension().map(|ext| ext == "png").unwrap_or(false))
+            .collect();
+        images.sort();
+        if images.is_empty() {
+            return Err(anyhow!("PDF has no pages to OCR"));
+        }
+
+        let mut pages = Vec::with_capacity(images.len());
+        for (index, image_path) in images.iter().enumerate() {
+            let image = image::open(image_path).map_err(|e| anyhow!("Page image decode: {}", e))?;
+            let (prepared, scale) = ocr::preprocess(image);
+            let text = ocr::recognize(&prepared, language, (dpi * scale) as i32)?;
+            pages.push(PageText {
+                page_number: index as u32 + 1,
+                text,
+            });
+        }
+
+        Ok(ExtractedText { pages, method: "ocr" })
     }
 
-    fn extract_via_ocr(path: &Path, language: &str) -> Result<String> {
-        let text = tesseract::Tesseract::new(None, Some(language))
-            .map_err(|e| anyhow!("Tesseract init: {}", e))?
-            .set_image(path.to_str().ok_or_else(|| anyhow!("Invalid path"))?)
-            .map_err(|e| anyhow!("Tesseract image: {}", e))?
-            .recognize()
-            .map_err(|e| anyhow!("Tesseract recognize: {}", e))?
-            .get_text()
-            .map_err(|e| anyhow!("OCR text: {}", e))?;
-        Ok(text)
+    /// Whether `pdftoppm` from Poppler, which scanned PDFs are rasterized
+    /// with, can be run. It is not bundled with Billly.
+    pub fn pdf_rasterizer_available() -> bool {
+        Command::new("pdftoppm").arg("-v").output().is_ok()
     }
 
     pub fn validate_text_quality(text: &str) -> bool {
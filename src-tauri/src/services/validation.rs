@@ -0,0 +1,174 @@
+use chrono::Duration;
+
+use crate::models::{ExtractedInvoiceData, ExtractedLineItem, ExtractedVatEntry};
+use crate::services::bookkeeping::parse_date;
+use crate::services::sepa;
+use crate::utils::{normalize_date, round_cents};
+
+/// Line sums may drift by a cent per line from rounding on the document.
+const LINE_TOLERANCE_PER_ITEM: f64 = 0.01;
+/// Confidence given to a header amount that disagrees with its breakdown, low
+/// enough to put the invoice in the review queue.
+const INCONSISTENT_CONFIDENCE: f64 = 0.6;
+
+/// Checks that the line items add up to `net_amount`. A mismatch is noted and
+/// lowers the confidence of `net_amount`; the values themselves are kept.
+pub fn check_line_items(data: &mut ExtractedInvoiceData) {
+    let Some(net_amount) = data.net_amount else {
+        return;
+    };
+    if data.line_items.is_empty() {
+        return;
+    }
+    let Some(sum) = data
+        .line_items
+        .iter()
+        .map(line_net)
+        .sum::<Option<f64>>()
+    else {
+        return;
+    };
+
+    let tolerance = 0.005 + LINE_TOLERANCE_PER_ITEM * data.line_items.len() as f64;
+    if (sum - net_amount).abs() > tolerance {
+        flag(
+            data,
+            "net_amount",
+            format!("line items sum {:.2} vs net_amount {:.2}", sum, net_amount),
+        );
+    }
+}
+
+/// Checks the VAT breakdown against the header amounts. Missing header
+/// amounts are filled from the breakdown; a single-rate breakdown is derived
+/// from net and tax when the document had none.
+pub fn check_vat_breakdown(data: &mut ExtractedInvoiceData) {
+    if data.vat_breakdown.is_empty() {
+        if let Some(entry) = derive_single_rate(data.net_amount, data.tax_amount) {
+            data.vat_breakdown.push(entry);
+        }
+        return;
+    }
+
+    let tax_sum: f64 = data.vat_breakdown.iter().map(|entry| entry.tax_amount).sum();
+    let net_sum: f64 = data.vat_breakdown.iter().map(|entry| entry.net_amount).sum();
+    let tolerance = 0.005 + LINE_TOLERANCE_PER_ITEM * data.vat_breakdown.len() as f64;
+
+    match data.tax_amount {
+        Some(tax) if (tax - tax_sum).abs() > tolerance => flag(
+            data,
+            "tax_amount",
+            format!("VAT breakdown tax {:.2} vs tax_amount {:.2}", tax_sum, tax),
+        ),
+        Some(_) => {}
+        None => {
+            data.tax_amount = Some(round_cents(tax_sum));
+            note(data, "tax_amount derived from VAT breakdown".to_string());
+        }
+    }
+
+    if data.net_amount.is_none() {
+        data.net_amount = Some(round_cents(net_sum));
+        note(data, "net_amount derived from VAT breakdown".to_string());
+    }
+
+    if let Some(total) = data.total_amount {
+        let gross = net_sum + tax_sum;
+        if (total - gross).abs() > tolerance {
+            flag(
+                data,
+                "total_amount",
+                format!("VAT breakdown gross {:.2} vs total_amount {:.2}", gross, total),
+            );
+        }
+    }
+}
+
+/// Normalizes the payment details. An IBAN failing its check digits or a
+/// malformed BIC is dropped with a note, as is a Skonto percentage outside
+/// (0, 100). A Skonto period in days becomes a deadline counted from the
+/// invoice date.
+pub fn check_payment_details(data: &mut ExtractedInvoiceData) {
+    if let Some(raw) = data.iban.take() {
+        data.iban = sepa::normalize_iban(&raw);
+        if data.iban.is_none() {
+            data.field_confidence.remove("iban");
+            note(data, format!("IBAN {} fails the checksum", raw.trim()));
+        }
+    }
+    if let Some(raw) = data.bic.take() {
+        data.bic = sepa::normalize_bic(&raw);
+        if data.bic.is_none() {
+            data.field_confidence.remove("bic");
+            note(data, format!("invalid BIC {}", raw.trim()));
+        }
+    }
+    for field in [&mut data.payment_reference, &mut data.payment_terms] {
+        if field.as_deref().is_some_and(|value| value.trim().is_empty()) {
+            *field = None;
+        }
+    }
+
+    if let Some(percent) = data.discount_percent {
+        if percent <= 0.0 || percent >= 100.0 {
+            data.discount_percent = None;
+            data.discount_days = None;
+            data.discount_due_date = None;
+            note(data, format!("Skonto of {}% ignored", percent));
+            return;
+        }
+    }
+    data.discount_due_date = normalize_date(data.discount_due_date.take())
+        .filter(|date| parse_date(Some(date)).is_some());
+    if data.discount_due_date.is_none() {
+        let invoice_date = parse_date(normalize_date(data.invoice_date.clone()).as_deref());
+        if let (Some(date), Some(days)) = (invoice_date, data.discount_days) {
+            data.discount_due_date = Some((date + Duration::days(days.into())).format("%Y-%m-%d").to_string());
+        }
+    }
+    if data.discount_percent.is_some() && data.discount_due_date.is_none() {
+        flag(data, "discount_percent", "Skonto without a deadline".to_string());
+    }
+}
+
+/// A plain net/tax pair whose ratio is a whole (or half) percentage is taken
+/// as a single-rate invoice; a zero tax means an exempt or reverse-charge one.
+fn derive_single_rate(net_amount: Option<f64>, tax_amount: Option<f64>) -> Option<ExtractedVatEntry> {
+    let (net, tax) = (net_amount?, tax_amount?);
+    if net <= 0.0 {
+        return None;
+    }
+    let rate = (tax / net * 200.0).round() / 2.0;
+    if (round_cents(net * rate / 100.0) - tax).abs() > 0.015 {
+        return None;
+    }
+    Some(ExtractedVatEntry {
+        rate,
+        net_amount: net,
+        tax_amount: tax,
+    })
+}
+
+/// Net value of a line, falling back to quantity times unit price.
+pub fn line_net(item: &ExtractedLineItem) -> Option<f64> {
+    let computed = match (item.quantity, item.unit_price) {
+        (Some(quantity), Some(price)) => Some(quantity * price),
+        _ => None,
+    };
+    item.net_amount.or(computed)
+}
+
+fn flag(data: &mut ExtractedInvoiceData, field: &str, message: String) {
+    let current = data.field_confidence.get(field).copied().unwrap_or(1.0);
+    data.field_confidence
+        .insert(field.to_string(), current.min(INCONSISTENT_CONFIDENCE));
+    note(data, message);
+}
+
+fn note(data: &mut ExtractedInvoiceData, message: String) {
+    data.extraction_notes = if data.extraction_notes.trim().is_empty() {
+        message
+    } else {
+        format!("{}; {}", data.extraction_notes, message)
+    };
+}
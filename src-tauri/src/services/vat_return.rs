@@ -0,0 +1,258 @@
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+
+use crate::db::Database;
+use crate::models::{Account, Counterparty, Invoice, InvoiceVatEntry, VatReturn, VatReturnLine};
+use crate::services::bookkeeping::{load_invoices, rate_to, vat_parts, InvoiceFilter};
+use crate::utils::Money;
+
+/// The UStVA is filed in EUR.
+const RETURN_CURRENCY: &str = "EUR";
+
+/// Rate applied to reverse-charge purchases whose account has no rate.
+const STANDARD_RATE: f64 = 19.0;
+
+/// Member states whose VAT IDs mark a supplier as an EU business for reverse
+/// charge. Greece uses `EL`; Germany is missing on purpose.
+const EU_VAT_PREFIXES: [&str; 26] = [
+    "AT", "BE", "BG", "CY", "CZ", "DK", "EE", "EL", "ES", "FI", "FR", "HR", "HU", "IE", "IT", "LT", "LU", "LV",
+    "MT", "NL", "PL", "PT", "RO", "SE", "SI", "SK",
+];
+
+/// Kennzahlen in form order with their labels. Where the form has a separate
+/// field for the tax, it is named in the label.
+const FIELDS: [(&str, &str); 11] = [
+    ("81", "Steuerpflichtige Umsätze zum Steuersatz von 19 %"),
+    ("86", "Steuerpflichtige Umsätze zum Steuersatz von 7 %"),
+    ("35", "Umsätze, die anderen Steuersätzen unterliegen (Steuer: Kz 36)"),
+    ("41", "Innergemeinschaftliche Lieferungen an Abnehmer mit USt-IdNr."),
+    ("43", "Weitere steuerfreie Umsätze mit Vorsteuerabzug (z. B. Ausfuhrlieferungen)"),
+    ("89", "Steuerpflichtige innergemeinschaftliche Erwerbe zum Steuersatz von 19 %"),
+    ("93", "Steuerpflichtige innergemeinschaftliche Erwerbe zum Steuersatz von 7 %"),
+    ("46", "Sonstige Leistungen eines im übrigen Gemeinschaftsgebiet ansässigen Unternehmers (§ 13b Abs. 1 UStG) (Steuer: Kz 47)"),
+    ("66", "Vorsteuerbeträge aus Rechnungen von anderen Unternehmern"),
+    ("61", "Vorsteuerbeträge aus dem innergemeinschaftlichen Erwerb von Gegenständen"),
+    ("67", "Vorsteuerbeträge aus Leistungen im Sinne des § 13b UStG"),
+];
+
+const BALANCE_FIELD: (&str, &str) = (
+    "83",
+    "Verbleibende Umsatzsteuer-Vorauszahlung bzw. verbleibender Überschuss",
+);
+
+/// Kennzahlen whose tax is input VAT; all other taxes are output VAT.
+const INPUT_VAT_FIELDS: [&str; 3] = ["66", "61", "67"];
+
+/// Parses `2024-03` (month) or `2024-Q1` (quarter) into its first and last day.
+pub fn parse_period(raw: &str) -> Result<(NaiveDate, NaiveDate)> {
+    let raw = raw.trim().to_uppercase();
+    let (year, part) = raw
+        .split_once('-')
+        .ok_or_else(|| anyhow!("Period must look like 2024-03 or 2024-Q1"))?;
+    let year: i32 = year.parse().map_err(|_| anyhow!("Invalid year: {}", year))?;
+    let (first_month, months) = match part.strip_prefix('Q') {
+        Some(quarter) => match quarter.parse::<u32>() {
+            Ok(quarter @ 1..=4) => (quarter * 3 - 2, 3),
+            _ => return Err(anyhow!("Invalid quarter: {}", part)),
+        },
+        None => match part.parse::<u32>() {
+            Ok(month @ 1..=12) => (month, 1),
+            _ => return Err(anyhow!("Invalid month: {}", part)),
+        },
+    };
+    let from = NaiveDate::from_ymd_opt(year, first_month, 1).ok_or_else(|| anyhow!("Invalid period: {}", raw))?;
+    let next = if first_month + months > 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(year, first_month + months, 1)
+    }
+    .ok_or_else(|| anyhow!("Invalid period: {}", raw))?;
+    Ok((from, next.pred_opt().unwrap_or(next)))
+}
+
+#[derive(Default)]
+struct Totals {
+    fields: BTreeMap<&'static str, (Money, Money)>,
+    invoices: u32,
+    warnings: Vec<String>,
+}
+
+impl Totals {
+    fn add(&mut self, field: &'static str, base: Money, tax: Money) {
+        let entry = self.fields.entry(field).or_insert((Money::ZERO, Money::ZERO));
+        entry.0 += base;
+        entry.1 += tax;
+    }
+}
+
+/// Computes the UStVA figures for a month or quarter. Under cash accounting
+/// (`Ist-Versteuerung`) revenue counts when it is paid; input VAT is always
+/// deductible by invoice date (§ 15 UStG), so payables use the invoice date
+/// under both bases. Reverse charge is assumed for payables without VAT from
+/// suppliers with a VAT ID of another member state. Bases are exact; the
+/// form itself takes whole euros.
+pub fn compute(db: &Database, period: &str, basis: &str, chart: &str) -> Result<VatReturn> {
+    let (from, to) = parse_period(period)?;
+    let cash_basis = match basis {
+        "cash" => true,
+        "accrual" => false,
+        other => return Err(anyhow!("Unknown VAT accounting basis: {}", other)),
+    };
+    let accounts = db.get_accounts(chart)?;
+    let counterparties = db.get_counterparties(None)?;
+
+    let mut totals = Totals::default();
+    for (category, by_payment_date) in [("revenue", cash_basis), ("payable", false)] {
+        let filter = InvoiceFilter {
+            category: Some(category.to_string()),
+            from: Some(from),
+            to: Some(to),
+            by_payment_date,
+            ..InvoiceFilter::default()
+        };
+        let (invoices, skipped_undated) = load_invoices(db, &filter)?;
+        if skipped_undated > 0 {
+            totals
+                .warnings
+                .push(format!("{} {} invoice(s) without a date are not included", skipped_undated, category));
+        }
+        for invoice in invoices {
+            let breakdown = db.get_vat_breakdown(&invoice.id)?;
+            add_invoice(&mut totals, &invoice, &breakdown, &accounts, &counterparties);
+        }
+    }
+
+    let mut lines = Vec::new();
+    let mut output_vat = Money::ZERO;
+    let mut input_vat = Money::ZERO;
+    for (code, label) in FIELDS {
+        let Some((base, tax)) = totals.fields.get(code).copied() else {
+            continue;
+        };
+        if INPUT_VAT_FIELDS.contains(&code) {
+            input_vat += tax;
+            lines.push(line(code, label, None, Some(tax)));
+        } else {
+            output_vat += tax;
+            let has_tax = !matches!(code, "41" | "43");
+            lines.push(line(code, label, Some(base), has_tax.then_some(tax)));
+        }
+    }
+    let balance = output_vat - input_vat;
+    lines.push(line(BALANCE_FIELD.0, BALANCE_FIELD.1, None, Some(balance)));
+
+    Ok(VatReturn {
+        period: period.trim().to_uppercase(),
+        from: from.format("%Y-%m-%d").to_string(),
+        to: to.format("%Y-%m-%d").to_string(),
+        basis: basis.to_string(),
+        currency: RETURN_CURRENCY.to_string(),
+        lines,
+        output_vat,
+        input_vat,
+        balance,
+        invoice_count: totals.invoices,
+        warnings: totals.warnings,
+    })
+}
+
+fn line(code: &str, label: &str, base: Option<Money>, tax: Option<Money>) -> VatReturnLine {
+    VatReturnLine {
+        code: code.to_string(),
+        label: label.to_string(),
+        base,
+        tax,
+    }
+}
+
+fn add_invoice(
+    totals: &mut Totals,
+    invoice: &Invoice,
+    breakdown: &[InvoiceVatEntry],
+    accounts: &[Account],
+    counterparties: &[Counterparty],
+) {
+    let name = invoice
+        .invoice_number
+        .clone()
+        .or_else(|| invoice.counterparty_name.clone())
+        .unwrap_or_else(|| invoice.id.clone());
+    let Some(to_eur) = rate_to(invoice, RETURN_CURRENCY) else {
+        totals
+            .warnings
+            .push(format!("{}: no EUR exchange rate for {}, not included", name, invoice.currency));
+        return;
+    };
+    let account = invoice
+        .account_number
+        .as_deref()
+        .and_then(|number| accounts.iter().find(|account| account.number == number));
+    let expense_category = account.and_then(|account| account.expense_category.as_deref());
+    let account_rate = account.and_then(|account| account.vat_rate);
+    totals.invoices += 1;
+
+    for part in vat_parts(invoice, breakdown) {
+        let net = part.net.convert(to_eur);
+        let tax = part.tax.convert(to_eur);
+        let close_to = |expected: f64| part.rate.is_some_and(|rate| (rate - expected).abs() < 0.5);
+
+        if invoice.category == "revenue" {
+            let field = if close_to(19.0) {
+                "81"
+            } else if close_to(7.0) {
+                "86"
+            } else if tax != Money::ZERO {
+                "35"
+            } else {
+                match expense_category {
+                    Some("sales_eu") => "41",
+                    Some("sales_export") => "43",
+                    Some("sales_small_business") => continue,
+                    _ => {
+                        totals.warnings.push(format!(
+                            "{}: revenue without VAT on an account that is neither EU nor export, not included",
+                            name
+                        ));
+                        continue;
+                    }
+                }
+            };
+            totals.add(field, net, tax);
+            continue;
+        }
+
+        if tax != Money::ZERO || !is_eu_supplier(invoice, counterparties) {
+            // Input VAT is only deductible as stated on the invoice.
+            if part.rate.is_none() {
+                totals
+                    .warnings
+                    .push(format!("{}: states neither net nor VAT, no input VAT deducted", name));
+            }
+            totals.add("66", Money::ZERO, tax);
+            continue;
+        }
+        // Reverse charge: the buyer owes the VAT and deducts it again.
+        let reverse_rate = account_rate.filter(|rate| *rate > 0.0).unwrap_or(STANDARD_RATE);
+        let reverse_tax = net.convert(reverse_rate / 100.0);
+        let goods = expense_category.is_some_and(|category| category.starts_with("goods_"));
+        let (field, input_field) = match (goods, (reverse_rate - 7.0).abs() < 0.5) {
+            (true, true) => ("93", "61"),
+            (true, false) => ("89", "61"),
+            (false, _) => ("46", "67"),
+        };
+        totals.add(field, net, reverse_tax);
+        totals.add(input_field, Money::ZERO, reverse_tax);
+    }
+}
+
+fn is_eu_supplier(invoice: &Invoice, counterparties: &[Counterparty]) -> bool {
+    invoice
+        .counterparty_id
+        .as_deref()
+        .and_then(|id| counterparties.iter().find(|counterparty| counterparty.id == id))
+        .and_then(|counterparty| counterparty.vat_id.as_deref())
+        .and_then(|vat_id| vat_id.get(..2))
+        .is_some_and(|prefix| EU_VAT_PREFIXES.contains(&prefix))
+}
+
@@ -4,6 +4,8 @@ use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 use std::time::Duration;
 
+use crate::services::einvoice::EInvoiceParser;
+
 #[derive(Debug, Clone)]
 pub enum FileEventKind {
     Created,
@@ -57,7 +59,7 @@ fn create_watcher(
                 _ => return,
             };
             for path in event.paths {
-                if is_pdf(&path) {
+                if has_invoice_extension(&path) {
                     let _ = tx.send(FileEvent {
                         path: path.to_path_buf(),
                         category: category.clone(),
@@ -72,10 +74,33 @@ fn create_watcher(
     Ok(watcher)
 }
 
-fn is_pdf(path: &Path) -> bool {
+/// PDFs, images and XML e-invoices; other XML in the folder, such as SEPA
+/// transfer files, is skipped. Reads the file to tell XML apart.
+pub fn is_invoice_file(path: &Path) -> bool {
+    is_pdf(path) || is_image(path) || (is_xml(path) && EInvoiceParser::is_einvoice_file(path))
+}
+
+/// Extension check only, for paths that may be gone or still being written.
+fn has_invoice_extension(path: &Path) -> bool {
+    is_pdf(path) || is_xml(path) || is_image(path)
+}
+
+pub fn is_pdf(path: &Path) -> bool {
+    has_extension(path, &["pdf"])
+}
+
+pub fn is_xml(path: &Path) -> bool {
+    has_extension(path, &["xml"])
+}
+
+pub fn is_image(path: &Path) -> bool {
+    has_extension(path, &["jpg", "jpeg", "png", "tif", "tiff"])
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
     path.extension()
         .and_then(|ext| ext.to_str())
-        .map(|ext| ext.eq_ignore_ascii_case("pdf"))
+        .map(|ext| extensions.iter().any(|candidate| ext.eq_ignore_ascii_case(candidate)))
         .unwrap_or(false)
 }
 
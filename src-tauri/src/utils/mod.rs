@@ -1,10 +1,14 @@
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use chrono::{DateTime, NaiveDate, Utc};
 use sha2::{Digest, Sha256};
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
 
+mod money;
+
+pub use money::Money;
+
 pub fn now_rfc3339() -> String {
     Utc::now().to_rfc3339()
 }
@@ -30,15 +34,73 @@ pub fn sha256_file(path: &Path) -> Result<String> {
     Ok(hex::encode(hasher.finalize()))
 }
 
-pub fn format_decimal(value: f64) -> String {
-    format!("{:.2}", value)
+/// Windows-1252 ("ANSI") as read by DATEV and used by the standard PDF
+/// fonts. Latin-1 characters map directly, the euro sign and typographic
+/// quotes and dashes to their code points in 0x80-0x9f; anything else
+/// becomes `?`.
+pub fn encode_windows_1252(content: &str) -> Vec<u8> {
+    content
+        .chars()
+        .map(|c| match c {
+            '€' => 0x80,
+            '‚' => 0x82,
+            '„' => 0x84,
+            '…' => 0x85,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            c => match c as u32 {
+                code @ (0x00..=0x7f | 0xa0..=0xff) => code as u8,
+                _ => b'?',
+            },
+        })
+        .collect()
+}
+
+/// Escapes text for XML element content and attribute values.
+pub fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
 }
 
-pub fn parse_decimal(value: &str) -> Result<f64> {
+/// Escapes text for HTML element content and quoted attribute values.
+pub fn escape_html(value: &str) -> String {
     value
-        .replace(',', ".")
-        .parse::<f64>()
-        .map_err(|e| anyhow!("Parse decimal: {}", e))
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// `1.234,56 €`
+pub fn format_eur(amount: Money) -> String {
+    let plain = amount.to_string();
+    let (sign, digits) = match plain.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", plain.as_str()),
+    };
+    let (units, cents) = digits.split_once('.').unwrap_or((digits, "00"));
+    let mut grouped = String::new();
+    for (index, digit) in units.chars().enumerate() {
+        if index > 0 && (units.len() - index) % 3 == 0 {
+            grouped.push('.');
+        }
+        grouped.push(digit);
+    }
+    format!("{}{},{} €", sign, grouped, cents)
+}
+
+/// Rounds an amount to whole cents.
+pub fn round_cents(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
 }
 
 pub fn normalize_date(value: Option<String>) -> Option<String> {
@@ -0,0 +1,289 @@
+use anyhow::{anyhow, Result};
+use rusqlite::types::{FromSql, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+
+const MINOR_PER_UNIT: i64 = 100;
+
+/// An exact amount in minor units (cents). Stored as INTEGER in SQLite and
+/// serialized as a decimal string such as `"1234.50"`, so sums never drift.
+/// The currency lives next to the amount on the owning record.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money(i64);
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+
+    /// Rounds to the nearest cent; used where amounts arrive as JSON numbers.
+    pub fn from_f64(value: f64) -> Self {
+        Money((value * MINOR_PER_UNIT as f64).round() as i64)
+    }
+
+    /// Converts with an exchange rate, rounding once to the nearest cent.
+    pub fn convert(self, rate: f64) -> Self {
+        Money((self.0 as f64 * rate).round() as i64)
+    }
+
+    /// For ratios such as effective VAT rates; sums stay in minor units.
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / MINOR_PER_UNIT as f64
+    }
+
+    /// Parses `1234.5`, `1234,50`, `1.234,50` or `1,234.50` without going
+    /// through floating point. A separator followed by three digits is read
+    /// as a thousands separator when the grouping is consistent and the
+    /// amount does not start with zero (`1.234`, `1.234.567`); anything else
+    /// with more than two decimals, such as `0.125`, is rejected. A currency
+    /// symbol or code may come before or after the amount (`€ 12,50`,
+    /// `12,50 EUR`).
+    pub fn parse(raw: &str) -> Result<Self> {
+        let is_currency = |c: char| c.is_alphabetic() || c == '€' || c == '$' || c == '£';
+        let cleaned: String = raw
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '\'')
+            .collect();
+        let cleaned = cleaned.trim_matches(is_currency);
+        let (negative, digits) = match cleaned.strip_prefix('-') {
+            Some(rest) => (true, rest.trim_start_matches(is_currency)),
+            None => (false, cleaned),
+        };
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit() || c == '.' || c == ',') {
+            return Err(anyhow!("Invalid amount: {}", raw));
+        }
+
+        let invalid = || anyhow!("Invalid amount: {}", raw);
+        let (units, fraction) = match digits.rfind(['.', ',']) {
+            None => (digits.to_string(), ""),
+            Some(index) => {
+                let mark = if digits.as_bytes()[index] == b'.' { '.' } else { ',' };
+                let grouping = if mark == '.' { ',' } else { '.' };
+                let fraction = &digits[index + 1..];
+                match fraction.len() {
+                    0..=2 => {
+                        let units = &digits[..index];
+                        let units = if units.contains(mark) {
+                            None
+                        } else if units.contains(grouping) {
+                            ungroup(units, grouping)
+                        } else {
+                            Some(units.to_string())
+                        };
+                        (units.ok_or_else(invalid)?, fraction)
+                    }
+                    3 if !digits.contains(grouping) => (ungroup(digits, mark).ok_or_else(invalid)?, ""),
+                    _ => return Err(invalid()),
+                }
+            }
+        };
+        let units: i64 = if units.is_empty() {
+            0
+        } else {
+            units.parse().map_err(|_| invalid())?
+        };
+        let cents: i64 = match fraction.len() {
+            0 => 0,
+            1 => fraction.parse::<i64>()? * 10,
+            _ => fraction.parse()?,
+        };
+
+        let minor = units
+            .checked_mul(MINOR_PER_UNIT)
+            .and_then(|value| value.checked_add(cents))
+            .ok_or_else(|| anyhow!("Amount out of range: {}", raw))?;
+        Ok(Money(if negative { -minor } else { minor }))
+    }
+}
+
+/// The digits of `units` if it is written in groups of three separated by
+/// `separator`, after a first group of one to three digits without a leading
+/// zero (`1.234.567`).
+fn ungroup(units: &str, separator: char) -> Option<String> {
+    let mut groups = units.split(separator);
+    let first = groups.next()?;
+    if first.is_empty() || first.len() > 3 || first.starts_with('0') {
+        return None;
+    }
+    let mut digits = first.to_string();
+    for group in groups {
+        if group.len() != 3 {
+            return None;
+        }
+        digits.push_str(group);
+    }
+    Some(digits)
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        write!(
+            f,
+            "{}{}.{:02}",
+            sign,
+            abs / MINOR_PER_UNIT as u64,
+            abs % MINOR_PER_UNIT as u64
+        )
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, other: Money) -> Money {
+        Money(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, other: Money) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, other: Money) -> Money {
+        Money(self.0 - other.0)
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, other: Money) {
+        self.0 -= other.0;
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money(-self.0)
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::ZERO, Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Money> for Money {
+    fn sum<I: Iterator<Item = &'a Money>>(iter: I) -> Money {
+        iter.copied().sum()
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Money, D::Error> {
+        deserializer.deserialize_any(MoneyVisitor)
+    }
+}
+
+struct MoneyVisitor;
+
+impl Visitor<'_> for MoneyVisitor {
+    type Value = Money;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a decimal amount as string or number")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Money, E> {
+        Money::parse(value).map_err(E::custom)
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Money, E> {
+        Ok(Money::from_f64(value))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Money, E> {
+        Ok(Money(value * MINOR_PER_UNIT))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Money, E> {
+        Ok(Money(value as i64 * MINOR_PER_UNIT))
+    }
+}
+
+impl ToSql for Money {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.0))
+    }
+}
+
+impl FromSql for Money {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        i64::column_result(value).map(Money)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cents(raw: &str) -> i64 {
+        Money::parse(raw).unwrap().0
+    }
+
+    #[test]
+    fn parses_german_and_english_notation() {
+        assert_eq!(cents("1.234,56"), 123456);
+        assert_eq!(cents("1,234.56"), 123456);
+        assert_eq!(cents("1234.5"), 123450);
+        assert_eq!(cents("1'234.50"), 123450);
+    }
+
+    #[test]
+    fn three_digits_after_a_separator_are_thousands() {
+        assert_eq!(cents("1.234"), 123400);
+        assert_eq!(cents("1,234"), 123400);
+        assert_eq!(cents("1.234.567"), 123456700);
+        assert_eq!(cents("12,345,678.90"), 1234567890);
+        assert_eq!(cents("12.345,6"), 1234560);
+    }
+
+    #[test]
+    fn parses_short_fractions_and_signs() {
+        assert_eq!(cents("-0,5"), -50);
+        assert_eq!(cents("100,"), 10000);
+        assert_eq!(cents("0,05"), 5);
+    }
+
+    #[test]
+    fn strips_currency_before_or_after() {
+        assert_eq!(cents("12,50 €"), 1250);
+        assert_eq!(cents("12,50 EUR"), 1250);
+        assert_eq!(cents("€ 12,50"), 1250);
+        assert_eq!(cents("EUR 12,50"), 1250);
+        assert_eq!(cents("$1,234.56"), 123456);
+        assert_eq!(cents("-€ 12,50"), -1250);
+        assert_eq!(cents("EUR -12,50"), -1250);
+    }
+
+    #[test]
+    fn rejects_what_is_not_an_amount() {
+        let invalid = [
+            "", "EUR", "12,3456", "1-2", "zwölf", "0.125", "0,125", ".125", "1.23.456", "1,234.567", "1.234.56,78",
+        ];
+        for raw in invalid {
+            assert!(Money::parse(raw).is_err(), "{:?} should not parse", raw);
+        }
+    }
+
+    #[test]
+    fn displays_with_two_decimals() {
+        assert_eq!(Money(123450).to_string(), "1234.50");
+        assert_eq!(Money(-5).to_string(), "-0.05");
+    }
+}
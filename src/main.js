@@ -6,12 +6,44 @@ const state = {
   currentView: "dashboard",
   currentCategory: "revenue",
   selectedInvoiceId: null,
+  selectedPayables: new Set(),
+  giroCodeUrl: null,
+  counterparties: [],
+  selectedCounterpartyId: null,
+  accounts: [],
+  dunningTemplates: [],
   charts: {
     monthly: null,
     profit: null
   }
 };
 
+const LOW_CONFIDENCE = 0.7;
+const DEFAULT_EXPORT_COLUMNS = [
+  "invoice_date",
+  "invoice_number",
+  "counterparty_name",
+  "category",
+  "net_amount",
+  "tax_amount",
+  "total_amount",
+  "currency",
+  "status"
+];
+
+const DUNNING_LEVELS = ["", "Zahlungserinnerung", "1. Mahnung", "2. Mahnung"];
+const INVOICE_UNITS = [
+  ["C62", "Stk."],
+  ["HUR", "Std."],
+  ["DAY", "Tag"],
+  ["MON", "Monat"],
+  ["LS", "pauschal"],
+  ["KGM", "kg"],
+  ["MTR", "m"],
+  ["KMT", "km"],
+  ["LTR", "l"]
+];
+
 const $ = (selector) => document.querySelector(selector);
 const $$ = (selector) => Array.from(document.querySelectorAll(selector));
 
@@ -21,11 +53,11 @@ function setStatus(text, ok = true) {
   indicator.querySelector(".dot").style.background = ok ? "var(--ok)" : "var(--danger)";
 }
 
-function formatCurrency(value) {
+function formatCurrency(value, currency = "EUR") {
   const amount = typeof value === "number" ? value : Number.parseFloat(value || "0");
   return new Intl.NumberFormat("en-US", {
     style: "currency",
-    currency: "EUR",
+    currency: currency || "EUR",
     minimumFractionDigits: 2
   }).format(amount);
 }
@@ -49,18 +81,55 @@ async function loadDashboard() {
   renderKpis(stats);
   renderRecent("#recent-revenue", stats.recent_revenue);
   renderRecent("#recent-payables", stats.recent_payables);
-  $("#open-payables").textContent = formatCurrency(stats.open_payables);
+  $("#open-payables").textContent = formatCurrency(stats.open_payables, stats.base_currency);
+  $("#basis-note").textContent = stats.reporting_basis === "cash"
+    ? "Cash basis: figures by payment date, unpaid invoices are not included" +
+      (stats.undated_payments ? ` (${stats.undated_payments} paid invoice(s) without a payment date are missing)` : "")
+    : "Accrual basis: figures by invoice date";
+  $("#unconverted-note").textContent = stats.unconverted_invoices
+    ? `${stats.unconverted_invoices} invoice(s) without an exchange rate are not included`
+    : "";
+  $("#ocr-banner").hidden = stats.pdf_ocr_available;
   renderCharts(stats);
+  await loadAccountTotals(monthValue ? monthValue.slice(0, 4) : String(new Date().getFullYear()), stats.base_currency);
+}
+
+function popplerInstallHint() {
+  const platform = navigator.userAgent;
+  if (platform.includes("Mac")) return "brew install poppler";
+  if (platform.includes("Windows")) return "Poppler for Windows, with its bin folder added to PATH";
+  return "e.g. sudo apt install poppler-utils";
+}
+
+async function loadAccountTotals(year, currency) {
+  const totals = await invoke("get_account_totals", { category: "payable", year });
+  const tbody = $("#account-totals");
+  tbody.innerHTML = "";
+  if (!totals.length) {
+    tbody.innerHTML = `<tr><td colspan="5" class="muted">No data</td></tr>`;
+    return;
+  }
+  totals.forEach((total) => {
+    const row = document.createElement("tr");
+    row.innerHTML = `
+      <td>${escapeHtml(total.account_number || "-")}</td>
+      <td>${escapeHtml(total.account_name || (total.account_number ? "" : "Unassigned"))}</td>
+      <td>${total.invoice_count}</td>
+      <td>${formatCurrency(total.net_amount, currency)}</td>
+      <td>${formatCurrency(total.total_amount, currency)}</td>
+    `;
+    tbody.appendChild(row);
+  });
 }
 
 function renderKpis(stats) {
   const kpis = [
-    { label: "Revenue (Month)", value: formatCurrency(stats.revenue_month) },
-    { label: "Revenue (Year)", value: formatCurrency(stats.revenue_year) },
-    { label: "Payables (Month)", value: formatCurrency(stats.payable_month) },
-    { label: "Payables (Year)", value: formatCurrency(stats.payable_year) },
-    { label: "Profit (Month)", value: formatCurrency(stats.profit_month) },
-    { label: "Profit (Year)", value: formatCurrency(stats.profit_year) }
+    { label: "Revenue (Month)", value: formatCurrency(stats.revenue_month, stats.base_currency) },
+    { label: "Revenue (Year)", value: formatCurrency(stats.revenue_year, stats.base_currency) },
+    { label: "Payables (Month)", value: formatCurrency(stats.payable_month, stats.base_currency) },
+    { label: "Payables (Year)", value: formatCurrency(stats.payable_year, stats.base_currency) },
+    { label: "Profit (Month)", value: formatCurrency(stats.profit_month, stats.base_currency) },
+    { label: "Profit (Year)", value: formatCurrency(stats.profit_year, stats.base_currency) }
   ];
 
   const container = $("#kpi-grid");
@@ -82,7 +151,7 @@ function renderRecent(selector, items) {
   }
   items.forEach((item) => {
     const li = document.createElement("li");
-    li.innerHTML = `<span>${item.counterparty_name || "Unknown"}</span><span>${formatCurrency(item.total_amount)}</span>`;
+    li.innerHTML = `<span>${item.counterparty_name || "Unknown"}</span><span>${formatCurrency(item.total_amount, item.currency)}</span>`;
     list.appendChild(li);
   });
 }
@@ -96,14 +165,14 @@ function renderCharts(stats) {
         datasets: [
           {
             label: "Revenue",
-            data: stats.chart_revenue,
+            data: stats.chart_revenue.map(Number),
             borderColor: "#22c55e",
             backgroundColor: "rgba(34,197,94,0.2)",
             tension: 0.3
           },
           {
             label: "Payables",
-            data: stats.chart_payables,
+            data: stats.chart_payables.map(Number),
             borderColor: "#ef4444",
             backgroundColor: "rgba(239,68,68,0.2)",
             tension: 0.3
@@ -123,8 +192,8 @@ function renderCharts(stats) {
     });
   } else {
     state.charts.monthly.data.labels = stats.chart_months;
-    state.charts.monthly.data.datasets[0].data = stats.chart_revenue;
-    state.charts.monthly.data.datasets[1].data = stats.chart_payables;
+    state.charts.monthly.data.datasets[0].data = stats.chart_revenue.map(Number);
+    state.charts.monthly.data.datasets[1].data = stats.chart_payables.map(Number);
     state.charts.monthly.update();
   }
 
@@ -136,7 +205,7 @@ function renderCharts(stats) {
         datasets: [
           {
             label: "Profit",
-            data: stats.chart_profit,
+            data: stats.chart_profit.map(Number),
             backgroundColor: "rgba(217,70,239,0.6)"
           }
         ]
@@ -154,45 +223,115 @@ function renderCharts(stats) {
     });
   } else {
     state.charts.profit.data.labels = stats.chart_months;
-    state.charts.profit.data.datasets[0].data = stats.chart_profit;
+    state.charts.profit.data.datasets[0].data = stats.chart_profit.map(Number);
     state.charts.profit.update();
   }
 }
 
 async function loadInvoices() {
+  if (state.currentCategory === "review") {
+    const queue = await invoke("get_review_queue", { category: null, limit: null });
+    renderInvoiceList(
+      queue.map((item) => ({
+        ...item,
+        confidence_score: item.weakest_confidence,
+        weakest_label: item.weakest_field
+      }))
+    );
+    return;
+  }
   const list = await invoke("get_invoices", { category: state.currentCategory });
   renderInvoiceList(list);
 }
 
 function renderInvoiceList(items) {
   const tbody = $("#invoice-list");
+  const payables = state.currentCategory === "payable";
+  const payable = (item) => payables && item.status !== "paid" && item.status !== "scheduled";
+  state.selectedPayables = new Set(items.filter((item) => payable(item) && state.selectedPayables.has(item.id)).map((item) => item.id));
+  $("#sepa-transfer").hidden = !payables;
+  updateSepaButton();
   tbody.innerHTML = "";
   if (!items.length) {
-    tbody.innerHTML = `<tr><td colspan="6" class="muted">No invoices</td></tr>`;
+    tbody.innerHTML = `<tr><td colspan="7" class="muted">No invoices</td></tr>`;
     return;
   }
 
   items.forEach((item) => {
     const row = document.createElement("tr");
     row.innerHTML = `
+      <td>${payable(item) ? `<input type="checkbox" data-pay="${item.id}" ${state.selectedPayables.has(item.id) ? "checked" : ""} />` : ""}</td>
       <td>${item.invoice_date || "-"}</td>
       <td>${item.counterparty_name || "Unknown"}</td>
-      <td>${formatCurrency(item.total_amount)}</td>
+      <td>${formatCurrency(item.total_amount, item.currency)}</td>
       <td>${item.status}</td>
       <td>${item.file_path ? item.file_path.split("/").pop() : "-"}</td>
-      <td>${Math.round((item.confidence_score || 0) * 100)}%</td>
+      <td>${Math.round((item.confidence_score || 0) * 100)}%${item.weakest_label ? ` (${item.weakest_label})` : ""}</td>
     `;
     row.addEventListener("click", () => selectInvoice(item.id));
+    const checkbox = row.querySelector("[data-pay]");
+    if (checkbox) {
+      checkbox.addEventListener("click", (event) => event.stopPropagation());
+      checkbox.addEventListener("change", () => {
+        if (checkbox.checked) {
+          state.selectedPayables.add(item.id);
+        } else {
+          state.selectedPayables.delete(item.id);
+        }
+        updateSepaButton();
+      });
+    }
     tbody.appendChild(row);
   });
 }
 
+function updateSepaButton() {
+  const count = state.selectedPayables.size;
+  const button = $("#create-sepa-transfer");
+  button.disabled = !count;
+  button.textContent = count ? `SEPA transfer (${count})` : "SEPA transfer";
+}
+
+async function createSepaTransfer() {
+  try {
+    const summary = await invoke("create_sepa_transfer", {
+      payload: {
+        invoice_ids: [...state.selectedPayables],
+        execution_date: $("#sepa-execution-date").value || null,
+        path: null
+      }
+    });
+    if (!summary) return;
+    const discount = Number(summary.discount) ? `, ${formatCurrency(summary.discount)} Skonto` : "";
+    setStatus(`${summary.transfers} transfers over ${formatCurrency(summary.total)}${discount} written to ${summary.path.split("/").pop()}`, true);
+    state.selectedPayables.clear();
+    await loadInvoices();
+    await loadDashboard();
+  } catch (error) {
+    setStatus(String(error), false);
+  }
+}
+
 async function selectInvoice(id) {
   state.selectedInvoiceId = id;
   const detail = await invoke("get_invoice_detail", { invoiceId: id });
   renderInvoiceDetail(detail);
 }
 
+async function loadGiroCode(invoiceId, target) {
+  try {
+    const bytes = await invoke("get_girocode", { invoiceId });
+    if (state.giroCodeUrl) {
+      URL.revokeObjectURL(state.giroCodeUrl);
+    }
+    state.giroCodeUrl = URL.createObjectURL(new Blob([new Uint8Array(bytes)], { type: "image/png" }));
+    target.classList.remove("muted");
+    target.innerHTML = `<img src="${state.giroCodeUrl}" alt="GiroCode" /><small class="muted">Scan with your banking app</small>`;
+  } catch (error) {
+    target.textContent = String(error);
+  }
+}
+
 function renderInvoiceDetail(detail) {
   const container = $("#detail-body");
   const invoice = detail.invoice;
@@ -205,30 +344,215 @@ function renderInvoiceDetail(detail) {
     ["currency", "Currency"],
     ["tax_amount", "Tax"],
     ["net_amount", "Net Amount"],
+    ["account_number", "Account"],
     ["status", "Status"],
     ["paid_at", "Paid At"]
   ];
 
-  container.innerHTML = "";
-  fields.forEach(([key, label]) => {
+  const evidence = Object.fromEntries((detail.evidence || []).map((entry) => [entry.field_name, entry]));
+  const overridden = new Set((detail.overrides || []).map((item) => item.field_name));
+
+  const fieldRow = (key, label, value) => {
     const row = document.createElement("div");
-    row.className = "detail-row";
+    const entry = evidence[key];
+    const lowConfidence = entry && !overridden.has(key) && entry.confidence < LOW_CONFIDENCE;
+    row.className = lowConfidence ? "detail-row low-confidence" : "detail-row";
+    if (entry) {
+      const source = entry.snippet ? `Page ${entry.page_number}: ${entry.snippet}` : "Not found in document text";
+      row.title = `${Math.round(entry.confidence * 100)}% confidence\n${source}`;
+    }
     row.innerHTML = `
-      <span>${label}</span>
+      <span>${label}${entry ? ` <small class="muted">${Math.round(entry.confidence * 100)}%</small>` : ""}</span>
       <div class="detail-value">
-        <input data-field="${key}" value="${invoice[key] ?? ""}" />
+        <input data-field="${key}" value="${escapeHtml(value ?? "")}" />
         <button class="ghost" data-clear="${key}">Clear override</button>
       </div>
     `;
-    container.appendChild(row);
+    return row;
+  };
+
+  container.innerHTML = "";
+  fields.forEach(([key, label]) => {
+    container.appendChild(fieldRow(key, label, invoice[key]));
   });
 
+  if (invoice.base_currency && invoice.base_currency !== invoice.currency) {
+    const conversion = document.createElement("div");
+    conversion.className = "detail-row";
+    conversion.innerHTML = invoice.base_total_amount
+      ? `<span>Total (${invoice.base_currency})</span><div class="detail-value">${formatCurrency(invoice.base_total_amount, invoice.base_currency)} <small class="muted">at ${invoice.exchange_rate.toFixed(4)}</small></div>`
+      : `<span>Total (${invoice.base_currency})</span><div class="detail-value muted">No exchange rate for this date</div>`;
+    container.appendChild(conversion);
+  }
+
+  if (invoice.category === "payable") {
+    const payment = detail.payment || {};
+    [
+      ["iban", "IBAN", payment.iban],
+      ["bic", "BIC", payment.bic],
+      ["payment_reference", "Payment Reference", payment.reference],
+      ["payment_terms", "Payment Terms", payment.terms],
+      ["discount_percent", "Skonto %", payment.discount_percent],
+      ["discount_due_date", "Skonto Until", payment.discount_due_date]
+    ].forEach(([key, label, value]) => {
+      container.appendChild(fieldRow(key, label, value));
+    });
+
+    if (invoice.status === "open" && payment.iban) {
+      const codeBlock = document.createElement("div");
+      codeBlock.className = "detail-block";
+      codeBlock.innerHTML = `<h4>GiroCode</h4><div class="girocode muted">Generating...</div>`;
+      container.appendChild(codeBlock);
+      loadGiroCode(invoice.id, codeBlock.querySelector(".girocode"));
+    }
+  }
+
+  const orders = detail.payment_orders || [];
+  if (orders.length) {
+    const ordersBlock = document.createElement("div");
+    ordersBlock.className = "detail-block";
+    ordersBlock.innerHTML = `
+      <h4>SEPA Transfers</h4>
+      <table class="table compact">
+        <thead>
+          <tr><th>Execution</th><th>Amount</th><th>Skonto</th><th>IBAN</th><th>File</th></tr>
+        </thead>
+        <tbody>
+          ${orders
+            .map(
+              (order) => `<tr>
+                <td>${escapeHtml(order.execution_date)}</td>
+                <td>${formatCurrency(order.amount, order.currency)}</td>
+                <td>${Number(order.discount) ? formatCurrency(order.discount, order.currency) : "-"}</td>
+                <td>${escapeHtml(order.creditor_iban)}</td>
+                <td>${escapeHtml(order.file_path.split("/").pop())}</td>
+              </tr>`
+            )
+            .join("")}
+        </tbody>
+      </table>
+    `;
+    container.appendChild(ordersBlock);
+  }
+
+  const steps = detail.dunning_steps || [];
+  if (steps.length) {
+    const dunningBlock = document.createElement("div");
+    dunningBlock.className = "detail-block";
+    dunningBlock.innerHTML = `
+      <h4>Dunning</h4>
+      <table class="table compact">
+        <thead>
+          <tr><th>Letter</th><th>Sent</th><th>Deadline</th><th>Claimed</th><th>File</th><th></th></tr>
+        </thead>
+        <tbody>
+          ${steps
+            .map((step) => {
+              const claimed = [step.outstanding, step.interest, step.fees].reduce((sum, value) => sum + Number(value), 0);
+              return `<tr>
+                <td>${DUNNING_LEVELS[step.level] || step.level}</td>
+                <td>${escapeHtml(step.created_at.slice(0, 10))}</td>
+                <td>${escapeHtml(step.deadline)}</td>
+                <td>${formatCurrency(claimed, invoice.currency)}</td>
+                <td><button class="ghost" data-open-letter="${step.id}">${escapeHtml(step.file_path.split("/").pop())}</button></td>
+                <td><button class="ghost" data-delete-step="${step.id}">Remove</button></td>
+              </tr>`;
+            })
+            .join("")}
+        </tbody>
+      </table>
+    `;
+    dunningBlock.querySelectorAll("[data-open-letter]").forEach((button) => {
+      const step = steps.find((entry) => entry.id === button.dataset.openLetter);
+      button.addEventListener("click", () => invoke("open_invoice_file", { path: step.file_path }).catch((error) => setStatus(String(error), false)));
+    });
+    dunningBlock.querySelectorAll("[data-delete-step]").forEach((button) => {
+      button.addEventListener("click", async () => {
+        await invoke("delete_dunning_step", { stepId: button.dataset.deleteStep });
+        await selectInvoice(invoice.id);
+        await loadDunning();
+      });
+    });
+    container.appendChild(dunningBlock);
+  }
+
+  if (detail.counterparty) {
+    const linked = document.createElement("div");
+    linked.className = "detail-row";
+    linked.innerHTML = `<span>Counterparty</span><div class="detail-value">${escapeHtml(detail.counterparty.name)}</div>`;
+    container.appendChild(linked);
+  }
+
+  const lineItems = detail.line_items || [];
+  if (lineItems.length) {
+    const itemsBlock = document.createElement("div");
+    itemsBlock.className = "detail-block";
+    itemsBlock.innerHTML = `
+      <h4>Line Items</h4>
+      <table class="table compact">
+        <thead>
+          <tr><th>Description</th><th>Qty</th><th>Unit Price</th><th>VAT</th><th>Net</th></tr>
+        </thead>
+        <tbody>
+          ${lineItems
+            .map(
+              (item) => `<tr>
+                <td>${escapeHtml(item.description || "-")}</td>
+                <td>${item.quantity ?? "-"} ${escapeHtml(item.unit || "")}</td>
+                <td>${item.unit_price ? formatCurrency(item.unit_price, invoice.currency) : "-"}</td>
+                <td>${item.vat_rate != null ? `${item.vat_rate}%` : "-"}</td>
+                <td>${item.net_amount ? formatCurrency(item.net_amount, invoice.currency) : "-"}</td>
+              </tr>`
+            )
+            .join("")}
+        </tbody>
+      </table>
+    `;
+    container.appendChild(itemsBlock);
+  }
+
+  const vatBreakdown = detail.vat_breakdown || [];
+  if (vatBreakdown.length) {
+    const vatBlock = document.createElement("div");
+    vatBlock.className = "detail-block";
+    vatBlock.innerHTML = `
+      <h4>VAT Breakdown</h4>
+      <table class="table compact">
+        <thead>
+          <tr><th>Rate</th><th>Net</th><th>Tax</th></tr>
+        </thead>
+        <tbody>
+          ${vatBreakdown
+            .map(
+              (entry) => `<tr>
+                <td>${entry.rate}%</td>
+                <td>${formatCurrency(entry.net_amount, invoice.currency)}</td>
+                <td>${formatCurrency(entry.tax_amount, invoice.currency)}</td>
+              </tr>`
+            )
+            .join("")}
+        </tbody>
+      </table>
+    `;
+    container.appendChild(vatBlock);
+  }
+
   const ocrBlock = document.createElement("div");
   ocrBlock.className = "detail-block";
-  ocrBlock.innerHTML = `
-    <h4>OCR / Extracted Text</h4>
-    <pre>${escapeHtml(invoice.ocr_text || "No text stored")}<\/pre>
-  `;
+  const pages = detail.pages || [];
+  if (pages.length > 1) {
+    ocrBlock.innerHTML = `
+      <h4>OCR / Extracted Text (${pages.length} pages, ${escapeHtml(pages[0].extraction_method)})</h4>
+      ${pages
+        .map((page) => `<h5>Page ${page.page_number}</h5><pre>${escapeHtml(page.text)}<\/pre>`)
+        .join("")}
+    `;
+  } else {
+    ocrBlock.innerHTML = `
+      <h4>OCR / Extracted Text</h4>
+      <pre>${escapeHtml(invoice.ocr_text || "No text stored")}<\/pre>
+    `;
+  }
   container.appendChild(ocrBlock);
 
   const jsonBlock = document.createElement("div");
@@ -242,13 +566,18 @@ function renderInvoiceDetail(detail) {
   container.querySelectorAll("input[data-field]").forEach((input) => {
     input.addEventListener("change", async (event) => {
       const field = event.target.dataset.field;
-      await invoke("update_invoice_field", {
-        payload: {
-          invoiceId: invoice.id,
-          fieldName: field,
-          value: event.target.value
-        }
-      });
+      try {
+        await invoke("update_invoice_field", {
+          payload: {
+            invoiceId: invoice.id,
+            fieldName: field,
+            value: event.target.value
+          }
+        });
+      } catch (error) {
+        setStatus(String(error), false);
+        return;
+      }
       await loadInvoices();
       await loadDashboard();
     });
@@ -268,12 +597,674 @@ function renderInvoiceDetail(detail) {
   });
 }
 
+async function loadCounterparties() {
+  state.counterparties = await invoke("get_counterparties");
+  const tbody = $("#counterparty-list");
+  tbody.innerHTML = "";
+  if (!state.counterparties.length) {
+    tbody.innerHTML = `<tr><td colspan="4" class="muted">No counterparties</td></tr>`;
+    return;
+  }
+
+  state.counterparties.forEach((counterparty) => {
+    const row = document.createElement("tr");
+    row.innerHTML = `
+      <td>${escapeHtml(counterparty.name)}</td>
+      <td class="muted">${escapeHtml(counterparty.aliases.join(", ")) || "-"}</td>
+      <td>${escapeHtml(counterparty.vat_id || "-")}</td>
+      <td>${counterparty.invoice_count}</td>
+    `;
+    row.addEventListener("click", () => renderCounterpartyDetail(counterparty.id));
+    tbody.appendChild(row);
+  });
+
+  if (state.selectedCounterpartyId) {
+    renderCounterpartyDetail(state.selectedCounterpartyId);
+  }
+  renderIssueCustomers();
+}
+
+function renderCounterpartyDetail(id) {
+  const container = $("#counterparty-detail");
+  const counterparty = state.counterparties.find((entry) => entry.id === id);
+  state.selectedCounterpartyId = counterparty ? id : null;
+  if (!counterparty) {
+    container.innerHTML = `<p class="muted">Select a counterparty from the list.</p>`;
+    return;
+  }
+
+  const others = state.counterparties
+    .filter((entry) => entry.id !== id)
+    .map((entry) => `<option value="${entry.id}">${escapeHtml(entry.name)}</option>`)
+    .join("");
+  container.innerHTML = `
+    <div class="field"><label>Name</label><input data-counterparty="name" /></div>
+    <div class="field"><label>VAT ID</label><input data-counterparty="vat_id" /></div>
+    <div class="field"><label>IBAN</label><input data-counterparty="iban" /></div>
+    <div class="field"><label>BIC</label><input data-counterparty="bic" /></div>
+    <div class="field"><label>Address</label><textarea data-counterparty="address" rows="3"></textarea></div>
+    <div class="field"><label>Email</label><input data-counterparty="email" type="email" /></div>
+    <div class="field"><label>DATEV Debtor/Creditor No.</label><input data-counterparty="personal_account" /></div>
+    <div class="field">
+      <label>Customer Type</label>
+      <select id="counterparty-consumer">
+        <option value="false">Business</option>
+        <option value="true">Consumer</option>
+      </select>
+    </div>
+    <div class="field">
+      <label>Default Category</label>
+      <select data-counterparty="default_category">
+        <option value="">-</option>
+        <option value="revenue">Revenue</option>
+        <option value="payable">Payables</option>
+      </select>
+    </div>
+    <div class="actions">
+      <button class="primary" id="save-counterparty">Save</button>
+    </div>
+    <div class="field">
+      <label>Merge another counterparty into this one</label>
+      <div class="field-row">
+        <select id="merge-source">${others}</select>
+        <button class="ghost" id="merge-counterparty" ${others ? "" : "disabled"}>Merge</button>
+      </div>
+    </div>
+  `;
+  $$("[data-counterparty]").forEach((input) => {
+    input.value = counterparty[input.dataset.counterparty] || "";
+  });
+  $("#counterparty-consumer").value = String(Boolean(counterparty.consumer));
+
+  $("#save-counterparty").addEventListener("click", async () => {
+    const updated = { ...counterparty };
+    $$("[data-counterparty]").forEach((input) => {
+      updated[input.dataset.counterparty] = input.value.trim() || null;
+    });
+    updated.consumer = $("#counterparty-consumer").value === "true";
+    try {
+      await invoke("update_counterparty", { counterparty: updated });
+      setStatus("Counterparty saved", true);
+      await loadCounterparties();
+    } catch (error) {
+      setStatus(String(error), false);
+    }
+  });
+
+  $("#merge-counterparty").addEventListener("click", async () => {
+    const sourceId = $("#merge-source").value;
+    if (!sourceId) return;
+    await invoke("merge_counterparties", { targetId: id, sourceId });
+    setStatus("Counterparties merged", true);
+    await loadCounterparties();
+    await loadInvoices();
+  });
+}
+
+async function loadAccounts() {
+  const [accounts, rules] = await Promise.all([invoke("get_accounts"), invoke("get_account_rules")]);
+  state.accounts = accounts;
+
+  const accountList = $("#account-list");
+  accountList.innerHTML = accounts.length ? "" : `<tr><td colspan="5" class="muted">No accounts. Load the standard accounts in Settings.</td></tr>`;
+  accounts.forEach((account) => {
+    const row = document.createElement("tr");
+    row.innerHTML = `
+      <td>${escapeHtml(account.number)}</td>
+      <td>${escapeHtml(account.name)}</td>
+      <td>${account.category === "revenue" ? "Revenue" : "Payables"}</td>
+      <td>${escapeHtml(account.expense_category || "-")}</td>
+      <td><input type="checkbox" ${account.active ? "checked" : ""} /></td>
+    `;
+    row.addEventListener("click", () => {
+      $("#account-number").value = account.number;
+      $("#account-name").value = account.name;
+      $("#account-category").value = account.category;
+    });
+    row.querySelector("input").addEventListener("change", async (event) => {
+      await invoke("save_account", { account: { ...account, active: event.target.checked } });
+      await loadAccounts();
+    });
+    accountList.appendChild(row);
+  });
+
+  $("#rule-account").innerHTML = accounts
+    .filter((account) => account.active)
+    .map((account) => `<option value="${escapeHtml(account.number)}">${escapeHtml(`${account.number} ${account.name}`)}</option>`)
+    .join("");
+  $("#rule-counterparty").innerHTML = `<option value="">Any</option>${state.counterparties
+    .map((entry) => `<option value="${entry.id}">${escapeHtml(entry.name)}</option>`)
+    .join("")}`;
+
+  const ruleList = $("#rule-list");
+  ruleList.innerHTML = rules.length ? "" : `<tr><td colspan="5" class="muted">No rules</td></tr>`;
+  rules.forEach((rule) => {
+    const row = document.createElement("tr");
+    row.innerHTML = `
+      <td>${escapeHtml(rule.account_number)}</td>
+      <td>${escapeHtml(rule.counterparty_name || "Any")}</td>
+      <td>${escapeHtml(rule.keyword || "-")}</td>
+      <td>${rule.priority}</td>
+      <td><button class="ghost">Delete</button></td>
+    `;
+    row.querySelector("button").addEventListener("click", async () => {
+      await invoke("delete_account_rule", { ruleId: rule.id });
+      await loadAccounts();
+    });
+    ruleList.appendChild(row);
+  });
+}
+
+function renderExportSummary(summary) {
+  const skipped = summary.skipped_undated
+    ? ` ${summary.skipped_undated} invoice(s) without a date were skipped.`
+    : "";
+  $("#export-result").textContent = `Written to ${summary.path}.${skipped}`;
+}
+
+async function loadExportColumns() {
+  const columns = await invoke("get_export_columns");
+  const stored = JSON.parse(localStorage.getItem("exportColumns") || "null");
+  const selected = new Set(stored || DEFAULT_EXPORT_COLUMNS);
+  const container = $("#export-columns");
+  container.innerHTML = "";
+  columns.forEach((column) => {
+    const label = document.createElement("label");
+    label.className = "checkbox";
+    label.innerHTML = `<input type="checkbox" value="${column.key}" ${selected.has(column.key) ? "checked" : ""} /> ${escapeHtml(column.label)}`;
+    container.appendChild(label);
+  });
+}
+
+async function exportInvoices() {
+  const columns = $$("#export-columns input:checked").map((input) => input.value);
+  localStorage.setItem("exportColumns", JSON.stringify(columns));
+  try {
+    const summary = await invoke("export_invoices", {
+      payload: {
+        format: $("#export-format").value,
+        category: $("#export-category").value || null,
+        from: $("#export-from").value || null,
+        to: $("#export-to").value || null,
+        status: $("#export-status").value || null,
+        columns,
+        locale: $("#export-locale").value,
+        path: null
+      }
+    });
+    if (summary) {
+      setStatus(`Exported ${summary.invoices} invoices`, true);
+      renderExportSummary(summary);
+    }
+  } catch (error) {
+    setStatus("Export failed", false);
+    $("#export-result").textContent = String(error);
+  }
+}
+
+async function loadBankProfiles() {
+  const profiles = await invoke("get_bank_csv_profiles");
+  const select = $("#bank-csv-profile");
+  profiles.forEach((profile) => {
+    const option = document.createElement("option");
+    option.value = profile;
+    option.textContent = profile.replace("_", " ");
+    select.insertBefore(option, select.querySelector('option[value="custom"]'));
+  });
+  const mapping = JSON.parse(localStorage.getItem("bankCsvMapping") || "{}");
+  $$("[data-csv-column]").forEach((input) => {
+    input.value = mapping[input.dataset.csvColumn] || "";
+  });
+}
+
+function bankCsvMapping() {
+  const mapping = {};
+  $$("[data-csv-column]").forEach((input) => {
+    mapping[input.dataset.csvColumn] = input.value.trim() || null;
+  });
+  localStorage.setItem("bankCsvMapping", JSON.stringify(mapping));
+  return mapping;
+}
+
+async function importBankStatement() {
+  const profile = $("#bank-csv-profile").value;
+  try {
+    const summary = await invoke("import_bank_statement", {
+      payload: {
+        path: null,
+        csvProfile: profile === "custom" ? null : profile,
+        csvMapping: profile === "custom" ? bankCsvMapping() : null
+      }
+    });
+    if (!summary) return;
+    $("#bank-import-result").textContent =
+      `${summary.path} (${summary.format}): ${summary.transactions} new, ${summary.duplicates} already imported, ` +
+      `${summary.matched} matched, ${summary.suggested} with suggestions`;
+    setStatus(`Imported ${summary.transactions} transactions`, true);
+    await loadBankTransactions();
+    await loadDashboard();
+  } catch (error) {
+    setStatus("Import failed", false);
+    $("#bank-import-result").textContent = String(error);
+  }
+}
+
+async function loadBankTransactions() {
+  const [transactions, revenue, payables] = await Promise.all([
+    invoke("get_bank_transactions", { status: $("#bank-status").value || null }),
+    invoke("get_invoices", { category: "revenue" }),
+    invoke("get_invoices", { category: "payable" })
+  ]);
+  const isOpen = (invoice) => invoice.status !== "paid";
+  const openInvoices = { revenue: revenue.filter(isOpen), payable: payables.filter(isOpen) };
+  const tbody = $("#bank-transactions");
+  tbody.innerHTML = "";
+  if (!transactions.length) {
+    tbody.innerHTML = `<tr><td colspan="7" class="muted">No transactions</td></tr>`;
+    return;
+  }
+
+  transactions.forEach(({ transaction, matches }) => {
+    const incoming = !String(transaction.amount).startsWith("-");
+    const candidates = openInvoices[incoming ? "revenue" : "payable"]
+      .map(
+        (invoice) =>
+          `<option value="${invoice.id}">${escapeHtml(invoice.counterparty_name || "Unknown")}, ${escapeHtml(invoice.invoice_date || "-")}, ${formatCurrency(invoice.total_amount, invoice.currency)}</option>`
+      )
+      .join("");
+    const matchList = matches
+      .filter((found) => found.status !== "rejected")
+      .map(
+        (found) => `
+          <li>
+            ${escapeHtml(found.invoice_number || found.invoice_counterparty || "Invoice")}:
+            ${formatCurrency(found.amount, transaction.currency)}
+            <span class="muted">${escapeHtml(found.reasons)}</span>
+            ${found.status === "suggested" ? `<button class="ghost" data-confirm="${found.id}">Confirm</button>` : ""}
+            <button class="ghost" data-reject="${found.id}">${found.status === "confirmed" ? "Undo" : "Reject"}</button>
+          </li>`
+      )
+      .join("");
+    const row = document.createElement("tr");
+    row.innerHTML = `
+      <td>${escapeHtml(transaction.booking_date)}</td>
+      <td>${escapeHtml(transaction.counterparty_name || "-")}</td>
+      <td class="muted">${escapeHtml(transaction.remittance || "")}</td>
+      <td>${formatCurrency(transaction.amount, transaction.currency)}</td>
+      <td>${escapeHtml(transaction.status)}</td>
+      <td><ul class="warnings">${matchList}</ul></td>
+      <td>
+        ${
+          transaction.status === "matched" || transaction.status === "ignored"
+            ? ""
+            : `<div class="field-row">
+                <select data-assign-select multiple size="3">${candidates}</select>
+                <button class="ghost" data-assign>Assign</button>
+                <button class="ghost" data-ignore>Ignore</button>
+              </div>`
+        }
+      </td>
+    `;
+    const run = async (action) => {
+      try {
+        await action();
+        await loadBankTransactions();
+        await loadDashboard();
+      } catch (error) {
+        setStatus(String(error), false);
+      }
+    };
+    row.querySelectorAll("[data-confirm]").forEach((button) => {
+      button.addEventListener("click", () => run(() => invoke("confirm_bank_match", { matchId: button.dataset.confirm })));
+    });
+    row.querySelectorAll("[data-reject]").forEach((button) => {
+      button.addEventListener("click", () => run(() => invoke("reject_bank_match", { matchId: button.dataset.reject })));
+    });
+    row.querySelector("[data-assign]")?.addEventListener("click", () => {
+      const invoiceIds = Array.from(row.querySelector("[data-assign-select]").selectedOptions).map((option) => option.value);
+      if (!invoiceIds.length) return;
+      run(() => invoke("assign_bank_transaction", { transactionId: transaction.id, invoiceIds }));
+    });
+    row.querySelector("[data-ignore]")?.addEventListener("click", () => {
+      run(() => invoke("ignore_bank_transaction", { transactionId: transaction.id }));
+    });
+    tbody.appendChild(row);
+  });
+}
+
+async function loadDunning() {
+  const tbody = $("#dunning-cases");
+  let overview;
+  try {
+    overview = await invoke("get_dunning_overview");
+  } catch (error) {
+    setStatus(String(error), false);
+    return;
+  }
+
+  tbody.innerHTML = "";
+  if (!overview.cases.length) {
+    tbody.innerHTML = `<tr><td colspan="8" class="muted">No overdue invoices</td></tr>`;
+  }
+  overview.cases.forEach((item) => {
+    const sent = item.level
+      ? `${DUNNING_LEVELS[item.level]}<br /><span class="muted">until ${escapeHtml(item.last_deadline || "-")}</span>`
+      : "-";
+    let action = "";
+    if (item.next_level && item.next_due) {
+      action = `<button class="primary" data-letter>${DUNNING_LEVELS[item.next_level]}</button>`;
+    } else if (item.next_level) {
+      action = `<span class="muted">Waiting for deadline</span>`;
+    }
+    const row = document.createElement("tr");
+    row.innerHTML = `
+      <td>${escapeHtml(item.invoice_number || "-")}</td>
+      <td>${escapeHtml(item.counterparty_name || "Unknown")}${item.consumer ? ` <span class="muted">(consumer)</span>` : ""}</td>
+      <td>${escapeHtml(item.default_since)} <span class="muted">(${item.days_overdue} days)</span></td>
+      <td>${formatCurrency(item.outstanding, item.currency)}</td>
+      <td>${Number(item.interest) ? `${formatCurrency(item.interest, item.currency)} <span class="muted">(${item.interest_rate.toFixed(2)}%)</span>` : "-"}</td>
+      <td>${Number(item.fees) ? formatCurrency(item.fees, item.currency) : "-"}</td>
+      <td>${sent}</td>
+      <td>${action}</td>
+    `;
+    row.querySelector("[data-letter]")?.addEventListener("click", async () => {
+      try {
+        const step = await invoke("create_dunning_letter", { invoiceId: item.invoice_id, path: null });
+        if (!step) return;
+        setStatus(`${DUNNING_LEVELS[step.level]} created`, true);
+        await invoke("open_invoice_file", { path: step.file_path });
+        await loadDunning();
+      } catch (error) {
+        setStatus(String(error), false);
+      }
+    });
+    tbody.appendChild(row);
+  });
+  $("#dunning-warnings").innerHTML = overview.warnings.map((warning) => `<li>${escapeHtml(warning)}</li>`).join("");
+  $("#base-rates").innerHTML = overview.base_rates
+    .slice()
+    .reverse()
+    .map((rate) => `<tr><td>${escapeHtml(rate.valid_from)}</td><td>${rate.rate.toFixed(2)}%</td></tr>`)
+    .join("");
+}
+
+async function loadDunningTemplates() {
+  state.dunningTemplates = await invoke("get_dunning_templates");
+  renderDunningTemplate();
+}
+
+function renderDunningTemplate() {
+  const level = Number($("#dunning-template-level").value);
+  const template = state.dunningTemplates.find((entry) => entry.level === level);
+  if (!template) return;
+  $("#dunning-template-subject").value = template.subject;
+  $("#dunning-template-body").value = template.body;
+  $("#reset-dunning-template").disabled = !template.customized;
+}
+
+function renderIssueCustomers() {
+  const select = $("#issue-counterparty");
+  const selected = select.value;
+  const customers = state.counterparties
+    .filter((counterparty) => counterparty.default_category !== "payable")
+    .sort((a, b) => a.name.localeCompare(b.name));
+  select.innerHTML = `<option value="">Select a customer</option>${customers
+    .map((counterparty) => `<option value="${counterparty.id}">${escapeHtml(counterparty.name)}</option>`)
+    .join("")}`;
+  select.value = customers.some((counterparty) => counterparty.id === selected) ? selected : "";
+}
+
+function parseDecimal(value) {
+  return Number.parseFloat(String(value).replace(",", "."));
+}
+
+function addIssueItem() {
+  const row = document.createElement("tr");
+  row.innerHTML = `
+    <td><textarea data-item="description" rows="1"></textarea></td>
+    <td><input data-item="quantity" type="text" inputmode="decimal" value="1" /></td>
+    <td>
+      <select data-item="unit">
+        ${INVOICE_UNITS.map(([code, label]) => `<option value="${code}">${label}</option>`).join("")}
+      </select>
+    </td>
+    <td><input data-item="unit_price" type="text" inputmode="decimal" placeholder="0.00" /></td>
+    <td><input data-item="vat_rate" type="text" inputmode="decimal" value="19" /></td>
+    <td data-item-amount>-</td>
+    <td><button class="ghost" data-remove-item>Remove</button></td>
+  `;
+  row.querySelectorAll("[data-item]").forEach((input) => input.addEventListener("input", updateIssueTotals));
+  row.querySelector("[data-remove-item]").addEventListener("click", () => {
+    row.remove();
+    updateIssueTotals();
+  });
+  $("#issue-items").appendChild(row);
+  updateIssueTotals();
+}
+
+function issueItems() {
+  return $$("#issue-items tr").map((row) => {
+    const value = (field) => row.querySelector(`[data-item="${field}"]`).value.trim();
+    return {
+      description: value("description"),
+      quantity: parseDecimal(value("quantity")),
+      unit: value("unit"),
+      unit_price: value("unit_price") || "0",
+      vat_rate: parseDecimal(value("vat_rate"))
+    };
+  });
+}
+
+function updateIssueTotals() {
+  const currency = $("#issue-currency").value.trim().toUpperCase() || "EUR";
+  const rows = $$("#issue-items tr");
+  const taxes = new Map();
+  let net = 0;
+  issueItems().forEach((item, index) => {
+    const amount = Math.round(item.quantity * parseDecimal(item.unit_price) * 100) / 100;
+    const cell = rows[index].querySelector("[data-item-amount]");
+    if (!Number.isFinite(amount)) {
+      cell.textContent = "-";
+      return;
+    }
+    cell.textContent = formatCurrency(amount, currency);
+    net += amount;
+    const rate = Number.isFinite(item.vat_rate) ? item.vat_rate : 0;
+    taxes.set(rate, (taxes.get(rate) || 0) + amount);
+  });
+  let tax = 0;
+  const parts = [`Net ${formatCurrency(net, currency)}`];
+  [...taxes.entries()]
+    .filter(([rate]) => rate > 0)
+    .forEach(([rate, basis]) => {
+      const amount = Math.round(basis * rate) / 100;
+      tax += amount;
+      parts.push(`VAT ${rate}% ${formatCurrency(amount, currency)}`);
+    });
+  parts.push(`Total ${formatCurrency(net + tax, currency)}`);
+  $("#issue-totals").textContent = parts.join(" · ");
+}
+
+async function loadNextInvoiceNumber() {
+  try {
+    const number = await invoke("get_next_invoice_number", { invoiceDate: $("#issue-date").value || null });
+    $("#issue-number-preview").textContent = number;
+  } catch (error) {
+    $("#issue-number-preview").textContent = String(error);
+  }
+}
+
+async function createInvoice() {
+  const paymentDays = $("#issue-payment-days").value;
+  const draft = {
+    counterparty_id: $("#issue-counterparty").value,
+    invoice_date: $("#issue-date").value,
+    delivery_date: $("#issue-delivery-date").value || null,
+    payment_days: paymentDays === "" ? null : Number(paymentDays),
+    currency: $("#issue-currency").value.trim() || "EUR",
+    buyer_reference: $("#issue-buyer-reference").value.trim() || null,
+    vat_exemption: $("#issue-vat-exemption").value || null,
+    note: $("#issue-note").value.trim() || null,
+    format: $("#issue-format").value,
+    items: issueItems()
+  };
+  if (!draft.counterparty_id) {
+    setStatus("Select a customer", false);
+    return;
+  }
+  try {
+    const invoice = await invoke("create_invoice", { draft });
+    setStatus(`Invoice ${invoice.invoice_number} created`, true);
+    $("#issue-items").innerHTML = "";
+    $("#issue-note").value = "";
+    addIssueItem();
+    await loadNextInvoiceNumber();
+    await loadInvoices();
+    await loadDashboard();
+    await invoke("open_invoice_file", { path: invoice.file_path });
+  } catch (error) {
+    setStatus(String(error), false);
+  }
+}
+
+async function loadVatReturn() {
+  const period = `${$("#vat-year").value}-${$("#vat-period").value}`;
+  const tbody = $("#vat-return-lines");
+  const warnings = $("#vat-return-warnings");
+  try {
+    const report = await invoke("get_vat_return", { period });
+    const basis = report.basis === "cash" ? "cash basis" : "accrual basis";
+    $("#vat-return-info").textContent = `${report.from} – ${report.to}, ${basis}, ${report.invoice_count} invoice(s)`;
+    tbody.innerHTML = "";
+    report.lines.forEach((line) => {
+      const row = document.createElement("tr");
+      row.innerHTML = `
+        <td>${line.code}</td>
+        <td>${escapeHtml(line.label)}</td>
+        <td>${line.base === null ? "" : formatCurrency(line.base, report.currency)}</td>
+        <td>${line.tax === null ? "" : formatCurrency(line.tax, report.currency)}</td>
+      `;
+      tbody.appendChild(row);
+    });
+    warnings.innerHTML = report.warnings.map((warning) => `<li>${escapeHtml(warning)}</li>`).join("");
+  } catch (error) {
+    setStatus(String(error), false);
+  }
+}
+
+async function loadEuerReport() {
+  const year = Number($("#vat-year").value);
+  const tbody = $("#euer-lines");
+  try {
+    const report = await invoke("get_euer_report", { year });
+    const money = (value) => formatCurrency(value, report.currency);
+    const section = (title) => `<tr><th colspan="3">${title}</th></tr>`;
+    const rows = (lines) =>
+      lines
+        .map(
+          (line) => `
+            <tr class="${line.deductible ? "" : "muted"}">
+              <td>${escapeHtml(line.label)}</td>
+              <td>${line.invoice_count}</td>
+              <td>${money(line.net_amount)}</td>
+            </tr>`
+        )
+        .join("");
+    $("#euer-info").textContent = `${report.year}, paid invoices only, ${report.invoice_count} invoice(s)`;
+    tbody.innerHTML = `
+      ${section("Revenue")}
+      ${rows(report.revenue)}
+      <tr><td>Total revenue</td><td></td><td>${money(report.total_revenue)}</td></tr>
+      ${section("Expenses")}
+      ${rows(report.expenses)}
+      <tr><td>Total deductible expenses</td><td></td><td>${money(report.total_expenses)}</td></tr>
+      <tr><th>Profit</th><th></th><th>${money(report.profit)}</th></tr>
+      <tr class="muted"><td>VAT received</td><td></td><td>${money(report.vat_received)}</td></tr>
+      <tr class="muted"><td>Input VAT paid</td><td></td><td>${money(report.input_vat_paid)}</td></tr>
+    `;
+    $("#euer-warnings").innerHTML = report.warnings.map((warning) => `<li>${escapeHtml(warning)}</li>`).join("");
+  } catch (error) {
+    setStatus(String(error), false);
+  }
+}
+
+async function loadAgingReport() {
+  const tbody = $("#aging-rows");
+  try {
+    const report = await invoke("get_aging_report", {
+      category: $("#aging-category").value,
+      asOf: $("#aging-as-of").value || null
+    });
+    const money = (value) => formatCurrency(value, report.currency);
+    const days = (value) => (value === null || value === undefined ? "-" : value.toFixed(1));
+    $("#aging-head").innerHTML = `
+      <tr>
+        <th>Counterparty</th>
+        ${report.buckets.map((label) => `<th>${escapeHtml(label)}</th>`).join("")}
+        <th>Total</th>
+        <th>Avg. days to pay</th>
+      </tr>`;
+    $("#aging-info").textContent =
+      `As of ${report.as_of}, ${report.open_invoices} open invoice(s), ` +
+      `average ${days(report.average_days_to_pay)} days to pay over ${report.paid_invoices} paid invoice(s)`;
+    if (!report.rows.length) {
+      tbody.innerHTML = `<tr><td colspan="${report.buckets.length + 3}" class="muted">No open invoices</td></tr>`;
+    } else {
+      tbody.innerHTML = `
+        ${report.rows
+          .map(
+            (row) => `
+              <tr>
+                <td>${escapeHtml(row.counterparty_name)}</td>
+                ${row.buckets.map((amount) => `<td>${money(amount)}</td>`).join("")}
+                <td>${money(row.total)}</td>
+                <td>${days(row.average_days_to_pay)}</td>
+              </tr>`
+          )
+          .join("")}
+        <tr>
+          <th>Total</th>
+          ${report.totals.map((amount) => `<th>${money(amount)}</th>`).join("")}
+          <th>${money(report.total)}</th>
+          <th>${days(report.average_days_to_pay)}</th>
+        </tr>`;
+    }
+    $("#aging-warnings").innerHTML = report.warnings.map((warning) => `<li>${escapeHtml(warning)}</li>`).join("");
+  } catch (error) {
+    setStatus(String(error), false);
+  }
+}
+
 async function loadSettings() {
   const settings = await invoke("get_settings");
   $("#revenue-folder").value = settings.revenue_folder || "";
   $("#payable-folder").value = settings.payable_folder || "";
   $("#openai-key").value = "";
   $("#ocr-language").value = settings.ocr_language || "deu";
+  $("#ocr-dpi").value = settings.ocr_dpi || 300;
+  $("#chart-of-accounts").value = settings.chart_of_accounts || "SKR03";
+  $("#base-currency").value = settings.base_currency || "EUR";
+  $("#datev-consultant").value = settings.datev_consultant_number || "";
+  $("#datev-client").value = settings.datev_client_number || "";
+  $("#vat-accounting").value = settings.vat_accounting || "accrual";
+  $("#reporting-basis").value = settings.reporting_basis || "accrual";
+  $("#extraction-backend").value = settings.extraction_backend || "openai";
+  $("#extraction-base-url").value = settings.extraction_base_url || "";
+  $("#extraction-model").value = settings.extraction_model || "";
+  $("#company-name").value = settings.company_name || "";
+  $("#company-iban").value = settings.company_iban || "";
+  $("#company-bic").value = settings.company_bic || "";
+  $("#company-address").value = settings.company_address || "";
+  $("#dunning-interval").value = settings.dunning_interval_days || 14;
+  $("#dunning-fee").value = settings.dunning_fee || "";
+  $("#company-vat-id").value = settings.company_vat_id || "";
+  $("#company-tax-number").value = settings.company_tax_number || "";
+  $("#company-email").value = settings.company_email || "";
+  $("#company-phone").value = settings.company_phone || "";
+  $("#invoice-number-format").value = settings.invoice_number_format || "";
+  $("#invoice-number-start").value = settings.invoice_number_start || 1;
+  $("#invoice-payment-days").value = settings.invoice_payment_days ?? 14;
+  $("#issue-payment-days").placeholder = settings.invoice_payment_days ?? 14;
 }
 
 async function saveSettings() {
@@ -281,17 +1272,43 @@ async function saveSettings() {
     revenueFolder: $("#revenue-folder").value || null,
     payableFolder: $("#payable-folder").value || null,
     openaiApiKey: $("#openai-key").value || null,
-    ocrLanguage: $("#ocr-language").value
+    ocrLanguage: $("#ocr-language").value,
+    ocrDpi: Number($("#ocr-dpi").value) || null,
+    baseCurrency: $("#base-currency").value,
+    chartOfAccounts: $("#chart-of-accounts").value,
+    datevConsultantNumber: $("#datev-consultant").value,
+    datevClientNumber: $("#datev-client").value,
+    vatAccounting: $("#vat-accounting").value,
+    reportingBasis: $("#reporting-basis").value,
+    extractionBackend: $("#extraction-backend").value,
+    extractionBaseUrl: $("#extraction-base-url").value,
+    extractionModel: $("#extraction-model").value,
+    companyName: $("#company-name").value,
+    companyIban: $("#company-iban").value,
+    companyBic: $("#company-bic").value,
+    companyAddress: $("#company-address").value,
+    dunningIntervalDays: Number($("#dunning-interval").value) || null,
+    dunningFee: $("#dunning-fee").value.trim() || null,
+    companyVatId: $("#company-vat-id").value,
+    companyTaxNumber: $("#company-tax-number").value,
+    companyEmail: $("#company-email").value,
+    companyPhone: $("#company-phone").value,
+    invoiceNumberFormat: $("#invoice-number-format").value.trim() || null,
+    invoiceNumberStart: Number($("#invoice-number-start").value) || null,
+    invoicePaymentDays: $("#invoice-payment-days").value === "" ? null : Number($("#invoice-payment-days").value)
   };
   await invoke("save_settings", { payload });
   await loadDashboard();
   await loadInvoices();
+  await loadAccounts();
+  await loadNextInvoiceNumber();
 }
 
 async function init() {
   const monthInput = $("#month-select");
   const now = new Date();
   monthInput.value = `${now.getFullYear()}-${String(now.getMonth() + 1).padStart(2, "0")}`;
+  $("#ocr-install").textContent = popplerInstallHint();
 
   $("#refresh-dashboard").addEventListener("click", loadDashboard);
   monthInput.addEventListener("change", loadDashboard);
@@ -336,6 +1353,175 @@ async function init() {
     setStatus("Reprocessing started", true);
   });
 
+  $("#import-rates").addEventListener("click", async () => {
+    try {
+      const imported = await invoke("import_exchange_rates", { path: null });
+      if (imported) {
+        setStatus(`Imported ${imported} exchange rates`, true);
+        await loadDashboard();
+        await loadInvoices();
+      }
+    } catch (error) {
+      setStatus(String(error), false);
+    }
+  });
+
+  $("#seed-accounts").addEventListener("click", async () => {
+    await saveSettings();
+    const seeded = await invoke("seed_accounts");
+    setStatus(`Loaded ${seeded} standard accounts`, true);
+    await loadAccounts();
+    await loadDashboard();
+  });
+
+  $("#save-account").addEventListener("click", async () => {
+    const number = $("#account-number").value.trim();
+    const existing = state.accounts.find((account) => account.number === number) || {
+      id: "",
+      chart: "",
+      expense_category: null,
+      vat_rate: null,
+      active: true,
+      automatic_vat: false
+    };
+    try {
+      await invoke("save_account", {
+        account: {
+          ...existing,
+          number,
+          name: $("#account-name").value,
+          category: $("#account-category").value
+        }
+      });
+      await loadAccounts();
+    } catch (error) {
+      setStatus(String(error), false);
+    }
+  });
+
+  $("#add-rule").addEventListener("click", async () => {
+    try {
+      await invoke("add_account_rule", {
+        payload: {
+          account_number: $("#rule-account").value,
+          counterparty_id: $("#rule-counterparty").value || null,
+          keyword: $("#rule-keyword").value || null,
+          priority: Number($("#rule-priority").value) || 0
+        }
+      });
+      $("#rule-keyword").value = "";
+      await loadAccounts();
+      await loadDashboard();
+    } catch (error) {
+      setStatus(String(error), false);
+    }
+  });
+
+  const year = now.getFullYear();
+  $("#export-from").value = `${year}-01-01`;
+  $("#export-to").value = `${year}-12-31`;
+  $("#export-datev").addEventListener("click", async () => {
+    try {
+      const summary = await invoke("export_datev", {
+        from: $("#export-from").value,
+        to: $("#export-to").value,
+        path: null
+      });
+      if (summary) {
+        setStatus(`Exported ${summary.records} bookings from ${summary.invoices} invoices`, true);
+        renderExportSummary(summary);
+      }
+    } catch (error) {
+      setStatus("DATEV export failed", false);
+      $("#export-result").textContent = String(error);
+    }
+  });
+
+  $("#export-invoices").addEventListener("click", exportInvoices);
+
+  $("#vat-year").value = year;
+  $("#vat-period").value = `Q${Math.floor(now.getMonth() / 3) + 1}`;
+  $("#load-vat-return").addEventListener("click", loadVatReturn);
+  $("#load-euer").addEventListener("click", loadEuerReport);
+  $("#load-aging").addEventListener("click", loadAgingReport);
+  $("#import-bank-statement").addEventListener("click", importBankStatement);
+  $("#bank-status").addEventListener("change", loadBankTransactions);
+  $("#reconcile-bank").addEventListener("click", async () => {
+    try {
+      const [matched, suggested] = await invoke("reconcile_bank_transactions");
+      setStatus(`${matched} matched, ${suggested} with suggestions`, true);
+      await loadBankTransactions();
+      await loadDashboard();
+    } catch (error) {
+      setStatus(String(error), false);
+    }
+  });
+  $("#open-euer").addEventListener("click", async () => {
+    try {
+      await invoke("open_euer_report", { year: Number($("#vat-year").value) });
+    } catch (error) {
+      setStatus(String(error), false);
+    }
+  });
+
+  $("#create-sepa-transfer").addEventListener("click", createSepaTransfer);
+
+  $("#issue-date").value = `${monthInput.value}-${String(now.getDate()).padStart(2, "0")}`;
+  $("#issue-date").addEventListener("change", loadNextInvoiceNumber);
+  $("#issue-currency").addEventListener("input", updateIssueTotals);
+  $("#add-issue-item").addEventListener("click", addIssueItem);
+  $("#create-invoice").addEventListener("click", createInvoice);
+  $("#create-customer").addEventListener("click", async () => {
+    try {
+      const counterparty = await invoke("create_counterparty", {
+        name: $("#new-customer-name").value,
+        category: "revenue"
+      });
+      $("#new-customer-name").value = "";
+      state.selectedCounterpartyId = counterparty.id;
+      await loadCounterparties();
+      $("#issue-counterparty").value = counterparty.id;
+      setActiveView("counterparties");
+      setStatus("Customer created; add the address before invoicing", true);
+    } catch (error) {
+      setStatus(String(error), false);
+    }
+  });
+  addIssueItem();
+
+  $("#dunning-template-level").addEventListener("change", renderDunningTemplate);
+  $("#save-dunning-template").addEventListener("click", async () => {
+    try {
+      await invoke("save_dunning_template", {
+        template: {
+          level: Number($("#dunning-template-level").value),
+          subject: $("#dunning-template-subject").value,
+          body: $("#dunning-template-body").value
+        }
+      });
+      setStatus("Template saved", true);
+      await loadDunningTemplates();
+    } catch (error) {
+      setStatus(String(error), false);
+    }
+  });
+  $("#reset-dunning-template").addEventListener("click", async () => {
+    await invoke("reset_dunning_template", { level: Number($("#dunning-template-level").value) });
+    await loadDunningTemplates();
+  });
+  $("#save-base-rate").addEventListener("click", async () => {
+    try {
+      await invoke("set_base_interest_rate", {
+        validFrom: $("#base-rate-from").value,
+        rate: Number.parseFloat($("#base-rate-value").value.replace(",", "."))
+      });
+      $("#base-rate-value").value = "";
+      await loadDunning();
+    } catch (error) {
+      setStatus(String(error), false);
+    }
+  });
+
   $("#reprocess-visible").addEventListener("click", async () => {
     await invoke("reprocess_all");
     setStatus("Reprocessing started", true);
@@ -358,10 +1544,20 @@ async function init() {
   await loadSettings();
   await loadDashboard();
   await loadInvoices();
+  await loadCounterparties();
+  await loadAccounts();
+  await loadExportColumns();
+  await loadBankProfiles();
+  await loadBankTransactions();
+  await loadDunning();
+  await loadDunningTemplates();
+  await loadNextInvoiceNumber();
 
   await listen("invoice-updated", async () => {
     await loadDashboard();
     await loadInvoices();
+    await loadCounterparties();
+    await loadDunning();
   });
   await listen("processing-error", (event) => {
     setStatus(`Error: ${event.payload}`, false);